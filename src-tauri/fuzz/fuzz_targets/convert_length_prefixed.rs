@@ -0,0 +1,7 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    mirrativ_app_lib::fuzzing::convert_length_prefixed(data);
+});
@@ -0,0 +1,902 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// comment_queue.rs
+//
+// 配信ごとのコメント送信キュー。
+//
+// 主な責務:
+//   - 配信ごとに 1 つの送信タスクで順番に送信し、最小送信間隔を守る
+//   - 連投制限（429 / 503・ok=0 の連投エラー）を Retry-After かバックオフで待って再送
+//   - 届いたか分からない送信は再送せず（重複防止）、届いていない送信だけを再送
+//   - 視聴中の配信の Broadcast WS の t=1（コメント）と照合して表示されたことを確認
+//     （本文での照合は自分の投稿に限る）
+//   - 状態の変化を comment-queue://state で通知（queued / sent / rejected と理由）
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::{Duration, Instant, MissedTickBehavior};
+
+use crate::live_poller::{as_flag, as_number};
+use crate::mirrativ::client::broadcast::BroadcastManager;
+use crate::mirrativ::client::core::{RawPostError, RawPostResponse};
+use crate::mirrativ::client::live::post_comment_once;
+use crate::mirrativ::MirrativClient;
+
+/// 同じ配信への送信の最小間隔
+const MIN_SEND_INTERVAL: Duration = Duration::from_millis(1500);
+/// 連投制限・送信失敗時の待ち時間（Retry-After がない場合。失敗が続くと倍にする）
+const INITIAL_BACKOFF: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// 1 件のコメントを送信する最大回数
+const MAX_ATTEMPTS: u32 = 3;
+/// 送信後に Broadcast WS に現れるのを待つ時間（過ぎると unconfirmed）
+const ECHO_TIMEOUT: Duration = Duration::from_secs(30);
+/// 未確認のコメントを確認する間隔
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(5);
+/// 配信ごとに送信待ちにできる件数
+const MAX_PENDING: usize = 20;
+/// 配信ごとに保持する終了済みのコメントの件数（古いものから削除）
+const MAX_FINISHED: usize = 50;
+/// Broadcast WS のコメントメッセージ
+const COMMENT_MESSAGE_TYPE: i64 = 1;
+/// ok=0 のエラーメッセージのうち連投制限とみなすもの
+const THROTTLE_HINTS: [&str; 5] = ["連投", "しばらく", "too many", "rate limit", "wait"];
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 型定義
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum CommentState {
+    Queued,
+    Sending,
+    /// サーバーが受け付けた（または応答がなく届いた可能性がある）。Broadcast WS で確認待ち
+    Sent,
+    /// Broadcast WS にコメントが現れた
+    Confirmed,
+    Rejected,
+    /// 送信後 ECHO_TIMEOUT 以内に Broadcast WS に現れなかった
+    Unconfirmed,
+    Cancelled,
+}
+
+impl CommentState {
+    fn is_finished(self) -> bool {
+        !matches!(self, Self::Queued | Self::Sending | Self::Sent)
+    }
+}
+
+/// キューの 1 件（comment-queue://state イベントの payload）
+#[derive(Serialize, Clone)]
+pub struct CommentItem {
+    pub id: String,
+    pub live_id: String,
+    pub message: String,
+    pub comment_type: Option<i32>,
+    pub state: CommentState,
+    /// 送信した回数
+    pub attempts: u32,
+    /// 拒否・再送待ち・確認できなかった理由
+    pub reason: Option<String>,
+    pub queued_at_ms: u64,
+    pub sent_at_ms: Option<u64>,
+    pub confirmed_at_ms: Option<u64>,
+    /// live_comment のレスポンスのコメント ID（Broadcast WS の lci と照合する）
+    pub comment_id: Option<String>,
+}
+
+/// 配信ごとのキュー
+struct LiveQueue {
+    items: Vec<CommentItem>,
+    sender_running: bool,
+    /// 次に送信してよい時刻
+    next_send: Instant,
+    /// 次に連投制限・送信失敗があったときの待ち時間
+    backoff: Duration,
+    /// 送信しているユーザーの ID（/api/user/me。分からなければ本文では照合しない）
+    user_id: Option<String>,
+}
+
+impl LiveQueue {
+    fn new() -> Self {
+        Self {
+            items: Vec::new(),
+            sender_running: false,
+            next_send: Instant::now(),
+            backoff: INITIAL_BACKOFF,
+            user_id: None,
+        }
+    }
+
+    fn item_mut(&mut self, id: &str) -> Option<&mut CommentItem> {
+        self.items.iter_mut().find(|item| item.id == id)
+    }
+
+    /// 終了済みのコメントを MAX_FINISHED 件まで減らす
+    fn prune(&mut self) {
+        let finished = self.items.iter().filter(|i| i.state.is_finished()).count();
+        let mut excess = finished.saturating_sub(MAX_FINISHED);
+        self.items.retain(|item| {
+            if excess > 0 && item.state.is_finished() {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+}
+
+/// 1 回の送信結果の分類
+enum SendOutcome {
+    /// 受け付けられた（reason は応答がなかった場合の注記）
+    Sent {
+        comment_id: Option<String>,
+        reason: Option<String>,
+    },
+    Rejected(String),
+    /// 届いていない・連投制限なので待って再送してよい
+    Retry {
+        delay: Option<Duration>,
+        reason: String,
+    },
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// CommentQueueManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// コメント送信キュー（Tauri の管理状態として登録される）
+#[derive(Default)]
+pub struct CommentQueueManager {
+    lives: Mutex<HashMap<String, LiveQueue>>,
+    next_id: AtomicU64,
+    /// 視聴用の Broadcast WS が接続している配信（echo はこの配信のキューとだけ照合する）
+    echo_live: Mutex<Option<String>>,
+}
+
+impl CommentQueueManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 配信のキューを操作し、変化したコメントを通知する
+    fn update<R>(
+        &self,
+        app: &AppHandle,
+        live_id: &str,
+        f: impl FnOnce(&mut LiveQueue, &mut Vec<CommentItem>) -> R,
+    ) -> Option<R> {
+        let mut changed = Vec::new();
+        let result = {
+            let mut lives = self.lives.lock().ok()?;
+            let queue = lives.get_mut(live_id)?;
+            let result = f(queue, &mut changed);
+            queue.prune();
+            result
+        };
+        for item in changed {
+            let _ = app.emit("comment-queue://state", item);
+        }
+        Some(result)
+    }
+
+    /// 送信待ち・確認待ちのコメントがなく、送信タスクもない配信のキューを削除する
+    fn remove_idle(&self, live_id: &str) {
+        let mut lives = self.lives.lock().unwrap_or_else(PoisonError::into_inner);
+        if lives
+            .get(live_id)
+            .is_some_and(|queue| queue.items.is_empty() && !queue.sender_running)
+        {
+            lives.remove(live_id);
+        }
+    }
+
+    /// 視聴用の Broadcast WS の接続先の配信を設定する（切断時は None）
+    pub(crate) fn set_echo_live(&self, live_id: Option<&str>) {
+        if let Ok(mut echo_live) = self.echo_live.lock() {
+            *echo_live = live_id.map(str::to_string);
+        }
+    }
+
+    fn echo_live(&self) -> Option<String> {
+        self.echo_live.lock().ok()?.clone()
+    }
+
+    /// 送信待ちのコメントを取り消し、終了済みのコメントを削除する（退室時など）。
+    /// 送信中・確認待ちのコメントはそのまま残す
+    pub(crate) fn clear(&self, app: &AppHandle, live_id: &str) {
+        self.update(app, live_id, |queue, changed| {
+            for item in queue.items.iter_mut() {
+                if item.state == CommentState::Queued {
+                    item.state = CommentState::Cancelled;
+                    changed.push(item.clone());
+                }
+            }
+            queue.items.retain(|item| !item.state.is_finished());
+        });
+        self.remove_idle(live_id);
+    }
+}
+
+/// 送信タスクの終了時に sender_running を戻す（途中で抜けた場合も次の enqueue で起動できる）
+struct SenderGuard<'a> {
+    manager: &'a CommentQueueManager,
+    live_id: &'a str,
+    /// false なら送信待ちがないのを確認したときに戻し済み
+    running: bool,
+}
+
+impl Drop for SenderGuard<'_> {
+    fn drop(&mut self) {
+        if self.running {
+            let mut lives = self.manager.lives.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(queue) = lives.get_mut(self.live_id) {
+                queue.sender_running = false;
+            }
+        }
+        self.manager.remove_idle(self.live_id);
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Broadcast WS 側とキュー側で比較できるようにコメント本文を正規化する
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 文字列・数値の ID を文字列として読む
+fn id_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 送信
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// live_comment の結果を分類する
+fn classify(result: Result<RawPostResponse, RawPostError>) -> SendOutcome {
+    let resp = match result {
+        Ok(resp) => resp,
+        Err(RawPostError::NotSent(e)) => {
+            return SendOutcome::Retry {
+                delay: None,
+                reason: format!("not sent: {}", e),
+            }
+        }
+        // 届いている可能性があるので再送しない（Broadcast WS で確認する）
+        Err(RawPostError::Unknown(e)) => {
+            return SendOutcome::Sent {
+                comment_id: None,
+                reason: Some(format!("no response ({}); waiting for echo", e)),
+            }
+        }
+    };
+
+    let body = &resp.body;
+    match resp.status {
+        200..=299 => {}
+        429 | 503 => {
+            return SendOutcome::Retry {
+                delay: resp.retry_after,
+                reason: format!("throttled (HTTP {})", resp.status),
+            }
+        }
+        500..=599 => {
+            return SendOutcome::Sent {
+                comment_id: None,
+                reason: Some(format!("HTTP {}; waiting for echo", resp.status)),
+            }
+        }
+        status => return SendOutcome::Rejected(error_message(body, status)),
+    }
+
+    if as_flag(&body["status"]["ok"]) == Some(false) {
+        let reason = error_message(body, resp.status);
+        let lower = reason.to_lowercase();
+        if THROTTLE_HINTS.iter().any(|hint| lower.contains(hint)) {
+            return SendOutcome::Retry {
+                delay: resp.retry_after,
+                reason,
+            };
+        }
+        return SendOutcome::Rejected(reason);
+    }
+
+    let comment_id = [&body["comment_id"], &body["id"], &body["comment"]["id"]]
+        .into_iter()
+        .find_map(id_string);
+    SendOutcome::Sent {
+        comment_id,
+        reason: None,
+    }
+}
+
+/// レスポンスのエラーメッセージ（なければ HTTP ステータス）
+fn error_message(body: &Value, status: u16) -> String {
+    [
+        &body["status"]["msg"],
+        &body["status"]["error"],
+        &body["status"]["message"],
+        &body["error"],
+        &body["message"],
+    ]
+    .into_iter()
+    .filter_map(Value::as_str)
+    .map(str::trim)
+    .find(|s| !s.is_empty())
+    .map(str::to_string)
+    .unwrap_or_else(|| format!("HTTP {}", status))
+}
+
+/// ログイン中のユーザーの ID（/api/user/me）
+async fn fetch_own_user_id(client: &MirrativClient) -> Option<String> {
+    let me = client
+        .fetch_json("https://www.mirrativ.com/api/user/me", Some("my_page"))
+        .await
+        .map_err(|e| eprintln!("[comment-queue] user/me failed: {}", e))
+        .ok()?;
+    id_string(&me["user_id"])
+}
+
+/// 配信ごとの送信タスク。送信待ちがなくなったら終了する
+async fn run_sender(app: AppHandle, live_id: String) {
+    let manager = app.state::<CommentQueueManager>();
+    let mut guard = SenderGuard {
+        manager: &manager,
+        live_id: &live_id,
+        running: true,
+    };
+    if manager.update(&app, &live_id, |queue, _| queue.user_id.is_none()) == Some(true) {
+        let user_id = fetch_own_user_id(&app.state::<MirrativClient>()).await;
+        manager.update(&app, &live_id, |queue, _| queue.user_id = user_id);
+    }
+    loop {
+        let next = manager.update(&app, &live_id, |queue, _| {
+            match queue.items.iter().find(|i| i.state == CommentState::Queued) {
+                Some(item) => Some((item.id.clone(), queue.next_send)),
+                None => {
+                    queue.sender_running = false;
+                    None
+                }
+            }
+        });
+        let (id, send_at) = match next {
+            Some(Some(next)) => next,
+            Some(None) => {
+                // 送信待ちがないことを確認したのと同じロックの中で戻している
+                guard.running = false;
+                return;
+            }
+            None => return,
+        };
+        tokio::time::sleep_until(send_at).await;
+
+        // 待っている間に取り消された場合は次へ
+        let picked = manager
+            .update(&app, &live_id, |queue, changed| {
+                let item = queue.item_mut(&id)?;
+                if item.state != CommentState::Queued {
+                    return None;
+                }
+                item.state = CommentState::Sending;
+                item.attempts += 1;
+                changed.push(item.clone());
+                Some(item.clone())
+            })
+            .flatten();
+        let Some(item) = picked else {
+            continue;
+        };
+
+        let client = app.state::<MirrativClient>();
+        let result = post_comment_once(&client, &live_id, &item.message, item.comment_type).await;
+        let outcome = classify(result);
+
+        manager.update(&app, &live_id, |queue, changed| {
+            let now = Instant::now();
+            let mut next_send = now + MIN_SEND_INTERVAL;
+            let backoff = queue.backoff;
+            let Some(item) = queue.items.iter_mut().find(|item| item.id == id) else {
+                return;
+            };
+            // レスポンスより先に Broadcast WS で確認できていることがある
+            let confirmed = item.state == CommentState::Confirmed;
+            match outcome {
+                SendOutcome::Sent { comment_id, reason } => {
+                    item.sent_at_ms = Some(unix_millis());
+                    item.comment_id = comment_id;
+                    item.reason = reason;
+                    if !confirmed {
+                        item.state = CommentState::Sent;
+                    }
+                    queue.backoff = INITIAL_BACKOFF;
+                }
+                SendOutcome::Rejected(reason) => {
+                    item.reason = Some(reason);
+                    if !confirmed {
+                        item.state = CommentState::Rejected;
+                    }
+                }
+                SendOutcome::Retry { delay, reason } => {
+                    eprintln!("[comment-queue] {} retry: {}", live_id, reason);
+                    item.reason = Some(reason);
+                    if !confirmed {
+                        item.state = if item.attempts >= MAX_ATTEMPTS {
+                            CommentState::Rejected
+                        } else {
+                            CommentState::Queued
+                        };
+                    }
+                    next_send = now + delay.unwrap_or(backoff).max(MIN_SEND_INTERVAL);
+                    queue.backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+            changed.push(item.clone());
+            queue.next_send = next_send;
+        });
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Broadcast WS での確認
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 視聴用の Broadcast WS を購読し、送信したコメントが現れたら confirmed にする
+/// バックグラウンドタスクを起動する（アプリの setup から呼ぶ）
+pub(crate) fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut messages = app.state::<BroadcastManager>().subscribe();
+        let mut expire = tokio::time::interval(EXPIRE_INTERVAL);
+        expire.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                message = messages.recv() => match message {
+                    Ok(message) => confirm_echo(&app, &message),
+                    Err(RecvError::Lagged(skipped)) => {
+                        eprintln!("[comment-queue] skipped {} broadcast messages", skipped);
+                    }
+                    Err(RecvError::Closed) => return,
+                },
+                _ = expire.tick() => expire_unconfirmed(&app),
+            }
+        }
+    });
+}
+
+/// Broadcast WS の t=1 メッセージのうち照合に使う部分
+struct Echo {
+    /// コメント ID（lci）
+    id: Option<String>,
+    /// 投稿者のユーザー ID（u）
+    user_id: Option<String>,
+    /// 正規化した本文
+    text: String,
+}
+
+impl Echo {
+    fn parse(message: &Value) -> Option<Self> {
+        if as_number(&message["t"]) != Some(COMMENT_MESSAGE_TYPE) {
+            return None;
+        }
+        let text = [&message["cm"], &message["comment"], &message["speech"]]
+            .into_iter()
+            .filter_map(Value::as_str)
+            .map(normalize_text)
+            .find(|s| !s.is_empty())?;
+        Some(Self {
+            id: [&message["lci"], &message["comment_id"]]
+                .into_iter()
+                .find_map(id_string),
+            user_id: [&message["u"], &message["user_id"]]
+                .into_iter()
+                .find_map(id_string),
+            text,
+        })
+    }
+}
+
+/// echo に対応する確認待ちのコメントの位置。
+/// コメント ID が分かっていれば ID で、なければ本文で最も古いものに対応させる。
+/// 本文での照合は echo の投稿者が自分（own_user_id）の場合に限る
+fn find_echo_match(items: &[CommentItem], echo: &Echo, own_user_id: Option<&str>) -> Option<usize> {
+    let waiting =
+        |item: &CommentItem| matches!(item.state, CommentState::Sending | CommentState::Sent);
+    let by_id = echo.id.as_ref().and_then(|echo_id| {
+        items
+            .iter()
+            .position(|item| waiting(item) && item.comment_id.as_ref() == Some(echo_id))
+    });
+    if by_id.is_some() {
+        return by_id;
+    }
+
+    let own = own_user_id.is_some() && echo.user_id.as_deref() == own_user_id;
+    if !own {
+        return None;
+    }
+    // ID が両方にあって異なる場合は本文が同じでも別のコメント
+    items.iter().position(|item| {
+        waiting(item)
+            && (echo.id.is_none() || item.comment_id.is_none())
+            && normalize_text(&item.message) == echo.text
+    })
+}
+
+/// t=1 のメッセージを視聴中の配信の送信済みのコメントと照合する
+fn confirm_echo(app: &AppHandle, message: &Value) {
+    let Some(echo) = Echo::parse(message) else {
+        return;
+    };
+    let manager = app.state::<CommentQueueManager>();
+    let Some(live_id) = manager.echo_live() else {
+        return;
+    };
+    manager.update(app, &live_id, |queue, changed| {
+        let index = find_echo_match(&queue.items, &echo, queue.user_id.as_deref())?;
+        let item = &mut queue.items[index];
+        item.state = CommentState::Confirmed;
+        item.confirmed_at_ms = Some(unix_millis());
+        if item.comment_id.is_none() {
+            item.comment_id = echo.id.clone();
+        }
+        changed.push(item.clone());
+        Some(())
+    });
+}
+
+/// 送信後 ECHO_TIMEOUT を過ぎても確認できないコメントを unconfirmed にする
+fn expire_unconfirmed(app: &AppHandle) {
+    let manager = app.state::<CommentQueueManager>();
+    let deadline = unix_millis().saturating_sub(ECHO_TIMEOUT.as_millis() as u64);
+    let live_ids: Vec<String> = match manager.lives.lock() {
+        Ok(lives) => lives.keys().cloned().collect(),
+        Err(_) => return,
+    };
+    for live_id in live_ids {
+        manager.update(app, &live_id, |queue, changed| {
+            for item in queue.items.iter_mut() {
+                if item.state == CommentState::Sent
+                    && item.sent_at_ms.is_some_and(|sent| sent <= deadline)
+                {
+                    item.state = CommentState::Unconfirmed;
+                    if item.reason.is_none() {
+                        item.reason = Some("not seen in broadcast".to_string());
+                    }
+                    changed.push(item.clone());
+                }
+            }
+        });
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// コメントを送信キューに追加する。状態の変化は comment-queue://state で通知する
+#[tauri::command]
+pub async fn enqueue_comment(
+    app: AppHandle,
+    manager: tauri::State<'_, CommentQueueManager>,
+    live_id: String,
+    message: String,
+    comment_type: Option<i32>,
+) -> Result<CommentItem, String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+    if message.trim().is_empty() {
+        return Err("comment is empty".to_string());
+    }
+
+    let (item, spawn_sender) = {
+        let mut lives = manager.lives.lock().map_err(|e| e.to_string())?;
+        let queue = lives.entry(live_id.clone()).or_insert_with(LiveQueue::new);
+        let pending = queue
+            .items
+            .iter()
+            .filter(|i| i.state == CommentState::Queued)
+            .count();
+        if pending >= MAX_PENDING {
+            return Err(format!("too many pending comments ({})", pending));
+        }
+        let item = CommentItem {
+            id: format!(
+                "{}-{}",
+                live_id,
+                manager.next_id.fetch_add(1, Ordering::Relaxed)
+            ),
+            live_id: live_id.clone(),
+            message,
+            comment_type,
+            state: CommentState::Queued,
+            attempts: 0,
+            reason: None,
+            queued_at_ms: unix_millis(),
+            sent_at_ms: None,
+            confirmed_at_ms: None,
+            comment_id: None,
+        };
+        queue.items.push(item.clone());
+        let spawn_sender = !queue.sender_running;
+        queue.sender_running = true;
+        (item, spawn_sender)
+    };
+
+    let _ = app.emit("comment-queue://state", &item);
+    if spawn_sender {
+        tauri::async_runtime::spawn(run_sender(app.clone(), live_id));
+    }
+    Ok(item)
+}
+
+/// 送信待ちのコメントを取り消す。送信中・送信済みの場合は false を返す
+#[tauri::command]
+pub async fn cancel_comment(
+    app: AppHandle,
+    manager: tauri::State<'_, CommentQueueManager>,
+    id: String,
+) -> Result<bool, String> {
+    let live_id = manager
+        .lives
+        .lock()
+        .map_err(|e| e.to_string())?
+        .iter()
+        .find(|(_, queue)| queue.items.iter().any(|item| item.id == id))
+        .map(|(live_id, _)| live_id.clone());
+    let Some(live_id) = live_id else {
+        return Ok(false);
+    };
+    let cancelled = manager.update(&app, &live_id, |queue, changed| {
+        let item = queue.item_mut(&id)?;
+        if item.state != CommentState::Queued {
+            return None;
+        }
+        item.state = CommentState::Cancelled;
+        changed.push(item.clone());
+        Some(())
+    });
+    Ok(cancelled.flatten().is_some())
+}
+
+/// 配信の送信キュー（終了済みを含む）を古い順に取得する
+#[tauri::command]
+pub async fn get_comment_queue(
+    manager: tauri::State<'_, CommentQueueManager>,
+    live_id: String,
+) -> Result<Vec<CommentItem>, String> {
+    let lives = manager.lives.lock().map_err(|e| e.to_string())?;
+    Ok(lives
+        .get(live_id.trim())
+        .map(|queue| queue.items.clone())
+        .unwrap_or_default())
+}
+
+/// 配信の送信待ちを取り消し、終了済みのコメントを削除する
+#[tauri::command]
+pub async fn clear_comment_queue(
+    app: AppHandle,
+    manager: tauri::State<'_, CommentQueueManager>,
+    live_id: String,
+) -> Result<(), String> {
+    manager.clear(&app, live_id.trim());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn response(status: u16, body: Value) -> Result<RawPostResponse, RawPostError> {
+        Ok(RawPostResponse {
+            status,
+            retry_after: None,
+            body,
+        })
+    }
+
+    fn item(message: &str, state: CommentState, comment_id: Option<&str>) -> CommentItem {
+        CommentItem {
+            id: format!("1-{}", message),
+            live_id: "1".to_string(),
+            message: message.to_string(),
+            comment_type: None,
+            state,
+            attempts: 1,
+            reason: None,
+            queued_at_ms: 0,
+            sent_at_ms: None,
+            confirmed_at_ms: None,
+            comment_id: comment_id.map(str::to_string),
+        }
+    }
+
+    fn echo(message: Value) -> Echo {
+        Echo::parse(&message).expect("comment message")
+    }
+
+    #[test]
+    fn classify_accepted_comment() {
+        let body = json!({ "status": { "ok": 1 }, "comment": { "id": 42 } });
+        match classify(response(200, body)) {
+            SendOutcome::Sent { comment_id, reason } => {
+                assert_eq!(comment_id.as_deref(), Some("42"));
+                assert!(reason.is_none());
+            }
+            _ => panic!("expected sent"),
+        }
+    }
+
+    #[test]
+    fn classify_retries_only_what_did_not_arrive() {
+        let retry_after = Some(Duration::from_secs(7));
+        let throttled = Ok(RawPostResponse {
+            status: 429,
+            retry_after,
+            body: Value::Null,
+        });
+        assert!(matches!(
+            classify(throttled),
+            SendOutcome::Retry { delay, .. } if delay == retry_after
+        ));
+        let not_sent = Err(RawPostError::NotSent("connect refused".to_string()));
+        assert!(matches!(
+            classify(not_sent),
+            SendOutcome::Retry { delay: None, .. }
+        ));
+        let body = json!({ "status": { "ok": 0, "msg": "連投はしばらくお待ちください" } });
+        assert!(matches!(
+            classify(response(200, body)),
+            SendOutcome::Retry { .. }
+        ));
+    }
+
+    #[test]
+    fn classify_unknown_delivery_waits_for_echo() {
+        let timeout = Err(RawPostError::Unknown("timed out".to_string()));
+        assert!(matches!(
+            classify(timeout),
+            SendOutcome::Sent {
+                comment_id: None,
+                reason: Some(_)
+            }
+        ));
+        assert!(matches!(
+            classify(response(502, Value::Null)),
+            SendOutcome::Sent {
+                comment_id: None,
+                reason: Some(_)
+            }
+        ));
+    }
+
+    #[test]
+    fn classify_rejections() {
+        let body = json!({ "status": { "ok": 0, "msg": "NGワードが含まれています" } });
+        match classify(response(200, body)) {
+            SendOutcome::Rejected(reason) => assert_eq!(reason, "NGワードが含まれています"),
+            _ => panic!("expected rejected"),
+        }
+        match classify(response(403, Value::Null)) {
+            SendOutcome::Rejected(reason) => assert_eq!(reason, "HTTP 403"),
+            _ => panic!("expected rejected"),
+        }
+    }
+
+    #[test]
+    fn echo_parse_ignores_other_messages() {
+        assert!(Echo::parse(&json!({ "t": 3, "cm": "joined", "u": "9" })).is_none());
+        assert!(Echo::parse(&json!({ "t": 1, "cm": "   ", "u": "9" })).is_none());
+        let parsed = echo(json!({ "t": 1, "lci": 5, "u": "9", "cm": " hello\n world " }));
+        assert_eq!(parsed.id.as_deref(), Some("5"));
+        assert_eq!(parsed.user_id.as_deref(), Some("9"));
+        assert_eq!(parsed.text, "hello world");
+    }
+
+    #[test]
+    fn echo_matches_by_comment_id_first() {
+        let items = [
+            item("hello", CommentState::Sent, Some("100")),
+            item("hello", CommentState::Sent, Some("101")),
+        ];
+        let by_id = echo(json!({ "t": 1, "lci": "101", "u": "other", "cm": "hello" }));
+        assert_eq!(find_echo_match(&items, &by_id, Some("9")), Some(1));
+        // ID が両方にあって異なる場合は本文が同じでも別のコメント
+        let other = echo(json!({ "t": 1, "lci": "102", "u": "9", "cm": "hello" }));
+        assert_eq!(find_echo_match(&items, &other, Some("9")), None);
+    }
+
+    #[test]
+    fn echo_text_fallback_requires_own_user() {
+        let items = [
+            item("hello", CommentState::Confirmed, None),
+            item("hello", CommentState::Sent, None),
+            item("hello", CommentState::Sending, None),
+        ];
+        let own = echo(json!({ "t": 1, "lci": "7", "u": "9", "cm": "hello" }));
+        assert_eq!(find_echo_match(&items, &own, Some("9")), Some(1));
+
+        let someone_else = echo(json!({ "t": 1, "lci": "8", "u": "10", "cm": "hello" }));
+        assert_eq!(find_echo_match(&items, &someone_else, Some("9")), None);
+        // 自分の ID が分からない場合はコメント ID でしか確認しない
+        assert_eq!(find_echo_match(&items, &own, None), None);
+        let anonymous = echo(json!({ "t": 1, "cm": "hello" }));
+        assert_eq!(find_echo_match(&items, &anonymous, Some("9")), None);
+    }
+
+    fn queue_with(manager: &CommentQueueManager, live_id: &str, items: Vec<CommentItem>) {
+        let mut queue = LiveQueue::new();
+        queue.items = items;
+        queue.sender_running = true;
+        manager.lives.lock().unwrap().insert(live_id.to_string(), queue);
+    }
+
+    #[test]
+    fn sender_guard_resets_flag_on_early_exit() {
+        let manager = CommentQueueManager::new();
+        let pending = item("a", CommentState::Queued, None);
+        queue_with(&manager, "1", vec![pending]);
+        drop(SenderGuard {
+            manager: &manager,
+            live_id: "1",
+            running: true,
+        });
+        // 送信待ちが残っているのでキューは残り、次の enqueue で送信タスクを起動できる
+        let lives = manager.lives.lock().unwrap();
+        assert!(!lives["1"].sender_running);
+    }
+
+    #[test]
+    fn finished_sender_does_not_reset_a_newer_sender() {
+        let manager = CommentQueueManager::new();
+        queue_with(&manager, "1", vec![item("a", CommentState::Queued, None)]);
+        // 送信待ちがないのを確認した後に enqueue が新しい送信タスクを起動した状態
+        drop(SenderGuard {
+            manager: &manager,
+            live_id: "1",
+            running: false,
+        });
+        assert!(manager.lives.lock().unwrap()["1"].sender_running);
+    }
+
+    #[test]
+    fn idle_queues_are_removed() {
+        let manager = CommentQueueManager::new();
+        queue_with(&manager, "1", Vec::new());
+        queue_with(&manager, "2", vec![item("b", CommentState::Sent, None)]);
+        drop(SenderGuard {
+            manager: &manager,
+            live_id: "1",
+            running: true,
+        });
+        drop(SenderGuard {
+            manager: &manager,
+            live_id: "2",
+            running: true,
+        });
+        let lives = manager.lives.lock().unwrap();
+        assert!(!lives.contains_key("1"));
+        // 確認待ちのコメントがある配信は残す
+        assert!(lives.contains_key("2"));
+    }
+}
@@ -0,0 +1,733 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// follow_watcher.rs
+//
+// フォロー中のユーザーの配信開始を検出して通知するバックグラウンドサービス。
+//
+// 主な責務:
+//   - catalog/follow（cursor でページを辿る）と指定ユーザーの live_history の定期取得
+//   - 前回との差分で新しく始まった配信を検出し notify://live-started で通知
+//   - ユーザーごとのミュートとおやすみ時間（ネイティブ通知のみ抑制）
+//   - 自動録画対象ユーザーの配信の録画キューへの追加
+//   - app_config_dir の follow_notifications.json への設定の保存
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_notification::NotificationExt;
+use tokio::sync::Notify;
+use tokio::time::Duration;
+
+use crate::live_poller::{as_flag, as_number};
+use crate::mirrativ::client::catalog::get_catalog_follow;
+use crate::mirrativ::client::live::get_live_history;
+use crate::recorder::{self, RecordingRequest};
+
+const SETTINGS_FILE: &str = "follow_notifications.json";
+/// 取得間隔（秒）の既定値と受け入れる範囲
+const DEFAULT_INTERVAL_SECS: u64 = 60;
+const MIN_INTERVAL_SECS: u64 = 30;
+const MAX_INTERVAL_SECS: u64 = 900;
+/// catalog/follow を辿る最大ページ数
+const MAX_FOLLOW_PAGES: usize = 10;
+/// 配信一覧として解釈するフィールド（フロントエンドの extractLives と同じ候補）
+const LIVE_LIST_FIELDS: [&str; 4] = ["list", "lives", "live_list", "history"];
+const MINUTES_PER_DAY: i64 = 24 * 60;
+/// catalog/follow の取得結果の識別子（live_history はユーザー ID ごと）
+const FOLLOW_SOURCE: &str = "follow";
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 設定と状態
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// おやすみ時間（ローカル時刻の 0:00 からの分。start > end の場合は日をまたぐ）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QuietHours {
+    pub start_minute: u16,
+    pub end_minute: u16,
+    /// UTC からのオフセット（分、フロントエンドの -Date#getTimezoneOffset()）
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+}
+
+/// follow_notifications.json の内容
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FollowWatchSettings {
+    /// 既定では無効（ユーザーが有効にするまで取得も通知もしない）
+    pub enabled: bool,
+    pub interval_secs: u64,
+    /// OS のネイティブ通知を出すか（false でも notify://live-started は送る）
+    pub native_notifications: bool,
+    /// 通知しないユーザー ID
+    pub muted_user_ids: BTreeSet<String>,
+    /// フォロー一覧とは別に live_history で確認するユーザー ID
+    pub watched_user_ids: BTreeSet<String>,
+    /// 配信開始時に自動で録画するユーザー ID（live_history でも確認する）
+    pub auto_record_user_ids: BTreeSet<String>,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for FollowWatchSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_secs: DEFAULT_INTERVAL_SECS,
+            native_notifications: false,
+            muted_user_ids: BTreeSet::new(),
+            watched_user_ids: BTreeSet::new(),
+            auto_record_user_ids: BTreeSet::new(),
+            quiet_hours: None,
+        }
+    }
+}
+
+/// get_follow_watch_status の戻り値
+#[derive(Serialize, Clone, Default)]
+pub struct FollowWatchStatus {
+    pub polls: u64,
+    /// 連続して失敗した回数
+    pub failures: u32,
+    pub last_polled_at_ms: Option<u64>,
+    pub last_error: Option<String>,
+    /// 前回の取得で配信中だった件数
+    pub live_count: usize,
+}
+
+/// notify://live-started イベントの payload
+#[derive(Serialize, Clone)]
+struct LiveStarted {
+    live_id: String,
+    user_id: Option<String>,
+    user_name: Option<String>,
+    title: Option<String>,
+    /// "follow" / "history"
+    source: &'static str,
+    /// おやすみ時間中のためネイティブ通知を出さなかった
+    quiet: bool,
+    live: Value,
+}
+
+/// 取得した配信中の配信 1 件
+struct FollowedLive {
+    live_id: String,
+    user_id: Option<String>,
+    user_name: Option<String>,
+    title: Option<String>,
+    source: &'static str,
+    live: Value,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// FollowWatcher
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 配信開始の監視サービス（Tauri の管理状態として登録される）
+#[derive(Default)]
+pub struct FollowWatcher {
+    settings: Mutex<FollowWatchSettings>,
+    status: Mutex<FollowWatchStatus>,
+    /// 設定変更で待機中のタスクを起こす
+    changed: Notify,
+}
+
+impl FollowWatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn settings(&self) -> FollowWatchSettings {
+        self.settings
+            .lock()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// 設定を変更して保存し、監視タスクに反映させる
+    fn update_settings(
+        &self,
+        app: &AppHandle,
+        update: impl FnOnce(&mut FollowWatchSettings),
+    ) -> Result<FollowWatchSettings, String> {
+        let mut settings = self.settings.lock().map_err(|e| e.to_string())?;
+        let mut next = settings.clone();
+        update(&mut next);
+        normalize_settings(&mut next)?;
+        save_settings(app, &next)?;
+        *settings = next.clone();
+        self.changed.notify_one();
+        Ok(next)
+    }
+
+    fn update_status(&self, update: impl FnOnce(&mut FollowWatchStatus)) {
+        if let Ok(mut status) = self.status.lock() {
+            update(&mut status);
+        }
+    }
+}
+
+/// 保存済みの設定を読み込んで監視タスクを開始する（setup から呼ぶ）
+pub(crate) fn start(app: &AppHandle) {
+    let settings = load_settings(app).unwrap_or_else(|e| {
+        eprintln!("[follow-watch] {}", e);
+        FollowWatchSettings::default()
+    });
+    if let Ok(mut current) = app.state::<FollowWatcher>().settings.lock() {
+        *current = settings;
+    }
+    tauri::async_runtime::spawn(run_watcher(app.clone()));
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 保存・読み込み
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(SETTINGS_FILE))
+}
+
+fn load_settings(app: &AppHandle) -> Result<FollowWatchSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(FollowWatchSettings::default());
+    }
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let mut settings: FollowWatchSettings = serde_json::from_slice(&bytes)
+        .map_err(|e| format!("Invalid {}: {}", SETTINGS_FILE, e))?;
+    normalize_settings(&mut settings)?;
+    Ok(settings)
+}
+
+fn save_settings(app: &AppHandle, settings: &FollowWatchSettings) -> Result<(), String> {
+    let path = settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// 間隔を範囲内に収め、ユーザー ID を整え、おやすみ時間を検証する
+fn normalize_settings(settings: &mut FollowWatchSettings) -> Result<(), String> {
+    settings.interval_secs = settings
+        .interval_secs
+        .clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS);
+    for ids in [
+        &mut settings.muted_user_ids,
+        &mut settings.watched_user_ids,
+        &mut settings.auto_record_user_ids,
+    ] {
+        *ids = ids
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+    }
+    if let Some(quiet) = &settings.quiet_hours {
+        if i64::from(quiet.start_minute) >= MINUTES_PER_DAY
+            || i64::from(quiet.end_minute) >= MINUTES_PER_DAY
+        {
+            return Err("quiet hours must be within 0..1440 minutes".to_string());
+        }
+        if i64::from(quiet.utc_offset_minutes).abs() > 14 * 60 {
+            return Err(format!("Invalid UTC offset: {}", quiet.utc_offset_minutes));
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// レスポンスの解釈
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// レスポンス直下・data 以下の配信一覧（要素の live オブジェクトを優先）
+fn live_items(response: &Value) -> Vec<&Value> {
+    let list = [response, &response["data"]]
+        .into_iter()
+        .find_map(|root| {
+            LIVE_LIST_FIELDS
+                .iter()
+                .find_map(|field| root[field].as_array())
+        })
+        .or_else(|| response["data"].as_array());
+    list.into_iter()
+        .flatten()
+        .map(|item| if item["live"].is_object() { &item["live"] } else { item })
+        .collect()
+}
+
+/// 次のページの cursor（最終ページでは None）
+fn next_cursor(response: &Value) -> Option<String> {
+    [response, &response["data"], &response["paging"]]
+        .into_iter()
+        .map(|root| &root["next_cursor"])
+        .find_map(value_string)
+}
+
+fn value_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()).filter(|s| !s.is_empty()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+fn first_string(values: &[&Value]) -> Option<String> {
+    values.iter().find_map(|value| value_string(value))
+}
+
+/// 配信中の配信を取り出す。is_live が無い場合は require_live でなければ配信中として扱う
+/// （catalog/follow は配信中のみ、live_history は過去の配信も含む）。
+fn parse_live(item: &Value, source: &'static str, require_live: bool) -> Option<FollowedLive> {
+    let is_live = item["is_live"].as_bool().or_else(|| as_flag(&item["is_live"]));
+    if is_live == Some(false) || (require_live && is_live.is_none()) {
+        return None;
+    }
+    if as_number(&item["ended_at"]).is_some_and(|ended_at| ended_at > 0) {
+        return None;
+    }
+    Some(FollowedLive {
+        live_id: first_string(&[&item["live_id"], &item["id"]])?,
+        user_id: first_string(&[
+            &item["owner"]["user_id"],
+            &item["user"]["user_id"],
+            &item["user_id"],
+        ]),
+        user_name: first_string(&[&item["owner"]["name"], &item["user"]["name"]]),
+        title: first_string(&[&item["title"]]),
+        source,
+        live: item.clone(),
+    })
+}
+
+/// おやすみ時間中かどうか
+fn in_quiet_hours(quiet: &QuietHours, now_secs: u64) -> bool {
+    let minute = (now_secs / 60) as i64 + i64::from(quiet.utc_offset_minutes);
+    let minute = minute.rem_euclid(MINUTES_PER_DAY);
+    let (start, end) = (i64::from(quiet.start_minute), i64::from(quiet.end_minute));
+    if start <= end {
+        (start..end).contains(&minute)
+    } else {
+        minute >= start || minute < end
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 取得
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// catalog/follow を cursor で最後まで（最大 MAX_FOLLOW_PAGES）辿る
+async fn fetch_follow(app: &AppHandle) -> Result<Vec<FollowedLive>, String> {
+    let mut lives = Vec::new();
+    let mut cursor: Option<String> = None;
+    for _ in 0..MAX_FOLLOW_PAGES {
+        let response = get_catalog_follow(app.state(), cursor.clone()).await?;
+        lives.extend(
+            live_items(&response)
+                .into_iter()
+                .filter_map(|item| parse_live(item, "follow", false)),
+        );
+        match next_cursor(&response) {
+            Some(next) if cursor.as_ref() != Some(&next) => cursor = Some(next),
+            _ => break,
+        }
+    }
+    Ok(lives)
+}
+
+async fn fetch_history(app: &AppHandle, user_id: &str) -> Result<Vec<FollowedLive>, String> {
+    let response = get_live_history(app.state(), user_id.to_string(), None).await?;
+    Ok(live_items(&response)
+        .into_iter()
+        .filter_map(|item| parse_live(item, "history", true))
+        .collect())
+}
+
+/// live_history で確認するユーザー（確認対象と自動録画対象）
+fn history_user_ids(settings: &FollowWatchSettings) -> BTreeSet<&str> {
+    settings
+        .watched_user_ids
+        .iter()
+        .chain(&settings.auto_record_user_ids)
+        .map(String::as_str)
+        .collect()
+}
+
+/// 取得元ごとの配信を取得する。失敗した取得元は含めない（前回の結果を引き継ぐ）
+async fn fetch_sources(
+    app: &AppHandle,
+    settings: &FollowWatchSettings,
+) -> (HashMap<String, Vec<FollowedLive>>, Vec<String>) {
+    let mut sources = HashMap::new();
+    let mut errors = Vec::new();
+    match fetch_follow(app).await {
+        Ok(lives) => {
+            sources.insert(FOLLOW_SOURCE.to_string(), lives);
+        }
+        Err(e) => errors.push(format!("catalog/follow: {}", e)),
+    }
+    for user_id in history_user_ids(settings) {
+        match fetch_history(app, user_id).await {
+            Ok(lives) => {
+                sources.insert(format!("user:{}", user_id), lives);
+            }
+            Err(e) => errors.push(format!("live_history {}: {}", user_id, e)),
+        }
+    }
+    (sources, errors)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 監視タスク
+// ─────────────────────────────────────────────────────────────────────────────
+
+async fn run_watcher(app: AppHandle) {
+    let watcher = app.state::<FollowWatcher>();
+    // 取得元ごとの前回の live_id（初回・新しく追加された取得元は通知せず基準にする）
+    let mut known: HashMap<String, HashSet<String>> = HashMap::new();
+
+    loop {
+        let settings = watcher.settings();
+        if !settings.enabled {
+            known.clear();
+            watcher.changed.notified().await;
+            continue;
+        }
+
+        let (sources, errors) = fetch_sources(&app, &settings).await;
+        let mut announced = HashSet::new();
+        for (source, lives) in &sources {
+            if !known.contains_key(source) {
+                continue;
+            }
+            for live in lives {
+                let seen = known.values().any(|ids| ids.contains(&live.live_id));
+                if !seen && announced.insert(live.live_id.clone()) {
+                    announce(&app, &settings, live);
+                    auto_record(&app, &settings, live);
+                }
+            }
+        }
+        for (source, lives) in &sources {
+            known.insert(
+                source.clone(),
+                lives.iter().map(|live| live.live_id.clone()).collect(),
+            );
+        }
+        let history_users = history_user_ids(&settings);
+        known.retain(|source, _| {
+            source == FOLLOW_SOURCE
+                || source
+                    .strip_prefix("user:")
+                    .is_some_and(|id| history_users.contains(id))
+        });
+
+        let live_count = known.values().flatten().collect::<HashSet<_>>().len();
+        watcher.update_status(|status| {
+            status.polls += 1;
+            status.last_polled_at_ms = Some(unix_millis());
+            status.live_count = live_count;
+            if errors.is_empty() {
+                status.failures = 0;
+                status.last_error = None;
+            } else {
+                status.failures += 1;
+                status.last_error = errors.first().cloned();
+            }
+        });
+        for error in &errors {
+            eprintln!("[follow-watch] {}", error);
+        }
+
+        tokio::select! {
+            _ = watcher.changed.notified() => {}
+            _ = tokio::time::sleep(Duration::from_secs(settings.interval_secs)) => {}
+        }
+    }
+}
+
+/// 自動録画対象のユーザーなら録画キューに追加する（ミュートとは無関係）
+fn auto_record(app: &AppHandle, settings: &FollowWatchSettings, live: &FollowedLive) {
+    let Some(user_id) = live
+        .user_id
+        .as_ref()
+        .filter(|id| settings.auto_record_user_ids.contains(*id))
+    else {
+        return;
+    };
+    recorder::enqueue(
+        app,
+        RecordingRequest {
+            live_id: live.live_id.clone(),
+            user_id: Some(user_id.clone()),
+            user_name: live.user_name.clone(),
+            title: live.title.clone(),
+            trigger: "auto",
+        },
+    );
+}
+
+/// 配信開始を通知する（ミュート中のユーザーは何もしない）
+fn announce(app: &AppHandle, settings: &FollowWatchSettings, live: &FollowedLive) {
+    if live
+        .user_id
+        .as_ref()
+        .is_some_and(|id| settings.muted_user_ids.contains(id))
+    {
+        return;
+    }
+    let now_secs = unix_millis() / 1000;
+    let quiet = settings
+        .quiet_hours
+        .as_ref()
+        .is_some_and(|quiet| in_quiet_hours(quiet, now_secs));
+
+    if settings.native_notifications && !quiet {
+        let name = live.user_name.as_deref().unwrap_or("フォロー中のユーザー");
+        let result = app
+            .notification()
+            .builder()
+            .title(format!("{} が配信を開始しました", name))
+            .body(live.title.clone().unwrap_or_default())
+            .show();
+        if let Err(e) = result {
+            eprintln!("[follow-watch] notification failed: {}", e);
+        }
+    }
+
+    let payload = LiveStarted {
+        live_id: live.live_id.clone(),
+        user_id: live.user_id.clone(),
+        user_name: live.user_name.clone(),
+        title: live.title.clone(),
+        source: live.source,
+        quiet,
+        live: live.live.clone(),
+    };
+    if let Err(e) = app.emit("notify://live-started", payload) {
+        eprintln!("Failed to emit notify://live-started: {}", e);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 配信開始通知の設定を取得する
+#[tauri::command]
+pub async fn get_follow_watch_settings(
+    watcher: tauri::State<'_, FollowWatcher>,
+) -> Result<FollowWatchSettings, String> {
+    Ok(watcher.settings())
+}
+
+/// 配信開始通知の設定を保存する（間隔は範囲内に丸められる）。すぐに再取得する。
+#[tauri::command]
+pub async fn set_follow_watch_settings(
+    app: AppHandle,
+    watcher: tauri::State<'_, FollowWatcher>,
+    settings: FollowWatchSettings,
+) -> Result<FollowWatchSettings, String> {
+    watcher.update_settings(&app, |current| *current = settings)
+}
+
+/// ユーザーの配信開始通知をミュート・解除する
+#[tauri::command]
+pub async fn set_follow_user_muted(
+    app: AppHandle,
+    watcher: tauri::State<'_, FollowWatcher>,
+    user_id: String,
+    muted: bool,
+) -> Result<FollowWatchSettings, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("user_id is empty".to_string());
+    }
+    watcher.update_settings(&app, |settings| {
+        if muted {
+            settings.muted_user_ids.insert(user_id);
+        } else {
+            settings.muted_user_ids.remove(&user_id);
+        }
+    })
+}
+
+/// ユーザーの自動録画を設定する（配信開始を検出すると録画キューに追加される）
+#[tauri::command]
+pub async fn set_follow_user_auto_record(
+    app: AppHandle,
+    watcher: tauri::State<'_, FollowWatcher>,
+    user_id: String,
+    enabled: bool,
+) -> Result<FollowWatchSettings, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("user_id is empty".to_string());
+    }
+    watcher.update_settings(&app, |settings| {
+        if enabled {
+            settings.auto_record_user_ids.insert(user_id);
+        } else {
+            settings.auto_record_user_ids.remove(&user_id);
+        }
+    })
+}
+
+/// 監視タスクの状態を取得する
+#[tauri::command]
+pub async fn get_follow_watch_status(
+    watcher: tauri::State<'_, FollowWatcher>,
+) -> Result<FollowWatchStatus, String> {
+    watcher
+        .status
+        .lock()
+        .map(|status| status.clone())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn quiet(start_minute: u16, end_minute: u16, utc_offset_minutes: i32) -> QuietHours {
+        QuietHours {
+            start_minute,
+            end_minute,
+            utc_offset_minutes,
+        }
+    }
+
+    /// UTC の hour:minute の unix 秒（1970-01-02）
+    fn at(hour: u64, minute: u64) -> u64 {
+        86_400 + hour * 3600 + minute * 60
+    }
+
+    #[test]
+    fn default_settings_are_opt_in() {
+        let settings = FollowWatchSettings::default();
+        assert!(!settings.enabled);
+        assert!(!settings.native_notifications);
+        // 保存済みの設定に項目が無い場合も既定値（無効）になる
+        let parsed: FollowWatchSettings = serde_json::from_str("{}").unwrap();
+        assert!(!parsed.enabled);
+        assert!(!parsed.native_notifications);
+    }
+
+    #[test]
+    fn quiet_hours_within_a_day() {
+        let q = quiet(9 * 60, 17 * 60, 0);
+        assert!(!in_quiet_hours(&q, at(8, 59)));
+        assert!(in_quiet_hours(&q, at(9, 0)));
+        assert!(in_quiet_hours(&q, at(16, 59)));
+        // 終了時刻は含まない
+        assert!(!in_quiet_hours(&q, at(17, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_across_midnight() {
+        let q = quiet(23 * 60, 7 * 60, 0);
+        assert!(in_quiet_hours(&q, at(23, 0)));
+        assert!(in_quiet_hours(&q, at(0, 0)));
+        assert!(in_quiet_hours(&q, at(6, 59)));
+        assert!(!in_quiet_hours(&q, at(7, 0)));
+        assert!(!in_quiet_hours(&q, at(22, 59)));
+        assert!(!in_quiet_hours(&q, at(12, 0)));
+    }
+
+    #[test]
+    fn quiet_hours_use_the_utc_offset() {
+        // JST（+9:00）の 23:00〜7:00 は UTC の 14:00〜22:00
+        let q = quiet(23 * 60, 7 * 60, 9 * 60);
+        assert!(in_quiet_hours(&q, at(14, 0)));
+        assert!(in_quiet_hours(&q, at(21, 59)));
+        assert!(!in_quiet_hours(&q, at(22, 0)));
+        assert!(!in_quiet_hours(&q, at(13, 59)));
+        // 負のオフセットで前日に戻る場合
+        let q = quiet(20 * 60, 22 * 60, -5 * 60);
+        assert!(in_quiet_hours(&q, at(1, 0)));
+        assert!(!in_quiet_hours(&q, at(3, 0)));
+    }
+
+    #[test]
+    fn empty_quiet_window_never_matches() {
+        let q = quiet(600, 600, 0);
+        assert!(!in_quiet_hours(&q, at(10, 0)));
+        assert!(!in_quiet_hours(&q, at(0, 0)));
+    }
+
+    #[test]
+    fn parse_live_reads_ids_and_owner() {
+        let item = json!({
+            "live_id": "abc",
+            "title": " タイトル ",
+            "is_live": 1,
+            "owner": { "user_id": 42, "name": "配信者" },
+        });
+        let live = parse_live(&item, "follow", false).expect("live");
+        assert_eq!(live.live_id, "abc");
+        assert_eq!(live.user_id.as_deref(), Some("42"));
+        assert_eq!(live.user_name.as_deref(), Some("配信者"));
+        assert_eq!(live.title.as_deref(), Some("タイトル"));
+        assert_eq!(live.source, "follow");
+
+        let fallback = json!({ "id": 7, "user": { "user_id": "9", "name": "u" } });
+        let live = parse_live(&fallback, "follow", false).expect("live");
+        assert_eq!(live.live_id, "7");
+        assert_eq!(live.user_id.as_deref(), Some("9"));
+    }
+
+    #[test]
+    fn parse_live_skips_ended_and_unknown_state() {
+        let follow = |item: Value| parse_live(&item, "follow", false).is_some();
+        let history = |item: Value| parse_live(&item, "history", true).is_some();
+        assert!(!follow(json!({ "live_id": "a", "is_live": false })));
+        assert!(!follow(json!({ "live_id": "a", "is_live": "0" })));
+        assert!(!follow(json!({ "live_id": "a", "ended_at": 1_700_000_000 })));
+        assert!(!follow(json!({ "live_id": " " })));
+        // catalog/follow は is_live が無くても配信中、live_history では配信中とみなさない
+        assert!(follow(json!({ "live_id": "a" })));
+        assert!(!history(json!({ "live_id": "a" })));
+        assert!(history(json!({ "live_id": "a", "is_live": true })));
+    }
+
+    #[test]
+    fn next_cursor_looks_in_each_root() {
+        assert_eq!(next_cursor(&json!({ "next_cursor": "c1" })).as_deref(), Some("c1"));
+        assert_eq!(
+            next_cursor(&json!({ "data": { "next_cursor": 20 } })).as_deref(),
+            Some("20")
+        );
+        assert_eq!(
+            next_cursor(&json!({ "paging": { "next_cursor": "c3" } })).as_deref(),
+            Some("c3")
+        );
+        // 空文字列・null は最終ページ
+        assert_eq!(next_cursor(&json!({ "next_cursor": "" })), None);
+        assert_eq!(next_cursor(&json!({ "next_cursor": null })), None);
+        assert_eq!(next_cursor(&json!({ "list": [] })), None);
+    }
+
+    #[test]
+    fn live_items_prefer_nested_live_objects() {
+        let lives = json!([{ "live": { "live_id": "x" } }, { "live_id": "y" }]);
+        let response = json!({ "data": { "lives": lives } });
+        let ids: Vec<_> = live_items(&response)
+            .into_iter()
+            .filter_map(|item| item["live_id"].as_str())
+            .collect();
+        assert_eq!(ids, ["x", "y"]);
+    }
+}
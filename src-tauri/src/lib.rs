@@ -1,10 +1,29 @@
+mod comment_queue;
+mod follow_watcher;
+mod live_poller;
 mod mirrativ;
+mod moderation;
+mod mpv_log;
 mod mpv_player;
+mod mpv_profile;
+mod playback;
+mod recorder;
+mod viewing;
+use comment_queue::CommentQueueManager;
+use follow_watcher::FollowWatcher;
 use mirrativ::client::broadcast::BroadcastManager;
 use mirrativ::client::llstream_relay::LlstreamRelayManager;
+use live_poller::LivePollerManager;
 use mirrativ::MirrativClient;
+use moderation::ModerationManager;
 use mpv_player::MpvPlayerManager;
-use tauri::{Emitter, Manager, WindowEvent};
+use playback::PlaybackManager;
+use recorder::RecordingManager;
+use tauri::{Emitter, Manager, RunEvent, WindowEvent};
+use viewing::ViewingSessionManager;
+
+#[cfg(feature = "fuzzing")]
+pub use mirrativ::client::llstream_relay::fuzzing;
 
 /// フロントエンドからのログをターミナルに出力する
 #[tauri::command]
@@ -23,14 +42,33 @@ pub fn run() {
     let mpv_player = MpvPlayerManager::new();
     let broadcast = BroadcastManager::new();
     let llstream_relay = LlstreamRelayManager::new();
+    let playback = PlaybackManager::new();
+    let live_poller = LivePollerManager::new();
+    let viewing = ViewingSessionManager::new();
+    let follow_watcher = FollowWatcher::new();
+    let recorder = RecordingManager::new();
+    let comment_queue = CommentQueueManager::new();
+    let moderation = ModerationManager::new();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .manage(client)
         .manage(mpv_player)
         .manage(broadcast)
         .manage(llstream_relay)
+        .manage(playback)
+        .manage(live_poller)
+        .manage(viewing)
+        .manage(follow_watcher)
+        .manage(recorder)
+        .manage(comment_queue)
+        .manage(moderation)
         .setup(|app| {
+            recorder::start(app.handle());
+            follow_watcher::start(app.handle());
+            comment_queue::start(app.handle());
+            moderation::start(app.handle());
             let app_handle = app.handle().clone();
             tauri::async_runtime::spawn(async move {
                 let client = app_handle.state::<MirrativClient>();
@@ -44,20 +82,29 @@ pub fn run() {
             Ok(())
         })
         .on_window_event(|window, event| match window.label() {
-            "player" => {
+            label if label == "player" || label.starts_with("player-") => {
                 if let WindowEvent::CloseRequested { .. } = event {
                     let app = window.app_handle().clone();
+                    let player_id = mpv_player::player_id_from_window_label(label);
                     tauri::async_runtime::spawn(async move {
                         let manager = app.state::<MpvPlayerManager>();
                         let _ = mpv_player::stop_mpv(
                             app.clone(),
                             manager,
                             Some("window-close".to_string()),
+                            player_id,
                         )
                         .await;
                     });
                 }
             }
+            "main" => {
+                // 最小化（Windows では 0x0 へのリサイズ）の間は live_polling を一時停止する
+                if let WindowEvent::Resized(size) = event {
+                    let visible = size.width > 0 && size.height > 0;
+                    window.app_handle().state::<LivePollerManager>().set_visible(visible);
+                }
+            }
             "twitter-auth" => {
                 if let WindowEvent::Destroyed = event {
                     let _ = window.app_handle().emit("auth://login-cancelled", ());
@@ -168,20 +215,99 @@ pub fn run() {
             // LLStream video relay (debug)
             mirrativ::client::llstream_relay::start_llstream_video_ts_relay,
             mirrativ::client::llstream_relay::start_llstream_av_ts_relay,
+            mirrativ::client::llstream_relay::start_llstream_audio_relay,
             mirrativ::client::llstream_relay::start_llstream_video_pipe_relay,
             mirrativ::client::llstream_relay::stop_llstream_relay,
             mirrativ::client::llstream_relay::get_llstream_relay_url,
+            mirrativ::client::llstream_relay::get_llstream_relay_stats,
+            mirrativ::client::llstream_relay::set_llstream_latency_target,
+            mirrativ::client::llstream_relay::save_llstream_relay_snapshot,
+            mirrativ::client::llstream_relay::save_llstream_relay_clip,
+            // 再生オーケストレーター（HLS / llstream 自動切り替え）
+            playback::start_playback,
+            playback::stop_playback,
+            playback::get_playback_state,
+            // ライブポーリング（バックグラウンド）
+            live_poller::start_live_polling,
+            live_poller::stop_live_polling,
+            live_poller::get_live_polling_status,
+            live_poller::set_live_polling_visible,
+            // 視聴セッション（入室〜退室）
+            viewing::start_viewing,
+            viewing::stop_viewing,
+            viewing::get_viewing_session,
+            // フォロー中の配信開始通知
+            follow_watcher::get_follow_watch_settings,
+            follow_watcher::set_follow_watch_settings,
+            follow_watcher::set_follow_user_muted,
+            follow_watcher::set_follow_user_auto_record,
+            follow_watcher::get_follow_watch_status,
+            // 録画キュー
+            recorder::enqueue_recording,
+            recorder::stop_recording,
+            recorder::list_recordings,
+            recorder::clear_recording_history,
+            // コメント送信キュー
+            comment_queue::enqueue_comment,
+            comment_queue::cancel_comment,
+            comment_queue::get_comment_queue,
+            comment_queue::clear_comment_queue,
+            // コメントのモデレーションルール
+            moderation::get_moderation_rules,
+            moderation::set_moderation_rules,
+            moderation::upsert_moderation_keyword,
+            moderation::remove_moderation_keyword,
+            moderation::set_moderation_user_blocked,
+            moderation::set_moderation_user_allowed,
+            moderation::test_moderation,
             // MPV Player
             mpv_player::create_player_window,
             mpv_player::start_mpv,
             mpv_player::stop_mpv,
             mpv_player::mpv_command,
             mpv_player::get_player_info,
+            mpv_player::list_players,
+            mpv_player::set_audio_focus,
+            mpv_player::mpv_play,
+            mpv_player::mpv_pause,
+            mpv_player::mpv_resume,
+            mpv_player::mpv_set_volume,
+            mpv_player::mpv_set_mute,
+            mpv_player::mpv_set_rotation,
+            mpv_player::mpv_set_speed,
+            mpv_player::mpv_seek,
+            mpv_player::mpv_screenshot,
+            mpv_player::mpv_get_tracks,
+            mpv_player::mpv_select_track,
+            mpv_player::set_mpv_render_size,
+            mpv_player::get_mpv_frame,
             mpv_player::close_player_window,
             mpv_player::position_mpv_window,
+            // 再生プロファイル
+            mpv_profile::list_playback_profiles,
+            mpv_profile::save_playback_profile,
+            mpv_profile::delete_playback_profile,
+            mpv_profile::get_live_playback_profile,
+            mpv_profile::set_live_playback_profile,
+            // mpv ログ
+            mpv_log::get_mpv_log_settings,
+            mpv_log::set_mpv_log_level,
             // フロントエンドログ
             frontend_log,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // 終了前に視聴中の配信から退室し、録画中のファイルを閉じる
+            if let RunEvent::Exit = event {
+                let viewing = app.state::<ViewingSessionManager>();
+                let _ = tauri::async_runtime::block_on(tokio::time::timeout(
+                    viewing::EXIT_TEARDOWN_TIMEOUT,
+                    async {
+                        viewing.end(app, None, "app-exit").await;
+                        recorder::stop_all(app, "app-exit").await;
+                    },
+                ));
+            }
+        });
 }
@@ -0,0 +1,538 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// live_poller.rs
+//
+// 入室中の配信ごとに live_polling を定期実行するバックグラウンドサービス。
+//
+// 主な責務:
+//   - サーバーが指定する間隔での live_polling 呼び出し（live_user_key を引き継ぐ）
+//   - 視聴者数・オンライン数などの変化の検出と live-polling://changes での通知
+//   - 配信終了の検出（live-polling://ended）とポーリングの自動停止
+//   - ウィンドウが非表示の間はポーリングを一時停止する
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use crate::mirrativ::client::live::live_polling;
+
+/// サーバーが間隔を返さない場合のポーリング間隔（フロントエンドの従来の間隔）
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+/// サーバー指定の間隔を受け入れる範囲
+const MIN_INTERVAL: Duration = Duration::from_secs(5);
+const MAX_INTERVAL: Duration = Duration::from_secs(120);
+/// 間隔として解釈するレスポンスのフィールド（秒）
+const INTERVAL_FIELDS: [&str; 3] = ["polling_interval", "polling_interval_sec", "interval"];
+/// 変化を検出するカウンター
+const COUNT_FIELDS: [&str; 5] = [
+    "online_user_num",
+    "total_viewer_num",
+    "comment_num",
+    "star_num",
+    "gift_num",
+];
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 状態とイベント
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// フロントエンドに返すポーリング状態
+#[derive(Serialize, Clone)]
+pub struct LivePollStatus {
+    pub live_id: String,
+    /// 次回の live_polling に渡す live_user_key（レスポンスで更新される）
+    pub live_user_key: Option<String>,
+    pub interval_ms: u64,
+    /// ウィンドウが非表示のため一時停止中
+    pub paused: bool,
+    pub polls: u64,
+    /// 連続した失敗の回数
+    pub failures: u32,
+    pub last_polled_at_ms: Option<u64>,
+    pub is_live: Option<bool>,
+    /// 最新のカウンター（online_user_num など）
+    pub counts: BTreeMap<String, i64>,
+}
+
+/// live-polling://response イベントの payload（フロントエンドが独自のタイマーを持たずに済むよう、
+/// レスポンスをそのまま渡す）
+#[derive(Serialize, Clone)]
+struct PollResponse {
+    live_id: String,
+    response: Value,
+}
+
+/// カウンター 1 つの変化
+#[derive(Serialize, Clone)]
+struct CountChange {
+    field: String,
+    previous: Option<i64>,
+    current: i64,
+}
+
+/// live-polling://changes イベントの payload
+#[derive(Serialize, Clone)]
+struct PollChanges {
+    live_id: String,
+    changes: Vec<CountChange>,
+    counts: BTreeMap<String, i64>,
+}
+
+/// live-polling://ended イベントの payload
+#[derive(Serialize, Clone)]
+struct PollEnded {
+    live_id: String,
+    ended_at: Option<i64>,
+}
+
+/// live-polling://error イベントの payload
+#[derive(Serialize, Clone)]
+struct PollError {
+    live_id: String,
+    error: String,
+    failures: u32,
+    retry_in_ms: u64,
+}
+
+/// live-polling://paused イベントの payload
+#[derive(Serialize, Clone)]
+struct PollPaused {
+    live_id: String,
+    paused: bool,
+}
+
+fn emit<T: Serialize + Clone>(app: &AppHandle, event: &str, payload: T) {
+    if let Err(err) = app.emit(event, payload) {
+        eprintln!("Failed to emit {}: {}", event, err);
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// レスポンスの解釈
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// live_polling のレスポンスから取り出した値
+struct PollSnapshot {
+    live_user_key: Option<String>,
+    interval: Option<Duration>,
+    is_live: Option<bool>,
+    ended_at: Option<i64>,
+    counts: BTreeMap<String, i64>,
+}
+
+impl PollSnapshot {
+    fn from_response(response: &Value) -> Self {
+        let counts = COUNT_FIELDS
+            .iter()
+            .filter_map(|field| Some((field.to_string(), as_number(pick(response, field)?)?)))
+            .collect();
+        let interval = INTERVAL_FIELDS
+            .iter()
+            .find_map(|field| as_number(pick(response, field)?))
+            .filter(|secs| *secs > 0)
+            .map(|secs| Duration::from_secs(secs as u64).clamp(MIN_INTERVAL, MAX_INTERVAL));
+
+        Self {
+            live_user_key: pick(response, "live_user_key")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_string),
+            interval,
+            is_live: pick(response, "is_live").and_then(as_flag),
+            ended_at: pick(response, "ended_at")
+                .and_then(as_number)
+                .filter(|ended_at| *ended_at > 0),
+            counts,
+        }
+    }
+
+    fn ended(&self) -> bool {
+        self.is_live == Some(false) || self.ended_at.is_some()
+    }
+}
+
+/// live_polling・get_live_status のレスポンスが配信終了を示しているか
+pub(crate) fn live_ended(response: &Value) -> bool {
+    PollSnapshot::from_response(response).ended()
+}
+
+/// live 以下・直下の順に null でないフィールドを探す（フロントエンドの watch-utils.ts と同じ優先順）
+fn pick<'a>(response: &'a Value, key: &str) -> Option<&'a Value> {
+    [&response["live"][key], &response[key]]
+        .into_iter()
+        .find(|value| !value.is_null())
+}
+
+/// 数値・数値文字列を i64 として読む
+pub(crate) fn as_number(value: &Value) -> Option<i64> {
+    value
+        .as_i64()
+        .or_else(|| value.as_f64().map(|v| v as i64))
+        .or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// bool・0/1・"0"/"1" をフラグとして読む
+pub(crate) fn as_flag(value: &Value) -> Option<bool> {
+    value.as_bool().or_else(|| as_number(value).map(|v| v != 0))
+}
+
+/// 前回から変化したカウンターを返す
+fn diff_counts(
+    previous: Option<&BTreeMap<String, i64>>,
+    current: &BTreeMap<String, i64>,
+) -> Vec<CountChange> {
+    current
+        .iter()
+        .filter_map(|(field, &value)| {
+            let before = previous.and_then(|counts| counts.get(field).copied());
+            (before != Some(value)).then(|| CountChange {
+                field: field.clone(),
+                previous: before,
+                current: value,
+            })
+        })
+        .collect()
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// LivePollerManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 配信ごとのポーリングタスク
+struct PollerHandle {
+    status: Arc<RwLock<LivePollStatus>>,
+    shutdown_tx: watch::Sender<bool>,
+    task: JoinHandle<()>,
+}
+
+/// live_polling の監視サービス（Tauri の管理状態として登録される）
+pub struct LivePollerManager {
+    pollers: Arc<RwLock<HashMap<String, PollerHandle>>>,
+    /// メインウィンドウの表示状態（false の間は全ポーリングを一時停止）
+    visible: watch::Sender<bool>,
+}
+
+impl Default for LivePollerManager {
+    fn default() -> Self {
+        Self {
+            pollers: Arc::default(),
+            visible: watch::channel(true).0,
+        }
+    }
+}
+
+impl LivePollerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ウィンドウの表示状態を更新する（変化しない場合は何もしない）
+    pub(crate) fn set_visible(&self, visible: bool) {
+        self.visible.send_if_modified(|current| {
+            let changed = *current != visible;
+            *current = visible;
+            changed
+        });
+    }
+
+    pub(crate) async fn is_polling(&self, live_id: &str) -> bool {
+        self.pollers.read().await.contains_key(live_id)
+    }
+
+    pub(crate) async fn stop(&self, live_id: &str) -> bool {
+        let Some(poller) = self.pollers.write().await.remove(live_id) else {
+            return false;
+        };
+        poller.shutdown().await;
+        true
+    }
+}
+
+impl PollerHandle {
+    /// 停止を要求し、終了しない場合は 2 秒で打ち切る
+    async fn shutdown(self) {
+        let _ = self.shutdown_tx.send(true);
+        let mut task = self.task;
+        if tokio::time::timeout(Duration::from_secs(2), &mut task)
+            .await
+            .is_err()
+        {
+            task.abort();
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ポーリングタスク
+// ─────────────────────────────────────────────────────────────────────────────
+
+async fn run_poller(
+    app: AppHandle,
+    live_id: String,
+    status: Arc<RwLock<LivePollStatus>>,
+    mut visible_rx: watch::Receiver<bool>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let mut interval = DEFAULT_INTERVAL;
+    let mut previous: Option<BTreeMap<String, i64>> = None;
+
+    loop {
+        if !wait_until_visible(&app, &live_id, &status, &mut visible_rx, &mut shutdown_rx).await {
+            return;
+        }
+
+        let live_user_key = status.read().await.live_user_key.clone();
+        let result =
+            live_polling(app.state(), live_id.clone(), live_user_key, None, None, None).await;
+        let delay = match result {
+            Ok(response) => {
+                let snapshot = PollSnapshot::from_response(&response);
+                if let Some(server_interval) = snapshot.interval {
+                    interval = server_interval;
+                }
+                {
+                    let mut s = status.write().await;
+                    if snapshot.live_user_key.is_some() {
+                        s.live_user_key = snapshot.live_user_key.clone();
+                    }
+                    s.interval_ms = interval.as_millis() as u64;
+                    s.polls += 1;
+                    s.failures = 0;
+                    s.last_polled_at_ms = Some(unix_millis());
+                    s.is_live = snapshot.is_live.or(s.is_live);
+                    s.counts = snapshot.counts.clone();
+                }
+
+                emit(
+                    &app,
+                    "live-polling://response",
+                    PollResponse {
+                        live_id: live_id.clone(),
+                        response,
+                    },
+                );
+                let changes = diff_counts(previous.as_ref(), &snapshot.counts);
+                if !changes.is_empty() {
+                    emit(
+                        &app,
+                        "live-polling://changes",
+                        PollChanges {
+                            live_id: live_id.clone(),
+                            changes,
+                            counts: snapshot.counts.clone(),
+                        },
+                    );
+                }
+                previous = Some(snapshot.counts.clone());
+
+                if snapshot.ended() {
+                    eprintln!("[live-polling] {} ended", live_id);
+                    emit(
+                        &app,
+                        "live-polling://ended",
+                        PollEnded {
+                            live_id: live_id.clone(),
+                            ended_at: snapshot.ended_at,
+                        },
+                    );
+                    // 自分自身を一覧から外す（同じ live_id で再開された後のタスクは残す）
+                    let manager = app.state::<LivePollerManager>();
+                    let mut pollers = manager.pollers.write().await;
+                    if pollers
+                        .get(&live_id)
+                        .is_some_and(|poller| Arc::ptr_eq(&poller.status, &status))
+                    {
+                        pollers.remove(&live_id);
+                    }
+                    return;
+                }
+                interval
+            }
+            Err(e) => {
+                let failures = {
+                    let mut s = status.write().await;
+                    s.failures += 1;
+                    s.failures
+                };
+                // 連続失敗時は間隔を倍々に延ばす（上限 MAX_INTERVAL）
+                let delay = interval
+                    .saturating_mul(1 << failures.min(4))
+                    .min(MAX_INTERVAL);
+                eprintln!("[live-polling] {} failed ({}): {}", live_id, failures, e);
+                emit(
+                    &app,
+                    "live-polling://error",
+                    PollError {
+                        live_id: live_id.clone(),
+                        error: e,
+                        failures,
+                        retry_in_ms: delay.as_millis() as u64,
+                    },
+                );
+                delay
+            }
+        };
+
+        if wait_or_shutdown(&mut shutdown_rx, delay).await {
+            return;
+        }
+    }
+}
+
+/// ウィンドウが表示されるまで待つ。停止要求を受けた場合は false を返す
+async fn wait_until_visible(
+    app: &AppHandle,
+    live_id: &str,
+    status: &RwLock<LivePollStatus>,
+    visible_rx: &mut watch::Receiver<bool>,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) -> bool {
+    if *visible_rx.borrow_and_update() {
+        // 送信側が無くなっている場合も停止要求として扱う
+        return !*shutdown_rx.borrow() && shutdown_rx.has_changed().is_ok();
+    }
+
+    set_paused(app, live_id, status, true).await;
+    loop {
+        tokio::select! {
+            changed = shutdown_rx.changed() => {
+                if changed.is_err() || *shutdown_rx.borrow() {
+                    return false;
+                }
+            }
+            changed = visible_rx.changed() => {
+                // 送信側が無くなった場合は表示中として扱う
+                if changed.is_err() || *visible_rx.borrow_and_update() {
+                    break;
+                }
+            }
+        }
+    }
+    set_paused(app, live_id, status, false).await;
+    true
+}
+
+async fn set_paused(app: &AppHandle, live_id: &str, status: &RwLock<LivePollStatus>, paused: bool) {
+    status.write().await.paused = paused;
+    emit(
+        app,
+        "live-polling://paused",
+        PollPaused {
+            live_id: live_id.to_string(),
+            paused,
+        },
+    );
+}
+
+/// 指定時間待機する。停止要求を受けた場合（送信側が無くなった場合を含む）は true を返す
+async fn wait_or_shutdown(shutdown_rx: &mut watch::Receiver<bool>, delay: Duration) -> bool {
+    tokio::select! {
+        changed = shutdown_rx.changed() => changed.is_err() || *shutdown_rx.borrow(),
+        _ = tokio::time::sleep(delay) => false,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// live_id の live_polling をバックグラウンドで開始する（実行中の場合はやり直す）。
+/// 結果は live-polling://response / changes / ended / error イベントで通知される。
+#[tauri::command]
+pub async fn start_live_polling(
+    app: AppHandle,
+    manager: tauri::State<'_, LivePollerManager>,
+    live_id: String,
+    live_user_key: Option<String>,
+) -> Result<LivePollStatus, String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+    let status = LivePollStatus {
+        live_id: live_id.clone(),
+        live_user_key: live_user_key.filter(|key| !key.trim().is_empty()),
+        interval_ms: DEFAULT_INTERVAL.as_millis() as u64,
+        paused: false,
+        polls: 0,
+        failures: 0,
+        last_polled_at_ms: None,
+        is_live: None,
+        counts: BTreeMap::new(),
+    };
+    let shared = Arc::new(RwLock::new(status.clone()));
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+
+    // 入れ替えは 1 回の書き込みロック内で行う
+    // （同じ live_id の同時開始で古いタスクが取り残されないように）
+    let previous = {
+        let mut pollers = manager.pollers.write().await;
+        let task = tokio::spawn(run_poller(
+            app.clone(),
+            live_id.clone(),
+            shared.clone(),
+            manager.visible.subscribe(),
+            shutdown_rx,
+        ));
+        pollers.insert(
+            live_id,
+            PollerHandle {
+                status: shared,
+                shutdown_tx,
+                task,
+            },
+        )
+    };
+    if let Some(previous) = previous {
+        previous.shutdown().await;
+    }
+    Ok(status)
+}
+
+/// live_id のポーリングを停止する。ポーリングしていなかった場合は false を返す
+#[tauri::command]
+pub async fn stop_live_polling(
+    manager: tauri::State<'_, LivePollerManager>,
+    live_id: String,
+) -> Result<bool, String> {
+    Ok(manager.stop(live_id.trim()).await)
+}
+
+/// 実行中のポーリングの状態を取得する
+#[tauri::command]
+pub async fn get_live_polling_status(
+    manager: tauri::State<'_, LivePollerManager>,
+) -> Result<Vec<LivePollStatus>, String> {
+    let pollers = manager.pollers.read().await;
+    let mut statuses = Vec::with_capacity(pollers.len());
+    for poller in pollers.values() {
+        statuses.push(poller.status.read().await.clone());
+    }
+    statuses.sort_by(|a, b| a.live_id.cmp(&b.live_id));
+    Ok(statuses)
+}
+
+/// ページの表示状態を通知する（document.visibilitychange から呼ぶ）。
+/// 非表示の間は全ポーリングを一時停止し、表示に戻ると即座に再開する。
+#[tauri::command]
+pub async fn set_live_polling_visible(
+    manager: tauri::State<'_, LivePollerManager>,
+    visible: bool,
+) -> Result<(), String> {
+    manager.set_visible(visible);
+    Ok(())
+}
@@ -1,14 +1,17 @@
 use futures_util::{SinkExt, StreamExt};
 use serde_json::Value;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
-use tokio::sync::{mpsc, watch, RwLock};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{broadcast, mpsc, watch, RwLock};
 use tokio::task::JoinHandle;
 use tokio::time::{sleep, Duration, MissedTickBehavior};
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::tungstenite::http::HeaderValue;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
+use crate::comment_queue::CommentQueueManager;
+use crate::moderation::ModerationManager;
+
 /// WebSocketストリームの型エイリアス
 type WsStream =
     tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
@@ -23,6 +26,8 @@ pub struct BroadcastManager {
     shutdown_tx: Arc<RwLock<Option<watch::Sender<bool>>>>,
     task_handle: Arc<RwLock<Option<JoinHandle<()>>>>,
     outgoing_tx: Arc<RwLock<Option<mpsc::Sender<String>>>>,
+    /// 視聴用の接続で受信したメッセージをバックエンド内に配る（コメント送信キューの確認など）
+    messages: broadcast::Sender<Value>,
 }
 
 impl BroadcastManager {
@@ -31,9 +36,15 @@ impl BroadcastManager {
             shutdown_tx: Arc::new(RwLock::new(None)),
             task_handle: Arc::new(RwLock::new(None)),
             outgoing_tx: Arc::new(RwLock::new(None)),
+            messages: broadcast::channel(256).0,
         }
     }
 
+    /// 視聴用の接続で受信したメッセージを購読する（接続し直しても購読は続く）
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<Value> {
+        self.messages.subscribe()
+    }
+
     pub async fn disconnect(&self) {
         *self.outgoing_tx.write().await = None;
         if let Some(tx) = self.shutdown_tx.write().await.take() {
@@ -56,8 +67,18 @@ pub async fn connect_broadcast(
     // 任意: Cookie/UA を外から渡せるようにしておく（未指定ならブラウザっぽい固定値）
     cookie: Option<String>,
     user_agent: Option<String>,
+    // 任意: 接続先の配信（送信キューのコメントをこの配信の echo で確認する）
+    live_id: Option<String>,
 ) -> Result<(), String> {
     state.disconnect().await;
+    // 別の配信に接続し直すことがあるので、フラッド検出は前の接続の履歴を引き継がない
+    if let Some(moderation) = app.try_state::<ModerationManager>() {
+        moderation.reset_flood_history();
+    }
+    if let Some(comment_queue) = app.try_state::<CommentQueueManager>() {
+        let live_id = live_id.as_deref().map(str::trim).filter(|id| !id.is_empty());
+        comment_queue.set_echo_live(live_id);
+    }
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let (outgoing_tx, outgoing_rx) = mpsc::channel::<String>(64);
@@ -66,7 +87,7 @@ pub async fn connect_broadcast(
     *state.outgoing_tx.write().await = Some(outgoing_tx);
 
     let handle = tokio::spawn(ws_loop(
-        app,
+        BroadcastOutput::App(app),
         bcsvr_key,
         broadcast_host,
         cookie,
@@ -79,12 +100,36 @@ pub async fn connect_broadcast(
     Ok(())
 }
 
+/// BroadcastManager とは別の接続で Broadcast WS を購読する（フロントエンドには emit しない）。
+/// 受信したメッセージは messages に送られ、shutdown_rx に true を送ると切断する。
+pub(crate) fn spawn_broadcast_listener(
+    bcsvr_key: String,
+    broadcast_host: String,
+    messages: mpsc::UnboundedSender<Value>,
+    shutdown_rx: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    // 送信はしないので送信側はすぐに閉じる（受信側の recv は None で無効になる）
+    let (_outgoing_tx, outgoing_rx) = mpsc::channel::<String>(1);
+    tokio::spawn(ws_loop(
+        BroadcastOutput::Channel(messages),
+        bcsvr_key,
+        broadcast_host,
+        None,
+        None,
+        shutdown_rx,
+        outgoing_rx,
+    ))
+}
+
 #[tauri::command]
 pub async fn disconnect_broadcast(
     app: AppHandle,
     state: tauri::State<'_, BroadcastManager>,
 ) -> Result<(), String> {
     state.disconnect().await;
+    if let Some(comment_queue) = app.try_state::<CommentQueueManager>() {
+        comment_queue.set_echo_live(None);
+    }
     let _ = app.emit("broadcast://status", "disconnected");
     Ok(())
 }
@@ -111,9 +156,47 @@ pub async fn send_broadcast(
 const BROWSER_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
      (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
 
-fn blog(app: &AppHandle, msg: &str) {
-    eprintln!("{}", msg);
-    let _ = app.emit("broadcast://log", msg);
+/// 受信したメッセージ・接続状態の送り先
+#[derive(Clone)]
+pub(crate) enum BroadcastOutput {
+    /// broadcast://status / message / log としてフロントエンドに emit する（視聴用の接続）
+    App(AppHandle),
+    /// メッセージをチャンネルに送り、状態・ログはターミナルのみに出す（録画などの裏の接続）
+    Channel(mpsc::UnboundedSender<Value>),
+}
+
+impl BroadcastOutput {
+    fn status(&self, status: &str) {
+        if let Self::App(app) = self {
+            let _ = app.emit("broadcast://status", status);
+        }
+    }
+
+    fn message(&self, value: &Value) {
+        match self {
+            Self::App(app) => {
+                // コメント（t=1）にはモデレーションルールの判定結果を verdict として付ける
+                let mut value = value.clone();
+                if let Some(moderation) = app.try_state::<ModerationManager>() {
+                    moderation.annotate(app, &mut value);
+                }
+                let _ = app.emit("broadcast://message", &value);
+                if let Some(manager) = app.try_state::<BroadcastManager>() {
+                    let _ = manager.messages.send(value);
+                }
+            }
+            Self::Channel(tx) => {
+                let _ = tx.send(value.clone());
+            }
+        }
+    }
+
+    fn log(&self, msg: &str) {
+        eprintln!("{}", msg);
+        if let Self::App(app) = self {
+            let _ = app.emit("broadcast://log", msg);
+        }
+    }
 }
 
 fn retry_delay(attempt: u32) -> Duration {
@@ -124,7 +207,7 @@ fn retry_delay(attempt: u32) -> Duration {
 }
 
 async fn ws_loop(
-    app: AppHandle,
+    output: BroadcastOutput,
     bcsvr_key: String,
     broadcast_host: String,
     cookie: Option<String>,
@@ -146,9 +229,9 @@ async fn ws_loop(
         let request = match build_request(&url, cookie.as_deref(), user_agent.as_deref()) {
             Ok(r) => r,
             Err(e) => {
-                blog(&app, &format!("broadcast: request error: {}", e));
-                let _ = app.emit("broadcast://status", "error");
-                if !wait_retry(&app, &mut shutdown_rx, &mut retry_count, max_retries).await {
+                output.log(&format!("broadcast: request error: {}", e));
+                output.status("error");
+                if !wait_retry(&output, &mut shutdown_rx, &mut retry_count, max_retries).await {
                     return;
                 }
                 continue;
@@ -159,9 +242,9 @@ async fn ws_loop(
         let ws = match connect_async(request).await {
             Ok((ws, _resp)) => ws,
             Err(e) => {
-                blog(&app, &format!("broadcast: connect failed: {}", e));
-                let _ = app.emit("broadcast://status", "error");
-                if !wait_retry(&app, &mut shutdown_rx, &mut retry_count, max_retries).await {
+                output.log(&format!("broadcast: connect failed: {}", e));
+                output.status("error");
+                if !wait_retry(&output, &mut shutdown_rx, &mut retry_count, max_retries).await {
                     return;
                 }
                 continue;
@@ -169,8 +252,8 @@ async fn ws_loop(
         };
 
         retry_count = 0;
-        blog(&app, &format!("broadcast: connected {}", url));
-        let _ = app.emit("broadcast://status", "connected");
+        output.log(&format!("broadcast: connected {}", url));
+        output.status("connected");
 
         let (mut sink, mut read) = ws.split();
 
@@ -178,7 +261,7 @@ async fn ws_loop(
         // ブラウザ互換: "PING\t" を送信（改行ではなくタブ終端）
         let _ = send_tab(&mut sink, "PING").await;
         let _ = wait_for_any(
-            &app,
+            &output,
             &mut sink,
             &mut read,
             &mut shutdown_rx,
@@ -190,7 +273,7 @@ async fn ws_loop(
 
         // --- phase 2: subscribe ---
         match subscribe(
-            &app,
+            &output,
             &mut sink,
             &mut read,
             &mut shutdown_rx,
@@ -200,17 +283,17 @@ async fn ws_loop(
         .await
         {
             SubOutcome::Subscribed => {
-                blog(&app, "broadcast: subscribed");
-                let _ = app.emit("broadcast://status", "subscribed");
+                output.log("broadcast: subscribed");
+                output.status("subscribed");
             }
             SubOutcome::Shutdown => {
                 let _ = sink.close().await;
-                let _ = app.emit("broadcast://status", "disconnected");
+                output.status("disconnected");
                 return;
             }
             SubOutcome::Disconnected => {
-                let _ = app.emit("broadcast://status", "disconnected");
-                if !wait_retry(&app, &mut shutdown_rx, &mut retry_count, max_retries).await {
+                output.status("disconnected");
+                if !wait_retry(&output, &mut shutdown_rx, &mut retry_count, max_retries).await {
                     return;
                 }
                 continue;
@@ -226,46 +309,46 @@ async fn ws_loop(
             tokio::select! {
                 _ = ping_iv.tick() => {
                     if send_tab(&mut sink, "PING").await.is_err() {
-                        blog(&app, "broadcast: keepalive failed");
+                        output.log("broadcast: keepalive failed");
                         break true;
                     }
                 }
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
                         let _ = sink.close().await;
-                        let _ = app.emit("broadcast://status", "disconnected");
+                        output.status("disconnected");
                         return;
                     }
                 }
                 Some(msg) = outgoing_rx.recv() => {
                     // 行プロトコルの可能性が高いので、末尾に改行が無ければ付ける
                     if send_raw_user_text(&mut sink, &msg).await.is_err() {
-                        blog(&app, "broadcast: send failed");
+                        output.log("broadcast: send failed");
                         break true;
                     }
                 }
                 frame = read.next() => {
                     match frame {
                         Some(Ok(Message::Text(text))) => {
-                            let _flags = handle_payload(&app, &mut sink, &text).await;
+                            let _flags = handle_payload(&output, &mut sink, &text).await;
                         }
                         Some(Ok(Message::Binary(data))) => {
                             let text = String::from_utf8_lossy(&data);
-                            let _flags = handle_payload(&app, &mut sink, &text).await;
+                            let _flags = handle_payload(&output, &mut sink, &text).await;
                         }
                         Some(Ok(Message::Ping(data))) => {
                             let _ = sink.send(Message::Pong(data)).await;
                         }
                         Some(Ok(Message::Close(reason))) => {
-                            blog(&app, &format!("broadcast: close {:?}", reason));
+                            output.log(&format!("broadcast: close {:?}", reason));
                             break true;
                         }
                         None => {
-                            blog(&app, "broadcast: stream ended");
+                            output.log("broadcast: stream ended");
                             break true;
                         }
                         Some(Err(e)) => {
-                            blog(&app, &format!("broadcast: ws error: {}", e));
+                            output.log(&format!("broadcast: ws error: {}", e));
                             break true;
                         }
                         _ => {}
@@ -275,10 +358,10 @@ async fn ws_loop(
         };
 
         if disconnected {
-            let _ = app.emit("broadcast://status", "disconnected");
+            output.status("disconnected");
         }
 
-        if !wait_retry(&app, &mut shutdown_rx, &mut retry_count, max_retries).await {
+        if !wait_retry(&output, &mut shutdown_rx, &mut retry_count, max_retries).await {
             return;
         }
     }
@@ -302,7 +385,7 @@ enum WaitPredicate {
 }
 
 async fn subscribe(
-    app: &AppHandle,
+    output: &BroadcastOutput,
     sink: &mut WsSink,
     read: &mut WsRead,
     shutdown_rx: &mut watch::Receiver<bool>,
@@ -312,10 +395,7 @@ async fn subscribe(
     // ブラウザ互換: "SUB\t{key}" (タブ区切り、改行なし)
     let cmd = format!("SUB\t{}", bcsvr_key);
 
-    blog(
-        app,
-        &format!("broadcast: tx {}", escape_for_log(cmd.trim_end())),
-    );
+    output.log(&format!("broadcast: tx {}", escape_for_log(cmd.trim_end())));
 
     if sink.send(Message::Text(cmd.into())).await.is_err() {
         return SubOutcome::Disconnected;
@@ -323,7 +403,7 @@ async fn subscribe(
 
     // ACK/MSG/ERRを待つ。静かな配信ではメッセージが来ないことがある。
     let flags = wait_for_any(
-        app,
+        output,
         sink,
         read,
         shutdown_rx,
@@ -338,23 +418,23 @@ async fn subscribe(
     }
 
     if flags.has_err {
-        blog(app, "broadcast: SUB rejected (ERR)");
+        output.log("broadcast: SUB rejected (ERR)");
         return SubOutcome::Disconnected;
     }
 
     if flags.has_msg || flags.has_ack {
-        blog(app, "broadcast: SUB confirmed (ACK/MSG)");
+        output.log("broadcast: SUB confirmed (ACK/MSG)");
     } else {
         // タイムアウト = ERRが返ってないので受理されたと見なす
         // (視聴者の少ない配信ではMSGが来ない)
-        blog(app, "broadcast: SUB assumed ok (no ERR within timeout)");
+        output.log("broadcast: SUB assumed ok (no ERR within timeout)");
     }
 
     SubOutcome::Subscribed
 }
 
 async fn wait_for_any(
-    app: &AppHandle,
+    output: &BroadcastOutput,
     sink: &mut WsSink,
     read: &mut WsRead,
     shutdown_rx: &mut watch::Receiver<bool>,
@@ -381,7 +461,7 @@ async fn wait_for_any(
             frame = read.next() => {
                 match frame {
                     Some(Ok(Message::Text(text))) => {
-                        let flags = handle_payload(app, sink, &text).await;
+                        let flags = handle_payload(output, sink, &text).await;
                         out.has_ack |= flags.has_ack;
                         out.has_err |= flags.has_err;
                         out.has_msg |= flags.has_msg;
@@ -389,7 +469,7 @@ async fn wait_for_any(
                     }
                     Some(Ok(Message::Binary(data))) => {
                         let text = String::from_utf8_lossy(&data);
-                        let flags = handle_payload(app, sink, &text).await;
+                        let flags = handle_payload(output, sink, &text).await;
                         out.has_ack |= flags.has_ack;
                         out.has_err |= flags.has_err;
                         out.has_msg |= flags.has_msg;
@@ -402,7 +482,7 @@ async fn wait_for_any(
                         return out;
                     }
                     Some(Err(e)) => {
-                        blog(app, &format!("broadcast: ws error: {}", e));
+                        output.log(&format!("broadcast: ws error: {}", e));
                         return out;
                     }
                     _ => {}
@@ -470,24 +550,21 @@ async fn send_raw_user_text(sink: &mut WsSink, msg: &str) -> Result<(), ()> {
 }
 
 async fn wait_retry(
-    app: &AppHandle,
+    output: &BroadcastOutput,
     shutdown_rx: &mut watch::Receiver<bool>,
     retry_count: &mut u32,
     max_retries: u32,
 ) -> bool {
     *retry_count += 1;
     if *retry_count > max_retries {
-        blog(
-            app,
-            &format!("broadcast: max retries ({}) reached", max_retries),
-        );
-        let _ = app.emit("broadcast://status", "failed");
+        output.log(&format!("broadcast: max retries ({}) reached", max_retries));
+        output.status("failed");
         return false;
     }
-    blog(
-        app,
-        &format!("broadcast: reconnecting ({}/{})", retry_count, max_retries),
-    );
+    output.log(&format!(
+        "broadcast: reconnecting ({}/{})",
+        retry_count, max_retries
+    ));
     let delay = retry_delay(*retry_count - 1);
     tokio::select! {
         _ = sleep(delay) => {}
@@ -499,7 +576,11 @@ async fn wait_retry(
 }
 
 /// サーバーペイロードを行ごとに処理（ACK/ERR/MSG をフラグ化して返す）
-async fn handle_payload(app: &AppHandle, sink: &mut WsSink, payload: &str) -> PayloadFlags {
+async fn handle_payload(
+    output: &BroadcastOutput,
+    sink: &mut WsSink,
+    payload: &str,
+) -> PayloadFlags {
     let mut flags = PayloadFlags::default();
 
     for raw in payload.lines() {
@@ -521,7 +602,7 @@ async fn handle_payload(app: &AppHandle, sink: &mut WsSink, payload: &str) -> Pa
             }
             "ERR" => {
                 flags.has_err = true;
-                blog(app, &format!("broadcast: rx {}", escape_for_log(line)));
+                output.log(&format!("broadcast: rx {}", escape_for_log(line)));
             }
             "MSG" => {
                 flags.has_msg = true;
@@ -529,19 +610,19 @@ async fn handle_payload(app: &AppHandle, sink: &mut WsSink, payload: &str) -> Pa
                 if let Some(json_str) = parts.next() {
                     match serde_json::from_str::<Value>(json_str) {
                         Ok(val) => {
-                            blog(app, &format!("broadcast: {}", summarize_msg(&val)));
-                            let _ = app.emit("broadcast://message", &val);
+                            output.log(&format!("broadcast: {}", summarize_msg(&val)));
+                            output.message(&val);
                         }
                         Err(e) => {
-                            blog(app, &format!("broadcast: JSON error: {}", e));
+                            output.log(&format!("broadcast: JSON error: {}", e));
                         }
                     }
                 } else {
-                    blog(app, &format!("broadcast: rx {}", escape_for_log(line)));
+                    output.log(&format!("broadcast: rx {}", escape_for_log(line)));
                 }
             }
             _ => {
-                blog(app, &format!("broadcast: rx {}", escape_for_log(line)));
+                output.log(&format!("broadcast: rx {}", escape_for_log(line)));
             }
         }
     }
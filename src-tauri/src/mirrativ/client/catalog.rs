@@ -82,7 +82,10 @@ pub async fn get_catalog_follow(
     cursor: Option<String>,
 ) -> Result<Value, String> {
     let url = if let Some(cur) = cursor {
-        format!("https://www.mirrativ.com/api/catalog/follow?cursor={}", cur)
+        format!(
+            "https://www.mirrativ.com/api/catalog/follow?cursor={}",
+            urlencoding::encode(&cur)
+        )
     } else {
         "https://www.mirrativ.com/api/catalog/follow".to_string()
     };
@@ -2,17 +2,25 @@ use super::core::MirrativClient;
 use serde_json::Value;
 use std::collections::HashMap;
 
-#[tauri::command]
-pub async fn join_live(
-    state: tauri::State<'_, MirrativClient>,
-    live_id: String,
-) -> Result<Value, String> {
+/// 入室時に取得する配信情報一式
+pub(crate) struct JoinedLive {
+    /// /api/live/live（bcsvr_key / broadcast_host を含む）
+    pub(crate) info: Option<Value>,
+    pub(crate) notice: Option<Value>,
+    pub(crate) comments: Option<Value>,
+    /// /api/live/get_streaming_url
+    pub(crate) status: Value,
+}
+
+/// 入室を通知し、配信情報・お知らせ・コメント・再生 URL をまとめて取得する。
+/// 再生 URL 以外の取得失敗はログに出して None とする。
+pub(crate) async fn join(client: &MirrativClient, live_id: &str) -> Result<JoinedLive, String> {
     // 入室通知トリガー: live_comment(type=3)
     let mut join_form = HashMap::new();
-    join_form.insert("live_id".to_string(), live_id.clone());
+    join_form.insert("live_id".to_string(), live_id.to_string());
     join_form.insert("comment".to_string(), String::new());
     join_form.insert("type".to_string(), "3".to_string());
-    state
+    client
         .post_json(
             "https://www.mirrativ.com/api/live/live_comment",
             join_form,
@@ -34,13 +42,32 @@ pub async fn join_live(
         live_id
     );
 
-    let (_info, _notice, _comments, status) = tokio::join!(
-        state.fetch_json(&info_url, Some("live_view")),
-        state.fetch_json(&notice_url, Some("live_view")),
-        state.fetch_json(&comments_url, Some("live_view")),
-        state.fetch_json(&stream_url, Some("live_view")),
+    let (info, notice, comments, status) = tokio::join!(
+        client.fetch_json(&info_url, Some("live_view")),
+        client.fetch_json(&notice_url, Some("live_view")),
+        client.fetch_json(&comments_url, Some("live_view")),
+        client.fetch_json(&stream_url, Some("live_view")),
     );
 
-    let status = status?;
-    Ok(status)
+    let optional = |name: &str, result: Result<Value, String>| match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("join {}: {} failed: {}", live_id, name, e);
+            None
+        }
+    };
+    Ok(JoinedLive {
+        info: optional("live", info),
+        notice: optional("notice", notice),
+        comments: optional("live_comments", comments),
+        status: status?,
+    })
+}
+
+#[tauri::command]
+pub async fn join_live(
+    state: tauri::State<'_, MirrativClient>,
+    live_id: String,
+) -> Result<Value, String> {
+    join(&state, &live_id).await.map(|joined| joined.status)
 }
@@ -12,7 +12,7 @@
 // ─────────────────────────────────────────────────────────────────────────────
 
 use reqwest::{
-    header::{HeaderMap, HeaderValue, SET_COOKIE, USER_AGENT},
+    header::{HeaderMap, HeaderValue, RETRY_AFTER, SET_COOKIE, USER_AGENT},
     multipart::Form,
     Client,
 };
@@ -27,6 +27,23 @@ use tokio::time::sleep;
 // クライアント構造体
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// post_form_once のレスポンス（ステータスに関わらずそのまま返す）
+pub(crate) struct RawPostResponse {
+    pub(crate) status: u16,
+    /// Retry-After ヘッダー（秒指定のみ）
+    pub(crate) retry_after: Option<Duration>,
+    /// JSON として読めない場合は Null
+    pub(crate) body: Value,
+}
+
+/// post_form_once の送信エラー
+pub(crate) enum RawPostError {
+    /// サーバーに届いていない（再送しても重複しない）
+    NotSent(String),
+    /// 届いたかどうか分からない（タイムアウト・レスポンスの途中切断など）
+    Unknown(String),
+}
+
 /// Mirrativ API クライアント。Tauri の管理状態として登録して使用する。
 ///
 /// セッション情報（mr_id / unique）は RwLock で保護しており、
@@ -282,6 +299,50 @@ impl MirrativClient {
         Err("HTTP request failed".to_string())
     }
 
+    /// フォームエンコードされた POST リクエストを 1 回だけ送信する。
+    /// 再送すると重複する API（コメント投稿など）用で、リトライの判断は呼び出し側に任せる。
+    pub(crate) async fn post_form_once(
+        &self,
+        url: &str,
+        form: HashMap<String, String>,
+        referer: Option<&str>,
+    ) -> Result<RawPostResponse, RawPostError> {
+        let mut headers = self.get_headers_for_url(url).await;
+        add_referer_header(&mut headers, referer);
+        headers.insert(
+            "Content-Type",
+            HeaderValue::from_static("application/x-www-form-urlencoded; charset=UTF-8"),
+        );
+
+        let resp = self
+            .client
+            .post(url)
+            .headers(headers)
+            .body(encode_form(&form))
+            .send()
+            .await
+            .map_err(|err| {
+                if err.is_connect() || err.is_builder() {
+                    RawPostError::NotSent(err.to_string())
+                } else {
+                    RawPostError::Unknown(err.to_string())
+                }
+            })?;
+
+        let status = resp.status().as_u16();
+        let retry_after = resp
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+            .map(Duration::from_secs);
+        let body = resp.json::<Value>().await.unwrap_or(Value::Null);
+        Ok(RawPostResponse {
+            status,
+            retry_after,
+            body,
+        })
+    }
+
     /// マルチパートフォームの POST リクエストを送信して JSON レスポンスを取得する。
     /// ファイルアップロード（プロフィール画像など）に使用する。リトライなし。
     pub(crate) async fn post_multipart_json(
@@ -1,4 +1,4 @@
-use super::core::MirrativClient;
+use super::core::{MirrativClient, RawPostError, RawPostResponse};
 use serde_json::Value;
 use std::collections::HashMap;
 
@@ -85,7 +85,7 @@ pub async fn get_live_history(
 ) -> Result<Value, String> {
     let mut url = format!(
         "https://www.mirrativ.com/api/live/live_history?user_id={}",
-        user_id
+        urlencoding::encode(&user_id)
     );
     if let Some(p) = page {
         if p > 1 {
@@ -132,22 +132,47 @@ pub async fn get_collaborators(
     state.fetch_json(&url, Some("live_view")).await
 }
 
-#[tauri::command]
-pub async fn comment(
-    state: tauri::State<'_, MirrativClient>,
+const LIVE_COMMENT_URL: &str = "https://www.mirrativ.com/api/live/live_comment";
+
+fn comment_form(
     live_id: String,
     message: String,
     comment_type: Option<i32>,
-) -> Result<Value, String> {
+) -> HashMap<String, String> {
     let mut form = HashMap::new();
     form.insert("live_id".to_string(), live_id);
     form.insert("comment".to_string(), message);
     form.insert("type".to_string(), comment_type.unwrap_or(1).to_string());
+    form
+}
 
+#[tauri::command]
+pub async fn comment(
+    state: tauri::State<'_, MirrativClient>,
+    live_id: String,
+    message: String,
+    comment_type: Option<i32>,
+) -> Result<Value, String> {
     state
         .post_json(
-            "https://www.mirrativ.com/api/live/live_comment",
-            form,
+            LIVE_COMMENT_URL,
+            comment_form(live_id, message, comment_type),
+            Some("live_view"),
+        )
+        .await
+}
+
+/// コメントを 1 回だけ投稿する（送信キュー用。リトライしないので重複しない）
+pub(crate) async fn post_comment_once(
+    client: &MirrativClient,
+    live_id: &str,
+    message: &str,
+    comment_type: Option<i32>,
+) -> Result<RawPostResponse, RawPostError> {
+    client
+        .post_form_once(
+            LIVE_COMMENT_URL,
+            comment_form(live_id.to_string(), message.to_string(), comment_type),
             Some("live_view"),
         )
         .await
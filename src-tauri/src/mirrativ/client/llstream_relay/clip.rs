@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest clip that can be requested; the buffer keeps at least this much.
+pub(crate) const MAX_CLIP_SECS: u32 = 120;
+/// Hard cap on buffered TS bytes regardless of duration (oldest GOPs go first).
+const MAX_CLIP_BYTES: usize = 64 * 1024 * 1024;
+
+// ---------------------------------------------------------------------------
+// ClipBuffer — rolling window of published TS chunks for "last N seconds" clips
+// ---------------------------------------------------------------------------
+
+/// Recent TS chunks of a relay session. The window always starts at a random
+/// access point (IDR for video, any frame for audio-only) so a dump decodes on its own.
+#[derive(Default)]
+pub(crate) struct ClipBuffer {
+    state: Mutex<ClipState>,
+}
+
+#[derive(Default)]
+struct ClipState {
+    /// PAT/PMT written ahead of a dump; None while buffering is off (non-TS relays).
+    bootstrap: Option<Vec<u8>>,
+    chunks: VecDeque<ClipChunk>,
+    bytes: usize,
+}
+
+struct ClipChunk {
+    received_at: Instant,
+    random_access: bool,
+    /// Shared so a capture can copy it out after releasing the lock.
+    data: Arc<[u8]>,
+}
+
+/// A dumped clip: PAT/PMT followed by the buffered chunks.
+pub(crate) struct ClipCapture {
+    pub(crate) data: Vec<u8>,
+    pub(crate) duration_ms: u64,
+}
+
+impl ClipBuffer {
+    /// Turns buffering on for a TS relay; `bootstrap` is used until the codec is known.
+    pub(crate) fn enable(&self, bootstrap: Vec<u8>) {
+        if let Ok(mut state) = self.state.lock() {
+            state.bootstrap = Some(bootstrap);
+        }
+    }
+
+    pub(crate) fn push(&self, data: &[u8], random_access: bool) {
+        self.push_at(data, random_access, Instant::now());
+    }
+
+    fn push_at(&self, data: &[u8], random_access: bool, now: Instant) {
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+        if state.bootstrap.is_none() || data.is_empty() {
+            return;
+        }
+        if state.chunks.is_empty() && !random_access {
+            return;
+        }
+
+        state.bytes += data.len();
+        state.chunks.push_back(ClipChunk {
+            received_at: now,
+            random_access,
+            data: data.into(),
+        });
+
+        // Eviction points are random access chunks, so only re-check when one arrives.
+        if random_access {
+            if let Some(cutoff) = now.checked_sub(Duration::from_secs(MAX_CLIP_SECS as u64)) {
+                if let Some(start) = state.start_index(cutoff) {
+                    state.drain_front(start);
+                }
+            }
+        }
+        while state.bytes > MAX_CLIP_BYTES {
+            state.drop_oldest_gop();
+        }
+    }
+
+    /// Dumps at least the last `secs` seconds (less if the buffer is younger).
+    /// `bootstrap` overrides the initial PAT/PMT once the muxer switched codecs.
+    pub(crate) fn capture(&self, secs: u32, bootstrap: Option<Vec<u8>>) -> Option<ClipCapture> {
+        self.capture_at(secs, bootstrap, Instant::now())
+    }
+
+    fn capture_at(
+        &self,
+        secs: u32,
+        bootstrap: Option<Vec<u8>>,
+        now: Instant,
+    ) -> Option<ClipCapture> {
+        // Only clone the chunk handles under the lock; the mux task pushes into it.
+        let (tables, first_at, chunks) = {
+            let state = self.state.lock().ok()?;
+            let tables = bootstrap.or_else(|| state.bootstrap.clone())?;
+            let start = now
+                .checked_sub(Duration::from_secs(secs as u64))
+                .and_then(|cutoff| state.start_index(cutoff))
+                .unwrap_or(0);
+            let first_at = state.chunks.get(start)?.received_at;
+            let chunks: Vec<Arc<[u8]>> = state
+                .chunks
+                .iter()
+                .skip(start)
+                .map(|c| c.data.clone())
+                .collect();
+            (tables, first_at, chunks)
+        };
+
+        let bytes: usize = chunks.iter().map(|c| c.len()).sum();
+        let mut data = Vec::with_capacity(tables.len() + bytes);
+        data.extend_from_slice(&tables);
+        for chunk in &chunks {
+            data.extend_from_slice(chunk);
+        }
+        Some(ClipCapture {
+            data,
+            duration_ms: now.duration_since(first_at).as_millis() as u64,
+        })
+    }
+}
+
+impl ClipState {
+    /// Last random access chunk received at or before `cutoff`.
+    fn start_index(&self, cutoff: Instant) -> Option<usize> {
+        self.chunks
+            .iter()
+            .rposition(|chunk| chunk.random_access && chunk.received_at <= cutoff)
+    }
+
+    fn drain_front(&mut self, count: usize) {
+        for chunk in self.chunks.drain(..count) {
+            self.bytes -= chunk.data.len();
+        }
+    }
+
+    fn drop_oldest_gop(&mut self) {
+        let next = self
+            .chunks
+            .iter()
+            .skip(1)
+            .position(|chunk| chunk.random_access)
+            .map_or(self.chunks.len(), |index| index + 1);
+        self.drain_front(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLES: &[u8] = b"PATPMT";
+
+    fn buffer() -> ClipBuffer {
+        let clip = ClipBuffer::default();
+        clip.enable(TABLES.to_vec());
+        clip
+    }
+
+    fn secs(base: Instant, secs: u64) -> Instant {
+        base + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn disabled_buffer_keeps_nothing() {
+        let clip = ClipBuffer::default();
+        clip.push(b"idr", true);
+        assert!(clip.capture(10, None).is_none());
+    }
+
+    #[test]
+    fn starts_at_random_access_with_bootstrap() {
+        let clip = buffer();
+        let base = Instant::now();
+        clip.push_at(b"p0", false, base);
+        clip.push_at(b"I1", true, secs(base, 1));
+        clip.push_at(b"p1", false, secs(base, 2));
+
+        let capture = clip.capture_at(10, None, secs(base, 3)).unwrap();
+        assert_eq!(capture.data, b"PATPMTI1p1");
+        assert_eq!(capture.duration_ms, 2_000);
+
+        // A newer PAT/PMT (codec switch) replaces the initial tables.
+        let capture = clip
+            .capture_at(10, Some(b"NEW".to_vec()), secs(base, 3))
+            .unwrap();
+        assert_eq!(capture.data, b"NEWI1p1");
+    }
+
+    #[test]
+    fn capture_covers_at_least_the_requested_seconds() {
+        let clip = buffer();
+        let base = Instant::now();
+        clip.push_at(b"I0", true, base);
+        clip.push_at(b"p0", false, secs(base, 2));
+        clip.push_at(b"I1", true, secs(base, 4));
+        clip.push_at(b"p1", false, secs(base, 6));
+        clip.push_at(b"I2", true, secs(base, 8));
+
+        // The last 5 s start at 3 s, so the clip goes back to the keyframe at 0 s.
+        let capture = clip.capture_at(5, None, secs(base, 8)).unwrap();
+        assert_eq!(capture.data, b"PATPMTI0p0I1p1I2");
+        // The last 3 s start at 5 s; the keyframe at 4 s is enough.
+        let capture = clip.capture_at(3, None, secs(base, 8)).unwrap();
+        assert_eq!(capture.data, b"PATPMTI1p1I2");
+        assert_eq!(capture.duration_ms, 4_000);
+    }
+
+    #[test]
+    fn evicts_gops_older_than_the_max_clip() {
+        let clip = buffer();
+        let base = Instant::now();
+        let max = u64::from(MAX_CLIP_SECS);
+        clip.push_at(b"I0", true, base);
+        clip.push_at(b"I1", true, secs(base, 10));
+        clip.push_at(b"I2", true, secs(base, max + 20));
+
+        // Keyframe at 10 s is the last one at or before the 120 s cutoff (20 s).
+        let capture = clip
+            .capture_at(MAX_CLIP_SECS, None, secs(base, max + 20))
+            .unwrap();
+        assert_eq!(capture.data, b"PATPMTI1I2");
+        assert_eq!(clip.state.lock().unwrap().chunks.len(), 2);
+    }
+
+    #[test]
+    fn byte_cap_drops_oldest_gops() {
+        let clip = buffer();
+        let base = Instant::now();
+        let gop = vec![0u8; MAX_CLIP_BYTES / 2];
+        clip.push_at(&gop, true, base);
+        clip.push_at(b"p0", false, base);
+        clip.push_at(&gop, true, base);
+        clip.push_at(b"p1", false, base);
+
+        let state = clip.state.lock().unwrap();
+        assert!(state.bytes <= MAX_CLIP_BYTES);
+        assert_eq!(state.chunks.len(), 2);
+        assert!(state.chunks[0].random_access);
+        assert_eq!(&*state.chunks[1].data, b"p1");
+        let total: usize = state.chunks.iter().map(|c| c.data.len()).sum();
+        assert_eq!(state.bytes, total);
+    }
+
+    #[test]
+    fn empty_chunks_are_ignored() {
+        let clip = buffer();
+        clip.push(b"", true);
+        assert!(clip.capture(10, None).is_none());
+        clip.push(b"I0", true);
+        assert_eq!(clip.capture(10, None).unwrap().data, b"PATPMTI0");
+    }
+}
@@ -0,0 +1,237 @@
+use serde::Serialize;
+
+use super::parser::MrPacketHeader;
+use super::stats::unix_millis;
+use super::{relay_log, RelayEvents};
+
+/// Consecutive +1 steps needed before header bytes 5..9 are trusted as a sequence number.
+const SEQUENCE_TRUST_RUN: u32 = 8;
+/// Frame interval assumed until the stream's own cadence has been measured (30 fps).
+const DEFAULT_FRAME_INTERVAL_NS: u64 = 33_333_333;
+/// Forward steps longer than this many frame intervals count as a jump. Shorter ones
+/// are ordinary pauses in the encoder output and need no resync.
+const FORWARD_JUMP_FRAMES: u64 = 300;
+/// Backward steps up to this many frame intervals are reordering, not a rewind.
+const BACKWARD_TOLERANCE_FRAMES: u64 = 2;
+
+// ---------------------------------------------------------------------------
+// Discontinuity events
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Clone)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub(crate) enum DiscontinuityReason {
+    SequenceGap {
+        expected: u32,
+        received: u32,
+        missing: u32,
+    },
+    TimestampJump {
+        gap_ms: u64,
+    },
+    TimestampBackwards {
+        rewind_ms: u64,
+    },
+    Reconnect {
+        connection: u64,
+    },
+}
+
+/// Payload of `llstream://discontinuity`.
+#[derive(Serialize, Clone)]
+struct DiscontinuityEvent {
+    stream: &'static str,
+    #[serde(flatten)]
+    reason: DiscontinuityReason,
+    timestamp_ns: Option<u64>,
+    at_ms: u64,
+}
+
+// ---------------------------------------------------------------------------
+// StreamContinuity — per-WS sequence / timestamp tracking
+// ---------------------------------------------------------------------------
+
+pub(crate) struct StreamContinuity {
+    stream: &'static str,
+    connections: u64,
+    last_sequence: Option<u32>,
+    sequence_run: u32,
+    last_timestamp_ns: Option<u64>,
+    /// Smoothed step between consecutive packets; scales the jump thresholds.
+    frame_interval_ns: u64,
+}
+
+impl StreamContinuity {
+    pub(crate) fn new(stream: &'static str) -> Self {
+        Self {
+            stream,
+            connections: 0,
+            last_sequence: None,
+            sequence_run: 0,
+            last_timestamp_ns: None,
+            frame_interval_ns: DEFAULT_FRAME_INTERVAL_NS,
+        }
+    }
+
+    /// Call on every WS connect. Returns true (and reports it) for reconnects.
+    pub(crate) fn connected(&mut self, events: &RelayEvents) -> bool {
+        self.connections += 1;
+        self.last_sequence = None;
+        self.sequence_run = 0;
+        let timestamp_ns = self.last_timestamp_ns.take();
+
+        if self.connections == 1 {
+            return false;
+        }
+        self.report(
+            events,
+            DiscontinuityReason::Reconnect {
+                connection: self.connections,
+            },
+            timestamp_ns,
+        );
+        true
+    }
+
+    /// Checks one packet header against the previous one. Returns true (and reports
+    /// it) when frames were lost or the clock jumped.
+    pub(crate) fn observe(&mut self, events: &RelayEvents, header: &MrPacketHeader) -> bool {
+        let reason = self
+            .check_sequence(header.sequence)
+            .or_else(|| self.check_timestamp(header.timestamp_ns));
+        self.last_timestamp_ns = Some(header.timestamp_ns);
+
+        match reason {
+            Some(reason) => {
+                self.report(events, reason, Some(header.timestamp_ns));
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn check_sequence(&mut self, sequence: u32) -> Option<DiscontinuityReason> {
+        let previous = self.last_sequence.replace(sequence)?;
+        let expected = previous.wrapping_add(1);
+        if sequence == expected {
+            self.sequence_run = self.sequence_run.saturating_add(1);
+            return None;
+        }
+
+        let trusted = self.sequence_run >= SEQUENCE_TRUST_RUN;
+        self.sequence_run = 0;
+        let missing = sequence.wrapping_sub(expected);
+        // Only forward jumps are losses; anything else is a reset or not a counter at all.
+        (trusted && missing < u32::MAX / 2).then_some(DiscontinuityReason::SequenceGap {
+            expected,
+            received: sequence,
+            missing,
+        })
+    }
+
+    fn check_timestamp(&mut self, timestamp_ns: u64) -> Option<DiscontinuityReason> {
+        let previous = self.last_timestamp_ns?;
+        let interval = self.frame_interval_ns;
+        if previous > timestamp_ns {
+            let rewind = previous - timestamp_ns;
+            return (rewind > interval * BACKWARD_TOLERANCE_FRAMES).then_some(
+                DiscontinuityReason::TimestampBackwards {
+                    rewind_ms: rewind / 1_000_000,
+                },
+            );
+        }
+
+        let step = timestamp_ns - previous;
+        if step > interval * FORWARD_JUMP_FRAMES {
+            return Some(DiscontinuityReason::TimestampJump {
+                gap_ms: step / 1_000_000,
+            });
+        }
+        // Clamp so a pause only nudges the estimate; a slower stream still converges.
+        if step > 0 {
+            self.frame_interval_ns = (interval * 7 + step.min(interval * 2)) / 8;
+        }
+        None
+    }
+
+    fn report(&self, events: &RelayEvents, reason: DiscontinuityReason, timestamp_ns: Option<u64>) {
+        let event = DiscontinuityEvent {
+            stream: self.stream,
+            reason,
+            timestamp_ns,
+            at_ms: unix_millis(),
+        };
+        if let Ok(json) = serde_json::to_string(&event) {
+            relay_log(events, &format!("llstream discontinuity: {}", json));
+        }
+        events.emit("llstream://discontinuity", event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_NS: u64 = 33_333_333;
+
+    fn header(sequence: u32, timestamp_ns: u64) -> MrPacketHeader<'static> {
+        MrPacketHeader {
+            media_type: 0,
+            flags: 0,
+            kind: 0,
+            sequence,
+            timestamp_ns,
+            extension: &[],
+        }
+    }
+
+    /// Feeds `count` packets at the default frame interval and returns the last timestamp.
+    fn feed(continuity: &mut StreamContinuity, events: &RelayEvents, count: u32) -> u64 {
+        let mut timestamp_ns = 0;
+        for sequence in 0..count {
+            timestamp_ns = u64::from(sequence) * FRAME_NS;
+            assert!(!continuity.observe(events, &header(sequence, timestamp_ns)));
+        }
+        timestamp_ns
+    }
+
+    #[test]
+    fn encoder_pause_is_not_a_discontinuity() {
+        let events = RelayEvents::detached();
+        let mut continuity = StreamContinuity::new("video");
+        let last = feed(&mut continuity, &events, 30);
+        // A 3 s pause (90 frames) keeps the stream continuous.
+        assert!(!continuity.observe(&events, &header(30, last + 3_000_000_000)));
+    }
+
+    #[test]
+    fn large_forward_jump_is_a_discontinuity() {
+        let events = RelayEvents::detached();
+        let mut continuity = StreamContinuity::new("video");
+        let last = feed(&mut continuity, &events, 30);
+        let jump = last + FRAME_NS * (FORWARD_JUMP_FRAMES + 1);
+        assert!(continuity.observe(&events, &header(30, jump)));
+    }
+
+    #[test]
+    fn backward_step_beyond_reordering_is_a_discontinuity() {
+        let events = RelayEvents::detached();
+        let mut continuity = StreamContinuity::new("video");
+        let last = feed(&mut continuity, &events, 30);
+        assert!(!continuity.observe(&events, &header(30, last - FRAME_NS)));
+        assert!(continuity.observe(&events, &header(31, last - FRAME_NS * 10)));
+    }
+
+    #[test]
+    fn threshold_follows_the_frame_interval() {
+        let events = RelayEvents::detached();
+        let mut continuity = StreamContinuity::new("audio");
+        // ~21 ms AAC frames pull the estimate below the 30 fps default.
+        let step = 21_333_333;
+        for sequence in 0..200u32 {
+            continuity.observe(&events, &header(sequence, u64::from(sequence) * step));
+        }
+        assert!(continuity.frame_interval_ns < FRAME_NS);
+        assert!(continuity.frame_interval_ns.abs_diff(step) < 1_000_000);
+    }
+}
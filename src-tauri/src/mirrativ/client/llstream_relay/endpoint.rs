@@ -0,0 +1,182 @@
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use super::mdns::{spawn_mdns_responder, MdnsService};
+use super::{relay_log, RelayEvents};
+
+// ---------------------------------------------------------------------------
+// Relay session options
+// ---------------------------------------------------------------------------
+
+/// Per-session settings of an HTTP relay. Every field is optional; the
+/// defaults keep the relay on an ephemeral loopback port without a token.
+#[derive(Deserialize, Default, Clone)]
+pub struct LlstreamRelayOptions {
+    /// Address to bind, e.g. "0.0.0.0" or a LAN IP to expose the relay to other devices.
+    pub bind_address: Option<String>,
+    /// Fixed port. 0 or None picks an ephemeral port.
+    pub port: Option<u16>,
+    /// Require an access token on every request. Always on for non-loopback binds.
+    pub require_token: Option<bool>,
+    /// Maximum number of concurrent stream clients.
+    pub max_clients: Option<u32>,
+    /// Advertise the stream over mDNS/DNS-SD (only for non-loopback binds).
+    pub advertise_mdns: Option<bool>,
+    /// Instance name used for the mDNS advertisement.
+    pub service_name: Option<String>,
+    /// A/V interleave latency target of the AV TS relay in ms (default 150, max 2000).
+    pub av_latency_ms: Option<u32>,
+}
+
+impl LlstreamRelayOptions {
+    fn bind_ip(&self) -> Result<IpAddr, String> {
+        match self
+            .bind_address
+            .as_deref()
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+        {
+            Some(raw) => raw
+                .parse::<IpAddr>()
+                .map_err(|_| format!("invalid bind_address: {}", raw)),
+            None => Ok(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// RelayEndpoint
+// ---------------------------------------------------------------------------
+
+/// Bound address, public URLs and access policy of one HTTP relay session.
+pub(super) struct RelayEndpoint {
+    pub(super) port: u16,
+    local_base_url: String,
+    /// LAN-reachable address, set when the relay is bound beyond loopback.
+    pub(super) lan_ip: Option<Ipv4Addr>,
+    pub(super) token: Option<String>,
+    pub(super) max_clients: Option<u64>,
+    pub(super) mdns_name: Option<String>,
+}
+
+impl RelayEndpoint {
+    /// Binds the relay listener according to `options`.
+    pub(super) async fn bind(
+        options: &LlstreamRelayOptions,
+    ) -> Result<(TcpListener, RelayEndpoint), String> {
+        let bind_ip = options.bind_ip()?;
+        let listener = TcpListener::bind(SocketAddr::new(bind_ip, options.port.unwrap_or(0)))
+            .await
+            .map_err(|e| format!("failed to bind relay server: {}", e))?;
+        let addr = listener
+            .local_addr()
+            .map_err(|e| format!("failed to get local addr: {}", e))?;
+
+        let exposed = !bind_ip.is_loopback();
+        let lan_ip = if exposed { resolve_lan_ip(bind_ip) } else { None };
+
+        // A relay reachable from other hosts must never be readable without a token.
+        let require_token = exposed || options.require_token.unwrap_or(false);
+        let mdns_name = (exposed && lan_ip.is_some() && options.advertise_mdns.unwrap_or(false))
+            .then(|| {
+                options
+                    .service_name
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| format!("Mirrativ Relay {}", addr.port()))
+            });
+
+        let loopback_ip = if bind_ip.is_unspecified() || bind_ip.is_loopback() {
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        } else {
+            bind_ip
+        };
+
+        Ok((
+            listener,
+            RelayEndpoint {
+                port: addr.port(),
+                local_base_url: format!("http://{}", SocketAddr::new(loopback_ip, addr.port())),
+                lan_ip,
+                token: require_token.then(|| Uuid::new_v4().simple().to_string()),
+                max_clients: options.max_clients.filter(|n| *n > 0).map(u64::from),
+                mdns_name,
+            },
+        ))
+    }
+
+    /// URL for consumers on this machine (mpv, the WebView).
+    pub(super) fn url(&self, path: &str) -> String {
+        self.with_token(&self.local_base_url, path)
+    }
+
+    /// URL for other devices on the LAN.
+    pub(super) fn lan_url(&self, path: &str) -> Option<String> {
+        self.lan_ip
+            .map(|ip| self.with_token(&format!("http://{}:{}", ip, self.port), path))
+    }
+
+    /// Starts the mDNS advertisement when it was requested and the relay is on the LAN.
+    /// Advertisement failure is logged but never fails the relay itself.
+    ///
+    /// The TXT record carries only the bare path: mDNS is multicast to the whole
+    /// LAN, so the access token is handed to clients out of band (`LlstreamRelayInfo`).
+    pub(super) fn spawn_mdns(
+        &self,
+        events: &RelayEvents,
+        stream_path: &str,
+        mode: &str,
+        shutdown_rx: watch::Receiver<bool>,
+    ) -> Option<JoinHandle<()>> {
+        let (Some(instance), Some(ip)) = (self.mdns_name.clone(), self.lan_ip) else {
+            return None;
+        };
+        let service = MdnsService {
+            instance,
+            ip,
+            port: self.port,
+            txt: vec![
+                format!("path={}", stream_path),
+                format!("mode={}", mode),
+                format!("auth={}", if self.token.is_some() { "token" } else { "none" }),
+            ],
+        };
+        match spawn_mdns_responder(events.clone(), service, shutdown_rx) {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                relay_log(events, &format!("llstream mdns disabled: {}", e));
+                None
+            }
+        }
+    }
+
+    fn with_token(&self, base: &str, path: &str) -> String {
+        match &self.token {
+            Some(token) => format!("{}{}?token={}", base, path, token),
+            None => format!("{}{}", base, path),
+        }
+    }
+}
+
+/// Picks the IPv4 address other devices can reach us on. For a wildcard bind the
+/// outbound interface is found by "connecting" a UDP socket, which sends nothing.
+fn resolve_lan_ip(bind_ip: IpAddr) -> Option<Ipv4Addr> {
+    match bind_ip {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        IpAddr::V4(_) => {
+            let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+            socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9)).ok()?;
+            match socket.local_addr().ok()?.ip() {
+                IpAddr::V4(ip) if !ip.is_unspecified() && !ip.is_loopback() => Some(ip),
+                _ => None,
+            }
+        }
+        IpAddr::V6(_) => None,
+    }
+}
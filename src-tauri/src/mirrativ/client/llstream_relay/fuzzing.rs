@@ -0,0 +1,49 @@
+//! Entry points for the cargo-fuzz targets in `src-tauri/fuzz` (feature `fuzzing`).
+//! Each function runs one parser on arbitrary input and panics if an invariant breaks.
+
+use super::parser::{self, MR_HEADER_FIXED_LEN, NAL_START_CODE};
+
+pub fn parse_video_packet(data: &[u8]) {
+    let Some(frame) = parser::parse_video_packet(data) else {
+        return;
+    };
+    let header_len = frame.header.len();
+    assert!(header_len > MR_HEADER_FIXED_LEN);
+    assert_eq!(header_len + frame.payload.len(), data.len());
+    assert_eq!(frame.header.encode(), data[..header_len]);
+}
+
+pub fn parse_audio_packet(data: &[u8]) {
+    let Some(frame) = parser::parse_audio_packet(data) else {
+        return;
+    };
+    assert!(frame.header.extension.is_empty());
+    assert_eq!(frame.header.len() + frame.payload.len(), data.len());
+    assert_eq!(frame.header.encode(), data[..MR_HEADER_FIXED_LEN]);
+}
+
+pub fn ensure_annexb(data: &[u8]) {
+    let out = parser::ensure_annexb(data);
+    if out.is_empty() {
+        return;
+    }
+    assert!(out.starts_with(&[0x00, 0x00, 0x01]) || out.starts_with(&NAL_START_CODE));
+    // Already Annex B input is passed through untouched.
+    if data.starts_with(&[0x00, 0x00, 0x01]) || data.starts_with(&NAL_START_CODE) {
+        assert_eq!(out, data);
+    }
+}
+
+/// The first byte selects the NAL length field size (1..=4), the rest is the payload.
+pub fn convert_length_prefixed(data: &[u8]) {
+    let Some((&selector, payload)) = data.split_first() else {
+        return;
+    };
+    let len_bytes = (selector % 4) as usize + 1;
+    let Some(out) = parser::convert_length_prefixed(payload, len_bytes) else {
+        return;
+    };
+    // Each length field is replaced by a 4-byte start code.
+    assert!(out.starts_with(&NAL_START_CODE));
+    assert!(out.len() >= payload.len());
+}
@@ -1,44 +1,87 @@
-use tauri::AppHandle;
+use std::collections::HashMap;
+use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::{broadcast, watch};
 use tokio::task::JoinHandle;
 use tokio::time::Duration;
 
-use super::relay_log;
+use super::stats::{RelayShared, RelayStats};
+use super::{relay_log, RelayEvents};
+
+const MAX_REQUEST_HEAD_LEN: usize = 16 * 1024;
+const MAX_DISCARDED_BODY_LEN: usize = 64 * 1024;
+const FIRST_REQUEST_TIMEOUT: Duration = Duration::from_secs(3);
+const KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(15);
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+// ---------------------------------------------------------------------------
+// Relay HTTP server
+// ---------------------------------------------------------------------------
+
+/// Per-session settings of the HTTP layer.
+pub(super) struct HttpRelayConfig {
+    /// Path of the live stream route ("/" always aliases it), e.g. "/live.ts".
+    pub(super) stream_path: &'static str,
+    /// Content-Type of the live stream route.
+    pub(super) content_type: &'static str,
+    /// Bytes written to each stream client before live chunks (PAT/PMT for TS),
+    /// unless the session has published codec-specific tables in `RelayShared`.
+    pub(super) bootstrap: Vec<u8>,
+    /// When set, every route except the CORS preflight requires this token
+    /// (`?token=...` or `Authorization: Bearer ...`).
+    pub(super) token: Option<String>,
+    /// Concurrent stream clients beyond this are turned away with 503.
+    pub(super) max_clients: Option<u64>,
+}
+
+struct HttpRelayContext {
+    config: HttpRelayConfig,
+    packet_tx: broadcast::Sender<Vec<u8>>,
+    shared: Arc<RelayShared>,
+}
 
 pub(super) fn spawn_http_relay_task(
-    app: AppHandle,
+    events: RelayEvents,
     listener: TcpListener,
-    bootstrap: Vec<u8>,
+    config: HttpRelayConfig,
     packet_tx: broadcast::Sender<Vec<u8>>,
+    shared: Arc<RelayShared>,
     mut shutdown_rx: watch::Receiver<bool>,
     label: &str,
 ) -> JoinHandle<()> {
     let label = format!("llstream {} relay http", label);
+    let ctx = Arc::new(HttpRelayContext {
+        config,
+        packet_tx,
+        shared,
+    });
     tokio::spawn(async move {
-        relay_log(&app, &format!("{} listening", label));
+        relay_log(&events, &format!("{} listening", label));
         loop {
             tokio::select! {
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
-                        relay_log(&app, &format!("{} stopping", label));
+                        relay_log(&events, &format!("{} stopping", label));
                         return;
                     }
                 }
                 accepted = listener.accept() => {
                     match accepted {
                         Ok((socket, peer)) => {
-                            relay_log(&app, &format!("{} client connected: {}", label, peer));
-                            let mut rx = packet_tx.subscribe();
-                            let bootstrap = bootstrap.clone();
+                            relay_log(&events, &format!("{} client connected: {}", label, peer));
+                            let ctx = ctx.clone();
+                            let events = events.clone();
+                            let label = label.clone();
                             let mut client_shutdown_rx = shutdown_rx.clone();
                             tokio::spawn(async move {
-                                let _ = handle_http_client(socket, bootstrap, &mut rx, &mut client_shutdown_rx).await;
+                                if let Err(e) = handle_http_client(socket, &ctx, &mut client_shutdown_rx).await {
+                                    relay_log(&events, &format!("{} client {}: {}", label, peer, e));
+                                }
                             });
                         }
                         Err(e) => {
-                            relay_log(&app, &format!("{} accept error: {}", label, e));
+                            relay_log(&events, &format!("{} accept error: {}", label, e));
                             return;
                         }
                     }
@@ -48,65 +91,511 @@ pub(super) fn spawn_http_relay_task(
     })
 }
 
+// ---------------------------------------------------------------------------
+// Connection handling
+// ---------------------------------------------------------------------------
+
+enum Route {
+    Stream,
+    Playlist,
+    Stats,
+    Snapshot,
+    NotFound,
+}
+
+fn route(path: &str, stream_path: &str) -> Route {
+    match path {
+        "/" => Route::Stream,
+        p if p == stream_path => Route::Stream,
+        "/live.m3u" => Route::Playlist,
+        "/stats.json" => Route::Stats,
+        // Latest keyframe as raw Annex B; the content type follows the stream codec.
+        "/snapshot" => Route::Snapshot,
+        _ => Route::NotFound,
+    }
+}
+
 async fn handle_http_client(
     mut socket: TcpStream,
-    bootstrap: Vec<u8>,
-    rx: &mut broadcast::Receiver<Vec<u8>>,
+    ctx: &HttpRelayContext,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
-    let mut req_buf = vec![0u8; 4096];
-    let n = tokio::time::timeout(Duration::from_secs(3), socket.read(&mut req_buf))
-        .await
-        .map_err(|_| "http request timeout".to_string())?
-        .map_err(|e| format!("http read failed: {}", e))?;
-    if n == 0 {
-        return Ok(());
+    let _ = socket.set_nodelay(true);
+    let mut buf = Vec::with_capacity(1024);
+    let mut timeout = FIRST_REQUEST_TIMEOUT;
+
+    loop {
+        let req = match read_request(&mut socket, &mut buf, timeout).await {
+            Ok(Some(req)) => req,
+            Ok(None) => return Ok(()),
+            Err(RequestError::Timeout) => return Ok(()),
+            Err(RequestError::Io(e)) => return Err(e),
+            Err(RequestError::Malformed(status)) => {
+                let resp = HttpResponse::empty(status);
+                write_response(&mut socket, &resp, false, false).await?;
+                return Ok(());
+            }
+        };
+        timeout = KEEP_ALIVE_TIMEOUT;
+
+        let keep_alive = req.keep_alive();
+        let is_head = req.method == "HEAD";
+
+        let resp = match req.method.as_str() {
+            "OPTIONS" => preflight_response(&req),
+            "GET" | "HEAD" => {
+                if !is_authorized(&req, ctx.config.token.as_deref()) {
+                    HttpResponse::empty(401).header("WWW-Authenticate", "Bearer")
+                } else {
+                    match route(&req.path, ctx.config.stream_path) {
+                        Route::Stream => {
+                            return serve_stream(socket, &req, ctx, shutdown_rx).await;
+                        }
+                        Route::Playlist => playlist_response(&req, ctx.config.stream_path),
+                        Route::Stats => stats_response(ctx),
+                        Route::Snapshot => snapshot_response(ctx),
+                        Route::NotFound => HttpResponse::empty(404),
+                    }
+                }
+            }
+            _ => HttpResponse::empty(405).header("Allow", ALLOWED_METHODS),
+        };
+
+        write_response(&mut socket, &resp, is_head, keep_alive).await?;
+        if !keep_alive {
+            return Ok(());
+        }
     }
+}
 
-    let req = String::from_utf8_lossy(&req_buf[..n]);
-    let first_line = req.lines().next().unwrap_or_default();
-    let is_stream_request = first_line.starts_with("GET /live.ts") || first_line.starts_with("GET / ");
+fn preflight_response(req: &HttpRequest) -> HttpResponse {
+    let allow_headers = req
+        .header("access-control-request-headers")
+        .unwrap_or("Authorization, Range")
+        .to_string();
+    HttpResponse::empty(204)
+        .header("Access-Control-Allow-Methods", ALLOWED_METHODS)
+        .header("Access-Control-Allow-Headers", allow_headers)
+        .header("Access-Control-Max-Age", "600")
+}
 
-    if !is_stream_request {
-        socket
-            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
-            .await
-            .map_err(|e| format!("http write 404 failed: {}", e))?;
-        return Ok(());
+fn playlist_response(req: &HttpRequest, stream_path: &str) -> HttpResponse {
+    let stream_file = stream_path.trim_start_matches('/');
+    let stream_ref = match req.query.get("token") {
+        Some(token) => format!("{}?token={}", stream_file, urlencoding::encode(token)),
+        None => stream_file.to_string(),
+    };
+    let body = format!("#EXTM3U\n#EXTINF:-1,mirrativ llstream\n{}\n", stream_ref);
+    HttpResponse::new(200, "audio/x-mpegurl", body.into_bytes())
+}
+
+fn stats_response(ctx: &HttpRelayContext) -> HttpResponse {
+    match serde_json::to_vec(&ctx.shared.stats_snapshot()) {
+        Ok(body) => HttpResponse::new(200, "application/json", body),
+        Err(_) => HttpResponse::empty(500),
     }
+}
+
+fn snapshot_response(ctx: &HttpRelayContext) -> HttpResponse {
+    match ctx.shared.latest_keyframe() {
+        Some(keyframe) => HttpResponse::new(200, keyframe.codec.mime_type(), keyframe.access_unit)
+            .header("X-Timestamp-Ns", keyframe.timestamp_ns.to_string())
+            .header("X-Captured-At-Ms", keyframe.captured_at_ms.to_string()),
+        // No decodable keyframe yet; the client may retry shortly.
+        None => HttpResponse::empty(503).header("Retry-After", "1"),
+    }
+}
+
+fn is_authorized(req: &HttpRequest, token: Option<&str>) -> bool {
+    let Some(expected) = token else {
+        return true;
+    };
+    if let Some(given) = req.query.get("token") {
+        return constant_time_eq(given.as_bytes(), expected.as_bytes());
+    }
+    req.header("authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|given| constant_time_eq(given.trim().as_bytes(), expected.as_bytes()))
+        .unwrap_or(false)
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// ---------------------------------------------------------------------------
+// Live stream route
+// ---------------------------------------------------------------------------
+
+/// Decrements the active client counter however the stream ends.
+struct ClientGuard<'a>(&'a RelayStats);
+
+impl<'a> ClientGuard<'a> {
+    fn acquire(stats: &'a RelayStats, max_clients: Option<u64>) -> Option<Self> {
+        stats
+            .try_client_connected(max_clients)
+            .then_some(Self(stats))
+    }
+}
+
+impl Drop for ClientGuard<'_> {
+    fn drop(&mut self) {
+        self.0.client_disconnected();
+    }
+}
+
+async fn serve_stream(
+    mut socket: TcpStream,
+    req: &HttpRequest,
+    ctx: &HttpRelayContext,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) -> Result<(), String> {
+    // A live stream has no addressable byte ranges; only "from the start" is satisfiable.
+    if let Some(range) = req.header("range") {
+        if range.trim() != "bytes=0-" {
+            let resp = HttpResponse::empty(416).header("Content-Range", "bytes */*");
+            return write_response(&mut socket, &resp, req.method == "HEAD", false).await;
+        }
+    }
+
+    let stats = &ctx.shared.stats;
+    let guard = if req.method == "HEAD" {
+        None
+    } else {
+        match ClientGuard::acquire(stats, ctx.config.max_clients) {
+            Some(guard) => Some(guard),
+            None => {
+                let resp = HttpResponse::empty(503).header("Retry-After", "5");
+                return write_response(&mut socket, &resp, false, false).await;
+            }
+        }
+    };
+
+    let chunked = req.version_minor >= 1;
+    let mut head = format!(
+        "HTTP/1.{} 200 OK\r\n\
+         Content-Type: {}\r\n\
+         Cache-Control: no-store\r\n\
+         Accept-Ranges: none\r\n\
+         Access-Control-Allow-Origin: *\r\n",
+        req.version_minor.min(1),
+        ctx.config.content_type
+    );
+    if chunked {
+        head.push_str("Transfer-Encoding: chunked\r\n");
+    }
+    head.push_str("Connection: close\r\n\r\n");
 
     socket
-        .write_all(
-            b"HTTP/1.1 200 OK\r\n\
-              Content-Type: video/mp2t\r\n\
-              Cache-Control: no-store\r\n\
-              Access-Control-Allow-Origin: *\r\n\
-              Connection: keep-alive\r\n\
-              \r\n",
-        )
+        .write_all(head.as_bytes())
         .await
         .map_err(|e| format!("http write header failed: {}", e))?;
-    socket
-        .write_all(&bootstrap)
-        .await
-        .map_err(|e| format!("http write bootstrap failed: {}", e))?;
+    if req.method == "HEAD" {
+        return Ok(());
+    }
+
+    let _guard = guard;
+    let mut rx = ctx.packet_tx.subscribe();
+
+    // The muxer publishes codec-specific tables once the video codec is known.
+    let bootstrap = ctx
+        .shared
+        .ts_bootstrap()
+        .unwrap_or_else(|| ctx.config.bootstrap.clone());
+    if !bootstrap.is_empty() {
+        write_body_chunk(&mut socket, &bootstrap, chunked).await?;
+        stats.add_bytes_sent(bootstrap.len());
+    }
 
     loop {
         tokio::select! {
             _ = shutdown_rx.changed() => {
                 if *shutdown_rx.borrow() {
+                    if chunked {
+                        let _ = socket.write_all(b"0\r\n\r\n").await;
+                    }
                     return Ok(());
                 }
             }
             recv = rx.recv() => {
                 match recv {
                     Ok(chunk) => {
-                        socket.write_all(&chunk).await.map_err(|e| format!("http stream write failed: {}", e))?;
+                        if chunk.is_empty() {
+                            continue;
+                        }
+                        write_body_chunk(&mut socket, &chunk, chunked).await?;
+                        stats.add_bytes_sent(chunk.len());
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        stats.add_lagged(skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        if chunked {
+                            let _ = socket.write_all(b"0\r\n\r\n").await;
+                        }
+                        return Ok(());
                     }
-                    Err(broadcast::error::RecvError::Lagged(_)) => {}
-                    Err(broadcast::error::RecvError::Closed) => return Ok(()),
                 }
             }
         }
     }
 }
+
+async fn write_body_chunk(socket: &mut TcpStream, data: &[u8], chunked: bool) -> Result<(), String> {
+    if chunked {
+        let mut framed = Vec::with_capacity(data.len() + 12);
+        framed.extend_from_slice(format!("{:X}\r\n", data.len()).as_bytes());
+        framed.extend_from_slice(data);
+        framed.extend_from_slice(b"\r\n");
+        socket.write_all(&framed).await
+    } else {
+        socket.write_all(data).await
+    }
+    .map_err(|e| format!("http stream write failed: {}", e))
+}
+
+// ---------------------------------------------------------------------------
+// Request parsing
+// ---------------------------------------------------------------------------
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    version_minor: u8,
+    headers: Vec<(String, String)>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").unwrap_or_default().to_ascii_lowercase();
+        if self.version_minor >= 1 {
+            !connection.contains("close")
+        } else {
+            connection.contains("keep-alive")
+        }
+    }
+}
+
+enum RequestError {
+    Timeout,
+    Io(String),
+    Malformed(u16),
+}
+
+/// Reads one request head from `socket`. Bytes past the head stay in `buf`
+/// so pipelined requests on a keep-alive connection are not lost.
+async fn read_request(
+    socket: &mut TcpStream,
+    buf: &mut Vec<u8>,
+    timeout: Duration,
+) -> Result<Option<HttpRequest>, RequestError> {
+    loop {
+        if let Some(end) = find_head_end(buf) {
+            let head = String::from_utf8_lossy(&buf[..end]).to_string();
+            buf.drain(..end + 4);
+            let req = parse_request_head(&head).ok_or(RequestError::Malformed(400))?;
+            discard_body(socket, buf, &req, timeout).await?;
+            return Ok(Some(req));
+        }
+        if buf.len() > MAX_REQUEST_HEAD_LEN {
+            return Err(RequestError::Malformed(431));
+        }
+
+        let mut chunk = [0u8; 4096];
+        let n = tokio::time::timeout(timeout, socket.read(&mut chunk))
+            .await
+            .map_err(|_| RequestError::Timeout)?
+            .map_err(|e| RequestError::Io(format!("http read failed: {}", e)))?;
+        if n == 0 {
+            return Ok(None);
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// None of the routes take a body, but a declared one must be skipped to keep
+/// the connection in sync.
+async fn discard_body(
+    socket: &mut TcpStream,
+    buf: &mut Vec<u8>,
+    req: &HttpRequest,
+    timeout: Duration,
+) -> Result<(), RequestError> {
+    if req.header("transfer-encoding").is_some() {
+        return Err(RequestError::Malformed(411));
+    }
+    let len = match req.header("content-length") {
+        Some(v) => v.trim().parse::<usize>().map_err(|_| RequestError::Malformed(400))?,
+        None => return Ok(()),
+    };
+    if len > MAX_DISCARDED_BODY_LEN {
+        return Err(RequestError::Malformed(413));
+    }
+
+    while buf.len() < len {
+        let mut chunk = [0u8; 4096];
+        let n = tokio::time::timeout(timeout, socket.read(&mut chunk))
+            .await
+            .map_err(|_| RequestError::Timeout)?
+            .map_err(|e| RequestError::Io(format!("http read failed: {}", e)))?;
+        if n == 0 {
+            return Err(RequestError::Io("connection closed inside request body".to_string()));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    buf.drain(..len);
+    Ok(())
+}
+
+fn find_head_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n")
+}
+
+fn parse_request_head(head: &str) -> Option<HttpRequest> {
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split(' ');
+    let method = request_line.next()?.to_string();
+    let target = request_line.next()?;
+    let version = request_line.next()?;
+    if request_line.next().is_some() || method.is_empty() {
+        return None;
+    }
+
+    let version_minor = match version {
+        "HTTP/1.1" => 1,
+        "HTTP/1.0" => 0,
+        _ => return None,
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, parse_query(query)),
+        None => (target, HashMap::new()),
+    };
+
+    let mut headers = Vec::new();
+    for line in lines {
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+
+    Some(HttpRequest {
+        method,
+        path: path.to_string(),
+        query,
+        version_minor,
+        headers,
+    })
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (k, v) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|c| c.into_owned())
+                    .unwrap_or_else(|_| s.to_string())
+            };
+            (decode(k), decode(v))
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Responses
+// ---------------------------------------------------------------------------
+
+struct HttpResponse {
+    status: u16,
+    content_type: Option<&'static str>,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    fn new(status: u16, content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type: Some(content_type),
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    fn empty(status: u16) -> Self {
+        Self {
+            status,
+            content_type: None,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        416 => "Range Not Satisfiable",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+async fn write_response(
+    socket: &mut TcpStream,
+    resp: &HttpResponse,
+    is_head: bool,
+    keep_alive: bool,
+) -> Result<(), String> {
+    let mut out = format!("HTTP/1.1 {} {}\r\n", resp.status, reason_phrase(resp.status));
+    if let Some(content_type) = resp.content_type {
+        out.push_str(&format!("Content-Type: {}\r\n", content_type));
+    }
+    out.push_str(&format!("Content-Length: {}\r\n", resp.body.len()));
+    out.push_str("Cache-Control: no-store\r\n");
+    out.push_str("Access-Control-Allow-Origin: *\r\n");
+    for (name, value) in &resp.headers {
+        out.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    out.push_str(if keep_alive {
+        "Connection: keep-alive\r\n\r\n"
+    } else {
+        "Connection: close\r\n\r\n"
+    });
+
+    let mut bytes = out.into_bytes();
+    if !is_head {
+        bytes.extend_from_slice(&resp.body);
+    }
+    socket
+        .write_all(&bytes)
+        .await
+        .map_err(|e| format!("http write {} failed: {}", resp.status, e))
+}
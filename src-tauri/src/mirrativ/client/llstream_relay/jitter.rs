@@ -0,0 +1,306 @@
+use std::collections::VecDeque;
+use tokio::time::{Duration, Instant};
+
+use super::AvSample;
+
+/// Default hold time before a sample is muxed without its counterpart stream.
+pub(super) const DEFAULT_AV_LATENCY_MS: u32 = 150;
+const MAX_AV_LATENCY_MS: u32 = 2_000;
+/// Larger differences between a timestamp and the expected stream time are a clock reset.
+const CLOCK_RESET_NS: u64 = 1_000_000_000;
+
+// ---------------------------------------------------------------------------
+// StreamClock — maps one stream's timestamps onto the shared A/V timeline
+// ---------------------------------------------------------------------------
+
+/// Video and audio start on the same source clock. After a reconnect either one
+/// may restart from a new origin, so each stream keeps its own offset and
+/// re-anchors it to the estimated current time when its timestamps jump.
+#[derive(Default)]
+struct StreamClock {
+    offset_ns: i128,
+    last_raw_ns: Option<u64>,
+    last_ns: Option<u64>,
+    last_arrival: Option<Instant>,
+}
+
+impl StreamClock {
+    /// Where this stream's timeline should be now, extrapolated with wall time.
+    fn expected_ns(&self, now: Instant) -> Option<u64> {
+        let last = self.last_ns?;
+        let elapsed = now.saturating_duration_since(self.last_arrival?);
+        Some(last.saturating_add(elapsed.as_nanos() as u64))
+    }
+
+    fn map(&mut self, raw_ns: u64, other: &StreamClock, now: Instant) -> u64 {
+        let expected = match self.last_raw_ns {
+            // A jump in the raw clock is only a reset if wall time does not explain it.
+            Some(last_raw) if raw_ns.abs_diff(last_raw) > CLOCK_RESET_NS => self.expected_ns(now),
+            Some(_) => None,
+            // First sample: trust the shared source clock unless the other stream disagrees.
+            None => other.expected_ns(now),
+        };
+        let mut mapped = apply_offset(raw_ns, self.offset_ns);
+
+        if let Some(expected) = expected {
+            if mapped.abs_diff(expected) > CLOCK_RESET_NS {
+                self.offset_ns = expected as i128 - raw_ns as i128;
+                mapped = apply_offset(raw_ns, self.offset_ns);
+            }
+        }
+
+        self.last_raw_ns = Some(raw_ns);
+        self.last_ns = Some(mapped);
+        self.last_arrival = Some(now);
+        mapped
+    }
+}
+
+fn apply_offset(raw_ns: u64, offset_ns: i128) -> u64 {
+    (raw_ns as i128 + offset_ns).clamp(0, u64::MAX as i128) as u64
+}
+
+// ---------------------------------------------------------------------------
+// AvJitterBuffer — timestamp-ordered interleaving of the two sample streams
+// ---------------------------------------------------------------------------
+
+struct Queued {
+    timestamp_ns: u64,
+    arrived: Instant,
+    sample: AvSample,
+}
+
+/// Holds samples for up to `latency` so video and audio leave in timestamp order.
+/// A sample is released as soon as the other stream has caught up with it, or
+/// when its hold time runs out (e.g. audio-only periods).
+pub(super) struct AvJitterBuffer {
+    latency: Duration,
+    video_clock: StreamClock,
+    audio_clock: StreamClock,
+    video: VecDeque<Queued>,
+    audio: VecDeque<Queued>,
+}
+
+impl AvJitterBuffer {
+    pub(super) fn new(latency_ms: Option<u32>) -> Self {
+        let latency_ms = latency_ms
+            .unwrap_or(DEFAULT_AV_LATENCY_MS)
+            .min(MAX_AV_LATENCY_MS);
+        Self {
+            latency: Duration::from_millis(latency_ms as u64),
+            video_clock: StreamClock::default(),
+            audio_clock: StreamClock::default(),
+            video: VecDeque::new(),
+            audio: VecDeque::new(),
+        }
+    }
+
+    pub(super) fn push(&mut self, mut sample: AvSample, now: Instant) {
+        let (clock, other, queue) = match sample {
+            AvSample::Video { .. } => (&mut self.video_clock, &self.audio_clock, &mut self.video),
+            AvSample::Audio { .. } => (&mut self.audio_clock, &self.video_clock, &mut self.audio),
+        };
+        let timestamp_ns = clock.map(sample.timestamp_ns(), other, now);
+        sample.set_timestamp_ns(timestamp_ns);
+
+        // Keep each queue sorted; out-of-order arrivals are rare and near the back.
+        let index = queue
+            .iter()
+            .rposition(|q| q.timestamp_ns <= timestamp_ns)
+            .map_or(0, |i| i + 1);
+        queue.insert(
+            index,
+            Queued {
+                timestamp_ns,
+                arrived: now,
+                sample,
+            },
+        );
+    }
+
+    /// Next sample that may be muxed at `now`, in timestamp order.
+    pub(super) fn pop_ready(&mut self, now: Instant) -> Option<AvSample> {
+        let expired = |q: &Queued| now >= q.arrived + self.latency;
+        let from_video = match (self.video.front(), self.audio.front()) {
+            (Some(v), Some(a)) => v.timestamp_ns <= a.timestamp_ns,
+            // Without the other stream to compare against, only an expired head may go.
+            (Some(v), None) if expired(v) => true,
+            (None, Some(a)) if expired(a) => false,
+            _ => return None,
+        };
+        let queue = if from_video {
+            &mut self.video
+        } else {
+            &mut self.audio
+        };
+        queue.pop_front().map(|q| q.sample)
+    }
+
+    /// When the oldest held sample expires, for the mux task's timer.
+    pub(super) fn next_deadline(&self) -> Option<Instant> {
+        [self.video.front(), self.audio.front()]
+            .into_iter()
+            .flatten()
+            .map(|q| q.arrived + self.latency)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mirrativ::client::llstream_relay::parser::VideoCodec;
+
+    const MS: u64 = 1_000_000;
+
+    fn video(timestamp_ns: u64) -> AvSample {
+        AvSample::Video {
+            timestamp_ns,
+            codec: VideoCodec::H264,
+            annexb: Vec::new(),
+            discontinuity: false,
+        }
+    }
+
+    fn audio(timestamp_ns: u64) -> AvSample {
+        AvSample::Audio {
+            timestamp_ns,
+            adts_frame: Vec::new(),
+            discontinuity: false,
+        }
+    }
+
+    /// ('v' | 'a', timestamp in ms) of everything releasable at `now`.
+    fn drain(buffer: &mut AvJitterBuffer, now: Instant) -> Vec<(char, u64)> {
+        std::iter::from_fn(|| buffer.pop_ready(now))
+            .map(|sample| match sample {
+                AvSample::Video { timestamp_ns, .. } => ('v', timestamp_ns / MS),
+                AvSample::Audio { timestamp_ns, .. } => ('a', timestamp_ns / MS),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn interleaves_out_of_order_samples() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(None);
+        let base = 10_000 * MS;
+        for (i, sample) in [
+            video(base),
+            audio(base + 21 * MS),
+            video(base + 66 * MS),
+            audio(base),
+            video(base + 33 * MS),
+            audio(base + 42 * MS),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            buffer.push(sample, start + Duration::from_millis(i as u64));
+        }
+
+        let now = start + Duration::from_millis(10);
+        assert_eq!(
+            drain(&mut buffer, now),
+            [
+                ('v', 10_000),
+                ('a', 10_000),
+                ('a', 10_021),
+                ('v', 10_033),
+                ('a', 10_042),
+            ]
+        );
+        // The last video frame waits for audio to catch up, or for its deadline.
+        let deadline = buffer.next_deadline().expect("video held");
+        assert_eq!(deadline, start + Duration::from_millis(2 + 150));
+        assert!(drain(&mut buffer, deadline - Duration::from_millis(1)).is_empty());
+        assert_eq!(drain(&mut buffer, deadline), [('v', 10_066)]);
+        assert_eq!(buffer.next_deadline(), None);
+    }
+
+    #[test]
+    fn single_stream_is_released_at_deadline() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(Some(100));
+        buffer.push(audio(0), start);
+        buffer.push(audio(21 * MS), start + Duration::from_millis(21));
+
+        assert!(drain(&mut buffer, start).is_empty());
+        assert_eq!(
+            buffer.next_deadline(),
+            Some(start + Duration::from_millis(100))
+        );
+        assert_eq!(
+            drain(&mut buffer, start + Duration::from_millis(100)),
+            [('a', 0)]
+        );
+        assert_eq!(
+            buffer.next_deadline(),
+            Some(start + Duration::from_millis(121))
+        );
+        assert_eq!(
+            drain(&mut buffer, start + Duration::from_millis(121)),
+            [('a', 21)]
+        );
+    }
+
+    #[test]
+    fn latency_is_capped() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(Some(60_000));
+        buffer.push(video(0), start);
+        let cap = Duration::from_millis(MAX_AV_LATENCY_MS as u64);
+        assert_eq!(buffer.next_deadline(), Some(start + cap));
+    }
+
+    #[test]
+    fn timestamp_reset_is_reanchored() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(Some(0));
+        buffer.push(video(100_000 * MS), start);
+        buffer.push(video(100_033 * MS), start + Duration::from_millis(33));
+        // Reconnect: the source clock restarts near zero 33 ms later.
+        buffer.push(video(5 * MS), start + Duration::from_millis(66));
+        buffer.push(video(38 * MS), start + Duration::from_millis(99));
+
+        let now = start + Duration::from_millis(99);
+        assert_eq!(
+            drain(&mut buffer, now),
+            [
+                ('v', 100_000),
+                ('v', 100_033),
+                ('v', 100_066),
+                ('v', 100_099),
+            ]
+        );
+    }
+
+    #[test]
+    fn wall_clock_gap_is_not_a_reset() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(Some(0));
+        buffer.push(audio(50_000 * MS), start);
+        // A 3 s stall: the timestamps jump, but so does wall time.
+        buffer.push(audio(53_000 * MS), start + Duration::from_secs(3));
+
+        let now = start + Duration::from_secs(3);
+        assert_eq!(drain(&mut buffer, now), [('a', 50_000), ('a', 53_000)]);
+    }
+
+    #[test]
+    fn new_stream_origin_follows_the_other_stream() {
+        let start = Instant::now();
+        let mut buffer = AvJitterBuffer::new(Some(0));
+        buffer.push(video(80_000 * MS), start);
+        // Audio joins 20 ms later on its own origin; it is mapped onto video's timeline.
+        buffer.push(audio(3 * MS), start + Duration::from_millis(20));
+        // Audio on the shared clock is kept as-is.
+        let mut shared = AvJitterBuffer::new(Some(0));
+        shared.push(video(80_000 * MS), start);
+        shared.push(audio(80_010 * MS), start + Duration::from_millis(20));
+
+        let now = start + Duration::from_millis(20);
+        assert_eq!(drain(&mut buffer, now), [('v', 80_000), ('a', 80_020)]);
+        assert_eq!(drain(&mut shared, now), [('v', 80_000), ('a', 80_010)]);
+    }
+}
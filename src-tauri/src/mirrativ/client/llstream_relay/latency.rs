@@ -0,0 +1,299 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use super::parser::{is_non_reference, VideoCodec};
+use super::stats::RelayShared;
+use super::{relay_log, RelayEvents};
+use crate::mpv_player::MpvPlayerManager;
+
+const CONTROL_INTERVAL: Duration = Duration::from_millis(500);
+/// Timestamps within this distance of the wall clock are taken as unix time.
+const ABSOLUTE_CLOCK_WINDOW_NS: u128 = 24 * 3600 * 1_000_000_000;
+/// Delay jumps beyond this re-baseline the relative clock (source clock reset).
+const BASELINE_RESET_NS: i128 = 10_000_000_000;
+/// MPEG-TS PTS are 33-bit; differences are taken modulo this.
+const PTS_MASK: u64 = (1 << 33) - 1;
+
+/// Playback speed steps by how far behind the target we are (excess ms, speed).
+const SPEED_STEPS: [(u64, f64); 3] = [(2_000, 1.15), (500, 1.08), (150, 1.04)];
+/// Back to normal speed once within this distance of the target.
+const SPEED_RELEASE_MS: u64 = 50;
+/// Non-reference frames are dropped at the relay beyond this excess, until below the release.
+const DROP_ENGAGE_MS: u64 = 1_000;
+const DROP_RELEASE_MS: u64 = 300;
+
+// ---------------------------------------------------------------------------
+// LatencyProbe — source delay measured from MR header timestamps
+// ---------------------------------------------------------------------------
+
+/// Source delay of the newest frame. When the MR timestamps are unix time the
+/// delay is absolute; otherwise it is relative to the fastest arrival seen.
+#[derive(Default)]
+pub(crate) struct LatencyProbe {
+    state: Mutex<ProbeState>,
+    drop_non_reference: AtomicBool,
+    dropped_frames: AtomicU64,
+}
+
+#[derive(Default)]
+struct ProbeState {
+    absolute: Option<bool>,
+    baseline_ns: Option<i128>,
+    delay_ns: Option<i128>,
+    /// PTS of the newest TS chunk handed to the players.
+    published_pts_90k: Option<u64>,
+}
+
+impl LatencyProbe {
+    pub(crate) fn observe(&self, timestamp_ns: u64) {
+        let Ok(now) = SystemTime::now().duration_since(UNIX_EPOCH) else {
+            return;
+        };
+        let now_ns = now.as_nanos();
+        let Ok(mut state) = self.state.lock() else {
+            return;
+        };
+
+        let absolute = *state
+            .absolute
+            .get_or_insert(now_ns.abs_diff(timestamp_ns as u128) < ABSOLUTE_CLOCK_WINDOW_NS);
+        let raw = now_ns as i128 - timestamp_ns as i128;
+        if absolute {
+            state.delay_ns = Some(raw.max(0));
+            return;
+        }
+
+        let baseline = match state.baseline_ns {
+            Some(baseline) if (raw - baseline).abs() < BASELINE_RESET_NS => baseline.min(raw),
+            _ => raw,
+        };
+        state.baseline_ns = Some(baseline);
+        state.delay_ns = Some(raw - baseline);
+    }
+
+    /// Records the PTS of a TS chunk just published to the players.
+    pub(crate) fn published(&self, pts_90k: u64) {
+        if let Ok(mut state) = self.state.lock() {
+            state.published_pts_90k = Some(pts_90k & PTS_MASK);
+        }
+    }
+
+    /// How far a player at `player_pts_90k` trails the newest published PTS.
+    fn player_lag_ms(&self, player_pts_90k: u64) -> Option<u64> {
+        let published = self.state.lock().ok()?.published_pts_90k?;
+        let behind = published.wrapping_sub(player_pts_90k) & PTS_MASK;
+        // Past half the PTS range the player is ahead (it just caught up with a chunk).
+        Some(if behind > PTS_MASK / 2 {
+            0
+        } else {
+            behind / 90
+        })
+    }
+
+    fn source_delay_ms(&self) -> Option<u64> {
+        let state = self.state.lock().ok()?;
+        state.delay_ns.map(|ns| (ns / 1_000_000) as u64)
+    }
+
+    fn source_clock(&self) -> &'static str {
+        match self.state.lock().ok().and_then(|state| state.absolute) {
+            Some(true) => "absolute",
+            _ => "relative",
+        }
+    }
+
+    /// Whether the relay should skip this access unit to catch up.
+    pub(crate) fn should_drop(
+        &self,
+        access_unit: &[u8],
+        codec: VideoCodec,
+        keyframe: bool,
+    ) -> bool {
+        if keyframe || !self.drop_non_reference.load(Ordering::Relaxed) {
+            return false;
+        }
+        if !is_non_reference(access_unit, codec) {
+            return false;
+        }
+        self.dropped_frames.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Controller task — measures end-to-end delay and steers mpv / the relay
+// ---------------------------------------------------------------------------
+
+/// Payload of `llstream://latency`.
+#[derive(Serialize, Clone)]
+struct LatencyReport {
+    /// None while catch-up control is disabled (measurement only).
+    target_ms: Option<u32>,
+    /// Source delay plus player lag: how far playback is behind the live edge.
+    total_ms: u64,
+    source_delay_ms: Option<u64>,
+    /// Newest PTS the relay published minus the player's position on the same clock.
+    /// None when no player is playing the relay (or the relay publishes raw ADTS).
+    player_lag_ms: Option<u64>,
+    source_clock: &'static str,
+    speed: f64,
+    dropping_non_reference: bool,
+    dropped_frames: u64,
+}
+
+/// Target end-to-end latency in ms; 0 disables catch-up and only reports.
+pub(crate) type LatencyTarget = Arc<AtomicU32>;
+
+/// Only the player whose current URL is `relay_url` is steered; other players
+/// (multi-view, non-relay streams) are left alone.
+pub(crate) fn spawn_latency_controller(
+    app: AppHandle,
+    relay_url: String,
+    shared: Arc<RelayShared>,
+    target: LatencyTarget,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let events = RelayEvents::frontend(&app);
+        let mut interval = tokio::time::interval(CONTROL_INTERVAL);
+        let mut speed = 1.0f64;
+        // Player the speed was last set on, to restore it on exit or when it moves on.
+        let mut steered: Option<String> = None;
+
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        break;
+                    }
+                }
+                _ = interval.tick() => {
+                    let mpv = app.state::<MpvPlayerManager>();
+                    let player_id = mpv.player_for_url(&relay_url);
+                    if player_id != steered {
+                        // A player that left the relay keeps its own speed settings.
+                        steered = None;
+                        speed = 1.0;
+                    }
+                    let probe = &shared.latency;
+                    let source_delay_ms = probe.source_delay_ms();
+                    let player_lag_ms = player_id
+                        .as_deref()
+                        .and_then(|id| player_pts_90k(&mpv, id))
+                        .and_then(|pts_90k| probe.player_lag_ms(pts_90k));
+                    if source_delay_ms.is_none() && player_lag_ms.is_none() {
+                        continue;
+                    }
+                    let total_ms = source_delay_ms.unwrap_or(0) + player_lag_ms.unwrap_or(0);
+
+                    let target_ms = match target.load(Ordering::Relaxed) {
+                        0 => None,
+                        ms => Some(ms),
+                    };
+                    let excess_ms = target_ms.map_or(0, |t| total_ms.saturating_sub(t as u64));
+
+                    let dropping = probe.drop_non_reference.load(Ordering::Relaxed);
+                    let drop_now = target_ms.is_some()
+                        && if dropping {
+                            excess_ms > DROP_RELEASE_MS
+                        } else {
+                            excess_ms > DROP_ENGAGE_MS
+                        };
+                    if drop_now != dropping {
+                        probe.drop_non_reference.store(drop_now, Ordering::Relaxed);
+                        let state = if drop_now { "on" } else { "off" };
+                        relay_log(&events, &format!("llstream latency: non-reference drop {}", state));
+                    }
+
+                    let next_speed = next_speed(speed, excess_ms, target_ms.is_some());
+                    if let Some(id) = player_id.as_deref().filter(|_| next_speed != speed) {
+                        match mpv.player_command(id, &["set", "speed", &format!("{:.2}", next_speed)]) {
+                            Ok(()) => {
+                                speed = next_speed;
+                                steered = player_id.clone();
+                            }
+                            Err(e) => {
+                                let msg = format!("llstream latency: set speed failed: {}", e);
+                                relay_log(&events, &msg);
+                            }
+                        }
+                    }
+
+                    let _ = app.emit(
+                        "llstream://latency",
+                        LatencyReport {
+                            target_ms,
+                            total_ms,
+                            source_delay_ms,
+                            player_lag_ms,
+                            source_clock: probe.source_clock(),
+                            speed,
+                            dropping_non_reference: drop_now,
+                            dropped_frames: probe.dropped_frames.load(Ordering::Relaxed),
+                        },
+                    );
+                }
+            }
+        }
+
+        // Leave the player at normal speed when the relay goes away, unless it has
+        // already moved on to another URL.
+        if let Some(id) = steered.filter(|_| speed != 1.0) {
+            let mpv = app.state::<MpvPlayerManager>();
+            if mpv.player_for_url(&relay_url).as_deref() == Some(id.as_str()) {
+                let _ = mpv.player_command(&id, &["set", "speed", "1.00"]);
+            }
+        }
+    })
+}
+
+/// Playback position of `player_id` on the stream's PTS clock. mpv rebases `time-pos`
+/// to the first timestamp it saw, so `demuxer-start-time` is added back.
+fn player_pts_90k(mpv: &MpvPlayerManager, player_id: &str) -> Option<u64> {
+    let seconds = |name: &str| {
+        let value = mpv.get_player_property(player_id, name).ok()?;
+        value.trim().parse::<f64>().ok()
+    };
+    let pts = seconds("time-pos")? + seconds("demuxer-start-time")?;
+    (pts >= 0.0).then_some((pts * 90_000.0) as u64 & PTS_MASK)
+}
+
+/// Speeds up in steps while behind; holds the current speed inside the hysteresis band.
+fn next_speed(current: f64, excess_ms: u64, enabled: bool) -> f64 {
+    if !enabled || excess_ms <= SPEED_RELEASE_MS {
+        return 1.0;
+    }
+    SPEED_STEPS
+        .iter()
+        .find(|(threshold, _)| excess_ms > *threshold)
+        .map_or(current, |&(_, speed)| speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_lag_is_measured_against_the_published_pts() {
+        let probe = LatencyProbe::default();
+        assert_eq!(probe.player_lag_ms(0), None);
+
+        probe.published(90_000 * 10);
+        assert_eq!(probe.player_lag_ms(90_000 * 9), Some(1_000));
+        // A player that already reached a newer chunk is not behind.
+        assert_eq!(probe.player_lag_ms(90_000 * 10 + 900), Some(0));
+    }
+
+    #[test]
+    fn player_lag_survives_the_pts_wrap() {
+        let probe = LatencyProbe::default();
+        probe.published(PTS_MASK + 1 + 4_500);
+        assert_eq!(probe.player_lag_ms(PTS_MASK - 4_499), Some(100));
+    }
+}
@@ -0,0 +1,338 @@
+use socket2::{Domain, Protocol, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use tokio::net::UdpSocket;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Duration;
+
+use super::{relay_log, RelayEvents};
+
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const MDNS_PORT: u16 = 5353;
+const SERVICE_TYPE: [&str; 3] = ["_http", "_tcp", "local"];
+const SERVICES_META: [&str; 4] = ["_services", "_dns-sd", "_udp", "local"];
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_SRV: u16 = 33;
+const CLASS_IN: u16 = 1;
+/// mDNS cache-flush bit on the class of records we are authoritative for.
+const CACHE_FLUSH: u16 = 0x8000;
+/// mDNS unicast-response bit on the class of a question.
+const UNICAST_RESPONSE: u16 = 0x8000;
+
+const TTL_HOST: u32 = 120;
+const TTL_SERVICE: u32 = 4500;
+
+/// Back-off after a failed receive, growing with each consecutive failure. Some
+/// errors repeat forever (e.g. WSAECONNRESET after an ICMP unreachable on Windows).
+const RECV_ERROR_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_RECV_ERROR_BACKOFF: Duration = Duration::from_secs(5);
+/// Consecutive receive failures after which the responder gives up.
+const MAX_RECV_ERRORS: u32 = 20;
+
+// ---------------------------------------------------------------------------
+// Advertised service
+// ---------------------------------------------------------------------------
+
+/// One `_http._tcp` DNS-SD service pointing at the relay stream.
+pub(super) struct MdnsService {
+    pub(super) instance: String,
+    pub(super) ip: Ipv4Addr,
+    pub(super) port: u16,
+    pub(super) txt: Vec<String>,
+}
+
+impl MdnsService {
+    fn service_name(&self) -> Vec<String> {
+        SERVICE_TYPE.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn instance_name(&self) -> Vec<String> {
+        let mut name = vec![self.instance.clone()];
+        name.extend(self.service_name());
+        name
+    }
+
+    fn host_name(&self) -> Vec<String> {
+        vec![format!("mirrativ-relay-{}", self.port), "local".to_string()]
+    }
+
+    fn meta_name() -> Vec<String> {
+        SERVICES_META.iter().map(|s| s.to_string()).collect()
+    }
+
+    /// Whether a question is about any of the names we own.
+    fn answers(&self, question: &[String]) -> bool {
+        [
+            self.service_name(),
+            self.instance_name(),
+            self.host_name(),
+            Self::meta_name(),
+        ]
+        .iter()
+        .any(|name| names_equal(name, question))
+    }
+
+    /// Full response carrying PTR/SRV/TXT/A. `goodbye` sends TTL 0 to flush caches.
+    fn response(&self, id: u16, goodbye: bool) -> Vec<u8> {
+        let ttl = |t: u32| if goodbye { 0 } else { t };
+        let mut answers: Vec<Vec<u8>> = Vec::new();
+
+        answers.push(encode_record(
+            &Self::meta_name(),
+            TYPE_PTR,
+            CLASS_IN,
+            ttl(TTL_SERVICE),
+            &encode_name(&self.service_name()),
+        ));
+        answers.push(encode_record(
+            &self.service_name(),
+            TYPE_PTR,
+            CLASS_IN,
+            ttl(TTL_SERVICE),
+            &encode_name(&self.instance_name()),
+        ));
+
+        let mut srv = Vec::new();
+        srv.extend_from_slice(&0u16.to_be_bytes()); // priority
+        srv.extend_from_slice(&0u16.to_be_bytes()); // weight
+        srv.extend_from_slice(&self.port.to_be_bytes());
+        srv.extend_from_slice(&encode_name(&self.host_name()));
+        answers.push(encode_record(
+            &self.instance_name(),
+            TYPE_SRV,
+            CLASS_IN | CACHE_FLUSH,
+            ttl(TTL_HOST),
+            &srv,
+        ));
+
+        let mut txt = Vec::new();
+        for entry in &self.txt {
+            let bytes = &entry.as_bytes()[..entry.len().min(255)];
+            txt.push(bytes.len() as u8);
+            txt.extend_from_slice(bytes);
+        }
+        if txt.is_empty() {
+            txt.push(0);
+        }
+        answers.push(encode_record(
+            &self.instance_name(),
+            TYPE_TXT,
+            CLASS_IN | CACHE_FLUSH,
+            ttl(TTL_SERVICE),
+            &txt,
+        ));
+
+        answers.push(encode_record(
+            &self.host_name(),
+            TYPE_A,
+            CLASS_IN | CACHE_FLUSH,
+            ttl(TTL_HOST),
+            &self.ip.octets(),
+        ));
+
+        let mut out = Vec::with_capacity(512);
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&0x8400u16.to_be_bytes()); // response + authoritative
+        out.extend_from_slice(&0u16.to_be_bytes()); // questions
+        out.extend_from_slice(&(answers.len() as u16).to_be_bytes());
+        out.extend_from_slice(&0u16.to_be_bytes()); // authority
+        out.extend_from_slice(&0u16.to_be_bytes()); // additional
+        for record in answers {
+            out.extend_from_slice(&record);
+        }
+        out
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Responder task
+// ---------------------------------------------------------------------------
+
+pub(super) fn spawn_mdns_responder(
+    events: RelayEvents,
+    service: MdnsService,
+    mut shutdown_rx: watch::Receiver<bool>,
+) -> Result<JoinHandle<()>, String> {
+    let socket = bind_mdns_socket(service.ip)
+        .map_err(|e| format!("failed to bind mdns socket: {}", e))?;
+    let group = SocketAddr::V4(SocketAddrV4::new(MDNS_GROUP, MDNS_PORT));
+
+    Ok(tokio::spawn(async move {
+        relay_log(
+            &events,
+            &format!(
+                "llstream mdns advertising \"{}\" at {}:{}",
+                service.instance, service.ip, service.port
+            ),
+        );
+
+        // RFC 6762 8.3: announce at least twice, one second apart.
+        let mut announce = tokio::time::interval(Duration::from_secs(1));
+        let mut announcements_left = 2u8;
+        let mut buf = vec![0u8; 9000];
+        let mut recv_errors = 0u32;
+
+        loop {
+            tokio::select! {
+                _ = shutdown_rx.changed() => {
+                    if *shutdown_rx.borrow() {
+                        let _ = socket.send_to(&service.response(0, true), group).await;
+                        relay_log(&events, "llstream mdns stopping");
+                        return;
+                    }
+                }
+                _ = announce.tick(), if announcements_left > 0 => {
+                    announcements_left -= 1;
+                    let _ = socket.send_to(&service.response(0, false), group).await;
+                }
+                recv = socket.recv_from(&mut buf) => {
+                    let (n, from) = match recv {
+                        Ok(received) => {
+                            recv_errors = 0;
+                            received
+                        }
+                        Err(e) => {
+                            recv_errors += 1;
+                            if recv_errors >= MAX_RECV_ERRORS {
+                                relay_log(&events, &format!("llstream mdns stopped: {}", e));
+                                return;
+                            }
+                            if recv_errors == 1 {
+                                relay_log(&events, &format!("llstream mdns recv failed: {}", e));
+                            }
+                            let backoff = RECV_ERROR_BACKOFF * recv_errors;
+                            tokio::time::sleep(backoff.min(MAX_RECV_ERROR_BACKOFF)).await;
+                            continue;
+                        }
+                    };
+                    let Some(query) = parse_query(&buf[..n]) else {
+                        continue;
+                    };
+                    if !query.questions.iter().any(|q| service.answers(&q.name)) {
+                        continue;
+                    }
+
+                    // Legacy resolvers (source port != 5353) and QU questions get a
+                    // unicast reply echoing their ID; everyone else hears it on the group.
+                    let unicast = from.port() != MDNS_PORT
+                        || query.questions.iter().any(|q| q.unicast_response);
+                    if unicast {
+                        let id = if from.port() != MDNS_PORT { query.id } else { 0 };
+                        let _ = socket.send_to(&service.response(id, false), from).await;
+                    } else {
+                        let _ = socket.send_to(&service.response(0, false), group).await;
+                    }
+                }
+            }
+        }
+    }))
+}
+
+fn bind_mdns_socket(interface: Ipv4Addr) -> std::io::Result<UdpSocket> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    // Share port 5353 with the OS responder (Bonjour / Avahi) if one is running.
+    socket.set_reuse_address(true)?;
+    #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+    socket.set_reuse_port(true)?;
+    socket.bind(&SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, MDNS_PORT)).into())?;
+    socket.join_multicast_v4(&MDNS_GROUP, &interface)?;
+    socket.set_multicast_if_v4(&interface)?;
+    socket.set_multicast_ttl_v4(255)?;
+    socket.set_nonblocking(true)?;
+    UdpSocket::from_std(socket.into())
+}
+
+// ---------------------------------------------------------------------------
+// DNS wire format
+// ---------------------------------------------------------------------------
+
+struct Question {
+    name: Vec<String>,
+    unicast_response: bool,
+}
+
+struct Query {
+    id: u16,
+    questions: Vec<Question>,
+}
+
+fn parse_query(packet: &[u8]) -> Option<Query> {
+    if packet.len() < 12 {
+        return None;
+    }
+    let id = u16::from_be_bytes([packet[0], packet[1]]);
+    let flags = u16::from_be_bytes([packet[2], packet[3]]);
+    if flags & 0x8000 != 0 {
+        return None; // a response, not a query
+    }
+    let qdcount = u16::from_be_bytes([packet[4], packet[5]]);
+
+    let mut pos = 12usize;
+    let mut questions = Vec::with_capacity(qdcount as usize);
+    for _ in 0..qdcount {
+        let (name, next) = read_name(packet, pos)?;
+        let class = u16::from_be_bytes([*packet.get(next + 2)?, *packet.get(next + 3)?]);
+        questions.push(Question {
+            name,
+            unicast_response: class & UNICAST_RESPONSE != 0,
+        });
+        pos = next + 4;
+    }
+
+    Some(Query { id, questions })
+}
+
+/// Reads a possibly compressed name at `pos`; returns the labels and the offset past it.
+fn read_name(packet: &[u8], mut pos: usize) -> Option<(Vec<String>, usize)> {
+    let mut labels = Vec::new();
+    let mut end: Option<usize> = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            return Some((labels, end.unwrap_or(pos + 1)));
+        }
+        if len & 0xC0 == 0xC0 {
+            let target = ((len & 0x3F) << 8) | *packet.get(pos + 1)? as usize;
+            end.get_or_insert(pos + 2);
+            jumps += 1;
+            if jumps > 16 || target >= packet.len() {
+                return None;
+            }
+            pos = target;
+            continue;
+        }
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+}
+
+fn names_equal(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| x.eq_ignore_ascii_case(y))
+}
+
+fn encode_name(labels: &[String]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for label in labels {
+        let bytes = &label.as_bytes()[..label.len().min(63)];
+        out.push(bytes.len() as u8);
+        out.extend_from_slice(bytes);
+    }
+    out.push(0);
+    out
+}
+
+fn encode_record(name: &[String], rtype: u16, class: u16, ttl: u32, rdata: &[u8]) -> Vec<u8> {
+    let mut out = encode_name(name);
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&class.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+    out
+}
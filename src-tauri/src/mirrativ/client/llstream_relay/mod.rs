@@ -1,26 +1,47 @@
 use serde::Serialize;
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpListener;
 #[cfg(windows)]
 use tokio::net::windows::named_pipe::ServerOptions;
 use tokio::sync::{broadcast, mpsc, watch, RwLock};
 use tokio::task::JoinHandle;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 use uuid::Uuid;
 
+mod clip;
+mod continuity;
+mod endpoint;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
 mod http;
+mod jitter;
+mod latency;
+mod mdns;
 mod mux;
 mod parser;
+mod sps;
+mod stats;
 mod ws;
 
-use http::spawn_http_relay_task;
-use mux::{build_bootstrap_tables, build_bootstrap_tables_av, AvMpegTsMuxer};
+use clip::{ClipCapture, MAX_CLIP_SECS};
+use continuity::StreamContinuity;
+use endpoint::RelayEndpoint;
+use http::{spawn_http_relay_task, HttpRelayConfig};
+use jitter::AvJitterBuffer;
+use latency::{spawn_latency_controller, LatencyTarget};
+use mux::{
+    build_bootstrap_tables, build_bootstrap_tables_audio, build_bootstrap_tables_av,
+    AudioMpegTsMuxer, AvMpegTsMuxer,
+};
 use parser::{
-    ensure_annexb, extract_parameter_sets, has_nal_type, parse_video_packet,
-    FRAME_KIND_IDR, FRAME_KIND_PPS, FRAME_KIND_SPS, NAL_START_CODE,
+    detect_video_codec, ensure_annexb, extract_parameter_sets, find_nal, has_nal_type,
+    has_random_access_nal, parse_video_packet, ParameterSets, VideoCodec, FRAME_KIND_IDR,
+    FRAME_KIND_PPS, FRAME_KIND_SPS, NAL_START_CODE,
 };
+pub use endpoint::LlstreamRelayOptions;
+pub use sps::VideoStreamInfo;
+use stats::{KeyframeSnapshot, RelayShared, RelayStatsSnapshot};
 use ws::{
     run_audio_ws_to_av_samples_loop, run_video_ws_loop, run_video_ws_to_annexb_loop,
     run_video_ws_to_av_samples_loop,
@@ -35,6 +56,8 @@ pub struct LlstreamRelayManager {
     shutdown_tx: Arc<RwLock<Option<watch::Sender<bool>>>>,
     task_handles: Arc<RwLock<Vec<JoinHandle<()>>>>,
     relay_url: Arc<RwLock<Option<String>>>,
+    shared: Arc<RwLock<Option<Arc<RelayShared>>>>,
+    latency_target: LatencyTarget,
 }
 
 impl LlstreamRelayManager {
@@ -44,21 +67,72 @@ impl LlstreamRelayManager {
 
     async fn set_running(
         &self,
+        app: &AppHandle,
         shutdown_tx: watch::Sender<bool>,
-        task_handles: Vec<JoinHandle<()>>,
+        mut task_handles: Vec<JoinHandle<()>>,
         relay_url: String,
+        shared: Arc<RelayShared>,
     ) {
+        task_handles.push(spawn_latency_controller(
+            app.clone(),
+            relay_url.clone(),
+            shared.clone(),
+            self.latency_target.clone(),
+            shutdown_tx.subscribe(),
+        ));
         *self.shutdown_tx.write().await = Some(shutdown_tx);
         *self.task_handles.write().await = task_handles;
         *self.relay_url.write().await = Some(relay_url);
+        *self.shared.write().await = Some(shared);
     }
 
     pub async fn current_url(&self) -> Option<String> {
         self.relay_url.read().await.clone()
     }
 
+    /// Relay mode (`LlstreamRelayInfo.mode`) if `url` is served by the running relay.
+    pub(crate) async fn mode_for_url(&self, url: &str) -> Option<&'static str> {
+        if self.current_url().await.as_deref() != Some(url) {
+            return None;
+        }
+        self.shared.read().await.as_ref().map(|shared| shared.mode)
+    }
+
+    pub async fn current_stats(&self) -> Option<RelayStatsSnapshot> {
+        self.shared
+            .read()
+            .await
+            .as_ref()
+            .map(|shared| shared.stats_snapshot())
+    }
+
+    async fn capture_clip(&self, secs: u32) -> Result<ClipCapture, String> {
+        let shared = self
+            .shared
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| "llstream relay is not running".to_string())?;
+        shared
+            .capture_clip(secs)
+            .ok_or_else(|| "no clip buffered yet (TS relays only, from a keyframe)".to_string())
+    }
+
+    async fn latest_keyframe(&self) -> Result<KeyframeSnapshot, String> {
+        let shared = self
+            .shared
+            .read()
+            .await
+            .clone()
+            .ok_or_else(|| "llstream relay is not running".to_string())?;
+        shared
+            .latest_keyframe()
+            .ok_or_else(|| "no decodable keyframe received yet".to_string())
+    }
+
     pub async fn stop(&self) {
         *self.relay_url.write().await = None;
+        *self.shared.write().await = None;
 
         if let Some(tx) = self.shutdown_tx.write().await.take() {
             let _ = tx.send(true);
@@ -68,14 +142,71 @@ impl LlstreamRelayManager {
             let mut guard = self.task_handles.write().await;
             std::mem::take(&mut *guard)
         };
+        stop_tasks(handles).await;
+    }
+}
 
-        for handle in handles {
+/// Gives tasks a moment to finish on their own (e.g. the mDNS goodbye packet)
+/// before aborting whatever is still running.
+async fn stop_tasks(handles: Vec<JoinHandle<()>>) {
+    for mut handle in handles {
+        if tokio::time::timeout(Duration::from_millis(200), &mut handle)
+            .await
+            .is_err()
+        {
             handle.abort();
             let _ = handle.await;
         }
     }
 }
 
+/// Relay tasks that have been spawned but are not owned by the manager yet.
+struct LaunchedRelay {
+    shutdown_tx: watch::Sender<bool>,
+    task_handles: Vec<JoinHandle<()>>,
+    shared: Arc<RelayShared>,
+    info: LlstreamRelayInfo,
+}
+
+// ---------------------------------------------------------------------------
+// Detached relays
+// ---------------------------------------------------------------------------
+
+/// An AV TS relay owned by its caller instead of `LlstreamRelayManager`, so it can
+/// run next to the playback relay (e.g. background recordings).
+pub(crate) struct DetachedRelay {
+    shutdown_tx: watch::Sender<bool>,
+    task_handles: Vec<JoinHandle<()>>,
+    /// Loopback URL of the MPEG-TS stream.
+    pub(crate) url: String,
+}
+
+impl DetachedRelay {
+    pub(crate) async fn stop(self) {
+        let _ = self.shutdown_tx.send(true);
+        stop_tasks(self.task_handles).await;
+    }
+}
+
+/// Starts an AV TS relay on an ephemeral loopback port without touching the managed relay.
+pub(crate) async fn start_detached_av_ts_relay(
+    video_ws_url: String,
+    audio_ws_url: String,
+) -> Result<DetachedRelay, String> {
+    let launched = launch_av_ts_relay(
+        &RelayEvents::detached(),
+        video_ws_url,
+        audio_ws_url,
+        LlstreamRelayOptions::default(),
+    )
+    .await?;
+    Ok(DetachedRelay {
+        shutdown_tx: launched.shutdown_tx,
+        task_handles: launched.task_handles,
+        url: launched.info.playlist_url,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Tauri commands
 // ---------------------------------------------------------------------------
@@ -85,6 +216,12 @@ pub struct LlstreamRelayInfo {
     pub playlist_url: String,
     pub mode: String,
     pub source: String,
+    /// Stats JSON endpoint of the HTTP relay (None for pipe relays).
+    pub stats_url: Option<String>,
+    /// Stream URL for other devices when the relay is bound beyond loopback.
+    pub lan_url: Option<String>,
+    /// Access token embedded in the URLs (always set for LAN-exposed relays).
+    pub token: Option<String>,
 }
 
 #[tauri::command]
@@ -92,6 +229,7 @@ pub async fn start_llstream_video_ts_relay(
     app: AppHandle,
     state: tauri::State<'_, LlstreamRelayManager>,
     video_ws_url: String,
+    options: Option<LlstreamRelayOptions>,
 ) -> Result<LlstreamRelayInfo, String> {
     let video_ws_url = video_ws_url.trim().to_string();
     if video_ws_url.is_empty() {
@@ -99,35 +237,55 @@ pub async fn start_llstream_video_ts_relay(
     }
 
     state.stop().await;
+    let events = RelayEvents::frontend(&app);
 
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let (packet_tx, _packet_rx) = broadcast::channel::<Vec<u8>>(512);
-
-    let listener = TcpListener::bind(("127.0.0.1", 0))
-        .await
-        .map_err(|e| format!("failed to bind relay server: {}", e))?;
-    let addr = listener
-        .local_addr()
-        .map_err(|e| format!("failed to get local addr: {}", e))?;
-    let relay_url = format!("http://127.0.0.1:{}/live.ts", addr.port());
-
-    let bootstrap = build_bootstrap_tables();
+    let shared = Arc::new(RelayShared::new("mpegts-video"));
+
+    let (listener, endpoint) = RelayEndpoint::bind(&options.unwrap_or_default()).await?;
+    let relay_url = endpoint.url("/live.ts");
+
+    let http_config = HttpRelayConfig {
+        stream_path: "/live.ts",
+        content_type: "video/mp2t",
+        bootstrap: build_bootstrap_tables(VideoCodec::H264),
+        token: endpoint.token.clone(),
+        max_clients: endpoint.max_clients,
+    };
+    shared.clip.enable(http_config.bootstrap.clone());
     let http_task = spawn_http_relay_task(
-        app.clone(), listener, bootstrap, packet_tx.clone(), shutdown_rx.clone(), "video",
+        events.clone(),
+        listener,
+        http_config,
+        packet_tx.clone(),
+        shared.clone(),
+        shutdown_rx.clone(),
+        "video",
     );
 
-    let app_for_ws = app.clone();
+    let events_for_ws = events.clone();
+    let shared_for_ws = shared.clone();
     let mut ws_shutdown_rx = shutdown_rx.clone();
     let ws_task = tokio::spawn(async move {
-        let result =
-            run_video_ws_loop(&app_for_ws, &video_ws_url, packet_tx, &mut ws_shutdown_rx).await;
+        let result = run_video_ws_loop(
+            &events_for_ws,
+            &video_ws_url,
+            packet_tx,
+            &shared_for_ws,
+            &mut ws_shutdown_rx,
+        )
+        .await;
         if let Err(e) = result {
-            relay_log(&app_for_ws, &format!("llstream relay ws error: {}", e));
+            relay_log(&events_for_ws, &format!("llstream relay ws error: {}", e));
         }
     });
 
+    let mut task_handles = vec![http_task, ws_task];
+    task_handles.extend(endpoint.spawn_mdns(&events, "/live.ts", "mpegts-video", shutdown_rx));
+
     state
-        .set_running(shutdown_tx, vec![http_task, ws_task], relay_url.clone())
+        .set_running(&app, shutdown_tx, task_handles, relay_url.clone(), shared)
         .await;
     let _ = app.emit("llstream://status", "started");
 
@@ -135,6 +293,9 @@ pub async fn start_llstream_video_ts_relay(
         playlist_url: relay_url,
         mode: "mpegts-video".to_string(),
         source: "llstream-video".to_string(),
+        stats_url: Some(endpoint.url("/stats.json")),
+        lan_url: endpoint.lan_url("/live.ts"),
+        token: endpoint.token,
     })
 }
 
@@ -144,7 +305,41 @@ pub async fn start_llstream_av_ts_relay(
     state: tauri::State<'_, LlstreamRelayManager>,
     video_ws_url: String,
     audio_ws_url: String,
+    options: Option<LlstreamRelayOptions>,
 ) -> Result<LlstreamRelayInfo, String> {
+    state.stop().await;
+
+    let launched = launch_av_ts_relay(
+        &RelayEvents::frontend(&app),
+        video_ws_url,
+        audio_ws_url,
+        options.unwrap_or_default(),
+    )
+    .await?;
+    // Only the managed relay keeps a clip window; detached (recording) relays have no reader.
+    launched
+        .shared
+        .clip
+        .enable(build_bootstrap_tables_av(VideoCodec::H264));
+    state
+        .set_running(
+            &app,
+            launched.shutdown_tx,
+            launched.task_handles,
+            launched.info.playlist_url.clone(),
+            launched.shared,
+        )
+        .await;
+    let _ = app.emit("llstream://status", "started");
+    Ok(launched.info)
+}
+
+async fn launch_av_ts_relay(
+    events: &RelayEvents,
+    video_ws_url: String,
+    audio_ws_url: String,
+    options: LlstreamRelayOptions,
+) -> Result<LaunchedRelay, String> {
     let video_ws_url = video_ws_url.trim().to_string();
     if video_ws_url.is_empty() {
         return Err("video_ws_url is empty".to_string());
@@ -154,69 +349,83 @@ pub async fn start_llstream_av_ts_relay(
         return Err("audio_ws_url is empty".to_string());
     }
 
-    state.stop().await;
-
     let (shutdown_tx, shutdown_rx) = watch::channel(false);
     let (packet_tx, _packet_rx) = broadcast::channel::<Vec<u8>>(2048);
     let (sample_tx, mut sample_rx) = mpsc::channel::<AvSample>(4096);
-
-    let listener = TcpListener::bind(("127.0.0.1", 0))
-        .await
-        .map_err(|e| format!("failed to bind relay server: {}", e))?;
-    let addr = listener
-        .local_addr()
-        .map_err(|e| format!("failed to get local addr: {}", e))?;
-    let relay_url = format!("http://127.0.0.1:{}/live.ts", addr.port());
-
-    let bootstrap = build_bootstrap_tables_av();
+    let shared = Arc::new(RelayShared::new("mpegts-av"));
+
+    let av_latency_ms = options.av_latency_ms;
+    let (listener, endpoint) = RelayEndpoint::bind(&options).await?;
+    let relay_url = endpoint.url("/live.ts");
+
+    let http_config = HttpRelayConfig {
+        stream_path: "/live.ts",
+        content_type: "video/mp2t",
+        bootstrap: build_bootstrap_tables_av(VideoCodec::H264),
+        token: endpoint.token.clone(),
+        max_clients: endpoint.max_clients,
+    };
     let http_task = spawn_http_relay_task(
-        app.clone(), listener, bootstrap, packet_tx.clone(), shutdown_rx.clone(), "av",
+        events.clone(),
+        listener,
+        http_config,
+        packet_tx.clone(),
+        shared.clone(),
+        shutdown_rx.clone(),
+        "av",
     );
 
     let sample_tx_for_video = sample_tx.clone();
     let sample_tx_for_audio = sample_tx.clone();
     drop(sample_tx);
 
-    let app_for_video_ws = app.clone();
+    let events_for_video_ws = events.clone();
+    let shared_for_video_ws = shared.clone();
     let mut video_shutdown_rx = shutdown_rx.clone();
     let video_ws_task = tokio::spawn(async move {
         let result = run_video_ws_to_av_samples_loop(
-            &app_for_video_ws,
+            &events_for_video_ws,
             &video_ws_url,
             sample_tx_for_video,
+            &shared_for_video_ws,
             &mut video_shutdown_rx,
         )
         .await;
         if let Err(e) = result {
-            relay_log(&app_for_video_ws, &format!("llstream av video ws error: {}", e));
+            relay_log(&events_for_video_ws, &format!("llstream av video ws error: {}", e));
         }
     });
 
-    let app_for_audio_ws = app.clone();
+    let events_for_audio_ws = events.clone();
+    let shared_for_audio_ws = shared.clone();
     let mut audio_shutdown_rx = shutdown_rx.clone();
     let audio_ws_task = tokio::spawn(async move {
         let result = run_audio_ws_to_av_samples_loop(
-            &app_for_audio_ws,
+            &events_for_audio_ws,
             &audio_ws_url,
             sample_tx_for_audio,
+            &shared_for_audio_ws,
             &mut audio_shutdown_rx,
         )
         .await;
         if let Err(e) = result {
-            relay_log(&app_for_audio_ws, &format!("llstream av audio ws error: {}", e));
+            relay_log(&events_for_audio_ws, &format!("llstream av audio ws error: {}", e));
         }
     });
 
-    let app_for_mux = app.clone();
+    let events_for_mux = events.clone();
+    let shared_for_mux = shared.clone();
     let mut mux_shutdown_rx = shutdown_rx.clone();
     let packet_tx_for_mux = packet_tx.clone();
     let mux_task = tokio::spawn(async move {
         let mut muxer = AvMpegTsMuxer::new();
+        let mut jitter = AvJitterBuffer::new(av_latency_ms);
         loop {
+            let deadline = jitter.next_deadline();
             tokio::select! {
                 _ = mux_shutdown_rx.changed() => {
                     if *mux_shutdown_rx.borrow() {
-                        relay_log(&app_for_mux, "llstream av relay mux stopping");
+                        relay_log(&events_for_mux, "llstream av relay mux stopping");
                         return;
                     }
                 }
@@ -224,17 +433,168 @@ pub async fn start_llstream_av_ts_relay(
                     let Some(sample) = sample else {
                         return;
                     };
+                    jitter.push(sample, Instant::now());
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)),
+                    if deadline.is_some() => {}
+            }
 
-                    let chunk = match sample {
-                        AvSample::Video { timestamp_ns, annexb } => {
-                            muxer.push_video_access_unit(&annexb, timestamp_ns)
+            while let Some(sample) = jitter.pop_ready(Instant::now()) {
+                let (chunk, random_access) = match sample {
+                    AvSample::Video { timestamp_ns, codec, annexb, discontinuity } => {
+                        if muxer.set_video_codec(codec) {
+                            shared_for_mux.set_ts_bootstrap(build_bootstrap_tables_av(codec));
+                        }
+                        if discontinuity {
+                            muxer.mark_video_discontinuity();
+                        }
+                        let keyframe = has_random_access_nal(&annexb, codec);
+                        (muxer.push_video_access_unit(&annexb, timestamp_ns), keyframe)
+                    }
+                    AvSample::Audio { timestamp_ns, adts_frame, discontinuity } => {
+                        if discontinuity {
+                            muxer.mark_audio_discontinuity();
                         }
-                        AvSample::Audio { timestamp_ns, adts_frame } => {
-                            muxer.push_audio_adts_frame(&adts_frame, timestamp_ns)
+                        (muxer.push_audio_adts_frame(&adts_frame, timestamp_ns), false)
+                    }
+                };
+
+                if !chunk.is_empty() {
+                    shared_for_mux.stats.chunk_published();
+                    if let Some(pts_90k) = muxer.last_pts_90k() {
+                        shared_for_mux.latency.published(pts_90k);
+                    }
+                    shared_for_mux.clip.push(&chunk, random_access);
+                    let _ = packet_tx_for_mux.send(chunk);
+                }
+            }
+        }
+    });
+
+    let mut task_handles = vec![http_task, video_ws_task, audio_ws_task, mux_task];
+    task_handles.extend(endpoint.spawn_mdns(events, "/live.ts", "mpegts-av", shutdown_rx));
+
+    Ok(LaunchedRelay {
+        shutdown_tx,
+        task_handles,
+        shared,
+        info: LlstreamRelayInfo {
+            playlist_url: relay_url,
+            mode: "mpegts-av".to_string(),
+            source: "llstream-av".to_string(),
+            stats_url: Some(endpoint.url("/stats.json")),
+            lan_url: endpoint.lan_url("/live.ts"),
+            token: endpoint.token,
+        },
+    })
+}
+
+#[tauri::command]
+pub async fn start_llstream_audio_relay(
+    app: AppHandle,
+    state: tauri::State<'_, LlstreamRelayManager>,
+    audio_ws_url: String,
+    format: Option<String>,
+    options: Option<LlstreamRelayOptions>,
+) -> Result<LlstreamRelayInfo, String> {
+    let audio_ws_url = audio_ws_url.trim().to_string();
+    if audio_ws_url.is_empty() {
+        return Err("audio_ws_url is empty".to_string());
+    }
+    let format = AudioRelayFormat::parse(format.as_deref())?;
+
+    state.stop().await;
+    let events = RelayEvents::frontend(&app);
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (packet_tx, _packet_rx) = broadcast::channel::<Vec<u8>>(512);
+    let (sample_tx, mut sample_rx) = mpsc::channel::<AvSample>(256);
+    let shared = Arc::new(RelayShared::new(format.mode()));
+
+    let (listener, endpoint) = RelayEndpoint::bind(&options.unwrap_or_default()).await?;
+    let relay_url = endpoint.url(format.stream_path());
+
+    let http_config = HttpRelayConfig {
+        stream_path: format.stream_path(),
+        content_type: format.content_type(),
+        bootstrap: match format {
+            AudioRelayFormat::Adts => Vec::new(),
+            AudioRelayFormat::MpegTs => build_bootstrap_tables_audio(),
+        },
+        token: endpoint.token.clone(),
+        max_clients: endpoint.max_clients,
+    };
+    if let AudioRelayFormat::MpegTs = format {
+        shared.clip.enable(http_config.bootstrap.clone());
+    }
+    let http_task = spawn_http_relay_task(
+        events.clone(),
+        listener,
+        http_config,
+        packet_tx.clone(),
+        shared.clone(),
+        shutdown_rx.clone(),
+        "audio",
+    );
+
+    let events_for_ws = events.clone();
+    let shared_for_ws = shared.clone();
+    let mut ws_shutdown_rx = shutdown_rx.clone();
+    let ws_task = tokio::spawn(async move {
+        let result = run_audio_ws_to_av_samples_loop(
+            &events_for_ws,
+            &audio_ws_url,
+            sample_tx,
+            &shared_for_ws,
+            &mut ws_shutdown_rx,
+        )
+        .await;
+        if let Err(e) = result {
+            relay_log(&events_for_ws, &format!("llstream audio ws error: {}", e));
+        }
+    });
+
+    let events_for_mux = events.clone();
+    let shared_for_mux = shared.clone();
+    let mut mux_shutdown_rx = shutdown_rx.clone();
+    let packet_tx_for_mux = packet_tx.clone();
+    let mux_task = tokio::spawn(async move {
+        let mut muxer = AudioMpegTsMuxer::new();
+        loop {
+            tokio::select! {
+                _ = mux_shutdown_rx.changed() => {
+                    if *mux_shutdown_rx.borrow() {
+                        relay_log(&events_for_mux, "llstream audio relay mux stopping");
+                        return;
+                    }
+                }
+                sample = sample_rx.recv() => {
+                    let Some(sample) = sample else {
+                        return;
+                    };
+                    let AvSample::Audio { timestamp_ns, adts_frame, discontinuity } = sample else {
+                        continue;
+                    };
+
+                    // ADTS frames are self-delimiting, so the AAC stream is just their concatenation.
+                    let chunk = match format {
+                        AudioRelayFormat::Adts => adts_frame,
+                        AudioRelayFormat::MpegTs => {
+                            if discontinuity {
+                                muxer.mark_discontinuity();
+                            }
+                            let chunk = muxer.push_audio_adts_frame(&adts_frame, timestamp_ns);
+                            if let Some(pts_90k) = muxer.last_pts_90k() {
+                                shared_for_mux.latency.published(pts_90k);
+                            }
+                            chunk
                         }
                     };
 
                     if !chunk.is_empty() {
+                        shared_for_mux.stats.chunk_published();
+                        // Every AAC frame is a random access point (no-op for raw ADTS).
+                        shared_for_mux.clip.push(&chunk, true);
                         let _ = packet_tx_for_mux.send(chunk);
                     }
                 }
@@ -242,19 +602,26 @@ pub async fn start_llstream_av_ts_relay(
         }
     });
 
+    let mut task_handles = vec![http_task, ws_task, mux_task];
+    task_handles.extend(endpoint.spawn_mdns(
+        &events,
+        format.stream_path(),
+        format.mode(),
+        shutdown_rx,
+    ));
+
     state
-        .set_running(
-            shutdown_tx,
-            vec![http_task, video_ws_task, audio_ws_task, mux_task],
-            relay_url.clone(),
-        )
+        .set_running(&app, shutdown_tx, task_handles, relay_url.clone(), shared)
         .await;
     let _ = app.emit("llstream://status", "started");
 
     Ok(LlstreamRelayInfo {
         playlist_url: relay_url,
-        mode: "mpegts-av".to_string(),
-        source: "llstream-av".to_string(),
+        mode: format.mode().to_string(),
+        source: "llstream-audio".to_string(),
+        stats_url: Some(endpoint.url("/stats.json")),
+        lan_url: endpoint.lan_url(format.stream_path()),
+        token: endpoint.token,
     })
 }
 
@@ -270,56 +637,62 @@ pub async fn start_llstream_video_pipe_relay(
     }
 
     state.stop().await;
+    let events = RelayEvents::frontend(&app);
 
-    #[cfg(not(windows))]
-    {
-        let _ = app;
-        let _ = video_ws_url;
-        return Err("named pipe relay is supported only on Windows".to_string());
-    }
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let (au_tx, _au_rx) = broadcast::channel::<Vec<u8>>(1024);
+    let shared = Arc::new(RelayShared::new("annexb-pipe"));
 
     #[cfg(windows)]
-    {
-        let (shutdown_tx, shutdown_rx) = watch::channel(false);
-        let (au_tx, _au_rx) = broadcast::channel::<Vec<u8>>(1024);
-        let pipe_path = format!(r"\\.\pipe\mirrativ_llstream_video_{}", Uuid::new_v4().simple());
-
-        let app_for_pipe = app.clone();
-        let mut pipe_shutdown_rx = shutdown_rx.clone();
-        let pipe_path_for_task = pipe_path.clone();
-        let au_tx_for_pipe = au_tx.clone();
-        let pipe_task = tokio::spawn(async move {
-            pipe_writer_loop(
-                &app_for_pipe,
-                &pipe_path_for_task,
-                au_tx_for_pipe,
-                &mut pipe_shutdown_rx,
-            )
-            .await;
-        });
-
-        let app_for_ws = app.clone();
-        let mut ws_shutdown_rx = shutdown_rx.clone();
-        let ws_url = video_ws_url.clone();
-        let ws_task = tokio::spawn(async move {
-            let result =
-                run_video_ws_to_annexb_loop(&app_for_ws, &ws_url, au_tx, &mut ws_shutdown_rx).await;
-            if let Err(e) = result {
-                relay_log(&app_for_ws, &format!("llstream pipe ws error: {}", e));
-            }
-        });
+    let pipe_path = format!(r"\\.\pipe\mirrativ_llstream_video_{}", Uuid::new_v4().simple());
+    #[cfg(unix)]
+    let pipe_path = create_fifo()?;
+
+    let events_for_pipe = events.clone();
+    let mut pipe_shutdown_rx = shutdown_rx.clone();
+    let pipe_path_for_task = pipe_path.clone();
+    let au_tx_for_pipe = au_tx.clone();
+    let pipe_task = tokio::spawn(async move {
+        pipe_writer_loop(
+            &events_for_pipe,
+            &pipe_path_for_task,
+            au_tx_for_pipe,
+            &mut pipe_shutdown_rx,
+        )
+        .await;
+    });
 
-        state
-            .set_running(shutdown_tx, vec![pipe_task, ws_task], pipe_path.clone())
-            .await;
-        let _ = app.emit("llstream://status", "started");
+    let events_for_ws = events.clone();
+    let shared_for_ws = shared.clone();
+    let mut ws_shutdown_rx = shutdown_rx.clone();
+    let ws_url = video_ws_url.clone();
+    let ws_task = tokio::spawn(async move {
+        let result = run_video_ws_to_annexb_loop(
+            &events_for_ws,
+            &ws_url,
+            au_tx,
+            &shared_for_ws,
+            &mut ws_shutdown_rx,
+        )
+        .await;
+        if let Err(e) = result {
+            relay_log(&events_for_ws, &format!("llstream pipe ws error: {}", e));
+        }
+    });
 
-        Ok(LlstreamRelayInfo {
-            playlist_url: pipe_path,
-            mode: "annexb-pipe".to_string(),
-            source: "llstream-video".to_string(),
-        })
-    }
+    state
+        .set_running(&app, shutdown_tx, vec![pipe_task, ws_task], pipe_path.clone(), shared)
+        .await;
+    let _ = app.emit("llstream://status", "started");
+
+    Ok(LlstreamRelayInfo {
+        playlist_url: pipe_path,
+        mode: "annexb-pipe".to_string(),
+        source: "llstream-video".to_string(),
+        stats_url: None,
+        lan_url: None,
+        token: None,
+    })
 }
 
 #[tauri::command]
@@ -339,13 +712,183 @@ pub async fn get_llstream_relay_url(
     Ok(state.current_url().await)
 }
 
+#[tauri::command]
+pub async fn get_llstream_relay_stats(
+    state: tauri::State<'_, LlstreamRelayManager>,
+) -> Result<Option<RelayStatsSnapshot>, String> {
+    Ok(state.current_stats().await)
+}
+
+/// Sets the end-to-end latency target for live-edge catch-up. None or 0 turns
+/// catch-up off; `llstream://latency` keeps reporting the measured delay either way.
+#[tauri::command]
+pub async fn set_llstream_latency_target(
+    state: tauri::State<'_, LlstreamRelayManager>,
+    target_ms: Option<u32>,
+) -> Result<(), String> {
+    let target_ms = target_ms.unwrap_or(0);
+    if target_ms != 0 && !(100..=30_000).contains(&target_ms) {
+        return Err(format!("latency target out of range (100-30000ms): {}", target_ms));
+    }
+    state
+        .latency_target
+        .store(target_ms, std::sync::atomic::Ordering::Relaxed);
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub struct LlstreamSnapshotInfo {
+    pub path: String,
+    pub bytes: usize,
+    pub timestamp_ns: u64,
+    pub captured_at_ms: u64,
+}
+
+/// Writes the latest keyframe access unit of the running relay (parameter sets
+/// included) as a standalone H.264/HEVC elementary stream, decodable on its own.
+/// Defaults to `<app cache>/llstream-snapshots/` when `path` is omitted.
+#[tauri::command]
+pub async fn save_llstream_relay_snapshot(
+    app: AppHandle,
+    state: tauri::State<'_, LlstreamRelayManager>,
+    path: Option<String>,
+) -> Result<LlstreamSnapshotInfo, String> {
+    let keyframe = state.latest_keyframe().await?;
+
+    let path = match path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => std::path::PathBuf::from(p),
+        None => app
+            .path()
+            .app_cache_dir()
+            .map_err(|e| e.to_string())?
+            .join("llstream-snapshots")
+            .join(format!(
+                "snapshot-{}.{}",
+                keyframe.captured_at_ms,
+                keyframe.codec.file_extension()
+            )),
+    };
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    tokio::fs::write(&path, &keyframe.access_unit)
+        .await
+        .map_err(|e| format!("failed to write snapshot: {}", e))?;
+
+    Ok(LlstreamSnapshotInfo {
+        path: path.to_string_lossy().to_string(),
+        bytes: keyframe.access_unit.len(),
+        timestamp_ns: keyframe.timestamp_ns,
+        captured_at_ms: keyframe.captured_at_ms,
+    })
+}
+
+#[derive(Serialize)]
+pub struct LlstreamClipInfo {
+    pub path: String,
+    pub bytes: usize,
+    /// Actual length; at least the requested seconds unless the buffer is younger.
+    pub duration_ms: u64,
+}
+
+/// Dumps the last `seconds` (default 30, max 120) of a running TS relay, starting
+/// at a keyframe. `.ts` is written as-is; `.mp4` is converted with mpv.
+/// Defaults to `<videos>/Mirrativ/clip-<unix ms>.ts` when `path` is omitted.
+#[tauri::command]
+pub async fn save_llstream_relay_clip(
+    app: AppHandle,
+    state: tauri::State<'_, LlstreamRelayManager>,
+    seconds: Option<u32>,
+    path: Option<String>,
+) -> Result<LlstreamClipInfo, String> {
+    let seconds = seconds.unwrap_or(30);
+    if !(1..=MAX_CLIP_SECS).contains(&seconds) {
+        return Err(format!("clip length out of range (1-{}s): {}", MAX_CLIP_SECS, seconds));
+    }
+
+    let path = match path.as_deref().map(str::trim).filter(|p| !p.is_empty()) {
+        Some(p) => std::path::PathBuf::from(p),
+        None => app
+            .path()
+            .video_dir()
+            .map_err(|e| e.to_string())?
+            .join("Mirrativ")
+            .join(format!("clip-{}.ts", stats::unix_millis())),
+    };
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let convert = match extension.as_deref() {
+        Some("ts") => false,
+        Some("mp4") => true,
+        _ => return Err("clip path must end in .ts or .mp4".to_string()),
+    };
+
+    let clip = state.capture_clip(seconds).await?;
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let ts_path = if convert {
+        path.with_extension("part.ts")
+    } else {
+        path.clone()
+    };
+    tokio::fs::write(&ts_path, &clip.data)
+        .await
+        .map_err(|e| format!("failed to write clip: {}", e))?;
+    if convert {
+        let result = crate::mpv_player::convert_clip_to_mp4(&app, &ts_path, &path).await;
+        let _ = tokio::fs::remove_file(&ts_path).await;
+        result?;
+    }
+
+    let bytes = tokio::fs::metadata(&path)
+        .await
+        .map(|meta| meta.len() as usize)
+        .unwrap_or(clip.data.len());
+    Ok(LlstreamClipInfo {
+        path: path.to_string_lossy().to_string(),
+        bytes,
+        duration_ms: clip.duration_ms,
+    })
+}
+
 // ---------------------------------------------------------------------------
 // Shared helpers (used by submodules via super::)
 // ---------------------------------------------------------------------------
 
-fn relay_log(app: &AppHandle, msg: &str) {
+/// Where a relay's log lines and `llstream://*` events go. Detached relays (background
+/// recording) only log to stderr so they don't drive the player UI.
+#[derive(Clone)]
+pub(crate) struct RelayEvents {
+    app: Option<AppHandle>,
+}
+
+impl RelayEvents {
+    fn frontend(app: &AppHandle) -> Self {
+        Self { app: Some(app.clone()) }
+    }
+
+    fn detached() -> Self {
+        Self { app: None }
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        if let Some(app) = &self.app {
+            let _ = app.emit(event, payload);
+        }
+    }
+}
+
+fn relay_log(events: &RelayEvents, msg: &str) {
     eprintln!("{}", msg);
-    let _ = app.emit("llstream://log", msg);
+    events.emit("llstream://log", msg);
 }
 
 fn reconnect_backoff(attempt: u64) -> Duration {
@@ -364,42 +907,142 @@ async fn wait_reconnect_or_shutdown(
     }
 }
 
+/// `discontinuity` marks the first sample after lost frames or a reconnect.
 enum AvSample {
-    Video { timestamp_ns: u64, annexb: Vec<u8> },
-    Audio { timestamp_ns: u64, adts_frame: Vec<u8> },
+    Video {
+        timestamp_ns: u64,
+        codec: VideoCodec,
+        annexb: Vec<u8>,
+        discontinuity: bool,
+    },
+    Audio {
+        timestamp_ns: u64,
+        adts_frame: Vec<u8>,
+        discontinuity: bool,
+    },
+}
+
+impl AvSample {
+    fn timestamp_ns(&self) -> u64 {
+        match self {
+            Self::Video { timestamp_ns, .. } | Self::Audio { timestamp_ns, .. } => *timestamp_ns,
+        }
+    }
+
+    fn set_timestamp_ns(&mut self, value: u64) {
+        match self {
+            Self::Video { timestamp_ns, .. } | Self::Audio { timestamp_ns, .. } => {
+                *timestamp_ns = value
+            }
+        }
+    }
+}
+
+/// Container served by `start_llstream_audio_relay`.
+#[derive(Clone, Copy)]
+enum AudioRelayFormat {
+    /// Raw ADTS AAC, no muxing at all.
+    Adts,
+    /// Audio-only MPEG-TS for players that want a TS container.
+    MpegTs,
+}
+
+impl AudioRelayFormat {
+    fn parse(raw: Option<&str>) -> Result<Self, String> {
+        match raw.map(str::trim).filter(|s| !s.is_empty()) {
+            None | Some("aac") | Some("adts") => Ok(Self::Adts),
+            Some("ts") | Some("mpegts") => Ok(Self::MpegTs),
+            Some(other) => Err(format!("unsupported audio relay format: {}", other)),
+        }
+    }
+
+    fn mode(self) -> &'static str {
+        match self {
+            Self::Adts => "adts-audio",
+            Self::MpegTs => "mpegts-audio",
+        }
+    }
+
+    fn stream_path(self) -> &'static str {
+        match self {
+            Self::Adts => "/live.aac",
+            Self::MpegTs => "/live.ts",
+        }
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Adts => "audio/aac",
+            Self::MpegTs => "video/mp2t",
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
-// VideoFrameAssembler — shared SPS/PPS tracking + access unit construction
+// VideoFrameAssembler — codec detection, parameter set tracking + access unit construction
 // ---------------------------------------------------------------------------
 
 struct AssembledFrame {
     access_unit: Vec<u8>,
     timestamp_ns: u64,
     kind: u8,
+    codec: VideoCodec,
+    is_keyframe: bool,
+    /// Set on the first keyframe after the parameter sets changed.
+    video_info: Option<VideoStreamInfo>,
+    /// Set on the first keyframe after a gap or reconnect.
+    discontinuity: bool,
 }
 
 struct VideoFrameAssembler {
+    codec: Option<VideoCodec>,
+    last_vps: Option<Vec<u8>>,
     last_sps: Option<Vec<u8>>,
     last_pps: Option<Vec<u8>>,
+    params_changed: bool,
     started: bool,
     waiting_log_counter: u64,
     prepend_aud: bool,
+    continuity: StreamContinuity,
+    discontinuity: bool,
 }
 
 impl VideoFrameAssembler {
     fn new(prepend_aud: bool) -> Self {
         Self {
+            codec: None,
+            last_vps: None,
             last_sps: None,
             last_pps: None,
+            params_changed: false,
             started: false,
             waiting_log_counter: 0,
             prepend_aud,
+            continuity: StreamContinuity::new("video"),
+            discontinuity: false,
         }
     }
 
-    fn process(&mut self, data: &[u8], app: &AppHandle, label: &str) -> Option<AssembledFrame> {
+    /// Starts over for a new WS connection. Continuity tracking survives so the
+    /// reconnect itself is reported and the next keyframe is flagged.
+    fn reconnected(&mut self, events: &RelayEvents) {
+        let continuity = std::mem::replace(&mut self.continuity, StreamContinuity::new("video"));
+        *self = Self {
+            continuity,
+            ..Self::new(self.prepend_aud)
+        };
+        self.discontinuity = self.continuity.connected(events);
+    }
+
+    fn process(&mut self, data: &[u8], events: &RelayEvents, label: &str) -> Option<AssembledFrame> {
         let frame = parse_video_packet(data)?;
+        if self.continuity.observe(events, &frame.header) && self.started {
+            // The rest of this GOP references lost frames; hold output until the next keyframe.
+            self.started = false;
+            self.discontinuity = true;
+            self.waiting_log_counter = 0;
+            relay_log(events, &format!("{} gap detected, waiting for next keyframe", label));
+        }
         if frame.payload.is_empty() {
             return None;
         }
@@ -409,73 +1052,169 @@ impl VideoFrameAssembler {
             return None;
         }
 
-        if frame.kind == FRAME_KIND_SPS {
-            let (sps, _) = extract_parameter_sets(&annexb);
-            self.last_sps = sps.or_else(|| Some(annexb.clone()));
-            return None;
-        }
-        if frame.kind == FRAME_KIND_PPS {
-            let (_, pps) = extract_parameter_sets(&annexb);
-            self.last_pps = pps.or_else(|| Some(annexb.clone()));
-            return None;
-        }
+        let is_param_frame =
+            frame.header.kind == FRAME_KIND_SPS || frame.header.kind == FRAME_KIND_PPS;
+        let codec = match self.codec {
+            Some(codec) => codec,
+            None => {
+                let detected = detect_video_codec(&annexb).map(|codec| (codec, "detected"));
+                let codec = match detected {
+                    Some(detected) => detected,
+                    None if is_param_frame => {
+                        // An H.264 PPS or a bare parameter set doesn't reveal the codec;
+                        // keep it for the first keyframe.
+                        self.store_bare_param(frame.header.kind, annexb);
+                        return None;
+                    }
+                    // Parameter sets without recognizable NAL headers: treat the stream as
+                    // H.264 like before HEVC support.
+                    None if frame.header.kind == FRAME_KIND_IDR
+                        && self.last_sps.is_some()
+                        && self.last_pps.is_some() =>
+                    {
+                        (VideoCodec::H264, "assumed")
+                    }
+                    // Nothing is decodable before the first parameter sets anyway.
+                    None => return None,
+                };
+                let (codec, how) = codec;
+                relay_log(events, &format!("{} codec {}: {}", label, how, codec.as_str()));
+                self.codec = Some(codec);
+                codec
+            }
+        };
 
-        let (sps_from_data, pps_from_data) = extract_parameter_sets(&annexb);
-        if sps_from_data.is_some() {
-            self.last_sps = sps_from_data;
-        }
-        if pps_from_data.is_some() {
-            self.last_pps = pps_from_data;
+        let params = extract_parameter_sets(&annexb, codec);
+        if is_param_frame {
+            if params.is_empty() {
+                // Bare parameter set without a recognizable NAL header; keep it as-is.
+                self.store_bare_param(frame.header.kind, annexb);
+            } else {
+                self.store_params(params);
+            }
+            return None;
         }
+        self.store_params(params);
 
-        let is_idr = frame.kind == FRAME_KIND_IDR || has_nal_type(&annexb, 5);
+        let is_idr = frame.header.kind == FRAME_KIND_IDR || has_random_access_nal(&annexb, codec);
+        let has_params = self.last_sps.is_some()
+            && self.last_pps.is_some()
+            && (codec.vps_type().is_none() || self.last_vps.is_some());
 
         if !self.started {
-            if !(is_idr && self.last_sps.is_some() && self.last_pps.is_some()) {
+            if !(is_idr && has_params) {
                 self.waiting_log_counter += 1;
                 if self.waiting_log_counter % 120 == 0 {
                     relay_log(
-                        app,
+                        events,
                         &format!(
-                            "{} waiting keyframe/sps/pps: idr={} sps={} pps={}",
-                            label, is_idr, self.last_sps.is_some(), self.last_pps.is_some()
+                            "{} waiting keyframe/params: idr={} vps={} sps={} pps={}",
+                            label,
+                            is_idr,
+                            self.last_vps.is_some(),
+                            self.last_sps.is_some(),
+                            self.last_pps.is_some()
                         ),
                     );
                 }
                 return None;
             }
             self.started = true;
-            relay_log(app, &format!("{} start at first decodable keyframe", label));
+            relay_log(events, &format!("{} start at first decodable keyframe", label));
         }
 
         let mut access_unit = Vec::with_capacity(annexb.len() + 512);
 
         if self.prepend_aud {
             access_unit.extend_from_slice(&NAL_START_CODE);
-            access_unit.extend_from_slice(&[0x09, 0xF0]);
+            access_unit.extend_from_slice(codec.aud_nal());
         }
 
         if is_idr {
-            if !has_nal_type(&annexb, 7) {
-                if let Some(sps) = self.last_sps.as_deref() {
-                    access_unit.extend_from_slice(sps);
-                }
-            }
-            if !has_nal_type(&annexb, 8) {
-                if let Some(pps) = self.last_pps.as_deref() {
-                    access_unit.extend_from_slice(pps);
+            let params = [
+                (codec.vps_type(), self.last_vps.as_deref()),
+                (Some(codec.sps_type()), self.last_sps.as_deref()),
+                (Some(codec.pps_type()), self.last_pps.as_deref()),
+            ];
+            for (nal_type, cached) in params {
+                let (Some(nal_type), Some(cached)) = (nal_type, cached) else {
+                    continue;
+                };
+                if !has_nal_type(&annexb, codec, nal_type) {
+                    access_unit.extend_from_slice(cached);
                 }
             }
         }
 
         access_unit.extend_from_slice(&annexb);
 
+        let video_info = if is_idr && self.params_changed {
+            self.params_changed = false;
+            self.video_info(codec)
+        } else {
+            None
+        };
+
         Some(AssembledFrame {
             access_unit,
-            timestamp_ns: frame.timestamp_ns,
-            kind: frame.kind,
+            timestamp_ns: frame.header.timestamp_ns,
+            kind: frame.header.kind,
+            codec,
+            is_keyframe: is_idr,
+            video_info,
+            discontinuity: std::mem::take(&mut self.discontinuity),
         })
     }
+
+    fn store_params(&mut self, params: ParameterSets) {
+        if let Some(vps) = params.vps {
+            self.set_param(ParamSlot::Vps, vps);
+        }
+        if let Some(sps) = params.sps {
+            self.set_param(ParamSlot::Sps, sps);
+        }
+        if let Some(pps) = params.pps {
+            self.set_param(ParamSlot::Pps, pps);
+        }
+    }
+
+    fn store_bare_param(&mut self, kind: u8, annexb: Vec<u8>) {
+        if kind == FRAME_KIND_SPS {
+            self.set_param(ParamSlot::Sps, annexb);
+        } else {
+            self.set_param(ParamSlot::Pps, annexb);
+        }
+    }
+
+    fn set_param(&mut self, slot: ParamSlot, nal: Vec<u8>) {
+        let cached = match slot {
+            ParamSlot::Vps => &mut self.last_vps,
+            ParamSlot::Sps => &mut self.last_sps,
+            ParamSlot::Pps => &mut self.last_pps,
+        };
+        if cached.as_deref() != Some(nal.as_slice()) {
+            *cached = Some(nal);
+            self.params_changed = true;
+        }
+    }
+
+    fn video_info(&self, codec: VideoCodec) -> Option<VideoStreamInfo> {
+        let sps = find_nal(self.last_sps.as_deref()?, codec, codec.sps_type())?;
+        let pps = self
+            .last_pps
+            .as_deref()
+            .and_then(|pps| find_nal(pps, codec, codec.pps_type()));
+        match codec {
+            VideoCodec::H264 => VideoStreamInfo::from_h264(sps, pps),
+            VideoCodec::Hevc => VideoStreamInfo::from_hevc(sps, pps),
+        }
+    }
+}
+
+enum ParamSlot {
+    Vps,
+    Sps,
+    Pps,
 }
 
 // ---------------------------------------------------------------------------
@@ -484,23 +1223,23 @@ impl VideoFrameAssembler {
 
 #[cfg(windows)]
 async fn pipe_writer_loop(
-    app: &AppHandle,
+    events: &RelayEvents,
     pipe_path: &str,
     au_tx: broadcast::Sender<Vec<u8>>,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) {
-    relay_log(app, &format!("llstream pipe listening: {}", pipe_path));
+    relay_log(events, &format!("llstream pipe listening: {}", pipe_path));
 
     loop {
         if *shutdown_rx.borrow() {
-            relay_log(app, "llstream pipe stopping");
+            relay_log(events, "llstream pipe stopping");
             return;
         }
 
         let mut pipe = match ServerOptions::new().create(pipe_path) {
             Ok(p) => p,
             Err(e) => {
-                relay_log(app, &format!("llstream pipe create failed: {}", e));
+                relay_log(events, &format!("llstream pipe create failed: {}", e));
                 tokio::time::sleep(Duration::from_millis(250)).await;
                 continue;
             }
@@ -509,7 +1248,7 @@ async fn pipe_writer_loop(
         let connect_result = tokio::select! {
             _ = shutdown_rx.changed() => {
                 if *shutdown_rx.borrow() {
-                    relay_log(app, "llstream pipe connect cancelled");
+                    relay_log(events, "llstream pipe connect cancelled");
                     return;
                 }
                 continue;
@@ -518,40 +1257,228 @@ async fn pipe_writer_loop(
         };
 
         if let Err(e) = connect_result {
-            relay_log(app, &format!("llstream pipe connect failed: {}", e));
+            relay_log(events, &format!("llstream pipe connect failed: {}", e));
             tokio::time::sleep(Duration::from_millis(150)).await;
             continue;
         }
 
-        relay_log(app, "llstream pipe client connected");
-        let mut rx = au_tx.subscribe();
+        relay_log(events, "llstream pipe client connected");
+        if write_access_units(events, &mut pipe, &au_tx, shutdown_rx).await {
+            return;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// FIFO relay (Linux / macOS)
+// ---------------------------------------------------------------------------
 
-        loop {
-            tokio::select! {
-                _ = shutdown_rx.changed() => {
-                    if *shutdown_rx.borrow() {
-                        relay_log(app, "llstream pipe writer stopping");
-                        return;
-                    }
+/// Creates a FIFO in the temp dir. The `.h264` extension lets ffplay / mpv
+/// pick the raw H.264 demuxer without extra options.
+#[cfg(unix)]
+fn create_fifo() -> Result<String, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::env::temp_dir().join(format!(
+        "mirrativ_llstream_video_{}.h264",
+        Uuid::new_v4().simple()
+    ));
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("invalid fifo path: {}", path.display()))?;
+    // SAFETY: c_path is a valid NUL-terminated string for the duration of the call.
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        return Err(format!(
+            "failed to create fifo {}: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        ));
+    }
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Removes the FIFO from the filesystem however the writer task ends (incl. abort).
+#[cfg(unix)]
+struct FifoCleanup<'a>(&'a str);
+
+#[cfg(unix)]
+impl Drop for FifoCleanup<'_> {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(self.0);
+    }
+}
+
+#[cfg(unix)]
+async fn pipe_writer_loop(
+    events: &RelayEvents,
+    pipe_path: &str,
+    au_tx: broadcast::Sender<Vec<u8>>,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) {
+    use tokio::net::unix::pipe;
+
+    let _cleanup = FifoCleanup(pipe_path);
+    relay_log(events, &format!("llstream fifo listening: {}", pipe_path));
+
+    loop {
+        if *shutdown_rx.borrow() {
+            relay_log(events, "llstream fifo stopping");
+            return;
+        }
+
+        // Opening the write end fails with ENXIO until a reader has the FIFO open,
+        // so poll until a consumer shows up.
+        let mut sender = match pipe::OpenOptions::new().open_sender(pipe_path) {
+            Ok(sender) => sender,
+            Err(e) => {
+                if e.raw_os_error() != Some(libc::ENXIO) {
+                    relay_log(events, &format!("llstream fifo open failed: {}", e));
                 }
-                recv = rx.recv() => {
-                    match recv {
-                        Ok(au) => {
-                            if let Err(e) = pipe.write_all(&au).await {
-                                relay_log(app, &format!("llstream pipe client disconnected: {}", e));
-                                break;
-                            }
-                        }
-                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                            relay_log(app, &format!("llstream pipe lagged: skipped {}", skipped));
-                        }
-                        Err(broadcast::error::RecvError::Closed) => {
-                            relay_log(app, "llstream pipe channel closed");
-                            return;
+                tokio::select! {
+                    _ = shutdown_rx.changed() => {}
+                    _ = tokio::time::sleep(Duration::from_millis(150)) => {}
+                }
+                continue;
+            }
+        };
+
+        relay_log(events, "llstream fifo client connected");
+        if write_access_units(events, &mut sender, &au_tx, shutdown_rx).await {
+            return;
+        }
+    }
+}
+
+/// Streams access units to one connected pipe client until it disconnects.
+/// Returns true when the relay is shutting down and the writer should exit.
+async fn write_access_units<W: tokio::io::AsyncWrite + Unpin>(
+    events: &RelayEvents,
+    writer: &mut W,
+    au_tx: &broadcast::Sender<Vec<u8>>,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) -> bool {
+    let mut rx = au_tx.subscribe();
+
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    relay_log(events, "llstream pipe writer stopping");
+                    return true;
+                }
+            }
+            recv = rx.recv() => {
+                match recv {
+                    Ok(au) => {
+                        if let Err(e) = writer.write_all(&au).await {
+                            relay_log(events, &format!("llstream pipe client disconnected: {}", e));
+                            return false;
                         }
                     }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        relay_log(events, &format!("llstream pipe lagged: skipped {}", skipped));
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        relay_log(events, "llstream pipe channel closed");
+                        return true;
+                    }
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parser::MrPacketHeader;
+
+    struct Packets {
+        sequence: u32,
+    }
+
+    impl Packets {
+        fn video(&mut self, kind: u8, nals: &[&[u8]]) -> Vec<u8> {
+            self.sequence += 1;
+            let header = MrPacketHeader {
+                media_type: parser::MR_MEDIA_VIDEO,
+                flags: 0,
+                kind,
+                sequence: self.sequence,
+                timestamp_ns: u64::from(self.sequence) * 33_000_000,
+                extension: &[0; 4],
+            };
+            let mut packet = header.encode();
+            for nal in nals {
+                packet.extend_from_slice(&NAL_START_CODE);
+                packet.extend_from_slice(nal);
+            }
+            packet
+        }
+    }
+
+    const SPS: &[u8] = &[0x67, 0x42, 0xc0, 0x1f, 0x1a];
+    const PPS: &[u8] = &[0x68, 0xce, 0x3c, 0x80];
+    const IDR: &[u8] = &[0x65, 0x88, 0x84, 0x21];
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
+
+    #[test]
+    fn pps_before_sps_is_kept_for_the_first_keyframe() {
+        let events = RelayEvents::detached();
+        let mut packets = Packets { sequence: 0 };
+        let mut assembler = VideoFrameAssembler::new(false);
+
+        let pps = packets.video(FRAME_KIND_PPS, &[PPS]);
+        assert!(assembler.process(&pps, &events, "test").is_none());
+        let sps = packets.video(FRAME_KIND_SPS, &[SPS]);
+        assert!(assembler.process(&sps, &events, "test").is_none());
+        let idr = packets.video(FRAME_KIND_IDR, &[IDR]);
+        let frame = assembler.process(&idr, &events, "test").expect("first keyframe");
+
+        assert_eq!(frame.codec, VideoCodec::H264);
+        assert!(frame.is_keyframe);
+        assert!(contains(&frame.access_unit, SPS));
+        assert!(contains(&frame.access_unit, PPS));
+        assert!(contains(&frame.access_unit, IDR));
+    }
+
+    #[test]
+    fn bare_parameter_sets_fall_back_to_h264() {
+        let events = RelayEvents::detached();
+        let mut packets = Packets { sequence: 0 };
+        let mut assembler = VideoFrameAssembler::new(false);
+
+        // Parameter set frames whose NAL headers don't say SPS/PPS.
+        let sps_body: &[u8] = &[0x06, 0x42, 0xc0, 0x1f];
+        let pps_body: &[u8] = &[0x06, 0xce, 0x3c, 0x80];
+        let sps = packets.video(FRAME_KIND_SPS, &[sps_body]);
+        assert!(assembler.process(&sps, &events, "test").is_none());
+        let pps = packets.video(FRAME_KIND_PPS, &[pps_body]);
+        assert!(assembler.process(&pps, &events, "test").is_none());
+
+        let idr = packets.video(FRAME_KIND_IDR, &[IDR]);
+        let frame = assembler.process(&idr, &events, "test").expect("first keyframe");
+        assert_eq!(frame.codec, VideoCodec::H264);
+        assert!(contains(&frame.access_unit, sps_body));
+        assert!(contains(&frame.access_unit, pps_body));
+    }
+
+    #[test]
+    fn slices_before_parameter_sets_are_dropped() {
+        let events = RelayEvents::detached();
+        let mut packets = Packets { sequence: 0 };
+        let mut assembler = VideoFrameAssembler::new(false);
+
+        let idr = packets.video(FRAME_KIND_IDR, &[IDR]);
+        assert!(assembler.process(&idr, &events, "test").is_none());
+        assert!(assembler.codec.is_none());
+
+        let keyframe = packets.video(FRAME_KIND_IDR, &[SPS, PPS, IDR]);
+        let frame = assembler.process(&keyframe, &events, "test").expect("keyframe");
+        assert_eq!(frame.codec, VideoCodec::H264);
+        assert!(frame.is_keyframe);
+    }
+}
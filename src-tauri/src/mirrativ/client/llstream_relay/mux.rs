@@ -1,18 +1,30 @@
+use super::parser::VideoCodec;
+
 const TS_PACKET_SIZE: usize = 188;
 const PID_PAT: u16 = 0x0000;
 const PID_PMT: u16 = 0x0100;
 const PID_VIDEO: u16 = 0x0101;
 const PID_AUDIO: u16 = 0x0102;
+/// PCR cadence of the AV muxer (40ms at 90kHz), independent of which stream is flowing.
+const PCR_INTERVAL_90K: u64 = 3_600;
+/// PCR runs this far behind PTS (100ms at 90kHz) so decoders have buffering headroom.
+const PCR_DELAY_90K: u64 = 9_000;
 
 pub(crate) fn ns_to_90k(ts_ns: u64) -> u64 {
     ts_ns.saturating_mul(90_000) / 1_000_000_000
 }
 
+fn pcr_for_pts(pts_90k: u64) -> u64 {
+    pts_90k.saturating_sub(PCR_DELAY_90K)
+}
+
 pub(crate) struct MpegTsMuxer {
     cc_pat: u8,
     cc_pmt: u8,
     cc_video: u8,
     frame_index: u64,
+    video_codec: VideoCodec,
+    discontinuity: bool,
 }
 
 impl MpegTsMuxer {
@@ -22,9 +34,28 @@ impl MpegTsMuxer {
             cc_pmt: 0,
             cc_video: 0,
             frame_index: 0,
+            video_codec: VideoCodec::H264,
+            discontinuity: false,
         }
     }
 
+    /// Flags the next access unit with `discontinuity_indicator` and fresh PAT/PMT.
+    pub(crate) fn mark_discontinuity(&mut self) {
+        self.discontinuity = true;
+        self.frame_index = 0;
+    }
+
+    /// Switches the PMT video stream_type. Returns true when the codec changed;
+    /// the next access unit then starts with fresh PAT/PMT.
+    pub(crate) fn set_video_codec(&mut self, codec: VideoCodec) -> bool {
+        if self.video_codec == codec {
+            return false;
+        }
+        self.video_codec = codec;
+        self.frame_index = 0;
+        true
+    }
+
     pub(crate) fn push_video_access_unit(&mut self, annexb: &[u8], pts_90k: u64) -> Vec<u8> {
         let mut out = Vec::new();
 
@@ -35,18 +66,19 @@ impl MpegTsMuxer {
                 &mut self.cc_pat,
             ));
             out.extend_from_slice(&packetize_psi(
-                &build_pmt_section(PID_VIDEO, PID_VIDEO),
+                &build_pmt_section(PID_VIDEO, PID_VIDEO, self.video_codec),
                 PID_PMT,
                 &mut self.cc_pmt,
             ));
         }
 
-        let pes = build_h264_pes(annexb, pts_90k);
+        let pes = build_video_pes(annexb, pts_90k);
         out.extend_from_slice(&packetize_pes(
             &pes,
             PID_VIDEO,
             &mut self.cc_video,
-            Some(pts_90k),
+            Some(pcr_for_pts(pts_90k)),
+            std::mem::take(&mut self.discontinuity),
         ));
         self.frame_index += 1;
 
@@ -54,33 +86,25 @@ impl MpegTsMuxer {
     }
 }
 
-pub(crate) struct AvMpegTsMuxer {
-    cc_pat: u8,
-    cc_pmt: u8,
-    cc_video: u8,
-    cc_audio: u8,
+/// Maps source timestamps onto a monotonic 90 kHz timeline starting at `PCR_DELAY_90K`,
+/// so the PCR derived from it starts at 0.
+struct PtsTimeline {
     origin_ns: Option<u64>,
     pts_offset_90k: u64,
     last_pts_90k: Option<u64>,
-    video_count: u64,
-    audio_count: u64,
 }
 
-impl AvMpegTsMuxer {
-    pub(crate) fn new() -> Self {
+impl Default for PtsTimeline {
+    fn default() -> Self {
         Self {
-            cc_pat: 0,
-            cc_pmt: 0,
-            cc_video: 0,
-            cc_audio: 0,
             origin_ns: None,
-            pts_offset_90k: 0,
+            pts_offset_90k: PCR_DELAY_90K,
             last_pts_90k: None,
-            video_count: 0,
-            audio_count: 0,
         }
     }
+}
 
+impl PtsTimeline {
     fn pts_90k(&mut self, timestamp_ns: u64) -> u64 {
         let base = self.origin_ns.get_or_insert(timestamp_ns);
         let raw_pts = ns_to_90k(timestamp_ns.saturating_sub(*base));
@@ -102,6 +126,62 @@ impl AvMpegTsMuxer {
         self.last_pts_90k = Some(pts);
         pts
     }
+}
+
+pub(crate) struct AvMpegTsMuxer {
+    cc_pat: u8,
+    cc_pmt: u8,
+    cc_video: u8,
+    /// Whether any packet (PES or PCR-only) has been sent on the video PID yet.
+    video_pid_started: bool,
+    cc_audio: u8,
+    timeline: PtsTimeline,
+    video_count: u64,
+    audio_count: u64,
+    video_codec: VideoCodec,
+    video_discontinuity: bool,
+    audio_discontinuity: bool,
+    last_pcr_90k: Option<u64>,
+}
+
+impl AvMpegTsMuxer {
+    pub(crate) fn new() -> Self {
+        Self {
+            cc_pat: 0,
+            cc_pmt: 0,
+            cc_video: 0,
+            video_pid_started: false,
+            cc_audio: 0,
+            timeline: PtsTimeline::default(),
+            video_count: 0,
+            audio_count: 0,
+            video_codec: VideoCodec::H264,
+            video_discontinuity: false,
+            audio_discontinuity: false,
+            last_pcr_90k: None,
+        }
+    }
+
+    /// Flags the next video PES with `discontinuity_indicator` and fresh PAT/PMT.
+    pub(crate) fn mark_video_discontinuity(&mut self) {
+        self.video_discontinuity = true;
+        self.video_count = 0;
+    }
+
+    /// Flags the next audio PES with `discontinuity_indicator`. The PCR stays on video.
+    pub(crate) fn mark_audio_discontinuity(&mut self) {
+        self.audio_discontinuity = true;
+    }
+
+    /// Same as `MpegTsMuxer::set_video_codec`.
+    pub(crate) fn set_video_codec(&mut self, codec: VideoCodec) -> bool {
+        if self.video_codec == codec {
+            return false;
+        }
+        self.video_codec = codec;
+        self.video_count = 0;
+        true
+    }
 
     fn write_tables(&mut self, out: &mut Vec<u8>) {
         out.extend_from_slice(&packetize_psi(
@@ -110,12 +190,30 @@ impl AvMpegTsMuxer {
             &mut self.cc_pat,
         ));
         out.extend_from_slice(&packetize_psi(
-            &build_pmt_section_av(PID_VIDEO, PID_VIDEO, PID_AUDIO),
+            &build_pmt_section_av(PID_VIDEO, PID_VIDEO, PID_AUDIO, self.video_codec),
             PID_PMT,
             &mut self.cc_pmt,
         ));
     }
 
+    /// Returns the PCR to write when the cadence is due (or forced).
+    fn pcr_due(&mut self, pts_90k: u64, force: bool) -> Option<u64> {
+        let due = force
+            || self
+                .last_pcr_90k
+                .is_none_or(|last| pts_90k >= last.saturating_add(PCR_INTERVAL_90K));
+        if !due {
+            return None;
+        }
+        self.last_pcr_90k = Some(pts_90k);
+        Some(pcr_for_pts(pts_90k))
+    }
+
+    /// PTS of the newest frame muxed so far (either stream).
+    pub(crate) fn last_pts_90k(&self) -> Option<u64> {
+        self.timeline.last_pts_90k
+    }
+
     pub(crate) fn push_video_access_unit(&mut self, annexb: &[u8], timestamp_ns: u64) -> Vec<u8> {
         let mut out = Vec::new();
 
@@ -123,14 +221,18 @@ impl AvMpegTsMuxer {
             self.write_tables(&mut out);
         }
 
-        let pts_90k = self.pts_90k(timestamp_ns);
-        let pes = build_h264_pes(annexb, pts_90k);
+        let pts_90k = self.timeline.pts_90k(timestamp_ns);
+        let discontinuity = std::mem::take(&mut self.video_discontinuity);
+        let pcr = self.pcr_due(pts_90k, discontinuity);
+        let pes = build_video_pes(annexb, pts_90k);
         out.extend_from_slice(&packetize_pes(
             &pes,
             PID_VIDEO,
             &mut self.cc_video,
-            Some(pts_90k),
+            pcr,
+            discontinuity,
         ));
+        self.video_pid_started = true;
         self.video_count += 1;
 
         out
@@ -148,16 +250,102 @@ impl AvMpegTsMuxer {
             self.write_tables(&mut out);
         }
 
-        let pts_90k = self.pts_90k(timestamp_ns);
+        let pts_90k = self.timeline.pts_90k(timestamp_ns);
+        // The PCR lives on the video PID; keep it ticking through audio-only stretches.
+        if let Some(pcr) = self.pcr_due(pts_90k, false) {
+            out.extend_from_slice(&pcr_only_packet(
+                PID_VIDEO,
+                &mut self.cc_video,
+                &mut self.video_pid_started,
+                pcr,
+            ));
+        }
+        let pes = build_aac_pes(adts_frame, pts_90k);
+        out.extend_from_slice(&packetize_pes(
+            &pes,
+            PID_AUDIO,
+            &mut self.cc_audio,
+            None,
+            std::mem::take(&mut self.audio_discontinuity),
+        ));
+        self.audio_count += 1;
+
+        out
+    }
+}
+
+/// Audio-only TS. The audio PID also carries the PCR since there is no video.
+pub(crate) struct AudioMpegTsMuxer {
+    cc_pat: u8,
+    cc_pmt: u8,
+    cc_audio: u8,
+    timeline: PtsTimeline,
+    audio_count: u64,
+    discontinuity: bool,
+}
+
+impl AudioMpegTsMuxer {
+    pub(crate) fn new() -> Self {
+        Self {
+            cc_pat: 0,
+            cc_pmt: 0,
+            cc_audio: 0,
+            timeline: PtsTimeline::default(),
+            audio_count: 0,
+            discontinuity: false,
+        }
+    }
+
+    /// Flags the next frame with `discontinuity_indicator`, a PCR and fresh PAT/PMT.
+    pub(crate) fn mark_discontinuity(&mut self) {
+        self.discontinuity = true;
+        self.audio_count = 0;
+    }
+
+    /// PTS of the newest frame muxed so far.
+    pub(crate) fn last_pts_90k(&self) -> Option<u64> {
+        self.timeline.last_pts_90k
+    }
+
+    pub(crate) fn push_audio_adts_frame(
+        &mut self,
+        adts_frame: &[u8],
+        timestamp_ns: u64,
+    ) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        // ~1s at 44.1/48kHz AAC (1024 samples per frame).
+        if self.audio_count.is_multiple_of(45) {
+            out.extend_from_slice(&packetize_psi(
+                &build_pat_section(PID_PMT),
+                PID_PAT,
+                &mut self.cc_pat,
+            ));
+            out.extend_from_slice(&packetize_psi(
+                &build_pmt_section_audio(PID_AUDIO, PID_AUDIO),
+                PID_PMT,
+                &mut self.cc_pmt,
+            ));
+        }
+
+        let pts_90k = self.timeline.pts_90k(timestamp_ns);
         let pes = build_aac_pes(adts_frame, pts_90k);
-        out.extend_from_slice(&packetize_pes(&pes, PID_AUDIO, &mut self.cc_audio, None));
+        // One PCR every few frames keeps the PCR interval well under 100ms.
+        let pcr = self.audio_count.is_multiple_of(4).then_some(pcr_for_pts(pts_90k));
+        out.extend_from_slice(&packetize_pes(
+            &pes,
+            PID_AUDIO,
+            &mut self.cc_audio,
+            pcr,
+            std::mem::take(&mut self.discontinuity),
+        ));
         self.audio_count += 1;
 
         out
     }
 }
 
-pub(crate) fn build_bootstrap_tables() -> Vec<u8> {
+pub(crate) fn build_bootstrap_tables(codec: VideoCodec) -> Vec<u8> {
     let mut cc_pat = 0u8;
     let mut cc_pmt = 0u8;
     let mut out = Vec::new();
@@ -167,14 +355,14 @@ pub(crate) fn build_bootstrap_tables() -> Vec<u8> {
         &mut cc_pat,
     ));
     out.extend_from_slice(&packetize_psi(
-        &build_pmt_section(PID_VIDEO, PID_VIDEO),
+        &build_pmt_section(PID_VIDEO, PID_VIDEO, codec),
         PID_PMT,
         &mut cc_pmt,
     ));
     out
 }
 
-pub(crate) fn build_bootstrap_tables_av() -> Vec<u8> {
+pub(crate) fn build_bootstrap_tables_av(codec: VideoCodec) -> Vec<u8> {
     let mut cc_pat = 0u8;
     let mut cc_pmt = 0u8;
     let mut out = Vec::new();
@@ -184,7 +372,24 @@ pub(crate) fn build_bootstrap_tables_av() -> Vec<u8> {
         &mut cc_pat,
     ));
     out.extend_from_slice(&packetize_psi(
-        &build_pmt_section_av(PID_VIDEO, PID_VIDEO, PID_AUDIO),
+        &build_pmt_section_av(PID_VIDEO, PID_VIDEO, PID_AUDIO, codec),
+        PID_PMT,
+        &mut cc_pmt,
+    ));
+    out
+}
+
+pub(crate) fn build_bootstrap_tables_audio() -> Vec<u8> {
+    let mut cc_pat = 0u8;
+    let mut cc_pmt = 0u8;
+    let mut out = Vec::new();
+    out.extend_from_slice(&packetize_psi(
+        &build_pat_section(PID_PMT),
+        PID_PAT,
+        &mut cc_pat,
+    ));
+    out.extend_from_slice(&packetize_psi(
+        &build_pmt_section_audio(PID_AUDIO, PID_AUDIO),
         PID_PMT,
         &mut cc_pmt,
     ));
@@ -207,7 +412,13 @@ fn packetize_psi(section: &[u8], pid: u16, cc: &mut u8) -> Vec<u8> {
     packet.to_vec()
 }
 
-fn packetize_pes(pes: &[u8], pid: u16, cc: &mut u8, pcr_90k: Option<u64>) -> Vec<u8> {
+fn packetize_pes(
+    pes: &[u8],
+    pid: u16,
+    cc: &mut u8,
+    pcr_90k: Option<u64>,
+    discontinuity: bool,
+) -> Vec<u8> {
     let mut out = Vec::new();
     let mut offset = 0usize;
     let mut first = true;
@@ -219,23 +430,27 @@ fn packetize_pes(pes: &[u8], pid: u16, cc: &mut u8, pcr_90k: Option<u64>) -> Vec
         packet[1] = (if first { 0x40 } else { 0x00 }) | ((pid >> 8) as u8 & 0x1F);
         packet[2] = (pid & 0xFF) as u8;
 
-        if first && pcr_90k.is_some() {
-            // Put PCR on the first TS packet of each video PES.
-            // adaptation_field_length includes flags(1) + PCR(6) + optional stuffing.
-            let min_adaptation_len = 7usize;
-            let max_payload_with_min_adaptation = 184 - (1 + min_adaptation_len); // 176 bytes
+        if first && (pcr_90k.is_some() || discontinuity) {
+            // Put PCR / discontinuity_indicator on the first TS packet of the PES.
+            // adaptation_field_length includes flags(1) + PCR(6, optional) + optional stuffing.
+            let min_adaptation_len = if pcr_90k.is_some() { 7usize } else { 1 };
+            let max_payload_with_min_adaptation = 184 - (1 + min_adaptation_len);
             let payload_len = remain.min(max_payload_with_min_adaptation);
             let adaptation_len = 184 - payload_len - 1;
 
             packet[3] = 0x30 | (*cc & 0x0F); // adaptation + payload
             packet[4] = adaptation_len as u8;
-            packet[5] = 0x10; // PCR_flag
-
-            let pcr = pcr_90k.unwrap_or(0);
-            write_pcr(&mut packet[6..12], pcr);
+            packet[5] = 0x00;
+            if discontinuity {
+                packet[5] |= 0x80; // discontinuity_indicator
+            }
+            if let Some(pcr) = pcr_90k {
+                packet[5] |= 0x10; // PCR_flag
+                write_pcr(&mut packet[6..12], pcr);
+            }
 
             if adaptation_len > min_adaptation_len {
-                let stuffing_start = 12usize;
+                let stuffing_start = 5 + min_adaptation_len;
                 let stuffing_len = adaptation_len - min_adaptation_len;
                 packet[stuffing_start..stuffing_start + stuffing_len].fill(0xFF);
             }
@@ -278,6 +493,25 @@ fn packetize_pes(pes: &[u8], pid: u16, cc: &mut u8, pcr_90k: Option<u64>) -> Vec
     out
 }
 
+/// Adaptation-field-only packet carrying a PCR. Without payload the continuity
+/// counter does not advance, so it repeats the last value sent on `pid`. If nothing
+/// has been sent on `pid` yet, it takes the PID's counter and steps it, so the first
+/// payload packet follows on from it.
+fn pcr_only_packet(pid: u16, cc: &mut u8, started: &mut bool, pcr_90k: u64) -> Vec<u8> {
+    if !std::mem::replace(started, true) {
+        *cc = (*cc + 1) & 0x0F;
+    }
+    let mut packet = [0xFFu8; TS_PACKET_SIZE];
+    packet[0] = 0x47;
+    packet[1] = (pid >> 8) as u8 & 0x1F;
+    packet[2] = (pid & 0xFF) as u8;
+    packet[3] = 0x20 | (cc.wrapping_sub(1) & 0x0F); // adaptation only
+    packet[4] = 183; // adaptation_field_length
+    packet[5] = 0x10; // PCR_flag
+    write_pcr(&mut packet[6..12], pcr_90k);
+    packet.to_vec()
+}
+
 fn write_pcr(out: &mut [u8], pcr_90k: u64) {
     // PCR is a 42-bit value: base(33 bits, 90kHz) + extension(9 bits, 27MHz remainder).
     // We use extension=0 and drive base from our 90k timeline.
@@ -291,7 +525,8 @@ fn write_pcr(out: &mut [u8], pcr_90k: u64) {
     out[5] = (ext & 0xFF) as u8;
 }
 
-fn build_h264_pes(annexb: &[u8], pts_90k: u64) -> Vec<u8> {
+/// PES for an H.264 or HEVC access unit (both use video stream_id 0xE0).
+fn build_video_pes(annexb: &[u8], pts_90k: u64) -> Vec<u8> {
     let mut pes = Vec::with_capacity(14 + annexb.len());
     pes.extend_from_slice(&[0x00, 0x00, 0x01, 0xE0, 0x00, 0x00, 0x80, 0x80, 0x05]);
     pes.extend_from_slice(&encode_pts(pts_90k));
@@ -338,17 +573,26 @@ fn build_pat_section(pmt_pid: u16) -> Vec<u8> {
     section
 }
 
-fn build_pmt_section(pcr_pid: u16, video_pid: u16) -> Vec<u8> {
+/// PMT version_number per video codec, so demuxers re-read the PMT when it changes.
+fn pmt_version(codec: VideoCodec) -> u8 {
+    let version = match codec {
+        VideoCodec::H264 => 0,
+        VideoCodec::Hevc => 1,
+    };
+    0xC1 | (version << 1)
+}
+
+fn build_pmt_section(pcr_pid: u16, video_pid: u16, codec: VideoCodec) -> Vec<u8> {
     let mut section = vec![
         0x02, 0xB0, 0x00, // table id + section length placeholder
         0x00, 0x01, // program number
-        0xC1, // version 0, current_next_indicator=1
+        pmt_version(codec), // version, current_next_indicator=1
         0x00, // section number
         0x00, // last section number
         0xE0 | ((pcr_pid >> 8) as u8 & 0x1F),
         (pcr_pid & 0xFF) as u8,
         0xF0, 0x00, // program info length
-        0x1B, // stream_type H.264
+        codec.stream_type(), // stream_type H.264 (0x1B) / HEVC (0x24)
         0xE0 | ((video_pid >> 8) as u8 & 0x1F),
         (video_pid & 0xFF) as u8,
         0xF0, 0x00, // ES info length
@@ -362,17 +606,22 @@ fn build_pmt_section(pcr_pid: u16, video_pid: u16) -> Vec<u8> {
     section
 }
 
-fn build_pmt_section_av(pcr_pid: u16, video_pid: u16, audio_pid: u16) -> Vec<u8> {
+fn build_pmt_section_av(
+    pcr_pid: u16,
+    video_pid: u16,
+    audio_pid: u16,
+    codec: VideoCodec,
+) -> Vec<u8> {
     let mut section = vec![
         0x02, 0xB0, 0x00, // table id + section length placeholder
         0x00, 0x01, // program number
-        0xC1, // version 0, current_next_indicator=1
+        pmt_version(codec), // version, current_next_indicator=1
         0x00, // section number
         0x00, // last section number
         0xE0 | ((pcr_pid >> 8) as u8 & 0x1F),
         (pcr_pid & 0xFF) as u8,
         0xF0, 0x00, // program info length
-        0x1B, // stream_type H.264
+        codec.stream_type(), // stream_type H.264 (0x1B) / HEVC (0x24)
         0xE0 | ((video_pid >> 8) as u8 & 0x1F),
         (video_pid & 0xFF) as u8,
         0xF0, 0x00, // ES info length
@@ -390,6 +639,30 @@ fn build_pmt_section_av(pcr_pid: u16, video_pid: u16, audio_pid: u16) -> Vec<u8>
     section
 }
 
+fn build_pmt_section_audio(pcr_pid: u16, audio_pid: u16) -> Vec<u8> {
+    let mut section = vec![
+        0x02, 0xB0, 0x00, // table id + section length placeholder
+        0x00, 0x01, // program number
+        0xC1, // version 0, current_next_indicator=1
+        0x00, // section number
+        0x00, // last section number
+        0xE0 | ((pcr_pid >> 8) as u8 & 0x1F),
+        (pcr_pid & 0xFF) as u8,
+        0xF0, 0x00, // program info length
+        0x0F, // stream_type AAC (ADTS)
+        0xE0 | ((audio_pid >> 8) as u8 & 0x1F),
+        (audio_pid & 0xFF) as u8,
+        0xF0, 0x00, // ES info length
+        0x00, 0x00, 0x00, 0x00, // crc placeholder
+    ];
+
+    set_section_length(&mut section);
+    let crc = mpeg_crc32(&section[..section.len() - 4]).to_be_bytes();
+    let n = section.len();
+    section[n - 4..n].copy_from_slice(&crc);
+    section
+}
+
 fn set_section_length(section: &mut [u8]) {
     let section_length = (section.len() - 3) as u16;
     section[1] = (section[1] & 0xF0) | ((section_length >> 8) as u8 & 0x0F);
@@ -410,3 +683,61 @@ fn mpeg_crc32(data: &[u8]) -> u32 {
     }
     crc
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packets_on(ts: &[u8], pid: u16) -> Vec<&[u8]> {
+        ts.chunks(TS_PACKET_SIZE)
+            .filter(|packet| u16::from(packet[1] & 0x1F) << 8 | u16::from(packet[2]) == pid)
+            .collect()
+    }
+
+    fn read_pcr_base(packet: &[u8]) -> Option<u64> {
+        let has_adaptation = packet[3] & 0x20 != 0;
+        if !has_adaptation || packet[4] == 0 || packet[5] & 0x10 == 0 {
+            return None;
+        }
+        let b = &packet[6..11];
+        Some(
+            (u64::from(b[0]) << 25)
+                | (u64::from(b[1]) << 17)
+                | (u64::from(b[2]) << 9)
+                | (u64::from(b[3]) << 1)
+                | u64::from(b[4] >> 7),
+        )
+    }
+
+    fn read_pts(packet: &[u8]) -> u64 {
+        let start = 4 + if packet[3] & 0x20 != 0 { 1 + usize::from(packet[4]) } else { 0 };
+        let p = &packet[start + 9..start + 14];
+        (u64::from(p[0] >> 1 & 0x07) << 30)
+            | (u64::from(p[1]) << 22)
+            | (u64::from(p[2] >> 1) << 15)
+            | (u64::from(p[3]) << 7)
+            | u64::from(p[4] >> 1)
+    }
+
+    #[test]
+    fn pcr_trails_pts_by_the_fixed_delay() {
+        let mut muxer = AvMpegTsMuxer::new();
+        let ts = muxer.push_video_access_unit(&[0, 0, 0, 1, 0x65], 1_000_000_000);
+        let first = packets_on(&ts, PID_VIDEO)[0];
+        let pcr = read_pcr_base(first).expect("first video PES carries a PCR");
+        assert_eq!(read_pts(first), pcr + PCR_DELAY_90K);
+    }
+
+    #[test]
+    fn pcr_only_packet_continues_into_the_video_pid_counter() {
+        let mut muxer = AvMpegTsMuxer::new();
+        let audio = muxer.push_audio_adts_frame(&[0xFF, 0xF1, 0x50, 0x80, 0x01, 0x7F, 0xFC], 0);
+        let pcr_only = packets_on(&audio, PID_VIDEO);
+        assert_eq!(pcr_only.len(), 1);
+        assert_eq!(pcr_only[0][3], 0x20); // adaptation only, cc 0
+
+        let video = muxer.push_video_access_unit(&[0, 0, 0, 1, 0x65], 33_000_000);
+        let first = packets_on(&video, PID_VIDEO)[0];
+        assert_eq!(first[3] & 0x0F, 1);
+    }
+}
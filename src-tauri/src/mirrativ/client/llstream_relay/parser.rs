@@ -9,17 +9,188 @@ pub(crate) const FRAME_KIND_IDR: u8 = 0x04;
 pub(crate) const AUDIO_KIND_ASC: u8 = 0x01;
 pub(crate) const AUDIO_KIND_AAC: u8 = 0x02;
 
-#[derive(Clone, Copy)]
-pub(crate) struct VideoFrame<'a> {
+/// Video codec of an llstream video stream, detected from its parameter sets.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum VideoCodec {
+    H264,
+    Hevc,
+}
+
+impl VideoCodec {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::H264 => "h264",
+            Self::Hevc => "hevc",
+        }
+    }
+
+    pub(crate) fn mime_type(self) -> &'static str {
+        match self {
+            Self::H264 => "video/h264",
+            Self::Hevc => "video/h265",
+        }
+    }
+
+    /// Elementary stream file extension.
+    pub(crate) fn file_extension(self) -> &'static str {
+        match self {
+            Self::H264 => "h264",
+            Self::Hevc => "h265",
+        }
+    }
+
+    /// NAL unit type from the first NAL header byte.
+    pub(crate) fn nal_type(self, header: u8) -> u8 {
+        match self {
+            Self::H264 => header & 0x1F,
+            Self::Hevc => (header >> 1) & 0x3F,
+        }
+    }
+
+    pub(crate) fn vps_type(self) -> Option<u8> {
+        match self {
+            Self::H264 => None,
+            Self::Hevc => Some(32),
+        }
+    }
+
+    pub(crate) fn sps_type(self) -> u8 {
+        match self {
+            Self::H264 => 7,
+            Self::Hevc => 33,
+        }
+    }
+
+    pub(crate) fn pps_type(self) -> u8 {
+        match self {
+            Self::H264 => 8,
+            Self::Hevc => 34,
+        }
+    }
+
+    /// IDR for H.264; any IRAP picture (BLA/IDR/CRA) for HEVC.
+    pub(crate) fn is_random_access(self, nal_type: u8) -> bool {
+        match self {
+            Self::H264 => nal_type == 5,
+            Self::Hevc => (16..=21).contains(&nal_type),
+        }
+    }
+
+    /// Access unit delimiter NAL (without start code) allowing any picture type.
+    pub(crate) fn aud_nal(self) -> &'static [u8] {
+        match self {
+            Self::H264 => &[0x09, 0xF0],
+            Self::Hevc => &[0x46, 0x01, 0x50],
+        }
+    }
+
+    /// MPEG-TS stream_type for the PMT.
+    pub(crate) fn stream_type(self) -> u8 {
+        match self {
+            Self::H264 => 0x1B,
+            Self::Hevc => 0x24,
+        }
+    }
+}
+
+/// Decides the codec from the parameter sets in an Annex B buffer. Slice NAL headers
+/// are ambiguous between H.264 and HEVC, so only parameter sets are trusted.
+pub(crate) fn detect_video_codec(annexb: &[u8]) -> Option<VideoCodec> {
+    for nal in annexb_nals(annexb) {
+        if nal.len() < 2 || nal[0] & 0x80 != 0 {
+            continue;
+        }
+        // HEVC header: type(6) + nuh_layer_id(6) == 0 + nuh_temporal_id_plus1 == 1.
+        let hevc_type = VideoCodec::Hevc.nal_type(nal[0]);
+        if (32..=34).contains(&hevc_type) && nal[0] & 0x01 == 0 && nal[1] == 0x01 {
+            return Some(VideoCodec::Hevc);
+        }
+        if VideoCodec::H264.nal_type(nal[0]) == 7 {
+            return Some(VideoCodec::H264);
+        }
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// MR packet header
+// ---------------------------------------------------------------------------
+
+pub(crate) const MR_MEDIA_VIDEO: u8 = 0x01;
+pub(crate) const MR_MEDIA_AUDIO: u8 = 0x02;
+/// Length of the header part common to video and audio packets.
+pub(crate) const MR_HEADER_FIXED_LEN: usize = 17;
+
+/// Decoded header of an llstream WS binary message.
+///
+/// | bytes | field                                                      |
+/// |-------|------------------------------------------------------------|
+/// | 0..2  | magic `"MR"`                                               |
+/// | 2     | media type (`0x01` video, `0x02` audio)                    |
+/// | 3     | flags, kept verbatim (only `0x00` seen so far)             |
+/// | 4     | frame kind (`FRAME_KIND_*` / `AUDIO_KIND_*`)               |
+/// | 5..9  | u32 BE, increments per message; treated as sequence number |
+/// | 9..17 | timestamp in nanoseconds, u64 BE                           |
+/// | 17..  | video only: extension bytes up to the payload (usually 4)  |
+///
+/// The video extension length is not signalled anywhere, so the payload start is
+/// still located by probing for a plausible NAL stream (see `detect_video_payload_offset`).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) struct MrPacketHeader<'a> {
+    pub(crate) media_type: u8,
+    pub(crate) flags: u8,
     pub(crate) kind: u8,
+    pub(crate) sequence: u32,
     pub(crate) timestamp_ns: u64,
+    pub(crate) extension: &'a [u8],
+}
+
+impl<'a> MrPacketHeader<'a> {
+    /// Decodes the fixed 17-byte part; `extension` is left empty.
+    fn parse_fixed(data: &'a [u8], media_type: u8) -> Option<Self> {
+        let fixed = data.get(..MR_HEADER_FIXED_LEN)?;
+        if fixed[0] != b'M' || fixed[1] != b'R' || fixed[2] != media_type {
+            return None;
+        }
+        Some(Self {
+            media_type,
+            flags: fixed[3],
+            kind: fixed[4],
+            sequence: u32::from_be_bytes(fixed[5..9].try_into().ok()?),
+            timestamp_ns: u64::from_be_bytes(fixed[9..17].try_into().ok()?),
+            extension: &[],
+        })
+    }
+
+    /// Total header length, i.e. the payload offset.
+    pub(crate) fn len(&self) -> usize {
+        MR_HEADER_FIXED_LEN + self.extension.len()
+    }
+
+    /// Encodes the header (inverse of parsing); used by tests and fuzz targets.
+    #[cfg(any(test, feature = "fuzzing"))]
+    pub(crate) fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.len());
+        out.extend_from_slice(b"MR");
+        out.push(self.media_type);
+        out.push(self.flags);
+        out.push(self.kind);
+        out.extend_from_slice(&self.sequence.to_be_bytes());
+        out.extend_from_slice(&self.timestamp_ns.to_be_bytes());
+        out.extend_from_slice(self.extension);
+        out
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct VideoFrame<'a> {
+    pub(crate) header: MrPacketHeader<'a>,
     pub(crate) payload: &'a [u8],
 }
 
 #[derive(Clone, Copy)]
 pub(crate) struct AudioFrame<'a> {
-    pub(crate) kind: u8,
-    pub(crate) timestamp_ns: u64,
+    pub(crate) header: MrPacketHeader<'a>,
     pub(crate) payload: &'a [u8],
 }
 
@@ -86,23 +257,13 @@ pub(crate) fn parse_video_packet(data: &[u8]) -> Option<VideoFrame<'_>> {
     if data.len() < VIDEO_HEADER_MIN_LEN {
         return None;
     }
-    if data[0] != b'M' || data[1] != b'R' || data[2] != 0x01 {
-        return None;
-    }
-
+    let mut header = MrPacketHeader::parse_fixed(data, MR_MEDIA_VIDEO)?;
     let payload_offset = detect_video_payload_offset(data)?;
-    let kind = data[4];
-    let timestamp_ns = data
-        .get(9..17)
-        .and_then(|raw| raw.try_into().ok())
-        .map(u64::from_be_bytes)
-        .unwrap_or(0);
-    let payload = &data[payload_offset..];
+    header.extension = &data[MR_HEADER_FIXED_LEN..payload_offset];
 
     Some(VideoFrame {
-        kind,
-        timestamp_ns,
-        payload,
+        header,
+        payload: &data[header.len()..],
     })
 }
 
@@ -110,36 +271,25 @@ pub(crate) fn parse_audio_packet(data: &[u8]) -> Option<AudioFrame<'_>> {
     if data.len() < AUDIO_HEADER_LEN {
         return None;
     }
-    if data[0] != b'M' || data[1] != b'R' || data[2] != 0x02 {
-        return None;
-    }
-
-    let kind = data[4];
-    let timestamp_ns = data
-        .get(9..17)
-        .and_then(|raw| raw.try_into().ok())
-        .map(u64::from_be_bytes)
-        .unwrap_or(0);
-    let payload = &data[AUDIO_HEADER_LEN..];
+    let header = MrPacketHeader::parse_fixed(data, MR_MEDIA_AUDIO)?;
 
     Some(AudioFrame {
-        kind,
-        timestamp_ns,
-        payload,
+        header,
+        payload: &data[AUDIO_HEADER_LEN..],
     })
 }
 
 fn detect_video_payload_offset(data: &[u8]) -> Option<usize> {
     let known_offsets = [VIDEO_HEADER_LEN, VIDEO_HEADER_MIN_LEN];
     for &offset in &known_offsets {
-        if offset < data.len() && looks_like_h264_payload(&data[offset..]) {
+        if offset < data.len() && looks_like_video_payload(&data[offset..]) {
             return Some(offset);
         }
     }
 
     let end = (VIDEO_HEADER_MAX_SCAN_LEN + 1).min(data.len());
     for offset in VIDEO_HEADER_MIN_LEN..end {
-        if looks_like_h264_payload(&data[offset..]) {
+        if looks_like_video_payload(&data[offset..]) {
             return Some(offset);
         }
     }
@@ -147,7 +297,7 @@ fn detect_video_payload_offset(data: &[u8]) -> Option<usize> {
     None
 }
 
-fn looks_like_h264_payload(payload: &[u8]) -> bool {
+fn looks_like_video_payload(payload: &[u8]) -> bool {
     if payload.is_empty() {
         return false;
     }
@@ -163,7 +313,7 @@ fn looks_like_h264_payload(payload: &[u8]) -> bool {
         return true;
     }
 
-    is_valid_h264_nal_type(payload[0] & 0x1F)
+    is_valid_nal_header(payload)
 }
 
 fn looks_like_length_prefixed(payload: &[u8], len_bytes: usize) -> bool {
@@ -195,7 +345,7 @@ fn looks_like_length_prefixed(payload: &[u8], len_bytes: usize) -> bool {
         }
 
         let nal = &payload[i + len_bytes..i + len_bytes + nal_len];
-        if nal.is_empty() || !is_valid_h264_nal_type(nal[0] & 0x1F) {
+        if !is_valid_nal_header(nal) {
             return false;
         }
 
@@ -213,13 +363,13 @@ pub(crate) fn hex_prefix(data: &[u8], max_len: usize) -> String {
         .join(" ")
 }
 
-pub(crate) fn nal_types_preview(data: &[u8], max_nals: usize) -> String {
+pub(crate) fn nal_types_preview(data: &[u8], codec: VideoCodec, max_nals: usize) -> String {
     let mut out = Vec::new();
     for nal in annexb_nals(data).into_iter().take(max_nals) {
         if nal.is_empty() {
             continue;
         }
-        out.push(format!("{}", nal[0] & 0x1F));
+        out.push(format!("{}", codec.nal_type(nal[0])));
     }
     if out.is_empty() {
         "-".to_string()
@@ -236,7 +386,25 @@ fn is_valid_h264_nal_type(ty: u8) -> bool {
     (1..=23).contains(&ty)
 }
 
-fn convert_length_prefixed(payload: &[u8], len_bytes: usize) -> Option<Vec<u8>> {
+/// Whether a NAL starts with a plausible H.264 or HEVC header. Used before the
+/// codec is known, so either interpretation is accepted.
+fn is_valid_nal_header(nal: &[u8]) -> bool {
+    let Some(&first) = nal.first() else {
+        return false;
+    };
+    if first & 0x80 != 0 {
+        return false;
+    }
+    if is_valid_h264_nal_type(first & 0x1F) {
+        return true;
+    }
+    // HEVC: VCL 0..=21 or non-VCL 32..=40, nuh_temporal_id_plus1 != 0.
+    let hevc_type = VideoCodec::Hevc.nal_type(first);
+    let tid_ok = nal.get(1).is_some_and(|b| b & 0x07 != 0);
+    tid_ok && (hevc_type <= 21 || (32..=40).contains(&hevc_type))
+}
+
+pub(crate) fn convert_length_prefixed(payload: &[u8], len_bytes: usize) -> Option<Vec<u8>> {
     if len_bytes == 0 || payload.len() < len_bytes + 1 {
         return None;
     }
@@ -261,7 +429,7 @@ fn convert_length_prefixed(payload: &[u8], len_bytes: usize) -> Option<Vec<u8>>
             return None;
         }
         let nal = &payload[i + len_bytes..i + len_bytes + nal_len];
-        if nal.is_empty() || !is_valid_h264_nal_type(nal[0] & 0x1F) {
+        if !is_valid_nal_header(nal) {
             return None;
         }
 
@@ -293,7 +461,7 @@ pub(crate) fn ensure_annexb(payload: &[u8]) -> Vec<u8> {
     if let Some(v) = convert_length_prefixed(payload, 2) {
         return v;
     }
-    if !payload.is_empty() && is_valid_h264_nal_type(payload[0] & 0x1F) {
+    if is_valid_nal_header(payload) {
         let mut out = Vec::with_capacity(payload.len() + 4);
         out.extend_from_slice(&NAL_START_CODE);
         out.extend_from_slice(payload);
@@ -342,36 +510,249 @@ fn annexb_nals(data: &[u8]) -> Vec<&[u8]> {
     out
 }
 
-pub(crate) fn has_nal_type(data: &[u8], target: u8) -> bool {
+pub(crate) fn has_nal_type(data: &[u8], codec: VideoCodec, target: u8) -> bool {
+    find_nal(data, codec, target).is_some()
+}
+
+pub(crate) fn has_random_access_nal(data: &[u8], codec: VideoCodec) -> bool {
     annexb_nals(data)
         .into_iter()
-        .any(|nal| !nal.is_empty() && (nal[0] & 0x1F) == target)
+        .any(|nal| !nal.is_empty() && codec.is_random_access(codec.nal_type(nal[0])))
 }
 
-pub(crate) fn extract_parameter_sets(data: &[u8]) -> (Option<Vec<u8>>, Option<Vec<u8>>) {
-    let mut sps: Option<Vec<u8>> = None;
-    let mut pps: Option<Vec<u8>> = None;
+/// Whether the access unit has slices and none of them is used for reference
+/// (H.264 nal_ref_idc == 0, HEVC sub-layer non-reference types). Such frames can
+/// be dropped without breaking decoding of the rest of the GOP.
+pub(crate) fn is_non_reference(data: &[u8], codec: VideoCodec) -> bool {
+    let mut has_slice = false;
+    for nal in annexb_nals(data) {
+        let Some(&header) = nal.first() else {
+            continue;
+        };
+        let ty = codec.nal_type(header);
+        let (is_slice, is_reference) = match codec {
+            VideoCodec::H264 => ((1..=5).contains(&ty), header & 0x60 != 0),
+            VideoCodec::Hevc => (ty <= 21, !(ty <= 14 && ty.is_multiple_of(2))),
+        };
+        if is_slice {
+            if is_reference {
+                return false;
+            }
+            has_slice = true;
+        }
+    }
+    has_slice
+}
+
+/// First NAL unit (without start code) of the given type in an Annex B buffer.
+pub(crate) fn find_nal(data: &[u8], codec: VideoCodec, target: u8) -> Option<&[u8]> {
+    annexb_nals(data)
+        .into_iter()
+        .find(|nal| !nal.is_empty() && codec.nal_type(nal[0]) == target)
+}
+
+/// Parameter set NALs found in an access unit, each with a 4-byte start code.
+#[derive(Default)]
+pub(crate) struct ParameterSets {
+    pub(crate) vps: Option<Vec<u8>>,
+    pub(crate) sps: Option<Vec<u8>>,
+    pub(crate) pps: Option<Vec<u8>>,
+}
+
+impl ParameterSets {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.vps.is_none() && self.sps.is_none() && self.pps.is_none()
+    }
+}
+
+pub(crate) fn extract_parameter_sets(data: &[u8], codec: VideoCodec) -> ParameterSets {
+    let mut sets = ParameterSets::default();
 
     for nal in annexb_nals(data) {
         if nal.is_empty() {
             continue;
         }
-        match nal[0] & 0x1F {
-            7 => {
-                let mut v = Vec::with_capacity(nal.len() + 4);
-                v.extend_from_slice(&NAL_START_CODE);
-                v.extend_from_slice(nal);
-                sps = Some(v);
-            }
-            8 => {
-                let mut v = Vec::with_capacity(nal.len() + 4);
-                v.extend_from_slice(&NAL_START_CODE);
-                v.extend_from_slice(nal);
-                pps = Some(v);
-            }
-            _ => {}
+        let ty = codec.nal_type(nal[0]);
+        let slot = if Some(ty) == codec.vps_type() {
+            &mut sets.vps
+        } else if ty == codec.sps_type() {
+            &mut sets.sps
+        } else if ty == codec.pps_type() {
+            &mut sets.pps
+        } else {
+            continue;
+        };
+        let mut v = Vec::with_capacity(nal.len() + 4);
+        v.extend_from_slice(&NAL_START_CODE);
+        v.extend_from_slice(nal);
+        *slot = Some(v);
+    }
+
+    sets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A NAL unit with a valid H.264 header byte and a non-empty body.
+    fn h264_nal() -> impl Strategy<Value = Vec<u8>> {
+        (1u8..=23, 0u8..=3, prop::collection::vec(any::<u8>(), 1..200)).prop_map(
+            |(ty, nri, body)| {
+                let mut nal = vec![(nri << 5) | ty];
+                nal.extend(body);
+                nal
+            },
+        )
+    }
+
+    fn to_annexb(nals: &[Vec<u8>]) -> Vec<u8> {
+        nals.iter()
+            .flat_map(|nal| NAL_START_CODE.iter().chain(nal.iter()).copied())
+            .collect()
+    }
+
+    fn to_length_prefixed(nals: &[Vec<u8>], len_bytes: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        for nal in nals {
+            let len = (nal.len() as u32).to_be_bytes();
+            out.extend_from_slice(&len[4 - len_bytes..]);
+            out.extend_from_slice(nal);
         }
+        out
+    }
+
+    #[test]
+    fn detects_codec_from_parameter_sets() {
+        let h264 = to_annexb(&[
+            vec![0x67, 0x42, 0xc0, 0x1f, 0x1a],
+            vec![0x68, 0xce, 0x3c, 0x80],
+            vec![0x65, 0x88, 0x84],
+        ]);
+        assert_eq!(detect_video_codec(&h264), Some(VideoCodec::H264));
+
+        let hevc = to_annexb(&[
+            vec![0x40, 0x01, 0x0c, 0x01],
+            vec![0x42, 0x01, 0x01, 0x01],
+            vec![0x44, 0x01, 0xc1],
+        ]);
+        assert_eq!(detect_video_codec(&hevc), Some(VideoCodec::Hevc));
+    }
+
+    #[test]
+    fn codec_detection_ignores_slices_and_lookalikes() {
+        // H.264 non-IDR slices: 0x41 reads as HEVC VPS but has nuh_layer_id bits set.
+        let slices = to_annexb(&[vec![0x41, 0x9a, 0x02], vec![0x41, 0x01, 0x24]]);
+        assert_eq!(detect_video_codec(&slices), None);
+        // Forbidden bit set on an otherwise valid SPS header.
+        assert_eq!(detect_video_codec(&to_annexb(&[vec![0xe7, 0x42, 0xc0]])), None);
+        // HEVC parameter set type with nuh_temporal_id_plus1 != 1 is not trusted.
+        assert_eq!(detect_video_codec(&to_annexb(&[vec![0x40, 0x02, 0x0c]])), None);
+        assert_eq!(detect_video_codec(&[0x67, 0x42, 0xc0, 0x1f]), None);
     }
 
-    (sps, pps)
+    #[test]
+    fn nal_header_validation() {
+        assert!(is_valid_nal_header(&[0x67, 0x42]));
+        assert!(is_valid_nal_header(&[0x65]));
+        assert!(is_valid_nal_header(&[0x40, 0x01]));
+        assert!(is_valid_nal_header(&[0x26, 0x01]));
+
+        assert!(!is_valid_nal_header(&[]));
+        assert!(!is_valid_nal_header(&[0xe7, 0x42]));
+        // H.264 type 0 and 24..=31 only pass as HEVC, which needs a non-zero temporal id.
+        assert!(!is_valid_nal_header(&[0x00, 0x00]));
+        assert!(!is_valid_nal_header(&[0x18, 0x00]));
+        assert!(!is_valid_nal_header(&[0x40]));
+        // HEVC types 41..=63 are reserved/unspecified.
+        assert!(!is_valid_nal_header(&[0x7e, 0x01]));
+    }
+
+    #[test]
+    fn non_video_payloads_are_rejected() {
+        // ADTS frame header, JSON text and an out-of-range length prefix.
+        assert!(!looks_like_video_payload(&[0xff, 0xf1, 0x50, 0x80, 0x02, 0x1f, 0xfc]));
+        assert!(!looks_like_video_payload(br#"{"t":1}"#));
+        assert!(!looks_like_video_payload(&[0x00, 0x00, 0xff, 0xff, 0x67, 0x42]));
+        assert!(looks_like_video_payload(&[0x00, 0x00, 0x00, 0x02, 0x67, 0x42]));
+    }
+
+    proptest! {
+        #[test]
+        fn video_packet_round_trip(
+            flags in any::<u8>(),
+            kind in any::<u8>(),
+            sequence in any::<u32>(),
+            timestamp_ns in any::<u64>(),
+            extension in prop::array::uniform4(any::<u8>()),
+            nals in prop::collection::vec(h264_nal(), 1..4),
+        ) {
+            let header = MrPacketHeader {
+                media_type: MR_MEDIA_VIDEO,
+                flags,
+                kind,
+                sequence,
+                timestamp_ns,
+                extension: &extension,
+            };
+            let payload = to_annexb(&nals);
+            let mut packet = header.encode();
+            packet.extend_from_slice(&payload);
+
+            let frame = parse_video_packet(&packet).expect("valid video packet");
+            prop_assert_eq!(frame.header, header);
+            prop_assert_eq!(frame.payload, payload.as_slice());
+        }
+
+        #[test]
+        fn audio_packet_round_trip(
+            flags in any::<u8>(),
+            kind in any::<u8>(),
+            sequence in any::<u32>(),
+            timestamp_ns in any::<u64>(),
+            payload in prop::collection::vec(any::<u8>(), 0..256),
+        ) {
+            let header = MrPacketHeader {
+                media_type: MR_MEDIA_AUDIO,
+                flags,
+                kind,
+                sequence,
+                timestamp_ns,
+                extension: &[],
+            };
+            let mut packet = header.encode();
+            packet.extend_from_slice(&payload);
+
+            let frame = parse_audio_packet(&packet).expect("valid audio packet");
+            prop_assert_eq!(frame.header, header);
+            prop_assert_eq!(frame.payload, payload.as_slice());
+        }
+
+        #[test]
+        fn convert_length_prefixed_round_trip(
+            nals in prop::collection::vec(h264_nal(), 1..6),
+            len_bytes in 2usize..=4,
+        ) {
+            let converted = convert_length_prefixed(&to_length_prefixed(&nals, len_bytes), len_bytes);
+            prop_assert_eq!(converted, Some(to_annexb(&nals)));
+        }
+
+        #[test]
+        fn ensure_annexb_converts_avcc(nals in prop::collection::vec(h264_nal(), 1..6)) {
+            let annexb = to_annexb(&nals);
+            prop_assert_eq!(ensure_annexb(&to_length_prefixed(&nals, 4)), annexb.clone());
+            prop_assert_eq!(ensure_annexb(&annexb), annexb);
+        }
+
+        #[test]
+        fn parsers_never_panic(data in prop::collection::vec(any::<u8>(), 0..512)) {
+            let _ = parse_video_packet(&data);
+            let _ = parse_audio_packet(&data);
+            let _ = ensure_annexb(&data);
+            for len_bytes in 0..=5 {
+                let _ = convert_length_prefixed(&data, len_bytes);
+            }
+        }
+    }
 }
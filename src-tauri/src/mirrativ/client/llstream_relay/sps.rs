@@ -0,0 +1,940 @@
+use serde::Serialize;
+
+/// Highest luma/chroma bit depth allowed by either spec (H.264 High 4:4:4, HEVC RExt).
+const MAX_BIT_DEPTH: u32 = 14;
+/// log2_max_frame_num / log2_max_pic_order_cnt_lsb are at most 16 (H.264 7.4.2.1.1).
+const MAX_LOG2_COUNTER: u32 = 16;
+
+// ---------------------------------------------------------------------------
+// Stream metadata reported to the frontend
+// ---------------------------------------------------------------------------
+
+/// Video stream metadata decoded from the active SPS/PPS.
+#[derive(Serialize, Clone, PartialEq)]
+pub struct VideoStreamInfo {
+    pub codec: String,
+    pub profile: String,
+    pub profile_idc: u8,
+    pub level: String,
+    /// Display size after cropping.
+    pub width: u32,
+    pub height: u32,
+    /// Decoded size before cropping.
+    pub coded_width: u32,
+    pub coded_height: u32,
+    pub crop: CropRect,
+    pub chroma_format_idc: u32,
+    pub bit_depth: u32,
+    pub interlaced: bool,
+    pub max_ref_frames: Option<u32>,
+    /// From VUI timing info; None when the encoder does not signal it.
+    pub frame_rate: Option<f64>,
+    pub fixed_frame_rate: Option<bool>,
+    pub sample_aspect_ratio: Option<[u16; 2]>,
+    pub color: Option<ColorInfo>,
+    /// "CABAC" or "CAVLC", from the PPS.
+    pub entropy_coding: Option<String>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Default)]
+pub struct CropRect {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+/// Colour description codes as defined in ISO/IEC 23091-2 (same values as in H.273).
+#[derive(Serialize, Clone, Copy, PartialEq)]
+pub struct ColorInfo {
+    pub full_range: bool,
+    pub primaries: Option<u8>,
+    pub transfer: Option<u8>,
+    pub matrix: Option<u8>,
+}
+
+impl VideoStreamInfo {
+    /// Builds the metadata from an SPS NAL (and optionally the matching PPS NAL),
+    /// both given without start code.
+    pub(crate) fn from_h264(sps_nal: &[u8], pps_nal: Option<&[u8]>) -> Option<Self> {
+        let sps = H264Sps::parse(sps_nal)?;
+        let pps = pps_nal.and_then(H264Pps::parse);
+
+        let (width, height, coded_width, coded_height) = sps.dimensions()?;
+        Some(Self {
+            codec: "h264".to_string(),
+            profile: h264_profile_name(sps.profile_idc, sps.constraint_flags).to_string(),
+            profile_idc: sps.profile_idc,
+            level: h264_level_name(sps.profile_idc, sps.level_idc, sps.constraint_flags),
+            width,
+            height,
+            coded_width,
+            coded_height,
+            crop: sps.crop,
+            chroma_format_idc: sps.chroma_format_idc,
+            bit_depth: sps.bit_depth_luma,
+            interlaced: !sps.frame_mbs_only,
+            max_ref_frames: Some(sps.max_num_ref_frames),
+            frame_rate: sps.vui.as_ref().and_then(|v| v.frame_rate()),
+            fixed_frame_rate: sps
+                .vui
+                .as_ref()
+                .and_then(|v| v.timing.map(|t| t.fixed_frame_rate)),
+            sample_aspect_ratio: sps.vui.as_ref().and_then(|v| v.sample_aspect_ratio),
+            color: sps.vui.as_ref().and_then(|v| v.color),
+            entropy_coding: pps.map(|p| {
+                if p.entropy_coding_mode {
+                    "CABAC".to_string()
+                } else {
+                    "CAVLC".to_string()
+                }
+            }),
+        })
+    }
+
+    /// Builds the metadata from an HEVC SPS NAL (and optionally the PPS NAL), both
+    /// given without start code. VUI is not parsed for HEVC (it sits behind the
+    /// reference picture sets), so frame rate and colour info stay unset.
+    pub(crate) fn from_hevc(sps_nal: &[u8], pps_nal: Option<&[u8]>) -> Option<Self> {
+        let sps = HevcSps::parse(sps_nal)?;
+        // HEVC always uses CABAC; the PPS is only checked for presence.
+        let entropy_coding = pps_nal
+            .filter(|nal| nal.len() > 2)
+            .map(|_| "CABAC".to_string());
+        let (width, height) = sps.display_size()?;
+
+        Some(Self {
+            codec: "hevc".to_string(),
+            profile: hevc_profile_name(sps.profile_idc).to_string(),
+            profile_idc: sps.profile_idc,
+            level: hevc_level_name(sps.tier_high, sps.level_idc),
+            width,
+            height,
+            coded_width: sps.pic_width,
+            coded_height: sps.pic_height,
+            crop: sps.conformance_window,
+            chroma_format_idc: sps.chroma_format_idc,
+            bit_depth: sps.bit_depth_luma,
+            interlaced: false,
+            max_ref_frames: None,
+            frame_rate: None,
+            fixed_frame_rate: None,
+            sample_aspect_ratio: None,
+            color: None,
+            entropy_coding,
+        })
+    }
+
+    pub(crate) fn is_portrait(&self) -> bool {
+        self.height > self.width
+    }
+}
+
+// ---------------------------------------------------------------------------
+// H.264 SPS / PPS (ITU-T H.264 7.3.2.1 / 7.3.2.2, E.1.1)
+// ---------------------------------------------------------------------------
+
+struct H264Sps {
+    profile_idc: u8,
+    constraint_flags: u8,
+    level_idc: u8,
+    chroma_format_idc: u32,
+    separate_colour_plane: bool,
+    bit_depth_luma: u32,
+    max_num_ref_frames: u32,
+    pic_width_in_mbs: u32,
+    pic_height_in_map_units: u32,
+    frame_mbs_only: bool,
+    crop: CropRect,
+    vui: Option<Vui>,
+}
+
+impl H264Sps {
+    fn parse(nal: &[u8]) -> Option<Self> {
+        if nal.first()? & 0x1F != 7 {
+            return None;
+        }
+        let rbsp = nal_to_rbsp(&nal[1..]);
+        let mut r = BitReader::new(&rbsp);
+
+        let profile_idc = r.u(8)? as u8;
+        let constraint_flags = r.u(8)? as u8;
+        let level_idc = r.u(8)? as u8;
+        let _sps_id = r.ue()?;
+
+        let mut chroma_format_idc = 1;
+        let mut separate_colour_plane = false;
+        let mut bit_depth_luma = 8;
+        if matches!(
+            profile_idc,
+            100 | 110 | 122 | 244 | 44 | 83 | 86 | 118 | 128 | 138 | 139 | 134 | 135
+        ) {
+            chroma_format_idc = chroma_format(&mut r)?;
+            if chroma_format_idc == 3 {
+                separate_colour_plane = r.flag()?;
+            }
+            bit_depth_luma = bit_depth(&mut r)?;
+            let _bit_depth_chroma = bit_depth(&mut r)?;
+            let _qpprime_y_zero_transform_bypass = r.flag()?;
+            if r.flag()? {
+                let lists = if chroma_format_idc != 3 { 8 } else { 12 };
+                for i in 0..lists {
+                    if r.flag()? {
+                        skip_scaling_list(&mut r, if i < 6 { 16 } else { 64 })?;
+                    }
+                }
+            }
+        }
+
+        let _log2_max_frame_num = log2_counter(&mut r)?;
+        match r.ue()? {
+            0 => {
+                let _log2_max_poc_lsb = log2_counter(&mut r)?;
+            }
+            1 => {
+                let _delta_pic_order_always_zero = r.flag()?;
+                let _offset_for_non_ref_pic = r.se()?;
+                let _offset_for_top_to_bottom_field = r.se()?;
+                let cycle = r.ue()?;
+                if cycle > 255 {
+                    return None;
+                }
+                for _ in 0..cycle {
+                    r.se()?;
+                }
+            }
+            _ => {}
+        }
+
+        let max_num_ref_frames = r.ue()?;
+        let _gaps_in_frame_num_allowed = r.flag()?;
+        let pic_width_in_mbs = r.ue()?.checked_add(1)?;
+        let pic_height_in_map_units = r.ue()?.checked_add(1)?;
+        let frame_mbs_only = r.flag()?;
+        if !frame_mbs_only {
+            let _mb_adaptive_frame_field = r.flag()?;
+        }
+        let _direct_8x8_inference = r.flag()?;
+
+        let mut crop = CropRect::default();
+        if r.flag()? {
+            crop = CropRect {
+                left: r.ue()?,
+                right: r.ue()?,
+                top: r.ue()?,
+                bottom: r.ue()?,
+            };
+        }
+
+        // A truncated VUI should not throw away the (valid) core SPS fields.
+        let vui = match r.flag() {
+            Some(true) => Vui::parse(&mut r),
+            _ => None,
+        };
+
+        let sps = Self {
+            profile_idc,
+            constraint_flags,
+            level_idc,
+            chroma_format_idc,
+            separate_colour_plane,
+            bit_depth_luma,
+            max_num_ref_frames,
+            pic_width_in_mbs,
+            pic_height_in_map_units,
+            frame_mbs_only,
+            crop,
+            vui,
+        };
+        // Rejects sizes that overflow and crops that leave no picture.
+        sps.dimensions()?;
+        Some(sps)
+    }
+
+    /// (width, height, coded_width, coded_height), see H.264 7.4.2.1.1.
+    /// None when the size overflows or the cropping is not smaller than the picture.
+    fn dimensions(&self) -> Option<(u32, u32, u32, u32)> {
+        let chroma_array_type = if self.separate_colour_plane {
+            0
+        } else {
+            self.chroma_format_idc
+        };
+        let (sub_width_c, sub_height_c) = match chroma_array_type {
+            1 => (2, 2),
+            2 => (2, 1),
+            _ => (1, 1),
+        };
+        let field_factor = if self.frame_mbs_only { 1 } else { 2 };
+        let (crop_unit_x, crop_unit_y) = if chroma_array_type == 0 {
+            (1, field_factor)
+        } else {
+            (sub_width_c, sub_height_c * field_factor)
+        };
+
+        let coded_width = self.pic_width_in_mbs.checked_mul(16)?;
+        let coded_height = self
+            .pic_height_in_map_units
+            .checked_mul(16)?
+            .checked_mul(field_factor)?;
+        let width = cropped(coded_width, crop_unit_x, self.crop.left, self.crop.right)?;
+        let height = cropped(coded_height, crop_unit_y, self.crop.top, self.crop.bottom)?;
+        Some((width, height, coded_width, coded_height))
+    }
+}
+
+struct H264Pps {
+    entropy_coding_mode: bool,
+}
+
+impl H264Pps {
+    fn parse(nal: &[u8]) -> Option<Self> {
+        if nal.first()? & 0x1F != 8 {
+            return None;
+        }
+        let rbsp = nal_to_rbsp(&nal[1..]);
+        let mut r = BitReader::new(&rbsp);
+        let _pps_id = r.ue()?;
+        let _sps_id = r.ue()?;
+        Some(Self {
+            entropy_coding_mode: r.flag()?,
+        })
+    }
+}
+
+struct Vui {
+    sample_aspect_ratio: Option<[u16; 2]>,
+    color: Option<ColorInfo>,
+    timing: Option<VuiTiming>,
+}
+
+#[derive(Clone, Copy)]
+struct VuiTiming {
+    num_units_in_tick: u32,
+    time_scale: u32,
+    fixed_frame_rate: bool,
+}
+
+impl Vui {
+    /// Parses VUI up to timing info; HRD and bitstream restrictions are not needed.
+    fn parse(r: &mut BitReader) -> Option<Self> {
+        let mut sample_aspect_ratio = None;
+        if r.flag()? {
+            let idc = r.u(8)? as u8;
+            sample_aspect_ratio = if idc == 255 {
+                Some([r.u(16)? as u16, r.u(16)? as u16])
+            } else {
+                sar_from_idc(idc)
+            };
+        }
+
+        if r.flag()? {
+            let _overscan_appropriate = r.flag()?;
+        }
+
+        let mut color = None;
+        if r.flag()? {
+            let _video_format = r.u(3)?;
+            let full_range = r.flag()?;
+            let mut info = ColorInfo {
+                full_range,
+                primaries: None,
+                transfer: None,
+                matrix: None,
+            };
+            if r.flag()? {
+                info.primaries = Some(r.u(8)? as u8);
+                info.transfer = Some(r.u(8)? as u8);
+                info.matrix = Some(r.u(8)? as u8);
+            }
+            color = Some(info);
+        }
+
+        if r.flag()? {
+            let _chroma_sample_loc_top = r.ue()?;
+            let _chroma_sample_loc_bottom = r.ue()?;
+        }
+
+        let mut timing = None;
+        if r.flag()? {
+            timing = Some(VuiTiming {
+                num_units_in_tick: r.u(32)?,
+                time_scale: r.u(32)?,
+                fixed_frame_rate: r.flag()?,
+            });
+        }
+
+        Some(Self {
+            sample_aspect_ratio,
+            color,
+            timing,
+        })
+    }
+
+    fn frame_rate(&self) -> Option<f64> {
+        let timing = self.timing?;
+        if timing.num_units_in_tick == 0 || timing.time_scale == 0 {
+            return None;
+        }
+        // One frame = two field ticks (H.264 E.2.1).
+        let fps = timing.time_scale as f64 / (2.0 * timing.num_units_in_tick as f64);
+        Some((fps * 1000.0).round() / 1000.0)
+    }
+}
+
+fn skip_scaling_list(r: &mut BitReader, size: usize) -> Option<()> {
+    let mut last_scale: i64 = 8;
+    let mut next_scale: i64 = 8;
+    for _ in 0..size {
+        if next_scale != 0 {
+            let delta = r.se()?;
+            next_scale = (last_scale + delta + 256).rem_euclid(256);
+        }
+        if next_scale != 0 {
+            last_scale = next_scale;
+        }
+    }
+    Some(())
+}
+
+fn sar_from_idc(idc: u8) -> Option<[u16; 2]> {
+    const TABLE: [[u16; 2]; 16] = [
+        [1, 1],
+        [12, 11],
+        [10, 11],
+        [16, 11],
+        [40, 33],
+        [24, 11],
+        [20, 11],
+        [32, 11],
+        [80, 33],
+        [18, 11],
+        [15, 11],
+        [64, 33],
+        [160, 99],
+        [4, 3],
+        [3, 2],
+        [2, 1],
+    ];
+    TABLE.get((idc as usize).checked_sub(1)?).copied()
+}
+
+fn h264_profile_name(profile_idc: u8, constraint_flags: u8) -> &'static str {
+    let constraint_set1 = constraint_flags & 0x40 != 0;
+    match profile_idc {
+        66 if constraint_set1 => "Constrained Baseline",
+        66 => "Baseline",
+        77 => "Main",
+        88 => "Extended",
+        100 => "High",
+        110 => "High 10",
+        122 => "High 4:2:2",
+        244 => "High 4:4:4 Predictive",
+        44 => "CAVLC 4:4:4 Intra",
+        _ => "Unknown",
+    }
+}
+
+fn h264_level_name(profile_idc: u8, level_idc: u8, constraint_flags: u8) -> String {
+    // Level 1b is signalled as 11 + constraint_set3 in Baseline/Main/Extended, or as 9.
+    let constraint_set3 = constraint_flags & 0x10 != 0;
+    if level_idc == 9 || (level_idc == 11 && constraint_set3 && matches!(profile_idc, 66 | 77 | 88))
+    {
+        return "1b".to_string();
+    }
+    format!("{}.{}", level_idc / 10, level_idc % 10)
+}
+
+// ---------------------------------------------------------------------------
+// HEVC SPS (ITU-T H.265 7.3.2.2, 7.3.3) — up to the bit depth fields
+// ---------------------------------------------------------------------------
+
+struct HevcSps {
+    profile_idc: u8,
+    tier_high: bool,
+    level_idc: u8,
+    chroma_format_idc: u32,
+    separate_colour_plane: bool,
+    pic_width: u32,
+    pic_height: u32,
+    conformance_window: CropRect,
+    bit_depth_luma: u32,
+}
+
+impl HevcSps {
+    fn parse(nal: &[u8]) -> Option<Self> {
+        if nal.len() < 3 || (nal[0] >> 1) & 0x3F != 33 {
+            return None;
+        }
+        let rbsp = nal_to_rbsp(&nal[2..]);
+        let mut r = BitReader::new(&rbsp);
+
+        let _vps_id = r.u(4)?;
+        let max_sub_layers_minus1 = r.u(3)?;
+        let _temporal_id_nesting = r.flag()?;
+
+        // profile_tier_level(1, max_sub_layers_minus1)
+        let _profile_space = r.u(2)?;
+        let tier_high = r.flag()?;
+        let profile_idc = r.u(5)? as u8;
+        r.skip(32 + 4 + 43 + 1)?; // compatibility flags, source flags, reserved
+        let level_idc = r.u(8)? as u8;
+
+        let mut sub_layer_profile_present = [false; 8];
+        let mut sub_layer_level_present = [false; 8];
+        for i in 0..max_sub_layers_minus1 as usize {
+            sub_layer_profile_present[i] = r.flag()?;
+            sub_layer_level_present[i] = r.flag()?;
+        }
+        if max_sub_layers_minus1 > 0 {
+            r.skip(2 * (8 - max_sub_layers_minus1))?;
+        }
+        for i in 0..max_sub_layers_minus1 as usize {
+            if sub_layer_profile_present[i] {
+                r.skip(88)?;
+            }
+            if sub_layer_level_present[i] {
+                r.skip(8)?;
+            }
+        }
+
+        let _sps_id = r.ue()?;
+        let chroma_format_idc = chroma_format(&mut r)?;
+        let separate_colour_plane = chroma_format_idc == 3 && r.flag()?;
+        let pic_width = r.ue()?;
+        let pic_height = r.ue()?;
+
+        let mut conformance_window = CropRect::default();
+        if r.flag()? {
+            conformance_window = CropRect {
+                left: r.ue()?,
+                right: r.ue()?,
+                top: r.ue()?,
+                bottom: r.ue()?,
+            };
+        }
+        let bit_depth_luma = bit_depth(&mut r)?;
+
+        let sps = Self {
+            profile_idc,
+            tier_high,
+            level_idc,
+            chroma_format_idc,
+            separate_colour_plane,
+            pic_width,
+            pic_height,
+            conformance_window,
+            bit_depth_luma,
+        };
+        sps.display_size()?;
+        Some(sps)
+    }
+
+    /// (width, height) after the conformance window (H.265 7.4.3.2.1).
+    /// None when the window is not smaller than the picture.
+    fn display_size(&self) -> Option<(u32, u32)> {
+        let (sub_width_c, sub_height_c) = match self.chroma_format_idc {
+            1 if !self.separate_colour_plane => (2, 2),
+            2 if !self.separate_colour_plane => (2, 1),
+            _ => (1, 1),
+        };
+        let crop = self.conformance_window;
+        Some((
+            cropped(self.pic_width, sub_width_c, crop.left, crop.right)?,
+            cropped(self.pic_height, sub_height_c, crop.top, crop.bottom)?,
+        ))
+    }
+}
+
+fn hevc_profile_name(profile_idc: u8) -> &'static str {
+    match profile_idc {
+        1 => "Main",
+        2 => "Main 10",
+        3 => "Main Still Picture",
+        4 => "Range Extensions",
+        _ => "Unknown",
+    }
+}
+
+fn hevc_level_name(tier_high: bool, level_idc: u8) -> String {
+    // general_level_idc is 30 × the level number (e.g. 93 → 3.1).
+    let major = level_idc / 30;
+    let minor = (level_idc % 30) / 3;
+    let tier = if tier_high { "High" } else { "Main" };
+    if minor == 0 {
+        format!("{}@{}", major, tier)
+    } else {
+        format!("{}.{}@{}", major, minor, tier)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Bitstream helpers
+// ---------------------------------------------------------------------------
+
+/// chroma_format_idc, which is 0..=3 in both specs.
+fn chroma_format(r: &mut BitReader) -> Option<u32> {
+    r.ue().filter(|idc| *idc <= 3)
+}
+
+/// bit_depth_*_minus8 + 8, rejecting depths no profile allows.
+fn bit_depth(r: &mut BitReader) -> Option<u32> {
+    r.ue()?
+        .checked_add(8)
+        .filter(|depth| *depth <= MAX_BIT_DEPTH)
+}
+
+/// log2_max_*_minus4 + 4.
+fn log2_counter(r: &mut BitReader) -> Option<u32> {
+    r.ue()?
+        .checked_add(4)
+        .filter(|log2| *log2 <= MAX_LOG2_COUNTER)
+}
+
+/// `size` minus `unit * (start + end)` cropped samples. None on overflow or when
+/// nothing of the picture would remain.
+fn cropped(size: u32, unit: u32, start: u32, end: u32) -> Option<u32> {
+    let crop = start.checked_add(end)?.checked_mul(unit)?;
+    size.checked_sub(crop).filter(|remaining| *remaining > 0)
+}
+
+/// Strips emulation prevention bytes (00 00 03 → 00 00).
+fn nal_to_rbsp(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut zeros = 0usize;
+    for &b in data {
+        if zeros >= 2 && b == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if b == 0 { zeros + 1 } else { 0 };
+        out.push(b);
+    }
+    out
+}
+
+/// MSB-first bit reader with Exp-Golomb support (H.264 9.1).
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bit(&mut self) -> Option<u32> {
+        let byte = *self.data.get(self.pos / 8)?;
+        let bit = (byte >> (7 - (self.pos % 8))) & 1;
+        self.pos += 1;
+        Some(bit as u32)
+    }
+
+    fn flag(&mut self) -> Option<bool> {
+        self.bit().map(|b| b == 1)
+    }
+
+    fn u(&mut self, bits: u32) -> Option<u32> {
+        debug_assert!(bits <= 32);
+        let mut value = 0u64;
+        for _ in 0..bits {
+            value = (value << 1) | self.bit()? as u64;
+        }
+        Some(value as u32)
+    }
+
+    fn skip(&mut self, bits: u32) -> Option<()> {
+        let end = self.pos + bits as usize;
+        if end > self.data.len() * 8 {
+            return None;
+        }
+        self.pos = end;
+        Some(())
+    }
+
+    fn ue(&mut self) -> Option<u32> {
+        let mut leading_zeros = 0u32;
+        while self.bit()? == 0 {
+            leading_zeros += 1;
+            if leading_zeros > 31 {
+                return None;
+            }
+        }
+        let suffix = self.u(leading_zeros)? as u64;
+        u32::try_from((1u64 << leading_zeros) - 1 + suffix).ok()
+    }
+
+    fn se(&mut self) -> Option<i64> {
+        let k = self.ue()? as i64;
+        Some(if k % 2 == 1 { (k + 1) / 2 } else { -(k / 2) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// SPS from a WebRTC offer: Constrained Baseline 3.1, 640x480, VUI without timing.
+    const WEBRTC_640X480: &[u8] = &[
+        0x67, 0x42, 0xc0, 0x1f, 0x1a, 0x32, 0x35, 0x01, 0x40, 0x7a, 0x40, 0x3c, 0x22, 0x11, 0xa8,
+    ];
+
+    /// x264 SPS: High 4.0, 1920x1088 cropped to 1080, SAR 1:1, 30 fps.
+    const X264_1080P: &[u8] = &[
+        0x67, 0x64, 0x00, 0x28, 0xac, 0xd9, 0x40, 0x78, 0x02, 0x27, 0xe5, 0xc0, 0x44, 0x00, 0x00,
+        0x03, 0x00, 0x04, 0x00, 0x00, 0x03, 0x00, 0xf0, 0x3c, 0x60, 0xc6, 0x58,
+    ];
+
+    /// x265 SPS: Main 3.1, 1280x720 without conformance window.
+    const X265_720P: &[u8] = &[
+        0x42, 0x01, 0x01, 0x01, 0x60, 0x00, 0x00, 0x03, 0x00, 0x90, 0x00, 0x00, 0x03, 0x00, 0x00,
+        0x03, 0x00, 0x5d, 0xa0, 0x02, 0x80, 0x80, 0x2d, 0x16, 0x59, 0x59, 0xa4, 0x93, 0x2b, 0xc0,
+        0x5a, 0x70, 0x80, 0x00, 0x01, 0xf4, 0x80, 0x00, 0x3a, 0x98, 0x04,
+    ];
+
+    /// MSB-first writer producing an escaped NAL, for building malformed SPS.
+    #[derive(Default)]
+    struct BitWriter {
+        bits: Vec<bool>,
+    }
+
+    impl BitWriter {
+        fn u(&mut self, bits: u32, value: u64) -> &mut Self {
+            for i in (0..bits).rev() {
+                self.bits.push((value >> i) & 1 == 1);
+            }
+            self
+        }
+
+        fn ue(&mut self, value: u32) -> &mut Self {
+            let code = value as u64 + 1;
+            let len = 64 - code.leading_zeros();
+            self.u(len - 1, 0).u(len, code)
+        }
+
+        /// Appends the stop bit and inserts emulation prevention bytes.
+        fn into_nal(mut self, header: &[u8]) -> Vec<u8> {
+            self.bits.push(true);
+            while !self.bits.len().is_multiple_of(8) {
+                self.bits.push(false);
+            }
+            let mut nal = header.to_vec();
+            let mut zeros = 0;
+            for byte in self.bits.chunks(8) {
+                let byte = byte.iter().fold(0u8, |acc, b| (acc << 1) | *b as u8);
+                if zeros >= 2 && byte <= 3 {
+                    nal.push(0x03);
+                    zeros = 0;
+                }
+                zeros = if byte == 0 { zeros + 1 } else { 0 };
+                nal.push(byte);
+            }
+            nal
+        }
+    }
+
+    /// Field values for a progressive 4:2:0 High profile SPS without VUI.
+    struct H264Fields {
+        bit_depth_minus8: u32,
+        log2_max_frame_num_minus4: u32,
+        width_in_mbs_minus1: u32,
+        height_in_mbs_minus1: u32,
+        crop: Option<[u32; 4]>,
+    }
+
+    impl Default for H264Fields {
+        fn default() -> Self {
+            Self {
+                bit_depth_minus8: 0,
+                log2_max_frame_num_minus4: 0,
+                width_in_mbs_minus1: 79,
+                height_in_mbs_minus1: 44,
+                crop: None,
+            }
+        }
+    }
+
+    fn high_sps(fields: H264Fields) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.u(8, 100).u(8, 0).u(8, 40).ue(0);
+        w.ue(1)
+            .ue(fields.bit_depth_minus8)
+            .ue(fields.bit_depth_minus8);
+        w.u(1, 0).u(1, 0); // transform bypass, scaling matrix
+        w.ue(fields.log2_max_frame_num_minus4).ue(0).ue(0); // poc type 0, log2 poc lsb 4
+        w.ue(1).u(1, 0); // ref frames, gaps
+        w.ue(fields.width_in_mbs_minus1)
+            .ue(fields.height_in_mbs_minus1);
+        w.u(1, 1).u(1, 1); // frame_mbs_only, direct_8x8_inference
+        match fields.crop {
+            Some(crop) => {
+                w.u(1, 1);
+                for offset in crop {
+                    w.ue(offset);
+                }
+            }
+            None => {
+                w.u(1, 0);
+            }
+        }
+        w.u(1, 0); // no VUI
+        w.into_nal(&[0x67])
+    }
+
+    #[test]
+    fn parses_webrtc_constrained_baseline() {
+        let info = VideoStreamInfo::from_h264(WEBRTC_640X480, None).expect("valid SPS");
+        assert_eq!(info.profile, "Constrained Baseline");
+        assert_eq!(info.level, "3.1");
+        assert_eq!((info.width, info.height), (640, 480));
+        assert_eq!((info.coded_width, info.coded_height), (640, 480));
+        assert_eq!(info.max_ref_frames, Some(1));
+        assert!(!info.interlaced);
+        assert_eq!(info.frame_rate, None);
+    }
+
+    #[test]
+    fn parses_x264_high_with_crop_and_timing() {
+        let info = VideoStreamInfo::from_h264(X264_1080P, None).expect("valid SPS");
+        assert_eq!(info.profile, "High");
+        assert_eq!(info.level, "4.0");
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!((info.coded_width, info.coded_height), (1920, 1088));
+        assert_eq!(info.crop.bottom, 4);
+        assert_eq!(info.bit_depth, 8);
+        assert_eq!(info.max_ref_frames, Some(4));
+        assert_eq!(info.sample_aspect_ratio, Some([1, 1]));
+        assert_eq!(info.frame_rate, Some(30.0));
+        assert_eq!(info.fixed_frame_rate, Some(false));
+    }
+
+    #[test]
+    fn generated_sps_matches_the_parser() {
+        let info =
+            VideoStreamInfo::from_h264(&high_sps(H264Fields::default()), None).expect("valid SPS");
+        assert_eq!((info.width, info.height), (1280, 720));
+
+        let cropped = high_sps(H264Fields {
+            height_in_mbs_minus1: 67,
+            crop: Some([0, 0, 0, 4]),
+            ..Default::default()
+        });
+        let info = VideoStreamInfo::from_h264(&cropped, None).expect("valid SPS");
+        assert_eq!((info.width, info.height), (1280, 1080));
+    }
+
+    #[test]
+    fn rejects_out_of_range_h264_fields() {
+        let rejected = [
+            H264Fields {
+                bit_depth_minus8: 7,
+                ..Default::default()
+            },
+            H264Fields {
+                bit_depth_minus8: u32::MAX - 1,
+                ..Default::default()
+            },
+            H264Fields {
+                log2_max_frame_num_minus4: 13,
+                ..Default::default()
+            },
+            H264Fields {
+                log2_max_frame_num_minus4: u32::MAX - 1,
+                ..Default::default()
+            },
+            H264Fields {
+                width_in_mbs_minus1: u32::MAX - 1,
+                ..Default::default()
+            },
+            H264Fields {
+                height_in_mbs_minus1: u32::MAX / 16,
+                ..Default::default()
+            },
+            // 720 rows, 2 × (180 + 180) cropped away.
+            H264Fields {
+                crop: Some([0, 0, 180, 180]),
+                ..Default::default()
+            },
+            H264Fields {
+                crop: Some([u32::MAX - 1, u32::MAX - 1, 0, 0]),
+                ..Default::default()
+            },
+            H264Fields {
+                crop: Some([0, 0, u32::MAX / 2, 0]),
+                ..Default::default()
+            },
+        ];
+        for fields in rejected {
+            assert!(VideoStreamInfo::from_h264(&high_sps(fields), None).is_none());
+        }
+
+        let max_depth = high_sps(H264Fields {
+            bit_depth_minus8: 6,
+            log2_max_frame_num_minus4: 12,
+            ..Default::default()
+        });
+        let info = VideoStreamInfo::from_h264(&max_depth, None).expect("valid SPS");
+        assert_eq!(info.bit_depth, 14);
+    }
+
+    /// Main profile 4:2:0 HEVC SPS with a single sub-layer, up to the bit depth.
+    fn hevc_sps(width: u32, height: u32, crop: [u32; 4], bit_depth_minus8: u32) -> Vec<u8> {
+        let mut w = BitWriter::default();
+        w.u(4, 0).u(3, 0).u(1, 1); // vps id, max_sub_layers_minus1, temporal nesting
+        w.u(2, 0).u(1, 0).u(5, 1); // profile space, tier, profile_idc
+        w.u(32, 0x6000_0000).u(48, 0x9000_0000_0000); // compat + source flags, reserved
+        w.u(8, 93);
+        w.ue(0).ue(1).ue(width).ue(height);
+        w.u(1, 1);
+        for offset in crop {
+            w.ue(offset);
+        }
+        w.ue(bit_depth_minus8).ue(bit_depth_minus8);
+        w.into_nal(&[0x42, 0x01])
+    }
+
+    #[test]
+    fn parses_x265_main() {
+        let info = VideoStreamInfo::from_hevc(X265_720P, Some(&[0x44, 0x01, 0xc1])).unwrap();
+        assert_eq!(info.codec, "hevc");
+        assert_eq!(info.profile, "Main");
+        assert_eq!(info.level, "3.1@Main");
+        assert_eq!((info.width, info.height), (1280, 720));
+        assert_eq!(info.chroma_format_idc, 1);
+        assert_eq!(info.bit_depth, 8);
+        assert_eq!(info.entropy_coding.as_deref(), Some("CABAC"));
+    }
+
+    #[test]
+    fn rejects_out_of_range_hevc_fields() {
+        let info = VideoStreamInfo::from_hevc(&hevc_sps(1920, 1088, [0, 0, 0, 4], 2), None)
+            .expect("valid SPS");
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!(info.bit_depth, 10);
+
+        let rejected = [
+            hevc_sps(1280, 720, [0; 4], 7),
+            hevc_sps(1280, 720, [0; 4], u32::MAX - 1),
+            hevc_sps(0, 720, [0; 4], 0),
+            hevc_sps(1280, 720, [320, 320, 0, 0], 0),
+            hevc_sps(1280, 720, [u32::MAX - 1, 1, 0, 0], 0),
+            hevc_sps(1280, 720, [0, 0, u32::MAX / 2, 0], 0),
+        ];
+        for sps in rejected {
+            assert!(VideoStreamInfo::from_hevc(&sps, None).is_none());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn sps_parsers_never_panic(body in prop::collection::vec(any::<u8>(), 0..64)) {
+            let mut h264 = vec![0x67];
+            h264.extend_from_slice(&body);
+            let _ = VideoStreamInfo::from_h264(&h264, Some(&body));
+            let mut hevc = vec![0x42, 0x01];
+            hevc.extend_from_slice(&body);
+            let _ = VideoStreamInfo::from_hevc(&hevc, None);
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use super::clip::{ClipBuffer, ClipCapture};
+use super::latency::LatencyProbe;
+use super::parser::VideoCodec;
+use super::sps::VideoStreamInfo;
+
+// ---------------------------------------------------------------------------
+// RelayShared — state shared by the WS loops, muxer and HTTP layer of a session
+// ---------------------------------------------------------------------------
+
+pub(crate) struct RelayShared {
+    pub(crate) mode: &'static str,
+    pub(crate) stats: RelayStats,
+    pub(crate) latency: LatencyProbe,
+    /// Rolling TS window for clip capture (enabled by the TS relays only).
+    pub(crate) clip: ClipBuffer,
+    keyframe: Mutex<Option<KeyframeSnapshot>>,
+    video_info: Mutex<Option<VideoStreamInfo>>,
+    /// PAT/PMT for newly joining TS clients once the video codec is known.
+    ts_bootstrap: Mutex<Option<Vec<u8>>>,
+}
+
+impl RelayShared {
+    pub(crate) fn new(mode: &'static str) -> Self {
+        Self {
+            mode,
+            stats: RelayStats::new(),
+            latency: LatencyProbe::default(),
+            clip: ClipBuffer::default(),
+            keyframe: Mutex::new(None),
+            video_info: Mutex::new(None),
+            ts_bootstrap: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn store_keyframe(&self, access_unit: &[u8], timestamp_ns: u64, codec: VideoCodec) {
+        if let Ok(mut slot) = self.keyframe.lock() {
+            *slot = Some(KeyframeSnapshot {
+                access_unit: access_unit.to_vec(),
+                codec,
+                timestamp_ns,
+                captured_at_ms: unix_millis(),
+            });
+        }
+    }
+
+    pub(crate) fn latest_keyframe(&self) -> Option<KeyframeSnapshot> {
+        self.keyframe.lock().ok().and_then(|slot| slot.clone())
+    }
+
+    pub(crate) fn set_ts_bootstrap(&self, tables: Vec<u8>) {
+        if let Ok(mut slot) = self.ts_bootstrap.lock() {
+            *slot = Some(tables);
+        }
+    }
+
+    pub(crate) fn ts_bootstrap(&self) -> Option<Vec<u8>> {
+        self.ts_bootstrap.lock().ok().and_then(|slot| slot.clone())
+    }
+
+    pub(crate) fn capture_clip(&self, secs: u32) -> Option<ClipCapture> {
+        self.clip.capture(secs, self.ts_bootstrap())
+    }
+
+    /// Stores the metadata of the current parameter sets and returns the previous one.
+    /// Returns None (and stores nothing) when the metadata is unchanged.
+    pub(crate) fn update_video_info(
+        &self,
+        info: &VideoStreamInfo,
+    ) -> Option<Option<VideoStreamInfo>> {
+        let mut slot = self.video_info.lock().ok()?;
+        if slot.as_ref() == Some(info) {
+            return None;
+        }
+        Some(slot.replace(info.clone()))
+    }
+
+    pub(crate) fn stats_snapshot(&self) -> RelayStatsSnapshot {
+        let has_keyframe = self
+            .keyframe
+            .lock()
+            .map(|slot| slot.is_some())
+            .unwrap_or(false);
+        let video = self.video_info.lock().ok().and_then(|slot| slot.clone());
+        self.stats.snapshot(self.mode, has_keyframe, video)
+    }
+}
+
+/// Latest decodable keyframe access unit (Annex B, parameter sets included).
+#[derive(Clone)]
+pub(crate) struct KeyframeSnapshot {
+    pub(crate) access_unit: Vec<u8>,
+    pub(crate) codec: VideoCodec,
+    pub(crate) timestamp_ns: u64,
+    pub(crate) captured_at_ms: u64,
+}
+
+// ---------------------------------------------------------------------------
+// RelayStats
+// ---------------------------------------------------------------------------
+
+pub(crate) struct RelayStats {
+    started_at: Instant,
+    clients_active: AtomicU64,
+    clients_total: AtomicU64,
+    bytes_sent: AtomicU64,
+    chunks_published: AtomicU64,
+    chunks_lagged: AtomicU64,
+    video_frames: AtomicU64,
+    audio_frames: AtomicU64,
+    last_frame_unix_ms: AtomicU64,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RelayStatsSnapshot {
+    pub mode: String,
+    pub uptime_ms: u64,
+    pub clients_active: u64,
+    pub clients_total: u64,
+    pub bytes_sent: u64,
+    pub chunks_published: u64,
+    pub chunks_lagged: u64,
+    pub video_frames: u64,
+    pub audio_frames: u64,
+    pub last_frame_unix_ms: Option<u64>,
+    pub has_keyframe: bool,
+    pub video: Option<VideoStreamInfo>,
+}
+
+impl RelayStats {
+    fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            clients_active: AtomicU64::new(0),
+            clients_total: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            chunks_published: AtomicU64::new(0),
+            chunks_lagged: AtomicU64::new(0),
+            video_frames: AtomicU64::new(0),
+            audio_frames: AtomicU64::new(0),
+            last_frame_unix_ms: AtomicU64::new(0),
+        }
+    }
+
+    /// Registers a stream client unless `max_clients` are already connected.
+    pub(crate) fn try_client_connected(&self, max_clients: Option<u64>) -> bool {
+        let admitted = self
+            .clients_active
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| match max_clients {
+                Some(max) if n >= max => None,
+                _ => Some(n + 1),
+            })
+            .is_ok();
+        if admitted {
+            self.clients_total.fetch_add(1, Ordering::Relaxed);
+        }
+        admitted
+    }
+
+    pub(crate) fn client_disconnected(&self) {
+        let _ = self
+            .clients_active
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+    }
+
+    pub(crate) fn add_bytes_sent(&self, n: usize) {
+        self.bytes_sent.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn chunk_published(&self) {
+        self.chunks_published.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add_lagged(&self, skipped: u64) {
+        self.chunks_lagged.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    pub(crate) fn video_frame(&self) {
+        self.video_frames.fetch_add(1, Ordering::Relaxed);
+        self.last_frame_unix_ms.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn audio_frame(&self) {
+        self.audio_frames.fetch_add(1, Ordering::Relaxed);
+        self.last_frame_unix_ms.store(unix_millis(), Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(
+        &self,
+        mode: &str,
+        has_keyframe: bool,
+        video: Option<VideoStreamInfo>,
+    ) -> RelayStatsSnapshot {
+        let last_frame = self.last_frame_unix_ms.load(Ordering::Relaxed);
+        RelayStatsSnapshot {
+            mode: mode.to_string(),
+            uptime_ms: self.started_at.elapsed().as_millis() as u64,
+            clients_active: self.clients_active.load(Ordering::Relaxed),
+            clients_total: self.clients_total.load(Ordering::Relaxed),
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            chunks_published: self.chunks_published.load(Ordering::Relaxed),
+            chunks_lagged: self.chunks_lagged.load(Ordering::Relaxed),
+            video_frames: self.video_frames.load(Ordering::Relaxed),
+            audio_frames: self.audio_frames.load(Ordering::Relaxed),
+            last_frame_unix_ms: (last_frame != 0).then_some(last_frame),
+            has_keyframe,
+            video,
+        }
+    }
+}
+
+pub(crate) fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -1,12 +1,18 @@
 use futures_util::StreamExt;
+use serde::Serialize;
 use std::ops::ControlFlow;
-use tauri::AppHandle;
 use tokio::sync::{broadcast, mpsc, watch};
 use tokio_tungstenite::connect_async;
 use tokio_tungstenite::tungstenite::Message;
 
-use super::{relay_log, reconnect_backoff, wait_reconnect_or_shutdown, AvSample, VideoFrameAssembler};
-use crate::mirrativ::client::llstream_relay::mux::{ns_to_90k, MpegTsMuxer};
+use super::stats::RelayShared;
+use super::{
+    relay_log, reconnect_backoff, wait_reconnect_or_shutdown, AssembledFrame, AvSample,
+    RelayEvents, StreamContinuity, VideoFrameAssembler,
+};
+use crate::mirrativ::client::llstream_relay::mux::{
+    build_bootstrap_tables, ns_to_90k, MpegTsMuxer,
+};
 use crate::mirrativ::client::llstream_relay::parser::{
     hex_prefix, nal_types_preview, parse_audio_packet, AacConfig, AUDIO_KIND_AAC, AUDIO_KIND_ASC,
 };
@@ -24,7 +30,7 @@ pub(super) enum WsEvent {
 /// The callback receives `WsEvent::Connected` once per connection and `WsEvent::Binary`
 /// for each binary message. Return `ControlFlow::Break(())` to stop the loop entirely.
 pub(super) async fn ws_reconnect_loop<F>(
-    app: &AppHandle,
+    events: &RelayEvents,
     ws_url: &str,
     label: &str,
     shutdown_rx: &mut watch::Receiver<bool>,
@@ -37,29 +43,29 @@ where
 
     loop {
         if *shutdown_rx.borrow() {
-            relay_log(app, &format!("{} stopping", label));
+            relay_log(events, &format!("{} stopping", label));
             return Ok(());
         }
 
-        relay_log(app, &format!("{} connect: {}", label, ws_url));
+        relay_log(events, &format!("{} connect: {}", label, ws_url));
         let ws = match connect_async(ws_url).await {
             Ok((ws, _resp)) => {
                 reconnect_attempt = 0;
-                relay_log(app, &format!("{} connected", label));
+                relay_log(events, &format!("{} connected", label));
                 ws
             }
             Err(e) => {
                 reconnect_attempt += 1;
                 let delay = reconnect_backoff(reconnect_attempt);
                 relay_log(
-                    app,
+                    events,
                     &format!(
                         "{} connect failed (attempt {}): {}. retry in {}ms",
                         label, reconnect_attempt, e, delay.as_millis()
                     ),
                 );
                 if wait_reconnect_or_shutdown(shutdown_rx, delay).await {
-                    relay_log(app, &format!("{} stopping", label));
+                    relay_log(events, &format!("{} stopping", label));
                     return Ok(());
                 }
                 continue;
@@ -74,7 +80,7 @@ where
             tokio::select! {
                 _ = shutdown_rx.changed() => {
                     if *shutdown_rx.borrow() {
-                        relay_log(app, &format!("{} stopping", label));
+                        relay_log(events, &format!("{} stopping", label));
                         return Ok(());
                     }
                 }
@@ -87,16 +93,16 @@ where
                             }
                         }
                         Some(Ok(Message::Close(_))) => {
-                            relay_log(app, &format!("{} closed, reconnecting", label));
+                            relay_log(events, &format!("{} closed, reconnecting", label));
                             break;
                         }
                         Some(Ok(_)) => {}
                         Some(Err(e)) => {
-                            relay_log(app, &format!("{} read failed: {}", label, e));
+                            relay_log(events, &format!("{} read failed: {}", label, e));
                             break;
                         }
                         None => {
-                            relay_log(app, &format!("{} ended, reconnecting", label));
+                            relay_log(events, &format!("{} ended, reconnecting", label));
                             break;
                         }
                     }
@@ -111,52 +117,119 @@ where
         reconnect_attempt += 1;
         let delay = reconnect_backoff(reconnect_attempt);
         if wait_reconnect_or_shutdown(shutdown_rx, delay).await {
-            relay_log(app, &format!("{} stopping", label));
+            relay_log(events, &format!("{} stopping", label));
             return Ok(());
         }
     }
 }
 
+#[derive(Serialize, Clone)]
+struct ResolutionChange {
+    previous_width: u32,
+    previous_height: u32,
+    width: u32,
+    height: u32,
+    orientation: &'static str,
+    orientation_changed: bool,
+}
+
+/// Counts an assembled video frame, keeps the latest keyframe for snapshots and
+/// reports new stream metadata (`llstream://video-info`, `llstream://resolution-change`).
+/// Returns false when the latency controller wants the frame dropped.
+fn record_video_frame(events: &RelayEvents, shared: &RelayShared, frame: &AssembledFrame) -> bool {
+    shared.stats.video_frame();
+    shared.latency.observe(frame.timestamp_ns);
+    if frame.is_keyframe {
+        shared.store_keyframe(&frame.access_unit, frame.timestamp_ns, frame.codec);
+    }
+
+    let keep = !shared
+        .latency
+        .should_drop(&frame.access_unit, frame.codec, frame.is_keyframe);
+
+    let Some(info) = frame.video_info.as_ref() else {
+        return keep;
+    };
+    let Some(previous) = shared.update_video_info(info) else {
+        return keep;
+    };
+
+    relay_log(
+        events,
+        &format!(
+            "llstream video info: {} {} L{} {}x{} fps={:?}",
+            info.codec, info.profile, info.level, info.width, info.height, info.frame_rate
+        ),
+    );
+    events.emit("llstream://video-info", info);
+
+    if let Some(previous) = previous {
+        if (previous.width, previous.height) != (info.width, info.height) {
+            let change = ResolutionChange {
+                previous_width: previous.width,
+                previous_height: previous.height,
+                width: info.width,
+                height: info.height,
+                orientation: if info.is_portrait() { "portrait" } else { "landscape" },
+                orientation_changed: previous.is_portrait() != info.is_portrait(),
+            };
+            relay_log(
+                events,
+                &format!(
+                    "llstream resolution change: {}x{} -> {}x{}",
+                    change.previous_width, change.previous_height, change.width, change.height
+                ),
+            );
+            events.emit("llstream://resolution-change", change);
+        }
+    }
+    keep
+}
+
 // ---------------------------------------------------------------------------
 // Video WS → Annex B (for pipe relay)
 // ---------------------------------------------------------------------------
 
 pub(super) async fn run_video_ws_to_annexb_loop(
-    app: &AppHandle,
+    events: &RelayEvents,
     video_ws_url: &str,
     au_tx: broadcast::Sender<Vec<u8>>,
+    shared: &RelayShared,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
     let mut assembler = VideoFrameAssembler::new(true);
     let mut au_sent: u64 = 0;
-    let app_clone = app.clone();
+    let events_clone = events.clone();
 
     ws_reconnect_loop(
-        app,
+        events,
         video_ws_url,
         "llstream pipe ws",
         shutdown_rx,
         |event| {
             match event {
                 WsEvent::Connected => {
-                    assembler = VideoFrameAssembler::new(true);
+                    assembler.reconnected(&events_clone);
                     au_sent = 0;
                 }
                 WsEvent::Binary(data) => {
-                    if let Some(frame) = assembler.process(&data, &app_clone, "llstream pipe") {
+                    if let Some(frame) = assembler.process(&data, &events_clone, "llstream pipe") {
+                        if !record_video_frame(&events_clone, shared, &frame) {
+                            return ControlFlow::Continue(());
+                        }
                         au_sent += 1;
                         if au_sent <= 8 {
                             relay_log(
-                                &app_clone,
+                                &events_clone,
                                 &format!(
                                     "llstream au#{} kind=0x{:02x} bytes={} nals=[{}] head={}",
                                     au_sent, frame.kind, frame.access_unit.len(),
-                                    nal_types_preview(&frame.access_unit, 8),
+                                    nal_types_preview(&frame.access_unit, frame.codec, 8),
                                     hex_prefix(&frame.access_unit, 24)
                                 ),
                             );
                         } else if au_sent % 300 == 0 {
-                            relay_log(&app_clone, &format!("llstream au sent: {}", au_sent));
+                            relay_log(&events_clone, &format!("llstream au sent: {}", au_sent));
                         }
                         let _ = au_tx.send(frame.access_unit);
                     }
@@ -173,29 +246,38 @@ pub(super) async fn run_video_ws_to_annexb_loop(
 // ---------------------------------------------------------------------------
 
 pub(super) async fn run_video_ws_to_av_samples_loop(
-    app: &AppHandle,
+    events: &RelayEvents,
     video_ws_url: &str,
     sample_tx: mpsc::Sender<AvSample>,
+    shared: &RelayShared,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
     let mut assembler = VideoFrameAssembler::new(true);
-    let app_clone = app.clone();
+    let mut pending_discontinuity = false;
+    let events_clone = events.clone();
 
     ws_reconnect_loop(
-        app,
+        events,
         video_ws_url,
         "llstream av video ws",
         shutdown_rx,
         |event| {
             match event {
                 WsEvent::Connected => {
-                    assembler = VideoFrameAssembler::new(true);
+                    assembler.reconnected(&events_clone);
                 }
                 WsEvent::Binary(data) => {
-                    if let Some(frame) = assembler.process(&data, &app_clone, "llstream av") {
+                    if let Some(frame) = assembler.process(&data, &events_clone, "llstream av") {
+                        if !record_video_frame(&events_clone, shared, &frame) {
+                            return ControlFlow::Continue(());
+                        }
+                        let discontinuity = pending_discontinuity || frame.discontinuity;
+                        pending_discontinuity = false;
                         match sample_tx.try_send(AvSample::Video {
                             timestamp_ns: frame.timestamp_ns,
+                            codec: frame.codec,
                             annexb: frame.access_unit,
+                            discontinuity,
                         }) {
                             Ok(()) => {}
                             Err(mpsc::error::TrySendError::Closed(_)) => {
@@ -203,6 +285,7 @@ pub(super) async fn run_video_ws_to_av_samples_loop(
                             }
                             Err(mpsc::error::TrySendError::Full(_)) => {
                                 // Muxer is behind; drop this frame to avoid blocking.
+                                pending_discontinuity = discontinuity;
                             }
                         }
                     }
@@ -219,17 +302,20 @@ pub(super) async fn run_video_ws_to_av_samples_loop(
 // ---------------------------------------------------------------------------
 
 pub(super) async fn run_audio_ws_to_av_samples_loop(
-    app: &AppHandle,
+    events: &RelayEvents,
     audio_ws_url: &str,
     sample_tx: mpsc::Sender<AvSample>,
+    shared: &RelayShared,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
     let mut aac_config = AacConfig::default();
     let mut sent_audio = 0u64;
-    let app_clone = app.clone();
+    let mut continuity = StreamContinuity::new("audio");
+    let mut pending_discontinuity = false;
+    let events_clone = events.clone();
 
     ws_reconnect_loop(
-        app,
+        events,
         audio_ws_url,
         "llstream av audio ws",
         shutdown_rx,
@@ -238,20 +324,23 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
                 WsEvent::Connected => {
                     aac_config = AacConfig::default();
                     sent_audio = 0;
+                    pending_discontinuity |= continuity.connected(&events_clone);
                 }
                 WsEvent::Binary(data) => {
                     let Some(frame) = parse_audio_packet(&data) else {
                         return ControlFlow::Continue(());
                     };
+                    // AAC frames decode independently, so a gap only needs flagging.
+                    pending_discontinuity |= continuity.observe(&events_clone, &frame.header);
                     if frame.payload.is_empty() {
                         return ControlFlow::Continue(());
                     }
 
-                    if frame.kind == AUDIO_KIND_ASC {
+                    if frame.header.kind == AUDIO_KIND_ASC {
                         if let Some(new_cfg) = AacConfig::from_asc(frame.payload) {
                             aac_config = new_cfg;
                             relay_log(
-                                &app_clone,
+                                &events_clone,
                                 &format!(
                                     "llstream av audio config: aot={} sr_idx={} ch={}",
                                     aac_config.audio_object_type,
@@ -262,7 +351,7 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
                         }
                         return ControlFlow::Continue(());
                     }
-                    if frame.kind != AUDIO_KIND_AAC {
+                    if frame.header.kind != AUDIO_KIND_AAC {
                         return ControlFlow::Continue(());
                     }
 
@@ -271,9 +360,11 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
                     adts_frame.extend_from_slice(frame.payload);
 
                     sent_audio += 1;
+                    shared.stats.audio_frame();
+                    shared.latency.observe(frame.header.timestamp_ns);
                     if sent_audio <= 4 {
                         relay_log(
-                            &app_clone,
+                            &events_clone,
                             &format!(
                                 "llstream av audio#{} bytes={} head={}",
                                 sent_audio, adts_frame.len(), hex_prefix(&adts_frame, 20)
@@ -281,9 +372,11 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
                         );
                     }
 
+                    let discontinuity = std::mem::take(&mut pending_discontinuity);
                     match sample_tx.try_send(AvSample::Audio {
-                        timestamp_ns: frame.timestamp_ns,
+                        timestamp_ns: frame.header.timestamp_ns,
                         adts_frame,
+                        discontinuity,
                     }) {
                         Ok(()) => {}
                         Err(mpsc::error::TrySendError::Closed(_)) => {
@@ -291,6 +384,7 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
                         }
                         Err(mpsc::error::TrySendError::Full(_)) => {
                             // Muxer is behind; drop this frame to avoid blocking.
+                            pending_discontinuity = discontinuity;
                         }
                     }
                 }
@@ -306,30 +400,43 @@ pub(super) async fn run_audio_ws_to_av_samples_loop(
 // ---------------------------------------------------------------------------
 
 pub(super) async fn run_video_ws_loop(
-    app: &AppHandle,
+    events: &RelayEvents,
     video_ws_url: &str,
     packet_tx: broadcast::Sender<Vec<u8>>,
+    shared: &RelayShared,
     shutdown_rx: &mut watch::Receiver<bool>,
 ) -> Result<(), String> {
     let mut assembler = VideoFrameAssembler::new(false);
     let mut muxer = MpegTsMuxer::new();
-    let app_clone = app.clone();
+    let events_clone = events.clone();
 
     ws_reconnect_loop(
-        app,
+        events,
         video_ws_url,
         "llstream ws",
         shutdown_rx,
         |event| {
             match event {
                 WsEvent::Connected => {
-                    assembler = VideoFrameAssembler::new(false);
+                    assembler.reconnected(&events_clone);
                     muxer = MpegTsMuxer::new();
                 }
                 WsEvent::Binary(data) => {
-                    if let Some(frame) = assembler.process(&data, &app_clone, "llstream relay") {
+                    if let Some(frame) = assembler.process(&data, &events_clone, "llstream relay") {
+                        if !record_video_frame(&events_clone, shared, &frame) {
+                            return ControlFlow::Continue(());
+                        }
+                        if muxer.set_video_codec(frame.codec) {
+                            shared.set_ts_bootstrap(build_bootstrap_tables(frame.codec));
+                        }
+                        if frame.discontinuity {
+                            muxer.mark_discontinuity();
+                        }
                         let pts_90k = ns_to_90k(frame.timestamp_ns);
                         let chunk = muxer.push_video_access_unit(&frame.access_unit, pts_90k);
+                        shared.stats.chunk_published();
+                        shared.latency.published(pts_90k);
+                        shared.clip.push(&chunk, frame.is_keyframe);
                         let _ = packet_tx.send(chunk);
                     }
                 }
@@ -0,0 +1,896 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// moderation.rs
+//
+// Broadcast WS のコメントをフロントエンドに送る前に判定するモデレーションルール。
+//
+// 主な責務:
+//   - キーワード・正規表現のルールとユーザー ID のブロック・許可リスト
+//   - 同じユーザー・同じ本文の連投（フラッド）の検出
+//   - URL・ドメインの検出（許可したドメインは除く）
+//   - 判定結果（非表示・ハイライト・アラート）を t=1 のメッセージの verdict に付与し、
+//     アラートは moderation://alert でも通知
+//   - app_config_dir の moderation_rules.json へのルールの保存
+// ─────────────────────────────────────────────────────────────────────────────
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::live_poller::as_number;
+
+const RULES_FILE: &str = "moderation_rules.json";
+/// Broadcast WS のコメントメッセージ
+const COMMENT_MESSAGE_TYPE: i64 = 1;
+/// フラッド検出の期間（秒）の既定値と受け入れる範囲
+const DEFAULT_FLOOD_WINDOW_SECS: u64 = 30;
+const MIN_FLOOD_WINDOW_SECS: u64 = 5;
+const MAX_FLOOD_WINDOW_SECS: u64 = 600;
+/// 期間内に許容する同じユーザーのコメント数・同じ本文のコメント数
+const DEFAULT_MAX_PER_USER: u32 = 5;
+const DEFAULT_MAX_SAME_TEXT: u32 = 3;
+/// フラッド検出のために覚えておくコメントの上限
+const MAX_RECENT_COMMENTS: usize = 1000;
+/// キーワードの正規表現のコンパイル後のサイズ上限（極端なパターンを拒否する）
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+/// URL（スキーム・www. 付き）と、よく使われる TLD の裸のドメイン
+const LINK_PATTERN: &str = concat!(
+    r"(?i)(?:https?://|www\.)\S+",
+    r"|\b[a-z0-9][a-z0-9-]*(?:\.[a-z0-9-]+)*",
+    r"\.(?:com|net|org|info|jp|io|me|ly|gg|co|tv|xyz|link|page)\b(?:/\S*)?",
+);
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ルールと判定結果
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ModerationAction {
+    /// コメント一覧に表示しない
+    Hide,
+    Highlight,
+    /// moderation://alert でも通知する
+    Alert,
+}
+
+/// キーワード・正規表現のルール
+#[derive(Serialize, Deserialize, Clone)]
+pub struct KeywordRule {
+    /// 空の場合は保存時に割り当てる
+    #[serde(default)]
+    pub id: String,
+    pub pattern: String,
+    /// false の場合は pattern を部分一致の文字列として扱う
+    #[serde(default)]
+    pub regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    pub action: ModerationAction,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// 連投（同じユーザー・同じ本文）の検出
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct FloodRule {
+    pub enabled: bool,
+    pub window_secs: u64,
+    /// 期間内に同じユーザーがこの件数を超えたら該当
+    pub max_per_user: u32,
+    /// 期間内に同じ本文がこの件数を超えたら該当（ユーザーを問わない）
+    pub max_same_text: u32,
+    pub action: ModerationAction,
+}
+
+impl Default for FloodRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_secs: DEFAULT_FLOOD_WINDOW_SECS,
+            max_per_user: DEFAULT_MAX_PER_USER,
+            max_same_text: DEFAULT_MAX_SAME_TEXT,
+            // 「888」「草」など多くの人が同時に書く本文もあるので、既定では隠さない
+            action: ModerationAction::Highlight,
+        }
+    }
+}
+
+/// URL・ドメインの検出
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LinkRule {
+    pub enabled: bool,
+    pub action: ModerationAction,
+    /// 該当させないドメイン（サブドメインを含む）
+    pub allowed_domains: BTreeSet<String>,
+}
+
+impl Default for LinkRule {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            action: ModerationAction::Highlight,
+            allowed_domains: BTreeSet::from(["mirrativ.com".to_string()]),
+        }
+    }
+}
+
+/// moderation_rules.json の内容
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ModerationRules {
+    pub enabled: bool,
+    pub keywords: Vec<KeywordRule>,
+    /// コメントを非表示にするユーザー ID
+    pub blocked_user_ids: BTreeSet<String>,
+    /// どのルールにも該当させないユーザー ID（モデレーター・配信者など）
+    pub allowed_user_ids: BTreeSet<String>,
+    pub flood: FloodRule,
+    pub links: LinkRule,
+}
+
+impl Default for ModerationRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keywords: Vec::new(),
+            blocked_user_ids: BTreeSet::new(),
+            allowed_user_ids: BTreeSet::new(),
+            flood: FloodRule::default(),
+            links: LinkRule::default(),
+        }
+    }
+}
+
+/// 該当したルール
+#[derive(Serialize, Clone)]
+pub struct RuleMatch {
+    /// "keyword:<id>" / "blocked-user" / "flood-user" / "flood-text" / "link"
+    pub rule: String,
+    pub action: ModerationAction,
+}
+
+/// t=1 のメッセージの verdict フィールド
+#[derive(Serialize, Clone, Default)]
+pub struct Verdict {
+    pub hide: bool,
+    pub highlight: bool,
+    pub alert: bool,
+    /// 許可リストのユーザーのためルールを適用しなかった
+    pub allowed: bool,
+    pub matches: Vec<RuleMatch>,
+}
+
+impl Verdict {
+    fn add(&mut self, rule: String, action: ModerationAction) {
+        match action {
+            ModerationAction::Hide => self.hide = true,
+            ModerationAction::Highlight => self.highlight = true,
+            ModerationAction::Alert => self.alert = true,
+        }
+        self.matches.push(RuleMatch { rule, action });
+    }
+}
+
+/// moderation://alert イベントの payload
+#[derive(Serialize, Clone)]
+struct ModerationAlert<'a> {
+    verdict: &'a Verdict,
+    message: &'a Value,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 判定エンジン
+// ─────────────────────────────────────────────────────────────────────────────
+
+struct CompiledKeyword {
+    id: String,
+    regex: Regex,
+    action: ModerationAction,
+}
+
+/// フラッド検出用の直近のコメント
+struct RecentComment {
+    at: Instant,
+    user_id: Option<String>,
+    text: String,
+}
+
+struct Engine {
+    rules: ModerationRules,
+    keywords: Vec<CompiledKeyword>,
+    link: Regex,
+    recent: VecDeque<RecentComment>,
+}
+
+impl Engine {
+    /// メッセージを判定する。t=1 以外は None。
+    /// record が true の場合はフラッド検出の履歴にも加える
+    fn evaluate(&mut self, message: &Value, record: bool) -> Option<Verdict> {
+        if as_number(&message["t"]) != Some(COMMENT_MESSAGE_TYPE) {
+            return None;
+        }
+        let mut verdict = Verdict::default();
+        if !self.rules.enabled {
+            return Some(verdict);
+        }
+        let text = [&message["cm"], &message["comment"], &message["speech"]]
+            .into_iter()
+            .filter_map(Value::as_str)
+            .find(|s| !s.trim().is_empty())
+            .unwrap_or_default();
+        let user_id =
+            [&message["u"], &message["user_id"]]
+                .into_iter()
+                .find_map(|value| match value {
+                    Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                });
+
+        if let Some(id) = &user_id {
+            if self.rules.allowed_user_ids.contains(id) {
+                verdict.allowed = true;
+                return Some(verdict);
+            }
+            if self.rules.blocked_user_ids.contains(id) {
+                verdict.add("blocked-user".to_string(), ModerationAction::Hide);
+            }
+        }
+
+        for keyword in &self.keywords {
+            if keyword.regex.is_match(text) {
+                verdict.add(format!("keyword:{}", keyword.id), keyword.action);
+            }
+        }
+
+        let links = &self.rules.links;
+        if links.enabled
+            && self
+                .link
+                .find_iter(text)
+                .any(|m| !is_allowed_domain(m.as_str(), &links.allowed_domains))
+        {
+            verdict.add("link".to_string(), links.action);
+        }
+
+        self.check_flood(&mut verdict, user_id, text, record);
+        Some(verdict)
+    }
+
+    /// 期間内の同じユーザー・同じ本文のコメント数を数える（今回のコメントを含む）
+    fn check_flood(
+        &mut self,
+        verdict: &mut Verdict,
+        user_id: Option<String>,
+        text: &str,
+        record: bool,
+    ) {
+        let flood = &self.rules.flood;
+        if !flood.enabled {
+            return;
+        }
+        let now = Instant::now();
+        let window = Duration::from_secs(flood.window_secs);
+        while self
+            .recent
+            .front()
+            .is_some_and(|c| now.duration_since(c.at) > window)
+        {
+            self.recent.pop_front();
+        }
+
+        let text = normalize_text(text);
+        let same_user = user_id.as_ref().map_or(0, |id| {
+            self.recent
+                .iter()
+                .filter(|c| c.user_id.as_ref() == Some(id))
+                .count()
+        });
+        let same_text = if text.is_empty() {
+            0
+        } else {
+            self.recent.iter().filter(|c| c.text == text).count()
+        };
+        if same_user + 1 > flood.max_per_user as usize {
+            verdict.add("flood-user".to_string(), flood.action);
+        }
+        if !text.is_empty() && same_text + 1 > flood.max_same_text as usize {
+            verdict.add("flood-text".to_string(), flood.action);
+        }
+
+        if record {
+            if self.recent.len() >= MAX_RECENT_COMMENTS {
+                self.recent.pop_front();
+            }
+            self.recent.push_back(RecentComment {
+                at: now,
+                user_id,
+                text,
+            });
+        }
+    }
+}
+
+/// 大文字小文字・空白の違いを無視して本文を比較できるようにする
+fn normalize_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// リンクのホストが許可したドメイン（またはそのサブドメイン）か。
+/// userinfo（"mirrativ.com@evil.com" の @ より前）とポートはホストに含めない
+fn is_allowed_domain(link: &str, allowed: &BTreeSet<String>) -> bool {
+    let lower = link.to_lowercase();
+    let rest = lower
+        .strip_prefix("https://")
+        .or_else(|| lower.strip_prefix("http://"))
+        .unwrap_or(&lower);
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority
+        .rsplit('@')
+        .next()
+        .unwrap_or_default()
+        .split(':')
+        .next()
+        .unwrap_or_default()
+        .trim_start_matches("www.");
+    allowed.iter().any(|domain| {
+        host == domain
+            || host
+                .strip_suffix(domain.as_str())
+                .is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+/// キーワードのルールを 1 件コンパイルする
+fn compile_keyword(rule: &KeywordRule) -> Result<CompiledKeyword, String> {
+    let pattern = if rule.regex {
+        rule.pattern.clone()
+    } else {
+        regex::escape(&rule.pattern)
+    };
+    let regex = RegexBuilder::new(&pattern)
+        .case_insensitive(!rule.case_sensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("Invalid pattern in rule {}: {}", rule.id, e))?;
+    Ok(CompiledKeyword {
+        id: rule.id.clone(),
+        regex,
+        action: rule.action,
+    })
+}
+
+/// 有効なキーワードのルールをコンパイルする（1 件でも不正なら失敗する）
+fn compile_keywords(rules: &ModerationRules) -> Result<Vec<CompiledKeyword>, String> {
+    rules
+        .keywords
+        .iter()
+        .filter(|rule| rule.enabled)
+        .map(compile_keyword)
+        .collect()
+}
+
+/// 有効なキーワードのルールをコンパイルする。不正なルールはログに出して飛ばす
+/// （保存済みのルールの読み込み用。1 件のために他のルールを無効にしない）
+fn compile_keywords_lenient(rules: &ModerationRules) -> Vec<CompiledKeyword> {
+    rules
+        .keywords
+        .iter()
+        .filter(|rule| rule.enabled)
+        .filter_map(|rule| {
+            compile_keyword(rule)
+                .map_err(|e| eprintln!("[moderation] skipping keyword rule: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ModerationManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// モデレーションルール（Tauri の管理状態として登録される）
+pub struct ModerationManager {
+    engine: Mutex<Engine>,
+}
+
+impl Default for ModerationManager {
+    fn default() -> Self {
+        Self {
+            engine: Mutex::new(Engine {
+                rules: ModerationRules::default(),
+                keywords: Vec::new(),
+                link: Regex::new(LINK_PATTERN).expect("link pattern"),
+                recent: VecDeque::new(),
+            }),
+        }
+    }
+}
+
+impl ModerationManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn rules(&self) -> Result<ModerationRules, String> {
+        self.engine
+            .lock()
+            .map(|engine| engine.rules.clone())
+            .map_err(|e| e.to_string())
+    }
+
+    /// ルールを変更し、検証・コンパイルしてから保存する（失敗した場合は変更しない）
+    fn update_rules(
+        &self,
+        app: &AppHandle,
+        update: impl FnOnce(&mut ModerationRules),
+    ) -> Result<ModerationRules, String> {
+        let mut engine = self.engine.lock().map_err(|e| e.to_string())?;
+        let mut next = engine.rules.clone();
+        update(&mut next);
+        normalize_rules(&mut next)?;
+        let keywords = compile_keywords(&next)?;
+        save_rules(app, &next)?;
+        engine.rules = next.clone();
+        engine.keywords = keywords;
+        Ok(next)
+    }
+
+    /// フラッド検出の履歴を消す（視聴用の Broadcast WS の接続先が変わったとき。
+    /// 前の配信のコメントで次の配信のコメントが連投と判定されないようにする）
+    pub(crate) fn reset_flood_history(&self) {
+        if let Ok(mut engine) = self.engine.lock() {
+            engine.recent.clear();
+        }
+    }
+
+    /// t=1 のメッセージに verdict を付与する（視聴用の Broadcast WS から emit 前に呼ばれる）。
+    /// アラートに該当した場合は moderation://alert も送る
+    pub(crate) fn annotate(&self, app: &AppHandle, message: &mut Value) {
+        let Some(verdict) = self
+            .engine
+            .lock()
+            .ok()
+            .and_then(|mut engine| engine.evaluate(message, true))
+        else {
+            return;
+        };
+        if verdict.alert {
+            let _ = app.emit(
+                "moderation://alert",
+                ModerationAlert {
+                    verdict: &verdict,
+                    message,
+                },
+            );
+        }
+        if let (Some(object), Ok(verdict)) =
+            (message.as_object_mut(), serde_json::to_value(verdict))
+        {
+            object.insert("verdict".to_string(), verdict);
+        }
+    }
+}
+
+/// 保存済みのルールを読み込む（setup から呼ぶ）
+pub(crate) fn start(app: &AppHandle) {
+    let rules = load_rules(app).unwrap_or_else(|e| {
+        eprintln!("[moderation] {}", e);
+        ModerationRules::default()
+    });
+    let keywords = compile_keywords_lenient(&rules);
+    if let Ok(mut engine) = app.state::<ModerationManager>().engine.lock() {
+        engine.rules = rules;
+        engine.keywords = keywords;
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 保存・読み込み
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn rules_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(RULES_FILE))
+}
+
+fn load_rules(app: &AppHandle) -> Result<ModerationRules, String> {
+    let path = rules_path(app)?;
+    if !path.exists() {
+        return Ok(ModerationRules::default());
+    }
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let mut rules: ModerationRules =
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", RULES_FILE, e))?;
+    normalize_rules(&mut rules)?;
+    Ok(rules)
+}
+
+fn save_rules(app: &AppHandle, rules: &ModerationRules) -> Result<(), String> {
+    let path = rules_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(rules).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// パターン・ID・ドメインを整え、フラッド検出の値を範囲内に収める
+fn normalize_rules(rules: &mut ModerationRules) -> Result<(), String> {
+    let mut ids = HashSet::new();
+    rules
+        .keywords
+        .retain(|rule| !rule.pattern.trim().is_empty());
+    for rule in rules.keywords.iter_mut() {
+        rule.pattern = rule.pattern.trim().to_string();
+        rule.id = rule.id.trim().to_string();
+        if rule.id.is_empty() {
+            rule.id = uuid::Uuid::new_v4().to_string();
+        }
+        if !ids.insert(rule.id.clone()) {
+            return Err(format!("Duplicate rule id: {}", rule.id));
+        }
+    }
+    for list in [&mut rules.blocked_user_ids, &mut rules.allowed_user_ids] {
+        *list = list
+            .iter()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+            .collect();
+    }
+    rules.links.allowed_domains = rules
+        .links
+        .allowed_domains
+        .iter()
+        .map(|domain| domain.trim().trim_start_matches('.').to_lowercase())
+        .filter(|domain| !domain.is_empty())
+        .collect();
+
+    let flood = &mut rules.flood;
+    flood.window_secs = flood
+        .window_secs
+        .clamp(MIN_FLOOD_WINDOW_SECS, MAX_FLOOD_WINDOW_SECS);
+    flood.max_per_user = flood.max_per_user.max(1);
+    flood.max_same_text = flood.max_same_text.max(1);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub async fn get_moderation_rules(
+    manager: tauri::State<'_, ModerationManager>,
+) -> Result<ModerationRules, String> {
+    manager.rules()
+}
+
+/// ルール全体を保存する（正規表現が不正な場合はエラーで、ルールは変更しない）
+#[tauri::command]
+pub async fn set_moderation_rules(
+    app: AppHandle,
+    manager: tauri::State<'_, ModerationManager>,
+    rules: ModerationRules,
+) -> Result<ModerationRules, String> {
+    manager.update_rules(&app, |current| *current = rules)
+}
+
+/// キーワードのルールを追加する（同じ ID があれば置き換える）
+#[tauri::command]
+pub async fn upsert_moderation_keyword(
+    app: AppHandle,
+    manager: tauri::State<'_, ModerationManager>,
+    rule: KeywordRule,
+) -> Result<ModerationRules, String> {
+    if rule.pattern.trim().is_empty() {
+        return Err("pattern is empty".to_string());
+    }
+    let id = rule.id.trim().to_string();
+    manager.update_rules(&app, |rules| {
+        match rules
+            .keywords
+            .iter_mut()
+            .find(|r| !id.is_empty() && r.id == id)
+        {
+            Some(existing) => *existing = rule,
+            None => rules.keywords.push(rule),
+        }
+    })
+}
+
+#[tauri::command]
+pub async fn remove_moderation_keyword(
+    app: AppHandle,
+    manager: tauri::State<'_, ModerationManager>,
+    id: String,
+) -> Result<ModerationRules, String> {
+    manager.update_rules(&app, |rules| rules.keywords.retain(|r| r.id != id.trim()))
+}
+
+/// ユーザーをブロックリストに追加・削除する（追加すると許可リストからは外す）
+#[tauri::command]
+pub async fn set_moderation_user_blocked(
+    app: AppHandle,
+    manager: tauri::State<'_, ModerationManager>,
+    user_id: String,
+    blocked: bool,
+) -> Result<ModerationRules, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("user_id is empty".to_string());
+    }
+    manager.update_rules(&app, |rules| {
+        if blocked {
+            rules.allowed_user_ids.remove(&user_id);
+            rules.blocked_user_ids.insert(user_id);
+        } else {
+            rules.blocked_user_ids.remove(&user_id);
+        }
+    })
+}
+
+/// ユーザーを許可リストに追加・削除する（追加するとブロックリストからは外す）
+#[tauri::command]
+pub async fn set_moderation_user_allowed(
+    app: AppHandle,
+    manager: tauri::State<'_, ModerationManager>,
+    user_id: String,
+    allowed: bool,
+) -> Result<ModerationRules, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("user_id is empty".to_string());
+    }
+    manager.update_rules(&app, |rules| {
+        if allowed {
+            rules.blocked_user_ids.remove(&user_id);
+            rules.allowed_user_ids.insert(user_id);
+        } else {
+            rules.allowed_user_ids.remove(&user_id);
+        }
+    })
+}
+
+/// ルール編集画面の確認用に、コメントを判定する（フラッド検出の履歴には加えない）
+#[tauri::command]
+pub async fn test_moderation(
+    manager: tauri::State<'_, ModerationManager>,
+    comment: String,
+    user_id: Option<String>,
+) -> Result<Verdict, String> {
+    let message = json!({ "t": COMMENT_MESSAGE_TYPE, "cm": comment, "u": user_id });
+    let mut engine = manager.engine.lock().map_err(|e| e.to_string())?;
+    Ok(engine.evaluate(&message, false).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(rules: ModerationRules) -> Engine {
+        let keywords = compile_keywords(&rules).expect("valid keywords");
+        Engine {
+            rules,
+            keywords,
+            link: Regex::new(LINK_PATTERN).expect("link pattern"),
+            recent: VecDeque::new(),
+        }
+    }
+
+    fn keyword(id: &str, pattern: &str, regex: bool, case_sensitive: bool) -> KeywordRule {
+        KeywordRule {
+            id: id.to_string(),
+            pattern: pattern.to_string(),
+            regex,
+            case_sensitive,
+            action: ModerationAction::Hide,
+            enabled: true,
+        }
+    }
+
+    fn comment(user_id: &str, text: &str) -> Value {
+        json!({ "t": COMMENT_MESSAGE_TYPE, "u": user_id, "cm": text })
+    }
+
+    fn matched(verdict: &Verdict) -> Vec<&str> {
+        verdict.matches.iter().map(|m| m.rule.as_str()).collect()
+    }
+
+    fn without_flood_and_links() -> ModerationRules {
+        let mut rules = ModerationRules::default();
+        rules.flood.enabled = false;
+        rules.links.enabled = false;
+        rules
+    }
+
+    #[test]
+    fn keyword_case_sensitivity() {
+        let mut rules = without_flood_and_links();
+        rules.keywords = vec![
+            keyword("plain", "Spam", false, false),
+            keyword("exact", "BAN", false, true),
+            keyword("re", r"^f(o)+$", true, false),
+            keyword("literal", "a.b", false, false),
+        ];
+        let mut engine = engine(rules);
+
+        let verdict = engine
+            .evaluate(&comment("1", "buy SPAM now"), true)
+            .unwrap();
+        assert_eq!(matched(&verdict), ["keyword:plain"]);
+        assert!(verdict.hide);
+        let verdict = engine.evaluate(&comment("1", "ban"), true).unwrap();
+        assert!(verdict.matches.is_empty());
+        let verdict = engine.evaluate(&comment("1", "BAN"), true).unwrap();
+        assert_eq!(matched(&verdict), ["keyword:exact"]);
+        let verdict = engine.evaluate(&comment("1", "FOOO"), true).unwrap();
+        assert_eq!(matched(&verdict), ["keyword:re"]);
+        // 正規表現でないパターンの記号はそのまま一致させる
+        let verdict = engine.evaluate(&comment("1", "axb"), true).unwrap();
+        assert!(verdict.matches.is_empty());
+        let verdict = engine.evaluate(&comment("1", "A.B"), true).unwrap();
+        assert_eq!(matched(&verdict), ["keyword:literal"]);
+    }
+
+    #[test]
+    fn invalid_saved_regex_skips_only_that_rule() {
+        let rules = ModerationRules {
+            keywords: vec![
+                keyword("broken", "(unclosed", true, false),
+                keyword("ok", "spam", false, false),
+            ],
+            ..without_flood_and_links()
+        };
+        assert!(compile_keywords(&rules).is_err());
+        let keywords = compile_keywords_lenient(&rules);
+        assert_eq!(keywords.len(), 1);
+        assert_eq!(keywords[0].id, "ok");
+    }
+
+    #[test]
+    fn allowed_user_skips_every_rule() {
+        let mut rules = ModerationRules {
+            keywords: vec![keyword("spam", "spam", false, false)],
+            ..ModerationRules::default()
+        };
+        rules.allowed_user_ids.insert("7".to_string());
+        rules.blocked_user_ids.insert("8".to_string());
+        let mut engine = engine(rules);
+
+        let verdict = engine
+            .evaluate(&comment("7", "spam https://evil.example.com"), true)
+            .unwrap();
+        assert!(verdict.allowed);
+        assert!(verdict.matches.is_empty());
+        assert!(engine.recent.is_empty());
+
+        let verdict = engine.evaluate(&comment("8", "hello"), true).unwrap();
+        assert!(!verdict.allowed);
+        assert_eq!(matched(&verdict), ["blocked-user"]);
+        assert!(verdict.hide);
+    }
+
+    #[test]
+    fn non_comment_messages_are_not_judged() {
+        let mut engine = engine(ModerationRules::default());
+        assert!(engine
+            .evaluate(&json!({ "t": 3, "cm": "spam" }), true)
+            .is_none());
+    }
+
+    #[test]
+    fn flood_thresholds() {
+        let mut rules = without_flood_and_links();
+        rules.flood = FloodRule {
+            enabled: true,
+            max_per_user: 2,
+            max_same_text: 3,
+            ..FloodRule::default()
+        };
+        let mut engine = engine(rules);
+
+        // 同じユーザーの 3 件目から該当する
+        for text in ["a", "b"] {
+            let verdict = engine.evaluate(&comment("1", text), true).unwrap();
+            assert!(verdict.matches.is_empty());
+        }
+        let verdict = engine.evaluate(&comment("1", "c"), true).unwrap();
+        assert_eq!(matched(&verdict), ["flood-user"]);
+
+        // 同じ本文（大文字小文字・空白を無視）はユーザーを問わず 4 件目から該当する
+        for user in ["2", "3"] {
+            let verdict = engine
+                .evaluate(&comment(user, "Hello  World"), true)
+                .unwrap();
+            assert!(verdict.matches.is_empty());
+        }
+        let verdict = engine.evaluate(&comment("4", "hello world"), true).unwrap();
+        assert!(verdict.matches.is_empty());
+        let verdict = engine.evaluate(&comment("5", "HELLO WORLD"), true).unwrap();
+        assert_eq!(matched(&verdict), ["flood-text"]);
+    }
+
+    #[test]
+    fn default_flood_rule_does_not_hide() {
+        let mut engine = engine(ModerationRules::default());
+        let mut verdict = Verdict::default();
+        for user in ["1", "2", "3", "4", "5"] {
+            verdict = engine.evaluate(&comment(user, "888"), true).unwrap();
+        }
+        // 多くの人が同じ本文を書いてもハイライトのみ
+        assert_eq!(matched(&verdict), ["flood-text"]);
+        assert!(verdict.highlight);
+        assert!(!verdict.hide);
+    }
+
+    #[test]
+    fn flood_check_without_record_keeps_history() {
+        let mut rules = without_flood_and_links();
+        rules.flood.enabled = true;
+        rules.flood.max_per_user = 1;
+        let mut engine = engine(rules);
+        for _ in 0..3 {
+            let verdict = engine.evaluate(&comment("1", "x"), false).unwrap();
+            assert!(verdict.matches.is_empty());
+        }
+        assert!(engine.recent.is_empty());
+    }
+
+    #[test]
+    fn link_allow_domains() {
+        let allowed = BTreeSet::from(["mirrativ.com".to_string()]);
+        for link in [
+            "mirrativ.com",
+            "https://www.mirrativ.com/live/abc",
+            "HTTP://Sub.Mirrativ.com:443/path",
+            "https://user@mirrativ.com/",
+        ] {
+            assert!(is_allowed_domain(link, &allowed), "{}", link);
+        }
+        for link in [
+            "evilmirrativ.com",
+            "https://evilmirrativ.com/live",
+            "https://mirrativ.com@evil.com/",
+            "mirrativ.com@evil.com",
+            "https://mirrativ.com.evil.com/",
+            "https://evil.com/?r=mirrativ.com",
+        ] {
+            assert!(!is_allowed_domain(link, &allowed), "{}", link);
+        }
+    }
+
+    #[test]
+    fn link_rule_flags_disallowed_hosts() {
+        let mut rules = ModerationRules::default();
+        rules.flood.enabled = false;
+        let mut engine = engine(rules);
+
+        let verdict = engine
+            .evaluate(&comment("1", "見て https://www.mirrativ.com/live/1"), true)
+            .unwrap();
+        assert!(verdict.matches.is_empty());
+        for text in [
+            "見て https://mirrativ.com@evil.com/login",
+            "evilmirrativ.com に来て",
+        ] {
+            let verdict = engine.evaluate(&comment("1", text), true).unwrap();
+            assert_eq!(matched(&verdict), ["link"], "{}", text);
+            assert!(verdict.highlight);
+        }
+    }
+}
@@ -0,0 +1,367 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// mpv_log.rs
+//
+// libmpv のログ（mpv_request_log_messages）をアプリのログに取り込むモジュール。
+//
+// 主な責務:
+//   - ログレベルの設定（フロントエンドから変更可能）
+//   - app_log_dir の mpv.log への書き込みとローテーション
+//   - 警告・エラー（デコーダーの失敗、デマクサの EOF など）の分類
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager};
+
+const LOG_FILE: &str = "mpv.log";
+/// このサイズを超えたらローテーションする
+const MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+/// 保持する過去ログの世代数（mpv.log.1 〜 mpv.log.3）
+const ROTATED_FILES: usize = 3;
+
+/// mpv のログレベル（冗長度の低い順）
+const LOG_LEVELS: [&str; 9] = [
+    "no", "fatal", "error", "warn", "info", "status", "v", "debug", "trace",
+];
+/// 既定のログレベル（info）
+const DEFAULT_LOG_LEVEL: usize = 4;
+/// この重要度以上のログはターミナルにも出力する（warn）
+const TERMINAL_LEVEL: usize = 3;
+
+/// 現在のログレベル（LOG_LEVELS のインデックス）
+static LOG_LEVEL: AtomicUsize = AtomicUsize::new(DEFAULT_LOG_LEVEL);
+/// mpv.log の書き込み先（最初の書き込み時に開く）
+static LOG_WRITER: Mutex<Option<RotatingLog>> = Mutex::new(None);
+/// 書き込み失敗を一度だけターミナルに出すためのフラグ
+static WRITE_ERROR_REPORTED: AtomicBool = AtomicBool::new(false);
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 型定義
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// mpv のログ 1 行（mpv://log イベントの payload）
+#[derive(Serialize, Clone)]
+pub(crate) struct LogEntry {
+    /// 出力元モジュール（"ffmpeg/video", "demux" など）
+    pub(crate) prefix: String,
+    pub(crate) level: String,
+    pub(crate) text: String,
+}
+
+/// mpv://warning イベントの payload
+#[derive(Serialize, Clone)]
+pub(crate) struct PlayerWarning {
+    /// "decoder-error" / "demuxer-eof" / "error" / "warning"
+    kind: &'static str,
+    level: String,
+    prefix: String,
+    text: String,
+}
+
+/// get_mpv_log_settings の戻り値
+#[derive(Serialize)]
+pub struct MpvLogSettings {
+    level: &'static str,
+    levels: Vec<&'static str>,
+    /// mpv.log のパス（ログディレクトリが取得できない場合は None）
+    path: Option<String>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ログレベル
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn level_index(level: &str) -> Option<usize> {
+    LOG_LEVELS.iter().position(|candidate| *candidate == level)
+}
+
+/// mpv_request_log_messages に渡すレベル（設定レベルのみを要求する）
+pub(crate) fn request_level() -> &'static str {
+    LOG_LEVELS[LOG_LEVEL.load(Ordering::Relaxed)]
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 記録・分類
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// ログ 1 行を記録する。設定レベル以内の場合は mpv.log に書き込んで true を返す。
+pub(crate) fn record(app: &AppHandle, player_id: &str, entry: &LogEntry) -> bool {
+    let Some(index) = level_index(&entry.level) else {
+        return false;
+    };
+    if index == 0 || index > LOG_LEVEL.load(Ordering::Relaxed) {
+        return false;
+    }
+    if index <= TERMINAL_LEVEL {
+        eprintln!("mpv [{}] {} {}: {}", player_id, entry.level, entry.prefix, entry.text);
+    }
+
+    let line = format!(
+        "[{}] [{}] [{}] {}: {}",
+        timestamp(),
+        player_id,
+        entry.level,
+        entry.prefix,
+        entry.text
+    );
+    match write_line(app, &line) {
+        Ok(()) => WRITE_ERROR_REPORTED.store(false, Ordering::Relaxed),
+        Err(err) => {
+            if !WRITE_ERROR_REPORTED.swap(true, Ordering::Relaxed) {
+                eprintln!("mpv log write failed: {}", err);
+            }
+        }
+    }
+    true
+}
+
+/// プレイヤーイベントとして通知すべきログを分類する（warn 以上のみ）
+pub(crate) fn classify(entry: &LogEntry) -> Option<PlayerWarning> {
+    let index = level_index(&entry.level)?;
+    if index == 0 || index > TERMINAL_LEVEL {
+        return None;
+    }
+    let prefix = entry.prefix.as_str();
+    let text = entry.text.to_ascii_lowercase();
+
+    let demuxer = prefix.starts_with("demux") || prefix == "lavf" || prefix == "ffmpeg/demuxer";
+    let decoder = matches!(prefix, "vd" | "ad")
+        || prefix.starts_with("ffmpeg/video")
+        || prefix.starts_with("ffmpeg/audio");
+
+    let kind = if demuxer && (text.contains("eof") || text.contains("end of file")) {
+        "demuxer-eof"
+    } else if decoder {
+        "decoder-error"
+    } else if index <= 2 {
+        "error"
+    } else {
+        "warning"
+    };
+
+    Some(PlayerWarning {
+        kind,
+        level: entry.level.clone(),
+        prefix: entry.prefix.clone(),
+        text: entry.text.clone(),
+    })
+}
+
+fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    format!("{}.{:03}", now.as_secs(), now.subsec_millis())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ログファイル（サイズでローテーション）
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn log_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(LOG_FILE))
+}
+
+fn write_line(app: &AppHandle, line: &str) -> Result<(), String> {
+    let mut writer = LOG_WRITER.lock().map_err(|e| e.to_string())?;
+    if writer.is_none() {
+        *writer = Some(RotatingLog::open(log_path(app)?)?);
+    }
+    match writer.as_mut() {
+        Some(log) => log.write_line(line),
+        None => Ok(()),
+    }
+}
+
+/// mpv.log -> mpv.log.1 -> ... -> mpv.log.{ROTATED_FILES} の順に世代を送るログファイル
+struct RotatingLog {
+    path: PathBuf,
+    /// ローテーション中は None（Windows では開いたままのファイルをリネームできない）
+    file: Option<File>,
+    size: u64,
+}
+
+impl RotatingLog {
+    fn open(path: PathBuf) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let size = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        Ok(Self {
+            path,
+            file: Some(file),
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), String> {
+        if self.size >= MAX_LOG_BYTES || self.file.is_none() {
+            self.rotate()?;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> Result<(), String> {
+        self.file = None;
+        for index in (1..ROTATED_FILES).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                std::fs::rename(&from, rotated_path(&self.path, index + 1))
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        if self.path.exists() {
+            std::fs::rename(&self.path, rotated_path(&self.path, 1)).map_err(|e| e.to_string())?;
+        }
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 現在のログレベルと mpv.log のパスを取得する
+#[tauri::command]
+pub async fn get_mpv_log_settings(app: AppHandle) -> Result<MpvLogSettings, String> {
+    Ok(MpvLogSettings {
+        level: LOG_LEVELS[LOG_LEVEL.load(Ordering::Relaxed)],
+        levels: LOG_LEVELS.to_vec(),
+        path: log_path(&app)
+            .ok()
+            .map(|path| path.to_string_lossy().into_owned()),
+    })
+}
+
+/// ログレベルを変更する（再生中のプレイヤーにも次のイベントから反映される）
+#[tauri::command]
+pub async fn set_mpv_log_level(level: String) -> Result<(), String> {
+    let index = level_index(&level).ok_or_else(|| {
+        format!("Unknown mpv log level: {} (expected one of {})", level, LOG_LEVELS.join(", "))
+    })?;
+    LOG_LEVEL.store(index, Ordering::Relaxed);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(level: &str, prefix: &str, text: &str) -> LogEntry {
+        LogEntry {
+            prefix: prefix.to_string(),
+            level: level.to_string(),
+            text: text.to_string(),
+        }
+    }
+
+    fn kind(level: &str, prefix: &str, text: &str) -> Option<&'static str> {
+        classify(&entry(level, prefix, text)).map(|warning| warning.kind)
+    }
+
+    /// テスト用の一時ディレクトリ内の mpv.log
+    fn temp_log_path() -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("mpv-log-test-{}", uuid::Uuid::new_v4().simple()))
+            .join(LOG_FILE)
+    }
+
+    fn read(path: &Path) -> String {
+        std::fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn classify_kinds() {
+        assert_eq!(kind("error", "ffmpeg/video", "h264: decode error"), Some("decoder-error"));
+        assert_eq!(kind("warn", "vd", "Error while decoding frame"), Some("decoder-error"));
+        assert_eq!(kind("error", "demux", "EOF reached"), Some("demuxer-eof"));
+        assert_eq!(kind("warn", "lavf", "end of file"), Some("demuxer-eof"));
+        assert_eq!(kind("fatal", "cplayer", "cannot open"), Some("error"));
+        assert_eq!(kind("warn", "ao", "underrun"), Some("warning"));
+    }
+
+    #[test]
+    fn classify_ignores_verbose_lines() {
+        // 冗長なデマクサのログは EOF を含んでも警告にしない
+        assert_eq!(kind("v", "demux", "EOF reached"), None);
+        assert_eq!(kind("debug", "ffmpeg/demuxer", "eof"), None);
+        assert_eq!(kind("info", "ffmpeg/video", "decoder error"), None);
+        assert_eq!(kind("no", "demux", "eof"), None);
+        assert_eq!(kind("unknown", "demux", "eof"), None);
+    }
+
+    #[test]
+    fn request_level_follows_the_setting() {
+        assert_eq!(request_level(), LOG_LEVELS[LOG_LEVEL.load(Ordering::Relaxed)]);
+        assert_ne!(LOG_LEVELS[DEFAULT_LOG_LEVEL], "v");
+    }
+
+    #[test]
+    fn rotates_at_size_limit() {
+        let path = temp_log_path();
+        let mut log = RotatingLog::open(path.clone()).unwrap();
+        log.write_line("first").unwrap();
+        assert_eq!(read(&path), "first\n");
+
+        // 上限を超えると次の書き込みの前に mpv.log.1 へ移す
+        log.size = MAX_LOG_BYTES;
+        log.write_line("second").unwrap();
+        assert_eq!(read(&path), "second\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "first\n");
+        assert_eq!(log.size, "second\n".len() as u64);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn keeps_three_generations() {
+        let path = temp_log_path();
+        let mut log = RotatingLog::open(path.clone()).unwrap();
+        for line in ["1", "2", "3", "4", "5"] {
+            log.write_line(line).unwrap();
+            log.size = MAX_LOG_BYTES;
+        }
+        log.write_line("6").unwrap();
+
+        assert_eq!(read(&path), "6\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "5\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "4\n");
+        assert_eq!(read(&rotated_path(&path, 3)), "3\n");
+        assert!(!rotated_path(&path, 4).exists());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+
+    #[test]
+    fn reopening_continues_the_current_size() {
+        let path = temp_log_path();
+        RotatingLog::open(path.clone()).unwrap().write_line("abc").unwrap();
+        let log = RotatingLog::open(path.clone()).unwrap();
+        assert_eq!(log.size, 4);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+    }
+}
@@ -2,25 +2,32 @@
 // mpv_player.rs
 //
 // libmpv を動的リンクして動画を再生する Tauri 統合モジュール。
-// Windows 向けに MPV を WebView ウィンドウへ埋め込む機能を提供する。
+// Windows では MPV を WebView ウィンドウへ埋め込み、Linux / macOS では
+// render API のソフトウェアレンダリングでフレームをフロントエンドへ渡す。
 //
 // 主な責務:
-//   - libmpv-2.dll の動的ロードと FFI バインディング
+//   - libmpv（.dll / .so / .dylib）の動的ロードと FFI バインディング
 //   - MpvPlayerManager による再生状態の管理
+//   - libmpv イベントループによる実際の再生状態の監視（mpv:// イベント）
 //   - Win32 API を使った埋め込みホストウィンドウの作成・削除
 //   - Tauri コマンド経由でフロントエンドと連携
 // ─────────────────────────────────────────────────────────────────────────────
 
 use libloading::Library;
 use serde::{Deserialize, Serialize};
-use std::ffi::{c_char, c_int, c_void, CString};
-#[cfg(windows)]
-use std::path::Path;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
 use std::ptr;
-use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
 use std::thread::ThreadId;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+use tokio::sync::mpsc;
+
+use crate::mirrativ::client::llstream_relay::LlstreamRelayManager;
+use crate::mpv_log;
+use crate::mpv_profile;
 
 // ─────────────────────────────────────────────────────────────────────────────
 // libmpv FFI 型定義
@@ -39,11 +46,126 @@ type MpvCommandFn = unsafe extern "C" fn(MpvHandle, *const *const c_char) -> c_i
 type MpvSetOptionStringFn = unsafe extern "C" fn(MpvHandle, *const c_char, *const c_char) -> c_int;
 /// mpv_terminate_destroy: MPV インスタンスを終了して破棄する
 type MpvTerminateDestroyFn = unsafe extern "C" fn(MpvHandle);
+/// mpv_get_property_string: プロパティを文字列で取得する（戻り値は mpv_free で解放）
+type MpvGetPropertyStringFn = unsafe extern "C" fn(MpvHandle, *const c_char) -> *mut c_char;
+/// mpv_free: libmpv が確保したメモリを解放する
+type MpvFreeFn = unsafe extern "C" fn(*mut c_void);
+/// mpv_create_client: 同じプレイヤーに対する追加のクライアントハンドルを作る
+type MpvCreateClientFn = unsafe extern "C" fn(MpvHandle, *const c_char) -> MpvHandle;
+/// mpv_destroy: クライアントハンドルを破棄する（プレイヤー本体は終了しない）
+type MpvDestroyFn = unsafe extern "C" fn(MpvHandle);
+/// mpv_wait_event: 次のイベントを待つ（timeout 秒、負値で無期限）
+type MpvWaitEventFn = unsafe extern "C" fn(MpvHandle, f64) -> *mut MpvEvent;
+/// mpv_observe_property: プロパティ変更を MPV_EVENT_PROPERTY_CHANGE で通知させる
+type MpvObservePropertyFn = unsafe extern "C" fn(MpvHandle, u64, *const c_char, c_int) -> c_int;
+/// mpv_get_property: プロパティを指定フォーマットで取得する
+type MpvGetPropertyFn = unsafe extern "C" fn(MpvHandle, *const c_char, c_int, *mut c_void) -> c_int;
+/// mpv_request_log_messages: 指定レベル以上のログを MPV_EVENT_LOG_MESSAGE で受け取る
+type MpvRequestLogMessagesFn = unsafe extern "C" fn(MpvHandle, *const c_char) -> c_int;
+
+/// libmpv の render context ハンドル型
+type MpvRenderContext = *mut c_void;
+/// mpv_render_context_create: render context を作成する（vo=libmpv が必要）
+type MpvRenderContextCreateFn =
+    unsafe extern "C" fn(*mut MpvRenderContext, MpvHandle, *mut MpvRenderParam) -> c_int;
+/// mpv_render_context_render: 現在のフレームを描画する
+type MpvRenderContextRenderFn =
+    unsafe extern "C" fn(MpvRenderContext, *mut MpvRenderParam) -> c_int;
+/// mpv_render_context_update: 更新フラグを取得する（MPV_RENDER_UPDATE_FRAME など）
+type MpvRenderContextUpdateFn = unsafe extern "C" fn(MpvRenderContext) -> u64;
+/// 新しいフレームが描画可能になった時に mpv のスレッドから呼ばれるコールバック
+type MpvRenderUpdateCallback = unsafe extern "C" fn(*mut c_void);
+/// mpv_render_context_set_update_callback: 更新コールバックを登録する
+type MpvRenderContextSetUpdateCallbackFn =
+    unsafe extern "C" fn(MpvRenderContext, Option<MpvRenderUpdateCallback>, *mut c_void);
+/// mpv_render_context_free: render context を破棄する（terminate_destroy より前に呼ぶ）
+type MpvRenderContextFreeFn = unsafe extern "C" fn(MpvRenderContext);
+
+// mpv_render_param_type
+const MPV_RENDER_PARAM_INVALID: c_int = 0;
+const MPV_RENDER_PARAM_API_TYPE: c_int = 1;
+const MPV_RENDER_PARAM_SW_SIZE: c_int = 17;
+const MPV_RENDER_PARAM_SW_FORMAT: c_int = 18;
+const MPV_RENDER_PARAM_SW_STRIDE: c_int = 19;
+const MPV_RENDER_PARAM_SW_POINTER: c_int = 20;
+/// mpv_render_update_flag
+const MPV_RENDER_UPDATE_FRAME: u64 = 1;
+
+/// mpv_render_param
+#[repr(C)]
+struct MpvRenderParam {
+    param_type: c_int,
+    data: *mut c_void,
+}
+
+// mpv_format
+const MPV_FORMAT_FLAG: c_int = 3;
+const MPV_FORMAT_INT64: c_int = 4;
+const MPV_FORMAT_DOUBLE: c_int = 5;
+
+// mpv_event_id
+const MPV_EVENT_SHUTDOWN: c_int = 1;
+const MPV_EVENT_LOG_MESSAGE: c_int = 2;
+const MPV_EVENT_END_FILE: c_int = 7;
+const MPV_EVENT_FILE_LOADED: c_int = 8;
+const MPV_EVENT_VIDEO_RECONFIG: c_int = 17;
+const MPV_EVENT_PLAYBACK_RESTART: c_int = 21;
+const MPV_EVENT_PROPERTY_CHANGE: c_int = 22;
+
+/// mpv_event
+#[repr(C)]
+struct MpvEvent {
+    event_id: c_int,
+    error: c_int,
+    reply_userdata: u64,
+    data: *mut c_void,
+}
+
+/// mpv_event_property（MPV_EVENT_PROPERTY_CHANGE の data）
+#[repr(C)]
+struct MpvEventProperty {
+    name: *const c_char,
+    format: c_int,
+    data: *mut c_void,
+}
+
+/// mpv_event_log_message（MPV_EVENT_LOG_MESSAGE の data）
+#[repr(C)]
+struct MpvEventLogMessage {
+    prefix: *const c_char,
+    level: *const c_char,
+    text: *const c_char,
+    log_level: c_int,
+}
+
+/// mpv_event_end_file（MPV_EVENT_END_FILE の data）
+#[repr(C)]
+struct MpvEventEndFile {
+    reason: c_int,
+    error: c_int,
+}
 
 // ─────────────────────────────────────────────────────────────────────────────
 // libmpv ライブラリラッパー
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// プラットフォームごとの libmpv ファイル名（優先順）
+#[cfg(windows)]
+const MPV_LIB_NAMES: &[&str] = &["libmpv-2.dll"];
+#[cfg(target_os = "macos")]
+const MPV_LIB_NAMES: &[&str] = &["libmpv.2.dylib", "libmpv.dylib"];
+#[cfg(all(unix, not(target_os = "macos")))]
+const MPV_LIB_NAMES: &[&str] = &["libmpv.so.2", "libmpv.so.1", "libmpv.so"];
+
+#[cfg(windows)]
+const MPV_INSTALL_HINT: &str =
+    "Please ensure mpv DLLs are in src-tauri/bin (dev) or bundled resources/bin (release).";
+#[cfg(target_os = "macos")]
+const MPV_INSTALL_HINT: &str = "Please install mpv (e.g. `brew install mpv`) or bundle libmpv.";
+#[cfg(all(unix, not(target_os = "macos")))]
+const MPV_INSTALL_HINT: &str =
+    "Please install libmpv (e.g. `apt install libmpv2` / `dnf install mpv-libs`).";
+
 /// 動的ロードした libmpv ライブラリと関数ポインタを保持する構造体
 struct MpvLib {
     _lib: Library,
@@ -52,6 +174,19 @@ struct MpvLib {
     command: MpvCommandFn,
     set_option_string: MpvSetOptionStringFn,
     terminate_destroy: MpvTerminateDestroyFn,
+    get_property_string: MpvGetPropertyStringFn,
+    free: MpvFreeFn,
+    create_client: MpvCreateClientFn,
+    destroy: MpvDestroyFn,
+    wait_event: MpvWaitEventFn,
+    observe_property: MpvObservePropertyFn,
+    get_property: MpvGetPropertyFn,
+    request_log_messages: MpvRequestLogMessagesFn,
+    render_context_create: MpvRenderContextCreateFn,
+    render_context_render: MpvRenderContextRenderFn,
+    render_context_update: MpvRenderContextUpdateFn,
+    render_context_set_update_callback: MpvRenderContextSetUpdateCallbackFn,
+    render_context_free: MpvRenderContextFreeFn,
 }
 
 // libmpv はスレッドセーフなので Send/Sync を実装
@@ -84,32 +219,40 @@ impl MpvLib {
         };
     }
 
-    /// libmpv-2.dll を複数のパスから順番に探してロードする。
+    /// libmpv を複数のパスから順番に探してロードする。
     ///
-    /// 検索順序:
+    /// 検索順序（ライブラリ名ごと）:
     ///   1. `<resource_dir>/bin/` (リリースバンドル)
     ///   2. `<resource_dir>/`
     ///   3. `<exe_dir>/bin/`
     ///   4. `<exe_dir>/resources/bin/`
-    ///   5. カレントディレクトリ（システムパス）
+    ///   5. macOS のみ: `<exe_dir>/../Frameworks/`, Homebrew (`/opt/homebrew/lib`, `/usr/local/lib`)
+    ///   6. ライブラリ名のみ（システムの検索パス）
     fn load(app: &AppHandle) -> Result<Self, String> {
-        const MPV_DLL_NAME: &str = "libmpv-2.dll";
-
-        // release bundle では resources/bin に配置されるため、resource_dir を優先する
         let mut candidates: Vec<PathBuf> = Vec::new();
-        if let Ok(resource_dir) = app.path().resource_dir() {
-            candidates.push(resource_dir.join("bin").join(MPV_DLL_NAME));
-            candidates.push(resource_dir.join(MPV_DLL_NAME));
-        }
+        for name in MPV_LIB_NAMES {
+            // release bundle では resources/bin に配置されるため、resource_dir を優先する
+            if let Ok(resource_dir) = app.path().resource_dir() {
+                candidates.push(resource_dir.join("bin").join(name));
+                candidates.push(resource_dir.join(name));
+            }
 
-        if let Ok(current_exe) = std::env::current_exe() {
-            if let Some(exe_dir) = current_exe.parent() {
-                candidates.push(exe_dir.join("bin").join(MPV_DLL_NAME));
-                candidates.push(exe_dir.join("resources").join("bin").join(MPV_DLL_NAME));
+            if let Ok(current_exe) = std::env::current_exe() {
+                if let Some(exe_dir) = current_exe.parent() {
+                    candidates.push(exe_dir.join("bin").join(name));
+                    candidates.push(exe_dir.join("resources").join("bin").join(name));
+                    #[cfg(target_os = "macos")]
+                    candidates.push(exe_dir.join("../Frameworks").join(name));
+                }
             }
-        }
 
-        candidates.push(PathBuf::from(MPV_DLL_NAME));
+            #[cfg(target_os = "macos")]
+            for dir in ["/opt/homebrew/lib", "/usr/local/lib"] {
+                candidates.push(Path::new(dir).join(name));
+            }
+
+            candidates.push(PathBuf::from(name));
+        }
 
         let mut tried: Vec<String> = Vec::new();
         let mut last_err: Option<String> = None;
@@ -141,6 +284,39 @@ impl MpvLib {
                 unsafe { Self::load_sym::<MpvSetOptionStringFn>(&lib, b"mpv_set_option_string")? };
             let terminate_destroy =
                 unsafe { Self::load_sym::<MpvTerminateDestroyFn>(&lib, b"mpv_terminate_destroy")? };
+            let get_property_string = unsafe {
+                Self::load_sym::<MpvGetPropertyStringFn>(&lib, b"mpv_get_property_string")?
+            };
+            let free = unsafe { Self::load_sym::<MpvFreeFn>(&lib, b"mpv_free")? };
+            let create_client =
+                unsafe { Self::load_sym::<MpvCreateClientFn>(&lib, b"mpv_create_client")? };
+            let destroy = unsafe { Self::load_sym::<MpvDestroyFn>(&lib, b"mpv_destroy")? };
+            let wait_event = unsafe { Self::load_sym::<MpvWaitEventFn>(&lib, b"mpv_wait_event")? };
+            let observe_property =
+                unsafe { Self::load_sym::<MpvObservePropertyFn>(&lib, b"mpv_observe_property")? };
+            let get_property =
+                unsafe { Self::load_sym::<MpvGetPropertyFn>(&lib, b"mpv_get_property")? };
+            let request_log_messages = unsafe {
+                Self::load_sym::<MpvRequestLogMessagesFn>(&lib, b"mpv_request_log_messages")?
+            };
+            let render_context_create = unsafe {
+                Self::load_sym::<MpvRenderContextCreateFn>(&lib, b"mpv_render_context_create")?
+            };
+            let render_context_render = unsafe {
+                Self::load_sym::<MpvRenderContextRenderFn>(&lib, b"mpv_render_context_render")?
+            };
+            let render_context_update = unsafe {
+                Self::load_sym::<MpvRenderContextUpdateFn>(&lib, b"mpv_render_context_update")?
+            };
+            let render_context_set_update_callback = unsafe {
+                Self::load_sym::<MpvRenderContextSetUpdateCallbackFn>(
+                    &lib,
+                    b"mpv_render_context_set_update_callback",
+                )?
+            };
+            let render_context_free = unsafe {
+                Self::load_sym::<MpvRenderContextFreeFn>(&lib, b"mpv_render_context_free")?
+            };
 
             return Ok(Self {
                 _lib: lib,
@@ -149,14 +325,28 @@ impl MpvLib {
                 command,
                 set_option_string,
                 terminate_destroy,
+                get_property_string,
+                free,
+                create_client,
+                destroy,
+                wait_event,
+                observe_property,
+                get_property,
+                request_log_messages,
+                render_context_create,
+                render_context_render,
+                render_context_update,
+                render_context_set_update_callback,
+                render_context_free,
             });
         }
 
         let last_err = last_err.unwrap_or_else(|| "Unknown error".to_string());
         Err(format!(
-            "Failed to load {MPV_DLL_NAME}. Tried: {}. Last error: {}. Please ensure mpv DLLs are in src-tauri/bin (dev) or bundled resources/bin (release).",
+            "Failed to load libmpv. Tried: {}. Last error: {}. {}",
             tried.join(", "),
-            last_err
+            last_err,
+            MPV_INSTALL_HINT
         ))
     }
 }
@@ -169,21 +359,32 @@ impl MpvLib {
 struct MpvPlayer {
     lib: Arc<MpvLib>,
     handle: MpvHandle,
+    /// ソフトウェアレンダリング時の描画スレッド（terminate_destroy より前に破棄する）
+    renderer: Option<MpvRenderer>,
 }
 
-// libmpv ハンドルはスレッドセーフ
+// libmpv ハンドルはスレッドセーフ（renderer の ctx は Drop でのみ使用する）
 unsafe impl Send for MpvPlayer {}
+unsafe impl Sync for MpvPlayer {}
 
 impl MpvPlayer {
     /// 新しい MPV インスタンスを生成する
     fn new(lib: Arc<MpvLib>) -> Result<Self, String> {
         unsafe {
+            // libmpv は LC_NUMERIC が "C" でないと mpv_create が失敗する（GTK が変更するため戻す）
+            #[cfg(unix)]
+            libc::setlocale(libc::LC_NUMERIC, c"C".as_ptr());
+
             let handle = (lib.create)();
             if handle.is_null() {
                 return Err("Failed to create mpv handle".to_string());
             }
 
-            Ok(Self { lib, handle })
+            Ok(Self {
+                lib,
+                handle,
+                renderer: None,
+            })
         }
     }
 
@@ -213,45 +414,49 @@ impl MpvPlayer {
     }
 
     /// MPV が作成したウィンドウの HWND を取得する（Windows のみ）。
-    /// ウィンドウのクラス名またはタイトルに "mpv" が含まれるものを探す。
-    fn get_window_handle(&self) -> Option<isize> {
+    /// プレイヤーごとに設定したウィンドウタイトルと一致するものを探す。
+    fn get_window_handle(&self, title: &str) -> Option<isize> {
         #[cfg(windows)]
         {
             use windows::core::BOOL;
             use windows::Win32::Foundation::{HWND, LPARAM};
-            use windows::Win32::UI::WindowsAndMessaging::{
-                EnumWindows, GetClassNameW, GetWindowTextW,
-            };
+            use windows::Win32::UI::WindowsAndMessaging::{EnumWindows, GetWindowTextW};
 
-            unsafe {
-                static mut FOUND_HWND: Option<isize> = None;
-                FOUND_HWND = None;
-
-                unsafe extern "system" fn enum_callback(hwnd: HWND, _lparam: LPARAM) -> BOOL {
-                    let mut title: [u16; 512] = [0; 512];
-                    let mut class: [u16; 512] = [0; 512];
-
-                    let _ = GetWindowTextW(hwnd, &mut title);
-                    let _ = GetClassNameW(hwnd, &mut class);
-
-                    let title_str = String::from_utf16_lossy(&title);
-                    let class_str = String::from_utf16_lossy(&class);
+            /// EnumWindows のコールバックへ LPARAM で渡す検索条件と結果
+            struct WindowSearch {
+                title: Vec<u16>,
+                found: Option<isize>,
+            }
 
-                    if class_str.contains("mpv") || title_str.contains("MPV-Tauri-Player") {
-                        FOUND_HWND = Some(hwnd.0 as isize);
-                        return BOOL(0); // 列挙を停止
-                    }
+            unsafe extern "system" fn enum_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+                let search = &mut *(lparam.0 as *mut WindowSearch);
+                let mut title: [u16; 512] = [0; 512];
+                let len = (GetWindowTextW(hwnd, &mut title).max(0) as usize).min(title.len());
 
-                    BOOL(1) // 列挙を継続
+                if title[..len] == search.title[..] {
+                    search.found = Some(hwnd.0 as isize);
+                    return BOOL(0); // 列挙を停止
                 }
 
-                let _ = EnumWindows(Some(enum_callback), LPARAM(0));
-                FOUND_HWND
+                BOOL(1) // 列挙を継続
+            }
+
+            let mut search = WindowSearch {
+                title: title.encode_utf16().collect(),
+                found: None,
+            };
+            unsafe {
+                let _ = EnumWindows(
+                    Some(enum_callback),
+                    LPARAM(&mut search as *mut WindowSearch as isize),
+                );
             }
+            search.found
         }
 
         #[cfg(not(windows))]
         {
+            let _ = title;
             None
         }
     }
@@ -272,42 +477,484 @@ impl MpvPlayer {
             Ok(())
         }
     }
+
+    /// プロパティを文字列で取得する（"time-pos", "demuxer-cache-duration" など）
+    fn get_property(&self, name: &str) -> Result<String, String> {
+        unsafe {
+            let name_c = CString::new(name).map_err(|e| e.to_string())?;
+            let value = (self.lib.get_property_string)(self.handle, name_c.as_ptr());
+            if value.is_null() {
+                return Err(format!("Failed to get property {}", name));
+            }
+            let result = CStr::from_ptr(value).to_string_lossy().into_owned();
+            (self.lib.free)(value as *mut c_void);
+            Ok(result)
+        }
+    }
 }
 
 impl Drop for MpvPlayer {
     /// ドロップ時に MPV インスタンスを正常終了させる
     fn drop(&mut self) {
+        // render context は terminate_destroy より前に解放する必要がある
+        self.renderer = None;
         unsafe {
             (self.lib.terminate_destroy)(self.handle);
         }
     }
 }
 
+// ─────────────────────────────────────────────────────────────────────────────
+// ソフトウェアレンダリング（Linux / macOS）
+// vo=libmpv + render API (MPV_RENDER_API_TYPE_SW) で CPU 上のバッファに描画し、
+// 最新フレームを get_mpv_frame でフロントエンドの canvas へ渡す。GPU 不要。
+// ─────────────────────────────────────────────────────────────────────────────
+
+const DEFAULT_RENDER_SIZE: (u32, u32) = (1280, 720);
+const MAX_RENDER_SIZE: (u32, u32) = (3840, 2160);
+/// mpv は stride を 64 バイト境界に揃えることを推奨するため、幅は 16 px 単位にする
+const RENDER_WIDTH_ALIGN: u32 = 16;
+
+/// 描画済みフレーム（RGBA、stride = width * 4）
+struct RenderedFrame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+/// フロントエンドが所有する描画先（canvas）のサイズと最新フレーム
+struct RenderSurface {
+    size: Mutex<(u32, u32)>,
+    frame: Mutex<Option<RenderedFrame>>,
+}
+
+impl Default for RenderSurface {
+    fn default() -> Self {
+        Self {
+            size: Mutex::new(DEFAULT_RENDER_SIZE),
+            frame: Mutex::new(None),
+        }
+    }
+}
+
+impl RenderSurface {
+    fn size(&self) -> (u32, u32) {
+        self.size.lock().map(|size| *size).unwrap_or(DEFAULT_RENDER_SIZE)
+    }
+
+    fn clear(&self) {
+        if let Ok(mut frame) = self.frame.lock() {
+            *frame = None;
+        }
+    }
+
+    /// 描画済みバッファを最新フレームと入れ替える（古いバッファは次の描画で再利用する）
+    fn swap_frame(&self, width: u32, height: u32, pixels: &mut Vec<u8>) {
+        let Ok(mut slot) = self.frame.lock() else {
+            return;
+        };
+        match slot.as_mut() {
+            Some(frame) => {
+                std::mem::swap(&mut frame.pixels, pixels);
+                frame.width = width;
+                frame.height = height;
+            }
+            None => {
+                *slot = Some(RenderedFrame {
+                    width,
+                    height,
+                    pixels: std::mem::take(pixels),
+                });
+            }
+        }
+    }
+}
+
+/// mpv://frame イベントの payload（ピクセルは get_mpv_frame で取得する）
+#[derive(Serialize, Clone)]
+struct FrameInfo {
+    width: u32,
+    height: u32,
+    seq: u64,
+}
+
+/// 描画スレッドへの通知フラグ
+#[derive(Default)]
+struct RenderWakeFlags {
+    /// mpv の更新コールバックが呼ばれた
+    update: bool,
+    /// 描画サイズの変更などで現在のフレームを描き直す
+    redraw: bool,
+    stop: bool,
+}
+
+#[derive(Default)]
+struct RenderWake {
+    flags: Mutex<RenderWakeFlags>,
+    cv: Condvar,
+}
+
+impl RenderWake {
+    fn notify(&self, set: impl FnOnce(&mut RenderWakeFlags)) {
+        if let Ok(mut flags) = self.flags.lock() {
+            set(&mut flags);
+            self.cv.notify_one();
+        }
+    }
+
+    /// いずれかのフラグが立つまで待ち、取り出す
+    fn wait(&self) -> RenderWakeFlags {
+        let Ok(mut flags) = self.flags.lock() else {
+            return RenderWakeFlags {
+                stop: true,
+                ..Default::default()
+            };
+        };
+        while !(flags.update || flags.redraw || flags.stop) {
+            flags = match self.cv.wait(flags) {
+                Ok(flags) => flags,
+                Err(_) => {
+                    return RenderWakeFlags {
+                        stop: true,
+                        ..Default::default()
+                    }
+                }
+            };
+        }
+        std::mem::take(&mut *flags)
+    }
+}
+
+/// mpv の内部スレッドから呼ばれる。mpv API は呼ばず、描画スレッドを起こすだけ。
+unsafe extern "C" fn on_render_update(data: *mut c_void) {
+    let wake = &*(data as *const RenderWake);
+    wake.notify(|flags| flags.update = true);
+}
+
+/// スレッド間で render context ハンドルを渡すためのラッパー
+struct RenderContextPtr(MpvRenderContext);
+
+// render context は描画スレッドからのみ使用し、解放はスレッド終了後に行う
+unsafe impl Send for RenderContextPtr {}
+
+/// render context と描画スレッド
+struct MpvRenderer {
+    lib: Arc<MpvLib>,
+    ctx: MpvRenderContext,
+    /// 更新コールバックの userdata（render context より長く生存させる）
+    wake: Arc<RenderWake>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+// ctx は Drop でのみ使用する
+unsafe impl Send for MpvRenderer {}
+
+impl MpvRenderer {
+    /// ソフトウェア render context を作成して描画スレッドを起動する（initialize 後、loadfile 前）
+    fn start(
+        player: &MpvPlayer,
+        app: AppHandle,
+        player_id: String,
+        surface: Arc<RenderSurface>,
+    ) -> Result<Self, String> {
+        let lib = player.lib.clone();
+        let mut params = [
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_API_TYPE,
+                data: c"sw".as_ptr() as *mut c_void,
+            },
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_INVALID,
+                data: ptr::null_mut(),
+            },
+        ];
+        let mut ctx: MpvRenderContext = ptr::null_mut();
+        let ret =
+            unsafe { (lib.render_context_create)(&mut ctx, player.handle, params.as_mut_ptr()) };
+        if ret < 0 || ctx.is_null() {
+            return Err(format!("Failed to create mpv render context: error code {}", ret));
+        }
+
+        let wake = Arc::new(RenderWake::default());
+        unsafe {
+            (lib.render_context_set_update_callback)(
+                ctx,
+                Some(on_render_update),
+                Arc::as_ptr(&wake) as *mut c_void,
+            );
+        }
+
+        let thread = {
+            let lib = lib.clone();
+            let wake = wake.clone();
+            let ctx = RenderContextPtr(ctx);
+            std::thread::Builder::new()
+                .name("mpv-render".to_string())
+                .spawn(move || render_loop(lib, ctx, wake, surface, app, player_id))
+        };
+        let thread = match thread {
+            Ok(thread) => thread,
+            Err(e) => {
+                unsafe { (lib.render_context_free)(ctx) };
+                return Err(format!("Failed to spawn mpv render thread: {}", e));
+            }
+        };
+
+        Ok(Self {
+            lib,
+            ctx,
+            wake,
+            thread: Some(thread),
+        })
+    }
+
+    /// 描画サイズ変更後などに現在のフレームを描き直させる
+    fn request_redraw(&self) {
+        self.wake.notify(|flags| flags.redraw = true);
+    }
+}
+
+impl Drop for MpvRenderer {
+    fn drop(&mut self) {
+        self.wake.notify(|flags| flags.stop = true);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        unsafe {
+            (self.lib.render_context_set_update_callback)(self.ctx, None, ptr::null_mut());
+            (self.lib.render_context_free)(self.ctx);
+        }
+    }
+}
+
+/// 描画スレッド本体。mpv のクライアント API は呼ばない（コアとのデッドロック回避）。
+fn render_loop(
+    lib: Arc<MpvLib>,
+    ctx: RenderContextPtr,
+    wake: Arc<RenderWake>,
+    surface: Arc<RenderSurface>,
+    app: AppHandle,
+    player_id: String,
+) {
+    let ctx = ctx.0;
+    let mut pixels: Vec<u8> = Vec::new();
+    let mut seq: u64 = 0;
+
+    loop {
+        let flags = wake.wait();
+        if flags.stop {
+            break;
+        }
+        let update = if flags.update {
+            unsafe { (lib.render_context_update)(ctx) }
+        } else {
+            0
+        };
+        if update & MPV_RENDER_UPDATE_FRAME == 0 && !flags.redraw {
+            continue;
+        }
+
+        let (width, height) = surface.size();
+        let mut stride = width as usize * 4;
+        pixels.resize(stride * height as usize, 0);
+        let mut sw_size = [width as c_int, height as c_int];
+        let mut params = [
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_SW_SIZE,
+                data: sw_size.as_mut_ptr() as *mut c_void,
+            },
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_SW_FORMAT,
+                data: c"rgb0".as_ptr() as *mut c_void,
+            },
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_SW_STRIDE,
+                data: &mut stride as *mut usize as *mut c_void,
+            },
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_SW_POINTER,
+                data: pixels.as_mut_ptr() as *mut c_void,
+            },
+            MpvRenderParam {
+                param_type: MPV_RENDER_PARAM_INVALID,
+                data: ptr::null_mut(),
+            },
+        ];
+        let ret = unsafe { (lib.render_context_render)(ctx, params.as_mut_ptr()) };
+        if ret < 0 {
+            eprintln!("mpv software render failed: error code {}", ret);
+            continue;
+        }
+
+        // rgb0 の 4 バイト目は未定義なので、canvas の ImageData 用に不透明にする
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 0xff;
+        }
+        seq += 1;
+        surface.swap_frame(width, height, &mut pixels);
+        emit_player_event(&app, "mpv://frame", &player_id, FrameInfo { width, height, seq });
+    }
+}
+
 // ─────────────────────────────────────────────────────────────────────────────
 // プレイヤー状態
 // ─────────────────────────────────────────────────────────────────────────────
 
+/// player_id を省略した場合のプレイヤー ID（従来の単一プレイヤー）
+pub(crate) const DEFAULT_PLAYER_ID: &str = "default";
+
 /// MPV プレイヤーマネージャー（Tauri の管理状態として登録される）
 #[derive(Default)]
 pub struct MpvPlayerManager {
-    state: Arc<Mutex<MpvPlayerState>>,
+    state: Arc<Mutex<MpvManagerState>>,
     /// ライブラリは遅延ロード（初回 start_mpv 時にロード）
     lib: Arc<Mutex<Option<Arc<MpvLib>>>>,
 }
 
+/// 全プレイヤーの状態（マルチビューでは複数のプレイヤーを同時に再生する）
+#[derive(Default)]
+struct MpvManagerState {
+    /// プレイヤー ID ごとの状態
+    players: HashMap<String, MpvPlayerState>,
+    /// 音声を出すプレイヤーの ID（それ以外のプレイヤーはミュートする）
+    audio_focus: Option<String>,
+    /// ユーザー操作で停止され、自動再生をブロック中のプレイヤー ID。
+    /// 停止時にプレイヤーの状態は削除するため、別に保持する
+    autoplay_blocked: HashSet<String>,
+}
+
+impl MpvManagerState {
+    /// 再生中プレイヤーを取得する
+    fn active_player(&self, player_id: &str) -> Result<&MpvPlayer, String> {
+        self.players
+            .get(player_id)
+            .and_then(|state| state.player.as_deref())
+            .ok_or_else(|| format!("MPV player not initialized: {}", player_id))
+    }
+
+    fn info(&self, player_id: &str) -> PlayerInfo {
+        let mut info = match self.players.get(player_id) {
+            Some(state) => PlayerInfo::from_state(player_id, state, self.audio_focus.as_deref()),
+            None => PlayerInfo::from_state(player_id, &MpvPlayerState::default(), None),
+        };
+        info.autoplay_blocked = self.autoplay_blocked.contains(player_id);
+        info
+    }
+
+    /// 音声フォーカスを移し、フォーカス以外のプレイヤーをミュートする
+    fn set_audio_focus(&mut self, focus: Option<String>) {
+        for (player_id, state) in &self.players {
+            if let Some(player) = &state.player {
+                let focused = focus.as_deref() == Some(player_id.as_str());
+                let _ = player.command(&["set", "mute", mpv_flag(state.muted || !focused)]);
+            }
+        }
+        self.audio_focus = focus;
+    }
+
+    /// フォーカス中のプレイヤーが停止した場合、再生中の別プレイヤーへフォーカスを移す
+    fn release_audio_focus(&mut self, player_id: &str) {
+        if self.audio_focus.as_deref() != Some(player_id) {
+            return;
+        }
+        let next = self
+            .players
+            .iter()
+            .filter(|(id, state)| id.as_str() != player_id && state.player.is_some())
+            .map(|(id, _)| id.clone())
+            .min();
+        self.set_audio_focus(next);
+    }
+}
+
+/// player_id を正規化する（省略時は DEFAULT_PLAYER_ID）。
+/// ウィンドウラベルに使うため英数字・'-'・'_' のみ許可する。
+fn normalize_player_id(player_id: Option<String>) -> Result<String, String> {
+    let Some(player_id) = player_id
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+    else {
+        return Ok(DEFAULT_PLAYER_ID.to_string());
+    };
+    if player_id.len() > 32
+        || !player_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!("Invalid player id: {}", player_id));
+    }
+    Ok(player_id)
+}
+
+/// プレイヤー ID に対応するプレイヤーウィンドウのラベル（既定プレイヤーは "player"）
+pub(crate) fn player_window_label(player_id: &str) -> String {
+    if player_id == DEFAULT_PLAYER_ID {
+        "player".to_string()
+    } else {
+        format!("player-{}", player_id)
+    }
+}
+
+/// プレイヤーウィンドウのラベルからプレイヤー ID を取り出す
+pub(crate) fn player_id_from_window_label(label: &str) -> Option<String> {
+    if label == "player" {
+        return Some(DEFAULT_PLAYER_ID.to_string());
+    }
+    label.strip_prefix("player-").map(str::to_string)
+}
+
+/// スタンドアロンモードの mpv ウィンドウタイトル（HWND の検索に使う）
+fn standalone_window_title(player_id: &str) -> String {
+    if player_id == DEFAULT_PLAYER_ID {
+        "MPV-Tauri-Player".to_string()
+    } else {
+        format!("MPV-Tauri-Player-{}", player_id)
+    }
+}
+
 /// MPV プレイヤーの内部状態
 struct MpvPlayerState {
-    player: Option<MpvPlayer>,
+    /// 時間のかかるコマンドはロックを外して実行できるよう Arc で保持する
+    player: Option<Arc<MpvPlayer>>,
     current_url: Option<String>,
     is_playing: bool,
     is_paused: bool,
-    autoplay_blocked: bool,
     /// 埋め込みホストウィンドウの HWND
     window_handle: Option<isize>,
     /// WebView への埋め込みモードかどうか
     embedded: bool,
+    /// render API で描画してフロントエンドへフレームを渡しているかどうか
+    software_render: bool,
     /// 埋め込み先の Tauri ウィンドウラベル
     window_label: Option<String>,
+    /// プレイヤーの世代番号（差し替え前のプレイヤーからのイベントを無視するため）
+    generation: u64,
+    /// ソフトウェアレンダリングの描画先（プレイヤーを差し替えてもサイズを維持する）
+    surface: Arc<RenderSurface>,
+    /// 音量（0〜130）。プレイヤーを差し替えても維持する
+    volume: f64,
+    /// ユーザーによるミュート（音声フォーカスによるミュートとは別）
+    muted: bool,
+    /// 映像の回転角度（0/90/180/270、縦長のモバイル配信向け）
+    rotation: u32,
+    speed: f64,
+    /// 適用中の再生プロファイル名
+    profile: Option<String>,
+}
+
+impl MpvPlayerState {
+    /// PlayerInfo に反映される値（変化の検出に使う）
+    fn observable(&self) -> (bool, bool, bool, f64, bool, u32, f64) {
+        (
+            self.is_playing,
+            self.is_paused,
+            self.current_url.is_some(),
+            self.volume,
+            self.muted,
+            self.rotation,
+            self.speed,
+        )
+    }
 }
 
 /// 埋め込みモード時にのみ HWND を取り出す（通常モードでは None を返す）
@@ -326,10 +973,17 @@ impl Default for MpvPlayerState {
             current_url: None,
             is_playing: false,
             is_paused: false,
-            autoplay_blocked: false,
             window_handle: None,
             embedded: false,
+            software_render: false,
             window_label: None,
+            generation: 0,
+            surface: Arc::default(),
+            volume: 100.0,
+            muted: false,
+            rotation: 0,
+            speed: 1.0,
+            profile: None,
         }
     }
 }
@@ -495,19 +1149,41 @@ fn focus_webview(app: &AppHandle, window_label: &str) {
 /// フロントエンドに送信するプレイヤー状態（Tauri イベント payload）
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PlayerInfo {
+    pub player_id: String,
     pub is_playing: bool,
     pub is_paused: bool,
     pub autoplay_blocked: bool,
     pub current_url: Option<String>,
+    /// true の場合、映像は mpv://frame / get_mpv_frame で canvas に描画する
+    pub software_render: bool,
+    /// 音声を出しているプレイヤーかどうか（false の場合はミュート中）
+    pub audio_focus: bool,
+    pub volume: f64,
+    /// ユーザーによるミュート（audio_focus=false によるミュートは含まない）
+    pub muted: bool,
+    pub rotation: u32,
+    pub speed: f64,
+    /// 適用中の再生プロファイル名（mpv_profile）
+    pub profile: Option<String>,
 }
 
 impl PlayerInfo {
-    fn from_state(state: &MpvPlayerState) -> Self {
+    /// autoplay_blocked はマネージャー側で管理するため false になる（MpvManagerState::info で補う）。
+    /// 再生中の世代のプレイヤーは起動時にブロックを解除済みなので、そのまま使ってよい。
+    fn from_state(player_id: &str, state: &MpvPlayerState, audio_focus: Option<&str>) -> Self {
         Self {
+            player_id: player_id.to_string(),
             is_playing: state.is_playing,
             is_paused: state.is_paused,
-            autoplay_blocked: state.autoplay_blocked,
+            autoplay_blocked: false,
             current_url: state.current_url.clone(),
+            software_render: state.software_render,
+            audio_focus: audio_focus == Some(player_id),
+            volume: state.volume,
+            muted: state.muted,
+            rotation: state.rotation,
+            speed: state.speed,
+            profile: state.profile.clone(),
         }
     }
 }
@@ -519,12 +1195,35 @@ fn emit_player_state(app: &AppHandle, info: PlayerInfo) {
     }
 }
 
-/// MPV の yes/no/true/false/0/1 形式の文字列を bool に変換する
-fn parse_mpv_bool(value: &str) -> Option<bool> {
-    match value.to_ascii_lowercase().as_str() {
-        "yes" | "true" | "1" | "on" => Some(true),
-        "no" | "false" | "0" | "off" => Some(false),
-        _ => None,
+/// player_id 付きのイベント payload（どのプレイヤーのイベントかを区別する）
+#[derive(Serialize, Clone)]
+struct PlayerEvent<T> {
+    player_id: String,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// player_id 以外のデータを持たないイベント用
+#[derive(Serialize, Clone)]
+struct NoPayload {}
+
+/// mpv:// の個別イベントを player_id 付きで emit する
+fn emit_player_event<T: Serialize + Clone>(app: &AppHandle, event: &str, player_id: &str, data: T) {
+    let payload = PlayerEvent {
+        player_id: player_id.to_string(),
+        data,
+    };
+    if let Err(err) = app.emit(event, payload) {
+        eprintln!("Failed to emit {}: {}", event, err);
+    }
+}
+
+/// bool を mpv のフラグ文字列に変換する
+fn mpv_flag(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
     }
 }
 
@@ -534,128 +1233,523 @@ fn should_block_autoplay(reason: Option<&str>) -> bool {
 }
 
 /// MPV コマンドの実行内容を元にプレイヤー状態を更新する。
+/// 一時停止などの実際の状態はイベントループ（mpv://property）で反映される。
 /// 状態が変化した場合は true を返す（イベント emit の判断に使用）。
 fn apply_command_to_state(state: &mut MpvPlayerState, args: &[String]) -> bool {
-    let cmd = args.get(0).map(|v| v.as_str()).unwrap_or_default();
-    let mut changed = false;
-    match cmd {
-        "stop" | "quit" => {
-            if state.is_playing || state.current_url.is_some() {
-                state.is_playing = false;
-                state.is_paused = false;
-                state.current_url = None;
-                changed = true;
-            }
-        }
-        "loadfile" => {
-            if let Some(url) = args.get(1) {
-                if state.current_url.as_deref() != Some(url) || !state.is_playing {
-                    state.current_url = Some(url.clone());
-                    state.is_playing = true;
-                    state.is_paused = false;
-                    changed = true;
-                }
-            }
-        }
-        "cycle" => {
-            // cycle pause: 一時停止の切り替え
-            if matches!(args.get(1).map(|v| v.as_str()), Some("pause")) {
-                state.is_paused = !state.is_paused;
-                changed = true;
-            }
-        }
-        "set" => {
-            // set pause yes/no: 一時停止の明示的な設定
-            if matches!(args.get(1).map(|v| v.as_str()), Some("pause")) {
-                if let Some(value) = args.get(2).and_then(|v| parse_mpv_bool(v)) {
-                    if state.is_paused != value {
-                        state.is_paused = value;
-                        changed = true;
-                    }
-                }
-            }
-        }
-        _ => {}
+    let stopped = args.first().is_some_and(|cmd| cmd == "stop");
+    if !stopped || (!state.is_playing && state.current_url.is_none()) {
+        return false;
     }
-    changed
+    state.is_playing = false;
+    state.is_paused = false;
+    state.current_url = None;
+    true
 }
 
 // ─────────────────────────────────────────────────────────────────────────────
-// MpvPlayerManager 実装
+// イベントループ
+// 専用のクライアントハンドルで mpv_wait_event を回し、実際の再生状態を
+// mpv:// イベントとしてフロントエンドへ通知する。
 // ─────────────────────────────────────────────────────────────────────────────
 
-impl MpvPlayerManager {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    /// libmpv ライブラリを遅延ロードして返す。
-    /// 既にロード済みの場合はキャッシュを返す。
-    fn ensure_lib(&self, app: &AppHandle) -> Result<Arc<MpvLib>, String> {
-        let mut lib_guard = self
-            .lib
-            .lock()
-            .map_err(|_| "Failed to lock mpv library state".to_string())?;
-
-        if lib_guard.is_none() {
-            *lib_guard = Some(Arc::new(MpvLib::load(app)?));
-        }
+/// 監視するプロパティと取得フォーマット（reply_userdata はインデックス）
+const OBSERVED_PROPERTIES: [(&str, c_int); 11] = [
+    ("pause", MPV_FORMAT_FLAG),
+    ("volume", MPV_FORMAT_DOUBLE),
+    ("speed", MPV_FORMAT_DOUBLE),
+    ("video-rotate", MPV_FORMAT_INT64),
+    ("time-pos", MPV_FORMAT_DOUBLE),
+    ("demuxer-cache-duration", MPV_FORMAT_DOUBLE),
+    ("paused-for-cache", MPV_FORMAT_FLAG),
+    ("core-idle", MPV_FORMAT_FLAG),
+    ("eof-reached", MPV_FORMAT_FLAG),
+    ("idle-active", MPV_FORMAT_FLAG),
+    ("seeking", MPV_FORMAT_FLAG),
+];
+/// 高頻度で変化するプロパティ（この間隔より短い通知は間引く）
+const THROTTLED_PROPERTIES: [&str; 2] = ["time-pos", "demuxer-cache-duration"];
+const PROPERTY_THROTTLE: Duration = Duration::from_millis(250);
+/// mpv_wait_event のタイムアウト（秒）。ログレベルの変更をこの間隔で反映する
+const EVENT_WAIT_TIMEOUT_SECS: f64 = 0.5;
+
+/// プロパティ値（mpv_format に対応）
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(untagged)]
+enum MpvValue {
+    Flag(bool),
+    Int(i64),
+    Double(f64),
+}
 
-        lib_guard
-            .as_ref()
-            .cloned()
-            .ok_or_else(|| "Failed to initialize mpv library".to_string())
-    }
+/// mpv://property イベントの payload
+#[derive(Serialize, Clone)]
+struct PropertyChange {
+    name: String,
+    /// プロパティが利用できない場合（再生前など）は None
+    value: Option<MpvValue>,
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// メインスレッド ディスパッチ
-// Win32 の多くの API はメインスレッドでのみ呼び出せるため、
-// 非同期タスクから呼ぶ際はこのユーティリティを経由する。
-// ─────────────────────────────────────────────────────────────────────────────
+/// mpv://end-file イベントの payload
+#[derive(Serialize, Clone)]
+struct EndFile {
+    /// "eof" / "stop" / "quit" / "error" / "redirect" / "unknown"
+    reason: &'static str,
+    /// reason が "error" の場合の mpv エラーコード
+    error: Option<i32>,
+}
 
-/// アプリ起動時にメインスレッドの ID を記録する（is_main_thread の判定に使用）
-static MAIN_THREAD_ID: OnceLock<ThreadId> = OnceLock::new();
+/// mpv://video-params イベントの payload（VIDEO_RECONFIG 時に取得）
+#[derive(Serialize, Clone)]
+struct VideoParams {
+    width: Option<i64>,
+    height: Option<i64>,
+    fps: Option<f64>,
+    rotate: Option<i64>,
+}
 
-pub fn init_main_thread_id() {
-    let _ = MAIN_THREAD_ID.set(std::thread::current().id());
+/// mpv://stall イベントの payload（paused-for-cache の変化）
+#[derive(Serialize, Clone)]
+struct StallEvent {
+    stalled: bool,
 }
 
-/// 現在のスレッドがメインスレッドかどうかを判定する
-fn is_main_thread() -> bool {
-    MAIN_THREAD_ID
-        .get()
-        .map(|id| *id == std::thread::current().id())
-        .unwrap_or(false)
+/// イベントスレッドから非同期タスクへ渡すメッセージ
+enum MpvEventMessage {
+    Property(PropertyChange),
+    FileLoaded,
+    EndFile(EndFile),
+    VideoParams(VideoParams),
+    PlaybackRestart,
+    Log(mpv_log::LogEntry),
+    Shutdown,
 }
 
-/// クロージャをメインスレッドで実行し、Result を返す。
-/// 既にメインスレッドにいる場合は直接実行する。
-fn run_on_main_thread_with_result<T: Send + 'static>(
-    app: &AppHandle,
-    f: impl FnOnce() -> Result<T, String> + Send + 'static,
-) -> Result<T, String> {
-    if is_main_thread() {
-        return f();
+fn end_file_reason(reason: c_int) -> &'static str {
+    match reason {
+        0 => "eof",
+        2 => "stop",
+        3 => "quit",
+        4 => "error",
+        5 => "redirect",
+        _ => "unknown",
     }
-    let (tx, rx) = std::sync::mpsc::channel();
-    app.run_on_main_thread(move || {
-        let _ = tx.send(f());
-    })
-    .map_err(|e| format!("Failed to run on main thread: {}", e))?;
-    rx.recv()
-        .map_err(|_| "Failed to receive main thread result".to_string())?
 }
 
-// ─────────────────────────────────────────────────────────────────────────────
-// Tauri コマンド
-// ─────────────────────────────────────────────────────────────────────────────
-
+/// イベント受信用のクライアントハンドル。
+/// 本体ハンドルの mpv_terminate_destroy で SHUTDOWN を受け取り、自身を破棄する。
+struct MpvEventClient {
+    lib: Arc<MpvLib>,
+    handle: MpvHandle,
+    /// mpv_request_log_messages で要求中のレベル
+    log_level: &'static str,
+}
+
+// クライアントハンドルはイベントスレッドだけが使用する
+unsafe impl Send for MpvEventClient {}
+
+impl Drop for MpvEventClient {
+    /// クライアントハンドルを破棄する（残っていると mpv_terminate_destroy が完了しない）。
+    /// スレッドの起動に失敗した場合も、クロージャと一緒にここで破棄される
+    fn drop(&mut self) {
+        unsafe {
+            (self.lib.destroy)(self.handle);
+        }
+    }
+}
+
+impl MpvEventClient {
+    /// SHUTDOWN を受け取るまでイベントを待ち続ける（専用スレッドで実行）
+    fn run(mut self, tx: mpsc::UnboundedSender<MpvEventMessage>) {
+        let mut last_emitted: HashMap<String, Instant> = HashMap::new();
+        loop {
+            self.request_log_messages(mpv_log::request_level());
+            // タイムアウト時は MPV_EVENT_NONE が返る。戻り値は常に非 NULL
+            let event = unsafe { &*(self.lib.wait_event)(self.handle, EVENT_WAIT_TIMEOUT_SECS) };
+            let message = match event.event_id {
+                MPV_EVENT_SHUTDOWN => {
+                    let _ = tx.send(MpvEventMessage::Shutdown);
+                    break;
+                }
+                MPV_EVENT_LOG_MESSAGE if !event.data.is_null() => {
+                    unsafe { Self::read_log_message(event.data) }.map(MpvEventMessage::Log)
+                }
+                MPV_EVENT_FILE_LOADED => Some(MpvEventMessage::FileLoaded),
+                MPV_EVENT_END_FILE if !event.data.is_null() => {
+                    let end = unsafe { &*(event.data as *const MpvEventEndFile) };
+                    let reason = end_file_reason(end.reason);
+                    Some(MpvEventMessage::EndFile(EndFile {
+                        reason,
+                        error: (reason == "error").then_some(end.error),
+                    }))
+                }
+                MPV_EVENT_VIDEO_RECONFIG => Some(MpvEventMessage::VideoParams(VideoParams {
+                    width: self.get_i64("width"),
+                    height: self.get_i64("height"),
+                    fps: self.get_f64("container-fps"),
+                    rotate: self.get_i64("video-params/rotate"),
+                })),
+                MPV_EVENT_PLAYBACK_RESTART => Some(MpvEventMessage::PlaybackRestart),
+                MPV_EVENT_PROPERTY_CHANGE if !event.data.is_null() => {
+                    let change = unsafe { Self::read_property(event.data) };
+                    change
+                        .filter(|change| Self::should_emit(&mut last_emitted, change))
+                        .map(MpvEventMessage::Property)
+                }
+                _ => None,
+            };
+            if let Some(message) = message {
+                let _ = tx.send(message);
+            }
+        }
+    }
+
+    /// mpv_event_property を読み取る
+    unsafe fn read_property(data: *mut c_void) -> Option<PropertyChange> {
+        let prop = &*(data as *const MpvEventProperty);
+        if prop.name.is_null() {
+            return None;
+        }
+        let name = CStr::from_ptr(prop.name).to_string_lossy().into_owned();
+        let value = if prop.data.is_null() {
+            None
+        } else {
+            match prop.format {
+                MPV_FORMAT_FLAG => Some(MpvValue::Flag(*(prop.data as *const c_int) != 0)),
+                MPV_FORMAT_INT64 => Some(MpvValue::Int(*(prop.data as *const i64))),
+                MPV_FORMAT_DOUBLE => Some(MpvValue::Double(*(prop.data as *const f64))),
+                _ => None,
+            }
+        };
+        Some(PropertyChange { name, value })
+    }
+
+    /// ログの要求レベルを変更する（現在と同じ場合は何もしない）
+    fn request_log_messages(&mut self, level: &'static str) {
+        if self.log_level == level {
+            return;
+        }
+        let Ok(level_c) = CString::new(level) else {
+            return;
+        };
+        let ret = unsafe { (self.lib.request_log_messages)(self.handle, level_c.as_ptr()) };
+        if ret < 0 {
+            eprintln!("mpv request_log_messages {} failed: error code {}", level, ret);
+        }
+        self.log_level = level;
+    }
+
+    /// mpv_event_log_message を読み取る（空行は None）
+    unsafe fn read_log_message(data: *mut c_void) -> Option<mpv_log::LogEntry> {
+        let message = &*(data as *const MpvEventLogMessage);
+        let read = |ptr: *const c_char| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(ptr).to_string_lossy().into_owned()
+            }
+        };
+        let text = read(message.text).trim_end().to_string();
+        if text.is_empty() {
+            return None;
+        }
+        Some(mpv_log::LogEntry {
+            prefix: read(message.prefix),
+            level: read(message.level),
+            text,
+        })
+    }
+
+    /// 高頻度プロパティの通知を間引く（値が無くなった通知は常に送る）
+    fn should_emit(last_emitted: &mut HashMap<String, Instant>, change: &PropertyChange) -> bool {
+        if !THROTTLED_PROPERTIES.contains(&change.name.as_str()) || change.value.is_none() {
+            return true;
+        }
+        let now = Instant::now();
+        match last_emitted.get(&change.name) {
+            Some(last) if now.duration_since(*last) < PROPERTY_THROTTLE => false,
+            _ => {
+                last_emitted.insert(change.name.clone(), now);
+                true
+            }
+        }
+    }
+
+    /// プロパティを指定フォーマットで取得する
+    fn get_raw<T: Default>(&self, name: &str, format: c_int) -> Option<T> {
+        let name_c = CString::new(name).ok()?;
+        let mut value = T::default();
+        let ret = unsafe {
+            (self.lib.get_property)(
+                self.handle,
+                name_c.as_ptr(),
+                format,
+                &mut value as *mut T as *mut c_void,
+            )
+        };
+        (ret >= 0).then_some(value)
+    }
+
+    fn get_i64(&self, name: &str) -> Option<i64> {
+        self.get_raw::<i64>(name, MPV_FORMAT_INT64)
+    }
+
+    fn get_f64(&self, name: &str) -> Option<f64> {
+        self.get_raw::<f64>(name, MPV_FORMAT_DOUBLE)
+    }
+}
+
+impl MpvPlayer {
+    /// イベント用クライアントを作成してプロパティを監視し、イベントスレッドを起動する。
+    /// generation はプレイヤー差し替え後に古いイベントを無視するために使う。
+    fn spawn_event_loop(
+        &self,
+        app: AppHandle,
+        player_id: String,
+        generation: u64,
+    ) -> Result<(), String> {
+        let client_name = CString::new("tauri-events").map_err(|e| e.to_string())?;
+        let handle = unsafe { (self.lib.create_client)(self.handle, client_name.as_ptr()) };
+        if handle.is_null() {
+            return Err("Failed to create mpv event client".to_string());
+        }
+        let mut client = MpvEventClient {
+            lib: self.lib.clone(),
+            handle,
+            log_level: "",
+        };
+        // loadfile 前のログも受け取れるよう、スレッド起動前に要求しておく
+        client.request_log_messages(mpv_log::request_level());
+
+        for (index, (name, format)) in OBSERVED_PROPERTIES.iter().enumerate() {
+            let name_c = CString::new(*name).map_err(|e| e.to_string())?;
+            let ret = unsafe {
+                (self.lib.observe_property)(handle, index as u64, name_c.as_ptr(), *format)
+            };
+            if ret < 0 {
+                eprintln!("mpv observe_property {} failed: error code {}", name, ret);
+            }
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::Builder::new()
+            .name("mpv-events".to_string())
+            .spawn(move || client.run(tx))
+            .map_err(|e| format!("Failed to spawn mpv event thread: {}", e))?;
+        tauri::async_runtime::spawn(forward_mpv_events(app, player_id, generation, rx));
+        Ok(())
+    }
+}
+
+/// イベントスレッドからのメッセージで状態を更新し、フロントエンドへ emit する。
+/// 状態の Mutex はここでのみ取得する（イベントスレッドは mpv_terminate_destroy の
+/// 完了待ちと競合しないよう、ロックを取らない）。
+async fn forward_mpv_events(
+    app: AppHandle,
+    player_id: String,
+    generation: u64,
+    mut rx: mpsc::UnboundedReceiver<MpvEventMessage>,
+) {
+    while let Some(message) = rx.recv().await {
+        // ログは状態に影響しないため、ロックを取らずに処理する（停止後のログも記録する）
+        if let MpvEventMessage::Log(entry) = &message {
+            forward_log(&app, &player_id, entry);
+            continue;
+        }
+
+        let manager = app.state::<MpvPlayerManager>();
+        let info = {
+            let Ok(mut guard) = manager.state.lock() else {
+                continue;
+            };
+            let manager_state = &mut *guard;
+            let Some(state) = manager_state.players.get_mut(&player_id) else {
+                continue;
+            };
+            if state.generation != generation {
+                // 差し替え・停止済みプレイヤーのイベント
+                continue;
+            }
+            apply_event_to_state(state, &message).then(|| {
+                PlayerInfo::from_state(&player_id, state, manager_state.audio_focus.as_deref())
+            })
+        };
+
+        let id = player_id.as_str();
+        match message {
+            MpvEventMessage::Property(change) => {
+                if let ("paused-for-cache", Some(MpvValue::Flag(stalled))) =
+                    (change.name.as_str(), change.value)
+                {
+                    emit_player_event(&app, "mpv://stall", id, StallEvent { stalled });
+                }
+                emit_player_event(&app, "mpv://property", id, change);
+            }
+            MpvEventMessage::FileLoaded => {
+                emit_player_event(&app, "mpv://file-loaded", id, NoPayload {})
+            }
+            MpvEventMessage::EndFile(end) => emit_player_event(&app, "mpv://end-file", id, end),
+            MpvEventMessage::VideoParams(params) => {
+                emit_player_event(&app, "mpv://video-params", id, params)
+            }
+            MpvEventMessage::PlaybackRestart => {
+                emit_player_event(&app, "mpv://playback-restart", id, NoPayload {})
+            }
+            MpvEventMessage::Shutdown => {
+                emit_player_event(&app, "mpv://shutdown", id, NoPayload {})
+            }
+            MpvEventMessage::Log(_) => {}
+        }
+        if let Some(info) = info {
+            emit_player_state(&app, info);
+        }
+    }
+}
+
+/// mpv のログを mpv.log に記録して mpv://log を emit する。
+/// 警告・エラー（デコーダーの失敗、デマクサの EOF など）は mpv://warning としても通知する。
+fn forward_log(app: &AppHandle, player_id: &str, entry: &mpv_log::LogEntry) {
+    if mpv_log::record(app, player_id, entry) {
+        emit_player_event(app, "mpv://log", player_id, entry.clone());
+    }
+    if let Some(warning) = mpv_log::classify(entry) {
+        emit_player_event(app, "mpv://warning", player_id, warning);
+    }
+}
+
+/// mpv イベントを元にプレイヤー状態を更新する。状態が変化した場合は true を返す。
+fn apply_event_to_state(state: &mut MpvPlayerState, message: &MpvEventMessage) -> bool {
+    let before = state.observable();
+    match message {
+        MpvEventMessage::Property(change) => match (change.name.as_str(), change.value) {
+            ("pause", Some(MpvValue::Flag(paused))) => state.is_paused = paused,
+            ("volume", Some(MpvValue::Double(volume))) => state.volume = volume,
+            ("speed", Some(MpvValue::Double(speed))) => state.speed = speed,
+            ("video-rotate", Some(MpvValue::Int(rotation))) => {
+                state.rotation = rotation.rem_euclid(360) as u32;
+            }
+            // keep-open=yes のため EOF では END_FILE ではなく eof-reached が立つ
+            ("eof-reached", Some(MpvValue::Flag(true))) => state.is_playing = false,
+            ("eof-reached", Some(MpvValue::Flag(false))) if state.current_url.is_some() => {
+                state.is_playing = true;
+            }
+            _ => {}
+        },
+        MpvEventMessage::FileLoaded => state.is_playing = state.current_url.is_some(),
+        MpvEventMessage::EndFile(end) if matches!(end.reason, "eof" | "error") => {
+            state.is_playing = false;
+            state.is_paused = false;
+        }
+        MpvEventMessage::Shutdown => {
+            // ユーザーが mpv ウィンドウを閉じた（q キーなど）
+            state.is_playing = false;
+            state.is_paused = false;
+            state.current_url = None;
+        }
+        _ => {}
+    }
+    before != state.observable()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// MpvPlayerManager 実装
+// ─────────────────────────────────────────────────────────────────────────────
+
+impl MpvPlayerManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// libmpv ライブラリを遅延ロードして返す。
+    /// 既にロード済みの場合はキャッシュを返す。
+    fn ensure_lib(&self, app: &AppHandle) -> Result<Arc<MpvLib>, String> {
+        let mut lib_guard = self
+            .lib
+            .lock()
+            .map_err(|_| "Failed to lock mpv library state".to_string())?;
+
+        if lib_guard.is_none() {
+            *lib_guard = Some(Arc::new(MpvLib::load(app)?));
+        }
+
+        lib_guard
+            .as_ref()
+            .cloned()
+            .ok_or_else(|| "Failed to initialize mpv library".to_string())
+    }
+
+    /// url を再生中のプレイヤー ID を返す（レイテンシ制御などバックエンド内部用）
+    pub(crate) fn player_for_url(&self, url: &str) -> Option<String> {
+        let state = self.state.lock().ok()?;
+        state
+            .players
+            .iter()
+            .find(|(_, player)| player.player.is_some() && player.current_url.as_deref() == Some(url))
+            .map(|(player_id, _)| player_id.clone())
+    }
+
+    /// 指定プレイヤーのプロパティを取得する（バックエンド内部用）
+    pub(crate) fn get_player_property(&self, player_id: &str, name: &str) -> Result<String, String> {
+        let state = self.state.lock().map_err(|e| e.to_string())?;
+        state.active_player(player_id)?.get_property(name)
+    }
+
+    /// 指定プレイヤーにコマンドを送信する（バックエンド内部用、状態の更新や emit は行わない）
+    pub(crate) fn player_command(&self, player_id: &str, args: &[&str]) -> Result<(), String> {
+        let state = self.state.lock().map_err(|e| e.to_string())?;
+        state.active_player(player_id)?.command(args)
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// メインスレッド ディスパッチ
+// Win32 の多くの API はメインスレッドでのみ呼び出せるため、
+// 非同期タスクから呼ぶ際はこのユーティリティを経由する。
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// アプリ起動時にメインスレッドの ID を記録する（is_main_thread の判定に使用）
+static MAIN_THREAD_ID: OnceLock<ThreadId> = OnceLock::new();
+
+pub fn init_main_thread_id() {
+    let _ = MAIN_THREAD_ID.set(std::thread::current().id());
+}
+
+/// 現在のスレッドがメインスレッドかどうかを判定する
+fn is_main_thread() -> bool {
+    MAIN_THREAD_ID
+        .get()
+        .map(|id| *id == std::thread::current().id())
+        .unwrap_or(false)
+}
+
+/// クロージャをメインスレッドで実行し、Result を返す。
+/// 既にメインスレッドにいる場合は直接実行する。
+fn run_on_main_thread_with_result<T: Send + 'static>(
+    app: &AppHandle,
+    f: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    if is_main_thread() {
+        return f();
+    }
+    let (tx, rx) = std::sync::mpsc::channel();
+    app.run_on_main_thread(move || {
+        let _ = tx.send(f());
+    })
+    .map_err(|e| format!("Failed to run on main thread: {}", e))?;
+    rx.recv()
+        .map_err(|_| "Failed to receive main thread result".to_string())?
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
 /// MPV プレイヤーを起動して指定 URL の動画を再生する。
 ///
 /// - embedded=true の場合は WebView ウィンドウに埋め込む（Windows 専用）
 /// - embedded=false の場合はスタンドアロンウィンドウで再生する
-/// - 既存のプレイヤーがある場合は停止してから新たに起動する
+/// - 同じ player_id の既存プレイヤーがある場合は停止してから新たに起動する
+/// - 他のプレイヤーが音声フォーカスを持っている場合はミュートで起動する
+/// - 再生プロファイルは再生ソースから自動選択する（明示指定は mpv_play を使う）
 #[tauri::command]
 pub async fn start_mpv(
     app: AppHandle,
@@ -664,19 +1758,83 @@ pub async fn start_mpv(
     embedded: Option<bool>,
     window_label: Option<String>,
     demuxer_format: Option<String>,
+    player_id: Option<String>,
 ) -> Result<(), String> {
+    let options = PlayOptions {
+        embedded,
+        window_label,
+        demuxer_format,
+        ..Default::default()
+    };
+    launch_player(app, &manager, url, options, player_id).await
+}
+
+/// start_mpv / mpv_play の本体。
+/// 再生プロファイルは options.profile > 配信ごとの上書き > 再生ソースからの自動選択 の順で決める。
+pub(crate) async fn launch_player(
+    app: AppHandle,
+    manager: &MpvPlayerManager,
+    url: String,
+    options: PlayOptions,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let PlayOptions {
+        embedded,
+        window_label,
+        demuxer_format,
+        profile,
+        live_id,
+    } = options;
+    let player_id = normalize_player_id(player_id)?;
+    let demuxer_format = demuxer_format
+        .as_deref()
+        .map(str::trim)
+        .filter(|fmt| !fmt.is_empty());
+
+    // URL が現在の llstream リレーのものであれば、そのモードからプロファイルを選ぶ
+    let relay_mode = app.state::<LlstreamRelayManager>().mode_for_url(&url).await;
+    let profile = mpv_profile::resolve_profile(
+        &app,
+        profile.as_deref(),
+        live_id.as_deref(),
+        relay_mode,
+        &url,
+        demuxer_format.is_some(),
+    )?;
+    eprintln!("mpv playback profile: {}", profile.name);
+
     let lib = manager.ensure_lib(&app)?;
 
     // 既存プレイヤーを停止して埋め込みウィンドウを破棄
-    let old_hwnd = {
-        let mut state = manager.state.lock().map_err(|e| e.to_string())?;
-        let hwnd = take_embed_hwnd(&mut state);
+    let (old_hwnd, generation, muted, surface, volume, rotation) = {
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        let manager_state = &mut *guard;
+        let state = manager_state.players.entry(player_id.clone()).or_default();
+        let hwnd = take_embed_hwnd(state);
         state.player = None;
         state.current_url = None;
         state.is_playing = false;
         state.embedded = false;
+        state.software_render = false;
         state.window_label = None;
-        hwnd
+        state.profile = None;
+        state.generation += 1;
+        state.speed = 1.0;
+        let (generation, surface) = (state.generation, state.surface.clone());
+        let (volume, rotation, user_muted) = (state.volume, state.rotation, state.muted);
+
+        // 音声フォーカスは再生中の別プレイヤーが持っていなければこのプレイヤーが取る
+        let focus_taken = manager_state.audio_focus.as_deref().is_some_and(|focus| {
+            focus != player_id
+                && manager_state
+                    .players
+                    .get(focus)
+                    .is_some_and(|other| other.player.is_some())
+        });
+        if !focus_taken {
+            manager_state.audio_focus = Some(player_id.clone());
+        }
+        (hwnd, generation, focus_taken || user_muted, surface, volume, rotation)
     };
     if let Some(hwnd) = old_hwnd {
         #[cfg(windows)]
@@ -692,8 +1850,11 @@ pub async fn start_mpv(
         .filter(|label| !label.is_empty())
         .unwrap_or("main");
 
+    // Windows 以外の埋め込みはウィンドウを持たず、render API で canvas 向けに描画する
+    let software_render = embedded && cfg!(not(windows));
+
     // 新しいプレイヤーを生成
-    let player = MpvPlayer::new(lib)?;
+    let mut player = MpvPlayer::new(lib)?;
     let mut created_embed_hwnd: Option<isize> = None;
 
     // エラー時にプレイヤーと埋め込みウィンドウを確実にクリーンアップするマクロ
@@ -713,8 +1874,14 @@ pub async fn start_mpv(
 
     // 映像出力バックエンドを選択する。
     // video-rotate が必要なため gpu を優先し、非対応の場合のみ direct3d にフォールバック。
+    // ソフトウェアレンダリングでは render API を使うため libmpv を指定する。
+    let vo_candidates: &[&str] = if software_render {
+        &["libmpv"]
+    } else {
+        &["gpu", "direct3d"]
+    };
     let mut selected_vo: Option<&str> = None;
-    for candidate in ["gpu", "direct3d"] {
+    for &candidate in vo_candidates {
         if player.set_option("vo", candidate).is_ok() {
             selected_vo = Some(candidate);
             break;
@@ -723,39 +1890,36 @@ pub async fn start_mpv(
     let selected_vo = selected_vo.ok_or_else(|| "Failed to configure mpv video output".to_string())?;
     eprintln!("mpv selected vo: {}", selected_vo);
 
-    // 共通オプションを設定
-    for (key, value) in [
-        ("hwdec", "no"),         // ハードウェアデコード無効（互換性のため）
-        ("keep-open", "yes"),    // 再生終了後もウィンドウを維持
-        ("border", "no"),        // ウィンドウ枠を非表示
-        ("msg-level", "all=v"),  // 詳細ログを有効化
-    ] {
-        player.set_option(key, value)?;
-    }
-
-    // ログファイルのパスを設定（exe と同階層、なければ一時ディレクトリ）
-    let log_path = std::env::current_exe()
-        .ok()
-        .and_then(|p| p.parent().map(|d| d.join("mirrativ-mpv.log")))
-        .unwrap_or_else(|| std::env::temp_dir().join("mirrativ-mpv.log"));
-    let log_path_str = log_path.to_string_lossy().to_string();
-    eprintln!("mpv log file: {}", log_path_str);
-    player.set_option("log-file", &log_path_str)?;
-
-    let demuxer_format = demuxer_format
-        .as_deref()
-        .map(str::trim)
-        .filter(|fmt| !fmt.is_empty());
+    // ウィンドウ枠を非表示
+    player.set_option("border", "no")?;
 
     if let Some(fmt) = demuxer_format {
         // Raw AnnexB over named pipe is extension-less, so force the demuxer format.
+        // キャッシュ・タイミングはどのプロファイルでもパイプ向けにする（プロファイルで上書き可）
         player.set_option("demuxer-lavf-format", fmt)?;
-        let _ = player.set_option("cache", "no");
-        let _ = player.set_option("demuxer-readahead-secs", "0");
-        let _ = player.set_option("untimed", "yes");
+        for (key, value) in mpv_profile::PIPE_OPTIONS {
+            let _ = player.set_option(key, value);
+        }
         eprintln!("mpv forced demuxer format: {}", fmt);
     }
 
+    // 再生プロファイルのオプションを設定（未対応のオプションはログに出して無視する）
+    for (key, value) in &profile.options {
+        if let Err(err) = player.set_option(key, value) {
+            eprintln!("mpv profile {}: {}", profile.name, err);
+        }
+    }
+
+    // 音量・ミュート・回転は同じ player_id の前回の設定を引き継ぐ
+    // （音声フォーカスを持たないプレイヤーはミュートで起動する）
+    for (key, value) in [
+        ("volume", format!("{}", volume)),
+        ("mute", mpv_flag(muted).to_string()),
+        ("video-rotate", rotation.to_string()),
+    ] {
+        player.set_option(key, &value)?;
+    }
+
     let mut window_handle = None;
     if embedded {
         #[cfg(windows)]
@@ -779,13 +1943,10 @@ pub async fn start_mpv(
             }
             window_handle = Some(host_hwnd);
         }
-        #[cfg(not(windows))]
-        {
-            return Err("Embedded mode is only supported on Windows".to_string());
-        }
+        // Windows 以外では initialize 後に render context を作成する（window_handle は None）
     } else {
         // スタンドアロンモード: 独立ウィンドウで常に最前面表示
-        player.set_option("title", "MPV-Tauri-Player")?;
+        player.set_option("title", &standalone_window_title(&player_id))?;
         player.set_option("ontop", "yes")?;
     }
 
@@ -794,6 +1955,20 @@ pub async fn start_mpv(
         cleanup_and_return!(err);
     }
 
+    // loadfile 前にイベントループを開始して FILE_LOADED などを取りこぼさない
+    if let Err(err) = player.spawn_event_loop(app.clone(), player_id.clone(), generation) {
+        cleanup_and_return!(err);
+    }
+
+    // vo=libmpv は映像出力の初期化前に render context が必要なため、loadfile 前に作成する
+    if software_render {
+        surface.clear();
+        match MpvRenderer::start(&player, app.clone(), player_id.clone(), surface) {
+            Ok(renderer) => player.renderer = Some(renderer),
+            Err(err) => cleanup_and_return!(err),
+        }
+    }
+
     if let Err(err) = player.command(&["loadfile", &url]) {
         cleanup_and_return!(err);
     }
@@ -802,7 +1977,8 @@ pub async fn start_mpv(
         // スタンドアロンモードではウィンドウ作成を待ってから HWND を取得
         tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
 
-        let mut mpv_window_handle = player.get_window_handle();
+        let window_title = standalone_window_title(&player_id);
+        let mut mpv_window_handle = player.get_window_handle(&window_title);
 
         eprintln!("MPV window handle: {:?}", mpv_window_handle);
         if mpv_window_handle.is_none() {
@@ -810,7 +1986,7 @@ pub async fn start_mpv(
             // ウィンドウ作成に時間がかかる場合があるので数回リトライ
             for i in 1..=3 {
                 tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-                mpv_window_handle = player.get_window_handle();
+                mpv_window_handle = player.get_window_handle(&window_title);
                 eprintln!("Retry {} - MPV window handle: {:?}", i, mpv_window_handle);
                 if mpv_window_handle.is_some() {
                     break;
@@ -822,16 +1998,27 @@ pub async fn start_mpv(
 
     // 状態を更新してフロントエンドに通知
     let info = {
-        let mut state = manager.state.lock().map_err(|e| e.to_string())?;
-        state.player = Some(player);
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        let Some(state) = guard
+            .players
+            .get_mut(&player_id)
+            .filter(|state| state.generation == generation)
+        else {
+            // 起動中に同じ player_id で停止・再起動された
+            drop(guard);
+            cleanup_and_return!("MPV player was replaced during startup".to_string());
+        };
+        state.player = Some(Arc::new(player));
         state.current_url = Some(url);
         state.is_playing = true;
         state.is_paused = false;
-        state.autoplay_blocked = false;
         state.window_handle = window_handle;
         state.embedded = embedded;
+        state.software_render = software_render;
+        state.profile = Some(profile.name);
         state.window_label = Some(target_window.to_string());
-        PlayerInfo::from_state(&state)
+        guard.autoplay_blocked.remove(&player_id);
+        guard.info(&player_id)
     };
     emit_player_state(&app, info);
 
@@ -846,28 +2033,39 @@ pub async fn start_mpv(
     Ok(())
 }
 
-/// MPV プレイヤーを停止して状態をリセットする。
+/// MPV プレイヤーを停止して状態を削除する（未知の player_id はエラー）。
 /// reason が "user" または "window-close" の場合は自動再生をブロックする。
+/// 音声フォーカスを持っていた場合は再生中の別プレイヤーへ移す。
 #[tauri::command]
 pub async fn stop_mpv(
     app: AppHandle,
     manager: tauri::State<'_, MpvPlayerManager>,
     reason: Option<String>,
+    player_id: Option<String>,
 ) -> Result<(), String> {
-    let (player, old_hwnd, info) = {
-        let mut state = manager.state.lock().map_err(|e| e.to_string())?;
+    let player_id = normalize_player_id(player_id)?;
+    let (player, old_hwnd, infos) = {
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        // 削除により起動中の start_mpv は世代の不一致として扱われる
+        let mut state = guard
+            .players
+            .remove(&player_id)
+            .ok_or_else(|| format!("Unknown player: {}", player_id))?;
         let player = state.player.take();
         let hwnd = take_embed_hwnd(&mut state);
-        state.current_url = None;
-        state.is_playing = false;
-        state.is_paused = false;
         if should_block_autoplay(reason.as_deref()) {
-            state.autoplay_blocked = true;
+            guard.autoplay_blocked.insert(player_id.clone());
         }
-        state.embedded = false;
-        state.window_label = None;
-        let info = PlayerInfo::from_state(&state);
-        (player, hwnd, info)
+
+        let previous_focus = guard.audio_focus.clone();
+        guard.release_audio_focus(&player_id);
+        let mut infos = vec![guard.info(&player_id)];
+        if let Some(focus) = guard.audio_focus.as_deref() {
+            if previous_focus.as_deref() != Some(focus) {
+                infos.push(guard.info(focus));
+            }
+        }
+        (player, hwnd, infos)
     };
 
     if let Some(player) = player {
@@ -881,35 +2079,38 @@ pub async fn stop_mpv(
         }
     }
 
-    emit_player_state(&app, info);
+    for info in infos {
+        emit_player_state(&app, info);
+    }
     Ok(())
 }
 
-/// MPV にコマンドを送信する（volume, pause, video-rotate などの制御）。
+/// MPV に生のコマンドを送信する（RAW_COMMANDS / RAW_PROPERTIES の許可リスト内のみ）。
+/// 通常の操作は mpv_pause / mpv_set_volume などの型付きコマンドを使う。
 /// 状態が変化した場合のみ mpv://state イベントを emit する。
 #[tauri::command]
 pub async fn mpv_command(
     app: AppHandle,
     manager: tauri::State<'_, MpvPlayerManager>,
     args: Vec<String>,
+    player_id: Option<String>,
 ) -> Result<(), String> {
     if args.is_empty() {
         return Err("No command arguments provided".to_string());
     }
+    check_raw_command(&args)?;
+    let player_id = normalize_player_id(player_id)?;
 
     let refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
     let (info, changed) = {
-        let mut state = manager.state.lock().map_err(|e| e.to_string())?;
-        let player = state
-            .player
-            .as_ref()
-            .ok_or_else(|| "MPV player not initialized".to_string())?;
-
-        player.command(&refs)?;
-
-        let changed = apply_command_to_state(&mut state, &args);
-        let info = PlayerInfo::from_state(&state);
-        (info, changed)
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        guard.active_player(&player_id)?.command(&refs)?;
+
+        let changed = guard
+            .players
+            .get_mut(&player_id)
+            .is_some_and(|state| apply_command_to_state(state, &args));
+        (guard.info(&player_id), changed)
     };
 
     if changed {
@@ -922,20 +2123,131 @@ pub async fn mpv_command(
 #[tauri::command]
 pub async fn get_player_info(
     manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
 ) -> Result<PlayerInfo, String> {
+    let player_id = normalize_player_id(player_id)?;
     let state = manager.state.lock().map_err(|e| e.to_string())?;
 
-    Ok(PlayerInfo::from_state(&state))
+    Ok(state.info(&player_id))
+}
+
+/// 再生中の全プレイヤーの状態を取得する（マルチビューの一覧表示用、ID 順）
+#[tauri::command]
+pub async fn list_players(
+    manager: tauri::State<'_, MpvPlayerManager>,
+) -> Result<Vec<PlayerInfo>, String> {
+    let state = manager.state.lock().map_err(|e| e.to_string())?;
+    let mut ids: Vec<&String> = state
+        .players
+        .iter()
+        .filter(|(_, player)| player.player.is_some())
+        .map(|(id, _)| id)
+        .collect();
+    ids.sort();
+
+    Ok(ids.into_iter().map(|id| state.info(id)).collect())
+}
+
+/// 音声フォーカスを指定プレイヤーへ移す（他のプレイヤーはすべてミュートになる）
+#[tauri::command]
+pub async fn set_audio_focus(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let player_id = normalize_player_id(player_id)?;
+    let infos = {
+        let mut state = manager.state.lock().map_err(|e| e.to_string())?;
+        state.active_player(&player_id)?;
+        let previous = state.audio_focus.clone();
+        state.set_audio_focus(Some(player_id.clone()));
+
+        let mut infos = vec![state.info(&player_id)];
+        if let Some(previous) = previous.filter(|previous| *previous != player_id) {
+            infos.push(state.info(&previous));
+        }
+        infos
+    };
+
+    for info in infos {
+        emit_player_state(&app, info);
+    }
+    Ok(())
+}
+
+/// ソフトウェアレンダリングの描画サイズ（canvas のピクセルサイズ）を設定する。
+/// 幅は 16 px 単位に切り下げる。再生中であれば現在のフレームを描き直す。
+#[tauri::command]
+pub async fn set_mpv_render_size(
+    manager: tauri::State<'_, MpvPlayerManager>,
+    width: u32,
+    height: u32,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let player_id = normalize_player_id(player_id)?;
+    let width = (width.min(MAX_RENDER_SIZE.0) / RENDER_WIDTH_ALIGN * RENDER_WIDTH_ALIGN)
+        .max(RENDER_WIDTH_ALIGN);
+    let height = height.clamp(1, MAX_RENDER_SIZE.1);
+
+    let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+    let state = guard
+        .players
+        .get_mut(&player_id)
+        .ok_or_else(|| format!("Unknown player: {}", player_id))?;
+    *state.surface.size.lock().map_err(|e| e.to_string())? = (width, height);
+    if let Some(renderer) = state.player.as_ref().and_then(|p| p.renderer.as_ref()) {
+        renderer.request_redraw();
+    }
+    Ok(())
+}
+
+/// ソフトウェアレンダリングの最新フレームを取得する。
+/// 先頭 8 バイトが幅・高さ（u32 LE）、続いて RGBA ピクセル（stride = 幅 * 4）。
+#[tauri::command]
+pub async fn get_mpv_frame(
+    manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
+) -> Result<tauri::ipc::Response, String> {
+    let player_id = normalize_player_id(player_id)?;
+    let surface = {
+        let state = manager.state.lock().map_err(|e| e.to_string())?;
+        state
+            .players
+            .get(&player_id)
+            .map(|player| player.surface.clone())
+            .ok_or_else(|| format!("MPV player not initialized: {}", player_id))?
+    };
+    let frame = surface.frame.lock().map_err(|e| e.to_string())?;
+    let frame = frame
+        .as_ref()
+        .ok_or_else(|| "No frame rendered yet".to_string())?;
+
+    let mut data = Vec::with_capacity(8 + frame.pixels.len());
+    data.extend_from_slice(&frame.width.to_le_bytes());
+    data.extend_from_slice(&frame.height.to_le_bytes());
+    data.extend_from_slice(&frame.pixels);
+    Ok(tauri::ipc::Response::new(data))
 }
 
 /// プレイヤー用ウィンドウを作成する。既に存在する場合は前面に出す。
+/// 既定プレイヤーは "player"、それ以外は "player-{id}" ラベルのウィンドウを使う。
 #[tauri::command]
-pub async fn create_player_window(app: AppHandle) -> Result<String, String> {
-    let window = if let Some(window) = app.get_webview_window("player") {
+pub async fn create_player_window(
+    app: AppHandle,
+    player_id: Option<String>,
+) -> Result<String, String> {
+    let player_id = normalize_player_id(player_id)?;
+    let label = player_window_label(&player_id);
+    let window = if let Some(window) = app.get_webview_window(&label) {
         window
     } else {
-        WebviewWindowBuilder::new(&app, "player", WebviewUrl::App("player".into()))
-            .title("Player")
+        let (path, title) = if player_id == DEFAULT_PLAYER_ID {
+            ("player".to_string(), "Player".to_string())
+        } else {
+            (format!("player?id={}", player_id), format!("Player - {}", player_id))
+        };
+        WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(path.into()))
+            .title(&title)
             .inner_size(960.0, 540.0)
             .min_inner_size(480.0, 270.0)
             .resizable(true)
@@ -946,7 +2258,7 @@ pub async fn create_player_window(app: AppHandle) -> Result<String, String> {
     window.show().map_err(|e| e.to_string())?;
     window.set_focus().map_err(|e| e.to_string())?;
 
-    Ok("player".to_string())
+    Ok(label)
 }
 
 /// プレイヤーを停止してウィンドウを閉じる
@@ -954,9 +2266,13 @@ pub async fn create_player_window(app: AppHandle) -> Result<String, String> {
 pub async fn close_player_window(
     app: AppHandle,
     manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
 ) -> Result<(), String> {
-    let _ = stop_mpv(app.clone(), manager, Some("window-close".to_string())).await;
-    if let Some(window) = app.get_webview_window("player") {
+    let player_id = normalize_player_id(player_id)?;
+    let label = player_window_label(&player_id);
+    let reason = Some("window-close".to_string());
+    let _ = stop_mpv(app.clone(), manager, reason, Some(player_id)).await;
+    if let Some(window) = app.get_webview_window(&label) {
         let _ = window.close();
     }
     Ok(())
@@ -972,9 +2288,15 @@ pub async fn position_mpv_window(
     y: i32,
     width: i32,
     height: i32,
+    player_id: Option<String>,
 ) -> Result<(), String> {
+    let player_id = normalize_player_id(player_id)?;
     let (mpv_hwnd, embedded, anchor_label) = {
-        let state = manager.state.lock().map_err(|e| e.to_string())?;
+        let guard = manager.state.lock().map_err(|e| e.to_string())?;
+        let state = guard
+            .players
+            .get(&player_id)
+            .ok_or_else(|| "MPV window not found".to_string())?;
         let hwnd = state
             .window_handle
             .ok_or_else(|| "MPV window not found".to_string())?;
@@ -1034,3 +2356,439 @@ pub async fn position_mpv_window(
 
     Ok(())
 }
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 型付きプレイヤー操作（Tauri コマンド）
+// 入力を検証してから mpv に送り、PlayerInfo を更新して mpv://state を emit する。
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// mpv_command で許可するコマンド。
+/// loadfile は引数の per-file オプションで任意の mpv オプションを設定できるため許可しない
+/// （URL の検証を行う mpv_play を使う）
+const RAW_COMMANDS: &[&str] = &[
+    "stop",
+    "set",
+    "cycle",
+    "add",
+    "seek",
+    "frame-step",
+    "frame-back-step",
+    "show-text",
+];
+/// mpv_command の set / cycle / add で許可するプロパティ
+const RAW_PROPERTIES: &[&str] = &[
+    "pause",
+    "volume",
+    "speed",
+    "video-rotate",
+    "video-zoom",
+    "video-pan-x",
+    "video-pan-y",
+    "panscan",
+    "aid",
+    "vid",
+    "sid",
+    "osd-level",
+];
+const MAX_VOLUME: f64 = 130.0;
+const SPEED_RANGE: (f64, f64) = (0.25, 4.0);
+const SCREENSHOT_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "webp"];
+
+/// 生コマンドが許可リストに含まれるか検証する
+fn check_raw_command(args: &[String]) -> Result<(), String> {
+    let command = args[0].as_str();
+    if !RAW_COMMANDS.contains(&command) {
+        return Err(format!("mpv command not allowed: {}", command));
+    }
+    if matches!(command, "set" | "cycle" | "add") {
+        let property = args.get(1).map(String::as_str).unwrap_or_default();
+        if !RAW_PROPERTIES.contains(&property) {
+            return Err(format!("mpv property not allowed: {}", property));
+        }
+    }
+    Ok(())
+}
+
+/// プレイヤーにコマンドを送り、成功したら状態を更新して mpv://state を emit する
+fn run_player_command(
+    app: &AppHandle,
+    manager: &MpvPlayerManager,
+    player_id: Option<String>,
+    args: &[&str],
+    update: impl FnOnce(&mut MpvPlayerState),
+) -> Result<(), String> {
+    let player_id = normalize_player_id(player_id)?;
+    let info = {
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        guard.active_player(&player_id)?.command(args)?;
+        if let Some(state) = guard.players.get_mut(&player_id) {
+            update(state);
+        }
+        guard.info(&player_id)
+    };
+    emit_player_state(app, info);
+    Ok(())
+}
+
+/// mpv_play の再生オプション（start_mpv の引数に対応）
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayOptions {
+    pub embedded: Option<bool>,
+    pub window_label: Option<String>,
+    pub demuxer_format: Option<String>,
+    /// 再生プロファイル名（省略時は配信ごとの上書き・再生ソースから自動選択）
+    pub profile: Option<String>,
+    /// 配信ごとのプロファイル上書きを引くための live_id
+    pub live_id: Option<String>,
+}
+
+/// URL を検証してプレイヤーを起動する
+#[tauri::command]
+pub async fn mpv_play(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    url: String,
+    options: Option<PlayOptions>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let url = url.trim().to_string();
+    if url.is_empty() || url.chars().any(char::is_control) {
+        return Err("Invalid playback url".to_string());
+    }
+    launch_player(app, &manager, url, options.unwrap_or_default(), player_id).await
+}
+
+/// 一時停止する
+#[tauri::command]
+pub async fn mpv_pause(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    run_player_command(&app, &manager, player_id, &["set", "pause", "yes"], |state| {
+        state.is_paused = true;
+    })
+}
+
+/// 再生を再開する
+#[tauri::command]
+pub async fn mpv_resume(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    run_player_command(&app, &manager, player_id, &["set", "pause", "no"], |state| {
+        state.is_paused = false;
+    })
+}
+
+/// 音量を設定する（0〜130）
+#[tauri::command]
+pub async fn mpv_set_volume(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    volume: f64,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    if !(0.0..=MAX_VOLUME).contains(&volume) {
+        return Err(format!("Volume must be between 0 and {}", MAX_VOLUME));
+    }
+    let value = format!("{:.1}", volume);
+    run_player_command(&app, &manager, player_id, &["set", "volume", &value], |state| {
+        state.volume = volume;
+    })
+}
+
+/// ミュートを設定する。音声フォーカスを持たないプレイヤーはミュート解除しても無音のまま。
+#[tauri::command]
+pub async fn mpv_set_mute(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    muted: bool,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let player_id = normalize_player_id(player_id)?;
+    let info = {
+        let mut guard = manager.state.lock().map_err(|e| e.to_string())?;
+        let focused = guard.audio_focus.as_deref() == Some(player_id.as_str());
+        guard
+            .active_player(&player_id)?
+            .command(&["set", "mute", mpv_flag(muted || !focused)])?;
+        if let Some(state) = guard.players.get_mut(&player_id) {
+            state.muted = muted;
+        }
+        guard.info(&player_id)
+    };
+    emit_player_state(&app, info);
+    Ok(())
+}
+
+/// 映像を回転する（0/90/180/270 度、縦長のモバイル配信向け）
+#[tauri::command]
+pub async fn mpv_set_rotation(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    degrees: u32,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    if !matches!(degrees, 0 | 90 | 180 | 270) {
+        return Err("Rotation must be 0, 90, 180 or 270".to_string());
+    }
+    let value = degrees.to_string();
+    run_player_command(&app, &manager, player_id, &["set", "video-rotate", &value], |state| {
+        state.rotation = degrees;
+    })
+}
+
+/// 再生速度を設定する（0.25〜4.0）
+#[tauri::command]
+pub async fn mpv_set_speed(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    speed: f64,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    if !(SPEED_RANGE.0..=SPEED_RANGE.1).contains(&speed) {
+        return Err(format!(
+            "Speed must be between {} and {}",
+            SPEED_RANGE.0, SPEED_RANGE.1
+        ));
+    }
+    let value = format!("{:.2}", speed);
+    run_player_command(&app, &manager, player_id, &["set", "speed", &value], |state| {
+        state.speed = speed;
+    })
+}
+
+/// シークの基準
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SeekMode {
+    /// 先頭からの秒数
+    Absolute,
+    /// 現在位置からの相対秒数
+    Relative,
+}
+
+/// シークする（position は秒）
+#[tauri::command]
+pub async fn mpv_seek(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    position: f64,
+    mode: Option<SeekMode>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    if !position.is_finite() {
+        return Err("Invalid seek position".to_string());
+    }
+    let mode = match mode.unwrap_or(SeekMode::Relative) {
+        SeekMode::Absolute if position >= 0.0 => "absolute",
+        SeekMode::Absolute => return Err("Absolute seek position must be >= 0".to_string()),
+        SeekMode::Relative => "relative",
+    };
+    let value = format!("{:.3}", position);
+    run_player_command(&app, &manager, player_id, &["seek", &value, mode], |_| {})
+}
+
+/// スクリーンショットをピクチャフォルダの Mirrativ/ に保存して保存先パスを返す。
+/// file_name を省略した場合は自動でファイル名を付ける。
+/// 保存は同期で時間がかかるため、マネージャーのロックを外して実行する。
+#[tauri::command]
+pub async fn mpv_screenshot(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    file_name: Option<String>,
+    player_id: Option<String>,
+) -> Result<String, String> {
+    let player_id = normalize_player_id(player_id)?;
+    let file_name = match file_name {
+        Some(name) => name,
+        None => {
+            let stamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_millis())
+                .unwrap_or_default();
+            format!("{}-{}.png", player_id, stamp)
+        }
+    };
+    check_screenshot_file_name(&file_name)?;
+    let dir = app.path().picture_dir().map_err(|e| e.to_string())?.join("Mirrativ");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let path_str = dir.join(&file_name).to_string_lossy().to_string();
+    let player = {
+        let state = manager.state.lock().map_err(|e| e.to_string())?;
+        state
+            .players
+            .get(&player_id)
+            .and_then(|state| state.player.clone())
+            .ok_or_else(|| format!("MPV player not initialized: {}", player_id))?
+    };
+    let target = path_str.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        player.command(&["screenshot-to-file", &target, "video"])
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+    Ok(path_str)
+}
+
+/// スクリーンショットのファイル名を検証する（ディレクトリを含まない .png/.jpg/.webp のみ）
+fn check_screenshot_file_name(file_name: &str) -> Result<(), String> {
+    let path = Path::new(file_name);
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+    if path.file_name().and_then(|name| name.to_str()) != Some(file_name)
+        || !SCREENSHOT_EXTENSIONS.contains(&extension.as_str())
+    {
+        return Err("Screenshot file name must be a bare .png/.jpg/.webp file name".to_string());
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// クリップ変換
+// llstream リレーのクリップ（MPEG-TS）を mpv のエンコードモードで MP4 に変換する。
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 変換の最大待ち時間（最長 120 秒のクリップを想定）
+const CLIP_CONVERT_TIMEOUT: Duration = Duration::from_secs(300);
+
+impl MpvPlayer {
+    /// END_FILE まで待つ（イベントループを持たないエンコード用インスタンス専用）
+    fn wait_end_file(&self, timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+        while Instant::now() < deadline {
+            let event = unsafe { &*(self.lib.wait_event)(self.handle, EVENT_WAIT_TIMEOUT_SECS) };
+            match event.event_id {
+                MPV_EVENT_END_FILE if !event.data.is_null() => {
+                    let end = unsafe { &*(event.data as *const MpvEventEndFile) };
+                    return match end_file_reason(end.reason) {
+                        "eof" => Ok(()),
+                        reason => Err(format!(
+                            "mpv stopped encoding: {} (error code {})",
+                            reason, end.error
+                        )),
+                    };
+                }
+                MPV_EVENT_SHUTDOWN => return Err("mpv shut down while encoding".to_string()),
+                _ => {}
+            }
+        }
+        Err("Timed out while encoding the clip".to_string())
+    }
+}
+
+/// MPEG-TS のクリップを MP4 に変換する。
+/// 再生中のプレイヤーとは別のヘッドレスインスタンスを使い、変換が終わるまで待つ。
+pub(crate) async fn convert_clip_to_mp4(
+    app: &AppHandle,
+    input: &Path,
+    output: &Path,
+) -> Result<(), String> {
+    let lib = app.state::<MpvPlayerManager>().ensure_lib(app)?;
+    let input = input.to_string_lossy().into_owned();
+    let output = output.to_string_lossy().into_owned();
+    tauri::async_runtime::spawn_blocking(move || {
+        let player = MpvPlayer::new(lib)?;
+        // o を指定するとエンコードモードになる（映像・音声のコーデックは mp4 の既定を使う）
+        for (key, value) in [("o", output.as_str()), ("of", "mp4"), ("idle", "no")] {
+            player.set_option(key, value)?;
+        }
+        player.initialize()?;
+        player.command(&["loadfile", &input])?;
+        // 出力ファイルは terminate_destroy（drop）で確定する
+        player.wait_end_file(CLIP_CONVERT_TIMEOUT)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// トラックの種類
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackKind {
+    Audio,
+    Video,
+}
+
+impl TrackKind {
+    /// 選択に使う mpv プロパティ名
+    fn property(self) -> &'static str {
+        match self {
+            TrackKind::Audio => "aid",
+            TrackKind::Video => "vid",
+        }
+    }
+}
+
+/// 音声・映像トラックの情報（mpv の track-list）
+#[derive(Serialize, Clone)]
+pub struct TrackInfo {
+    pub id: i64,
+    pub kind: TrackKind,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    pub selected: bool,
+}
+
+/// 音声・映像トラックの一覧を取得する
+#[tauri::command]
+pub async fn mpv_get_tracks(
+    manager: tauri::State<'_, MpvPlayerManager>,
+    player_id: Option<String>,
+) -> Result<Vec<TrackInfo>, String> {
+    let player_id = normalize_player_id(player_id)?;
+    let state = manager.state.lock().map_err(|e| e.to_string())?;
+    let player = state.active_player(&player_id)?;
+
+    let count: usize = player
+        .get_property("track-list/count")?
+        .trim()
+        .parse()
+        .map_err(|_| "Invalid track-list/count".to_string())?;
+    let mut tracks = Vec::new();
+    for index in 0..count {
+        let field = |name: &str| player.get_property(&format!("track-list/{}/{}", index, name));
+        let kind = match field("type").as_deref() {
+            Ok("audio") => TrackKind::Audio,
+            Ok("video") => TrackKind::Video,
+            _ => continue,
+        };
+        let Some(id) = field("id").ok().and_then(|id| id.trim().parse().ok()) else {
+            continue;
+        };
+        tracks.push(TrackInfo {
+            id,
+            kind,
+            title: field("title").ok(),
+            lang: field("lang").ok(),
+            codec: field("codec").ok(),
+            selected: field("selected").as_deref() == Ok("yes"),
+        });
+    }
+    Ok(tracks)
+}
+
+/// 音声・映像トラックを選択する。track_id を省略するとそのトラック種別を無効化する。
+#[tauri::command]
+pub async fn mpv_select_track(
+    app: AppHandle,
+    manager: tauri::State<'_, MpvPlayerManager>,
+    kind: TrackKind,
+    track_id: Option<i64>,
+    player_id: Option<String>,
+) -> Result<(), String> {
+    let value = match track_id {
+        Some(id) if id >= 1 => id.to_string(),
+        Some(_) => return Err("Track id must be >= 1".to_string()),
+        None => "no".to_string(),
+    };
+    run_player_command(&app, &manager, player_id, &["set", kind.property(), &value], |_| {})
+}
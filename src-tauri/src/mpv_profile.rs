@@ -0,0 +1,462 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// mpv_profile.rs
+//
+// mpv の再生プロファイル（名前付きの mpv オプションセット）を管理するモジュール。
+//
+// 主な責務:
+//   - 組み込みプロファイル（low-latency-llstream / hls-stable / audio-only など）
+//   - app_config_dir の playback_profiles.json へのユーザープロファイル・配信ごとの上書きの保存
+//   - 再生ソース（llstream リレーのモード / URL）からのプロファイル自動選択
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+const PROFILES_FILE: &str = "playback_profiles.json";
+pub(crate) const DEFAULT_PROFILE: &str = "default";
+
+/// mpv オプションの組（名前, 値）
+type OptionSet = &'static [(&'static str, &'static str)];
+
+/// 全プロファイル共通の基本オプション（プロファイル側で上書きできる）
+const BASE_OPTIONS: OptionSet = &[
+    ("hwdec", "no"),      // ハードウェアデコード無効（互換性のため）
+    ("keep-open", "yes"), // 再生終了後もウィンドウを維持
+];
+
+/// 生の Annex B パイプ入力に必須のオプション（demuxer を強制する再生では
+/// プロファイルに関係なく先に設定する）
+pub(crate) const PIPE_OPTIONS: OptionSet = &[
+    ("cache", "no"),
+    ("demuxer-readahead-secs", "0"),
+    ("untimed", "yes"),
+];
+
+/// 組み込みプロファイル（名前, 説明, オプション）
+const BUILTIN_PROFILES: &[(&str, &str, OptionSet)] = &[
+    (DEFAULT_PROFILE, "基本設定のみ", &[]),
+    (
+        "low-latency-llstream",
+        "llstream リレー向け（バッファを最小化して低遅延を優先）",
+        &[
+            ("cache", "no"),
+            ("demuxer-readahead-secs", "0"),
+            ("demuxer-lavf-probesize", "32768"),
+            ("demuxer-lavf-analyzeduration", "0.1"),
+            ("video-latency-hacks", "yes"),
+            ("interpolation", "no"),
+        ],
+    ),
+    (
+        "llstream-pipe",
+        "Annex B パイプ入力向け（タイムスタンプなしの生ストリーム）",
+        PIPE_OPTIONS,
+    ),
+    (
+        "hls-stable",
+        "HLS 向け（安定性を優先して多めにバッファ）",
+        &[
+            ("cache", "yes"),
+            ("cache-secs", "10"),
+            ("demuxer-readahead-secs", "10"),
+            ("demuxer-max-bytes", "64MiB"),
+            ("hls-bitrate", "max"),
+            ("network-timeout", "15"),
+        ],
+    ),
+    (
+        "audio-only",
+        "音声のみ（映像のデコードを無効化）",
+        &[("vid", "no"), ("audio-display", "no")],
+    ),
+];
+
+/// プロファイルで設定できるオプション（キャッシュ・demuxer・遅延・デコード・同期の調整のみ）。
+/// ファイル書き込み・スクリプト読み込み・ウィンドウ埋め込みにつながるオプションは含めない
+const ALLOWED_OPTIONS: &[&str] = &[
+    // キャッシュ
+    "cache",
+    "cache-secs",
+    "cache-pause",
+    "cache-pause-initial",
+    "cache-pause-wait",
+    // demuxer
+    "demuxer-readahead-secs",
+    "demuxer-max-bytes",
+    "demuxer-max-back-bytes",
+    "demuxer-seekable-cache",
+    "demuxer-lavf-probesize",
+    "demuxer-lavf-analyzeduration",
+    "hls-bitrate",
+    "network-timeout",
+    // 遅延
+    "video-latency-hacks",
+    "untimed",
+    "audio-buffer",
+    // デコード
+    "hwdec",
+    "hwdec-codecs",
+    "vd-lavc-threads",
+    "vd-lavc-fast",
+    "vd-lavc-skiploopfilter",
+    "vd-lavc-framedrop",
+    // 同期・描画
+    "video-sync",
+    "video-sync-max-video-change",
+    "framedrop",
+    "interpolation",
+    "tscale",
+    "scale",
+    "deband",
+    // トラック・再生
+    "vid",
+    "aid",
+    "audio-display",
+    "keep-open",
+];
+/// リスト型オプションの操作（-append / -add など）。許可したオプションでも受け付けない
+const LIST_OPTION_SUFFIXES: &[&str] = &["-append", "-add", "-set", "-pre", "-clr", "-remove"];
+
+/// 保存ファイルの読み書きを直列化する
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 型定義
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 再生プロファイル
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlaybackProfile {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// mpv オプション（名前 → 値）
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+    /// 組み込みプロファイルかどうか（一覧表示用、保存時は無視される）
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+/// 配信ごとのプロファイル上書き
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LiveProfileOverride {
+    /// 使用するプロファイル名（None の場合は自動選択）
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// プロファイルのオプションに追加・上書きする mpv オプション
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+/// playback_profiles.json の内容
+#[derive(Serialize, Deserialize, Default)]
+struct ProfileStore {
+    /// ユーザー定義プロファイル（組み込みと同名の場合は組み込みを上書きする）
+    #[serde(default)]
+    profiles: Vec<PlaybackProfile>,
+    /// live_id ごとの上書き
+    #[serde(default)]
+    live_overrides: HashMap<String, LiveProfileOverride>,
+}
+
+/// start_mpv で適用するプロファイルの解決結果
+pub(crate) struct ResolvedProfile {
+    pub(crate) name: String,
+    /// 基本オプション・プロファイル・配信ごとの上書きをマージしたもの
+    pub(crate) options: BTreeMap<String, String>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 保存・読み込み
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(PROFILES_FILE))
+}
+
+fn load_store(app: &AppHandle) -> Result<ProfileStore, String> {
+    let path = store_path(app)?;
+    if !path.exists() {
+        return Ok(ProfileStore::default());
+    }
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", PROFILES_FILE, e))
+}
+
+fn save_store(app: &AppHandle, store: &ProfileStore) -> Result<(), String> {
+    let path = store_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(store).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// ストアを読み込んで変更し、保存する
+fn update_store(
+    app: &AppHandle,
+    update: impl FnOnce(&mut ProfileStore) -> Result<(), String>,
+) -> Result<(), String> {
+    let _guard = STORE_LOCK.lock().map_err(|e| e.to_string())?;
+    let mut store = load_store(app)?;
+    update(&mut store)?;
+    save_store(app, &store)
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// プロファイル解決
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn builtin_profile(name: &str) -> Option<PlaybackProfile> {
+    BUILTIN_PROFILES
+        .iter()
+        .find(|(builtin, _, _)| *builtin == name)
+        .map(|(name, description, options)| PlaybackProfile {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            options: options
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            builtin: true,
+        })
+}
+
+/// ユーザー定義を優先してプロファイルを探す
+fn find_profile(store: &ProfileStore, name: &str) -> Option<PlaybackProfile> {
+    store
+        .profiles
+        .iter()
+        .find(|profile| profile.name == name)
+        .cloned()
+        .or_else(|| builtin_profile(name))
+}
+
+/// 再生ソースからプロファイルを自動選択する。
+/// relay_mode は LlstreamRelayInfo.mode（URL が現在のリレーのものである場合）。
+fn auto_profile(relay_mode: Option<&str>, url: &str, forced_demuxer: bool) -> &'static str {
+    match relay_mode {
+        Some("mpegts-video" | "mpegts-av") => "low-latency-llstream",
+        Some("annexb-pipe") => "llstream-pipe",
+        Some("adts-audio" | "mpegts-audio") => "audio-only",
+        _ if forced_demuxer => "llstream-pipe",
+        _ if url.to_ascii_lowercase().contains(".m3u8") => "hls-stable",
+        _ => DEFAULT_PROFILE,
+    }
+}
+
+/// start_mpv で使うプロファイルを解決する。
+///
+/// 優先順位: 明示指定 > 配信ごとの上書き > 再生ソースからの自動選択
+pub(crate) fn resolve_profile(
+    app: &AppHandle,
+    requested: Option<&str>,
+    live_id: Option<&str>,
+    relay_mode: Option<&str>,
+    url: &str,
+    forced_demuxer: bool,
+) -> Result<ResolvedProfile, String> {
+    let store = match load_store(app) {
+        Ok(store) => store,
+        Err(err) => {
+            // 設定ファイルが壊れていても再生は止めない
+            eprintln!("playback profiles unavailable: {}", err);
+            ProfileStore::default()
+        }
+    };
+    let live_override = live_id.and_then(|id| store.live_overrides.get(id));
+
+    let profile = match requested {
+        Some(name) => {
+            find_profile(&store, name).ok_or_else(|| format!("Unknown playback profile: {}", name))?
+        }
+        None => {
+            let from_live = live_override
+                .and_then(|o| o.profile.as_deref())
+                .and_then(|name| find_profile(&store, name));
+            let auto = auto_profile(relay_mode, url, forced_demuxer);
+            from_live
+                .or_else(|| find_profile(&store, auto))
+                .or_else(|| builtin_profile(DEFAULT_PROFILE))
+                .ok_or_else(|| "Default playback profile missing".to_string())?
+        }
+    };
+
+    let mut options: BTreeMap<String, String> = BASE_OPTIONS
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    options.extend(profile.options);
+    if let Some(live_override) = live_override {
+        options.extend(live_override.options.clone());
+    }
+    options.retain(|key, _| check_option(key).is_ok());
+
+    Ok(ResolvedProfile {
+        name: profile.name,
+        options,
+    })
+}
+
+/// プロファイル名を検証する（英小文字・数字・'-'・'_'）
+fn check_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Invalid profile name: {}", name));
+    }
+    Ok(())
+}
+
+/// mpv オプション名を検証する（ALLOWED_OPTIONS のみ設定可）
+fn check_option(key: &str) -> Result<(), String> {
+    let list_operation = LIST_OPTION_SUFFIXES
+        .iter()
+        .any(|suffix| key.ends_with(suffix));
+    if list_operation || !ALLOWED_OPTIONS.contains(&key) {
+        return Err(format!("mpv option not allowed in profiles: {}", key));
+    }
+    Ok(())
+}
+
+fn check_options(options: &BTreeMap<String, String>) -> Result<(), String> {
+    for (key, value) in options {
+        check_option(key)?;
+        if value.chars().any(char::is_control) {
+            return Err(format!("Invalid value for mpv option {}", key));
+        }
+    }
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 組み込みとユーザー定義のプロファイル一覧を取得する（同名はユーザー定義を優先）
+#[tauri::command]
+pub async fn list_playback_profiles(app: AppHandle) -> Result<Vec<PlaybackProfile>, String> {
+    let store = load_store(&app)?;
+    let mut profiles: Vec<PlaybackProfile> = BUILTIN_PROFILES
+        .iter()
+        .filter_map(|(name, _, _)| find_profile(&store, name))
+        .collect();
+    profiles.extend(
+        store
+            .profiles
+            .iter()
+            .filter(|profile| builtin_profile(&profile.name).is_none())
+            .cloned(),
+    );
+    Ok(profiles)
+}
+
+/// ユーザー定義プロファイルを保存する（組み込みと同名の場合は組み込みを上書きする）
+#[tauri::command]
+pub async fn save_playback_profile(app: AppHandle, profile: PlaybackProfile) -> Result<(), String> {
+    check_profile_name(&profile.name)?;
+    check_options(&profile.options)?;
+    let profile = PlaybackProfile {
+        builtin: false,
+        ..profile
+    };
+    update_store(&app, |store| {
+        store.profiles.retain(|p| p.name != profile.name);
+        store.profiles.push(profile);
+        Ok(())
+    })
+}
+
+/// ユーザー定義プロファイルを削除する（組み込みを上書きしていた場合は組み込みに戻る）
+#[tauri::command]
+pub async fn delete_playback_profile(app: AppHandle, name: String) -> Result<(), String> {
+    update_store(&app, |store| {
+        let before = store.profiles.len();
+        store.profiles.retain(|p| p.name != name);
+        if store.profiles.len() == before {
+            return Err(format!("No user playback profile named {}", name));
+        }
+        Ok(())
+    })
+}
+
+/// 配信ごとのプロファイル上書きを取得する
+#[tauri::command]
+pub async fn get_live_playback_profile(
+    app: AppHandle,
+    live_id: String,
+) -> Result<Option<LiveProfileOverride>, String> {
+    Ok(load_store(&app)?.live_overrides.get(&live_id).cloned())
+}
+
+/// 配信ごとのプロファイル上書きを設定する（None で削除）
+#[tauri::command]
+pub async fn set_live_playback_profile(
+    app: AppHandle,
+    live_id: String,
+    profile_override: Option<LiveProfileOverride>,
+) -> Result<(), String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+    if let Some(profile_override) = &profile_override {
+        if let Some(name) = &profile_override.profile {
+            check_profile_name(name)?;
+        }
+        check_options(&profile_override.options)?;
+    }
+    update_store(&app, |store| {
+        match profile_override {
+            Some(profile_override) => {
+                store.live_overrides.insert(live_id, profile_override);
+            }
+            None => {
+                store.live_overrides.remove(&live_id);
+            }
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_profiles_only_use_allowed_options() {
+        let builtin = BUILTIN_PROFILES.iter().flat_map(|(_, _, options)| options.iter());
+        for (key, _) in BASE_OPTIONS.iter().chain(builtin) {
+            assert!(check_option(key).is_ok(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn rejects_side_effect_and_list_options() {
+        for key in [
+            "scripts-append",
+            "scripts-add",
+            "script-opts",
+            "stream-record",
+            "screenshot-dir",
+            "watch-later-dir",
+            "ytdl-raw-options",
+            "input-commands",
+            "log-file",
+            "wid",
+            "cache-append",
+            "hwdec-codecs-add",
+            "",
+        ] {
+            assert!(check_option(key).is_err(), "{}", key);
+        }
+    }
+}
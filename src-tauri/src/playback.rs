@@ -0,0 +1,744 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// playback.rs
+//
+// ライブ視聴の再生経路（llstream リレー / HLS）を自動選択するオーケストレーター。
+//
+// 主な責務:
+//   - get_live_status から HLS URL と llstream WS URL を解決
+//   - AV リレー → 映像リレー → HLS の優先順で再生を開始
+//   - リレーと mpv の健全性を監視し、異常時は次の経路へ自動フェイルオーバー
+//   - 経路の切り替えを playback://transition イベントで通知
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{watch, RwLock};
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant};
+
+use crate::mirrativ::client::live::get_live_status;
+use crate::mirrativ::client::llstream_relay::{
+    start_llstream_av_ts_relay, start_llstream_video_ts_relay, LlstreamRelayManager,
+};
+use crate::mpv_player::{self, MpvPlayerManager, PlayOptions, DEFAULT_PLAYER_ID};
+
+/// 健全性チェックの間隔
+const HEALTH_INTERVAL: Duration = Duration::from_secs(2);
+/// 経路開始直後、フレーム未着やプレイヤー準備中を異常と見なさない猶予
+const STARTUP_GRACE: Duration = Duration::from_secs(12);
+/// フレーム到着・再生位置の更新がこの時間止まったら異常と判定する
+const STALL_TIMEOUT: Duration = Duration::from_secs(8);
+/// 全経路が失敗した後、URL を再解決して最初からやり直すまでの待機時間
+const RETRY_DELAY: Duration = Duration::from_secs(5);
+/// 全経路の失敗（または URL の解決失敗）が続いた場合に再試行する回数
+const MAX_RETRY_ROUNDS: u32 = 3;
+/// 経路がこの時間以上再生できていれば、失敗からの回復と見なして再試行回数を戻す
+const RETRY_RESET_AFTER: Duration = Duration::from_secs(60);
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 再生経路と URL 解決
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 再生経路（優先度の高い順）
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PlaybackPath {
+    /// llstream 映像 + 音声を AV TS リレー経由で mpv に渡す
+    LlstreamAv,
+    /// llstream 映像のみを TS リレー経由で mpv に渡す
+    LlstreamVideo,
+    /// HLS URL を mpv で直接再生する
+    Hls,
+}
+
+impl PlaybackPath {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::LlstreamAv => "llstream-av",
+            Self::LlstreamVideo => "llstream-video",
+            Self::Hls => "hls",
+        }
+    }
+
+    fn uses_relay(self) -> bool {
+        !matches!(self, Self::Hls)
+    }
+}
+
+/// get_live_status から取り出した再生元 URL
+#[derive(Clone, Default)]
+struct PlaybackSources {
+    hls: Option<String>,
+    video_ws: Option<String>,
+    audio_ws: Option<String>,
+}
+
+impl PlaybackSources {
+    /// API バージョンによってフィールド名・位置が異なるため、フロントエンドの
+    /// watch-utils.ts と同じ順序で候補を探す。
+    fn from_status(status: &Value) -> Self {
+        let stream_key = pick_field(status, "streaming_key");
+        let edge = pick_field(status, "streaming_url_edge");
+        let ws_url = |direct: &str, suffix: &str| {
+            pick_field(status, direct).or_else(|| {
+                build_llstream_ws_url(edge.as_deref()?, stream_key.as_deref()?, suffix)
+            })
+        };
+
+        Self {
+            hls: hls_url(status),
+            video_ws: ws_url("streaming_url_llstream_video", "video/avc"),
+            audio_ws: ws_url("streaming_url_llstream_audio", "audio/aac"),
+        }
+    }
+
+    /// 利用可能な経路を優先順に返す
+    fn candidates(&self) -> Vec<PlaybackPath> {
+        let mut paths = Vec::new();
+        if self.video_ws.is_some() && self.audio_ws.is_some() {
+            paths.push(PlaybackPath::LlstreamAv);
+        }
+        if self.video_ws.is_some() {
+            paths.push(PlaybackPath::LlstreamVideo);
+        }
+        if self.hls.is_some() {
+            paths.push(PlaybackPath::Hls);
+        }
+        paths
+    }
+}
+
+/// status 直下・live・data の順に空でない文字列フィールドを探す
+fn pick_field(status: &Value, key: &str) -> Option<String> {
+    [&status[key], &status["live"][key], &status["data"][key]]
+        .into_iter()
+        .filter_map(Value::as_str)
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+pub(crate) fn hls_url(status: &Value) -> Option<String> {
+    for key in ["streaming_url_hls", "streaming_url", "hls_url", "playlist_url"] {
+        if let Some(url) = status[key].as_str().filter(|s| !s.is_empty()) {
+            return Some(url.to_string());
+        }
+    }
+
+    // リスト形式の場合は最初の有効な URL を使う
+    let list = ["streaming_url_list", "streaming_urls", "url_list"]
+        .into_iter()
+        .find_map(|key| status[key].as_array())?;
+    list.iter().find_map(|item| {
+        item.as_str()
+            .or_else(|| item["url"].as_str())
+            .or_else(|| item["streaming_url"].as_str())
+            .or_else(|| item["hls_url"].as_str())
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+    })
+}
+
+/// edge が ws:// / wss:// ならそのまま、そうでなければ ws://<edge>:1883 を使う
+fn build_llstream_ws_url(edge: &str, stream_key: &str, suffix: &str) -> Option<String> {
+    if edge.is_empty() || stream_key.is_empty() {
+        return None;
+    }
+    if edge.starts_with("ws://") || edge.starts_with("wss://") {
+        return Some(format!(
+            "{}/ws/{}/{}",
+            edge.trim_end_matches('/'),
+            stream_key,
+            suffix
+        ));
+    }
+    let host = if edge.contains(':') {
+        edge.to_string()
+    } else {
+        format!("{}:1883", edge)
+    };
+    Some(format!("ws://{}/ws/{}/{}", host, stream_key, suffix))
+}
+
+async fn resolve_sources(app: &AppHandle, live_id: &str) -> Result<PlaybackSources, String> {
+    let status = get_live_status(app.state(), live_id.to_string()).await?;
+    playable_sources(&status)
+}
+
+/// 取得済みの get_live_status の結果から再生元 URL を取り出す
+fn playable_sources(status: &Value) -> Result<PlaybackSources, String> {
+    let sources = PlaybackSources::from_status(status);
+    if sources.candidates().is_empty() {
+        return Err("no playable stream URL in live status".to_string());
+    }
+    Ok(sources)
+}
+
+/// get_live_status の結果から llstream の映像・音声 WS URL を取り出す（録画用）
+pub(crate) fn llstream_av_urls(status: &Value) -> Option<(String, String)> {
+    let sources = PlaybackSources::from_status(status);
+    Some((sources.video_ws?, sources.audio_ws?))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 状態とイベント
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// フロントエンドに返す再生状態
+#[derive(Serialize, Clone)]
+pub struct PlaybackStatus {
+    pub live_id: String,
+    /// 再生に使っている mpv のプレイヤー ID
+    pub player_id: String,
+    /// 現在の経路（"llstream-av" / "llstream-video" / "hls"）。開始前・待機中は None
+    pub path: Option<String>,
+    /// mpv に渡している URL
+    pub url: Option<String>,
+    /// 利用可能な経路（優先順）
+    pub candidates: Vec<String>,
+    /// フェイルオーバーの回数
+    pub failovers: u32,
+}
+
+/// playback://transition イベントの payload
+#[derive(Serialize, Clone)]
+struct PlaybackTransition {
+    live_id: String,
+    from: Option<&'static str>,
+    /// 新しい経路、または "waiting"（全経路失敗後の再試行待ち）/ "stopped" /
+    /// "failed"（再試行の上限に達して再生を諦めた）
+    to: &'static str,
+    reason: String,
+}
+
+fn emit_transition(
+    app: &AppHandle,
+    live_id: &str,
+    from: Option<PlaybackPath>,
+    to: &'static str,
+    reason: &str,
+) {
+    eprintln!(
+        "[playback] {} -> {}: {}",
+        from.map(PlaybackPath::as_str).unwrap_or("-"),
+        to,
+        reason
+    );
+    let _ = app.emit(
+        "playback://transition",
+        PlaybackTransition {
+            live_id: live_id.to_string(),
+            from: from.map(PlaybackPath::as_str),
+            to,
+            reason: reason.to_string(),
+        },
+    );
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// PlaybackManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 再生オーケストレーター（Tauri の管理状態として登録される）
+#[derive(Default)]
+pub struct PlaybackManager {
+    status: Arc<RwLock<Option<PlaybackStatus>>>,
+    shutdown_tx: Arc<RwLock<Option<watch::Sender<bool>>>>,
+    task: Arc<RwLock<Option<JoinHandle<()>>>>,
+}
+
+/// mpv の起動オプション（launch_player に渡す）
+#[derive(Clone)]
+pub(crate) struct PlayerTarget {
+    pub(crate) embedded: Option<bool>,
+    pub(crate) window_label: Option<String>,
+    /// 再生に使う mpv のプレイヤー ID
+    pub(crate) player_id: String,
+    /// 配信ごとの再生プロファイル上書きに使う
+    pub(crate) live_id: String,
+}
+
+impl PlaybackManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 再生を開始する。live_status に取得済みの get_live_status の結果を渡すと再取得しない
+    pub(crate) async fn start(
+        &self,
+        app: &AppHandle,
+        live_id: String,
+        live_status: Option<&Value>,
+        target: PlayerTarget,
+    ) -> Result<PlaybackStatus, String> {
+        self.stop_supervisor().await;
+
+        // 最初の解決はここで行い、再生できる URL がなければ即座にエラーを返す
+        let sources = match live_status {
+            Some(status) => playable_sources(status)?,
+            None => resolve_sources(app, &live_id).await?,
+        };
+        let status = PlaybackStatus {
+            live_id: live_id.clone(),
+            player_id: target.player_id.clone(),
+            path: None,
+            url: None,
+            candidates: sources
+                .candidates()
+                .iter()
+                .map(|p| p.as_str().to_string())
+                .collect(),
+            failovers: 0,
+        };
+        *self.status.write().await = Some(status.clone());
+
+        let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let task = tokio::spawn(run_supervisor(
+            app.clone(),
+            live_id,
+            Some(sources),
+            target,
+            self.status.clone(),
+            shutdown_rx,
+        ));
+        *self.shutdown_tx.write().await = Some(shutdown_tx);
+        *self.task.write().await = Some(task);
+
+        Ok(status)
+    }
+
+    /// 再生中の live_id（停止中・プレイヤーが閉じられた後は None）
+    pub(crate) async fn current_live_id(&self) -> Option<String> {
+        self.status.read().await.as_ref().map(|s| s.live_id.clone())
+    }
+
+    /// 監視タスクを止める（リレーと mpv はそのまま残す）
+    async fn stop_supervisor(&self) {
+        *self.status.write().await = None;
+        if let Some(tx) = self.shutdown_tx.write().await.take() {
+            let _ = tx.send(true);
+        }
+        if let Some(mut task) = self.task.write().await.take() {
+            if tokio::time::timeout(Duration::from_secs(2), &mut task)
+                .await
+                .is_err()
+            {
+                task.abort();
+            }
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 監視タスク
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 健全性監視の結果
+enum HealthOutcome {
+    /// 停止要求を受けた
+    Shutdown,
+    /// ユーザーがプレイヤーを閉じた
+    PlayerClosed,
+    /// 経路が異常（理由付き）
+    Unhealthy(String),
+}
+
+/// 経路を開始し、mpv に渡した URL を返す
+async fn start_path(
+    app: &AppHandle,
+    path: PlaybackPath,
+    sources: &PlaybackSources,
+    target: &PlayerTarget,
+) -> Result<String, String> {
+    let relay = app.state::<LlstreamRelayManager>();
+    let url = match path {
+        PlaybackPath::LlstreamAv => {
+            let video = sources.video_ws.clone().unwrap_or_default();
+            let audio = sources.audio_ws.clone().unwrap_or_default();
+            start_llstream_av_ts_relay(app.clone(), relay, video, audio, None)
+                .await?
+                .playlist_url
+        }
+        PlaybackPath::LlstreamVideo => {
+            let video = sources.video_ws.clone().unwrap_or_default();
+            start_llstream_video_ts_relay(app.clone(), relay, video, None)
+                .await?
+                .playlist_url
+        }
+        PlaybackPath::Hls => {
+            relay.stop().await;
+            sources.hls.clone().unwrap_or_default()
+        }
+    };
+
+    let options = PlayOptions {
+        embedded: target.embedded,
+        window_label: target.window_label.clone(),
+        live_id: Some(target.live_id.clone()),
+        ..Default::default()
+    };
+    let player_id = Some(target.player_id.clone());
+    mpv_player::launch_player(app.clone(), &app.state(), url.clone(), options, player_id).await?;
+    Ok(url)
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 経路が異常になるか停止要求を受けるまで監視する
+async fn watch_health(
+    app: &AppHandle,
+    path: PlaybackPath,
+    player_id: &str,
+    shutdown_rx: &mut watch::Receiver<bool>,
+) -> HealthOutcome {
+    let started = Instant::now();
+    let mut interval = tokio::time::interval(HEALTH_INTERVAL);
+    let mut last_pos: Option<String> = None;
+    let mut last_progress = Instant::now();
+
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    return HealthOutcome::Shutdown;
+                }
+                continue;
+            }
+            _ = interval.tick() => {}
+        }
+        let in_grace = started.elapsed() < STARTUP_GRACE;
+
+        // リレー: フレームが届き続けているか
+        if path.uses_relay() {
+            let Some(stats) = app.state::<LlstreamRelayManager>().current_stats().await else {
+                return HealthOutcome::Unhealthy("relay stopped".to_string());
+            };
+            match stats.last_frame_unix_ms {
+                None if !in_grace => {
+                    return HealthOutcome::Unhealthy("no llstream frames received".to_string());
+                }
+                Some(ms) if unix_millis().saturating_sub(ms) > STALL_TIMEOUT.as_millis() as u64 => {
+                    return HealthOutcome::Unhealthy("llstream frames stalled".to_string());
+                }
+                _ => {}
+            }
+        }
+
+        // プレイヤー: 閉じられていないか、再生位置が進んでいるか
+        let mpv = app.state::<MpvPlayerManager>();
+        let property = |name: &str| mpv.get_player_property(player_id, name);
+        let Ok(idle) = property("idle-active") else {
+            return HealthOutcome::PlayerClosed;
+        };
+        if in_grace {
+            continue;
+        }
+        if idle == "yes" {
+            return HealthOutcome::Unhealthy("player went idle".to_string());
+        }
+        if property("pause").as_deref() == Ok("yes") {
+            last_progress = Instant::now();
+            continue;
+        }
+        let pos = property("time-pos").ok();
+        if pos.is_some() && pos != last_pos {
+            last_pos = pos;
+            last_progress = Instant::now();
+        } else if last_progress.elapsed() > STALL_TIMEOUT {
+            return HealthOutcome::Unhealthy("playback stalled".to_string());
+        }
+    }
+}
+
+/// 経路の開始・監視・フェイルオーバーを繰り返す監視タスク本体
+async fn run_supervisor(
+    app: AppHandle,
+    live_id: String,
+    mut sources: Option<PlaybackSources>,
+    target: PlayerTarget,
+    status: Arc<RwLock<Option<PlaybackStatus>>>,
+    mut shutdown_rx: watch::Receiver<bool>,
+) {
+    let mut path_index = 0usize;
+    let mut active: Option<PlaybackPath> = None;
+    let mut reason = "start".to_string();
+    let mut failovers = 0u32;
+    let mut retry_rounds = 0u32;
+
+    loop {
+        if *shutdown_rx.borrow() {
+            return;
+        }
+
+        // URL を（再）解決する
+        let current_sources = match sources.clone() {
+            Some(s) => s,
+            None => match resolve_sources(&app, &live_id).await {
+                Ok(s) => {
+                    sources = Some(s.clone());
+                    s
+                }
+                Err(e) => {
+                    retry_rounds += 1;
+                    if retry_rounds > MAX_RETRY_ROUNDS {
+                        give_up(&app, &live_id, active, &target, &status, &e).await;
+                        return;
+                    }
+                    emit_transition(&app, &live_id, active.take(), "waiting", &e);
+                    if wait_or_shutdown(&mut shutdown_rx, RETRY_DELAY).await {
+                        return;
+                    }
+                    continue;
+                }
+            },
+        };
+        let candidates = current_sources.candidates();
+
+        let Some(&path) = candidates.get(path_index) else {
+            // 全経路が失敗: ライブ側の再接続で URL が変わっている可能性があるので再解決する
+            retry_rounds += 1;
+            if retry_rounds > MAX_RETRY_ROUNDS {
+                let msg = format!("all playback paths failed ({})", reason);
+                give_up(&app, &live_id, active, &target, &status, &msg).await;
+                return;
+            }
+            let msg = format!("all playback paths failed ({}), retrying", reason);
+            emit_transition(&app, &live_id, active.take(), "waiting", &msg);
+            if let Some(s) = status.write().await.as_mut() {
+                s.path = None;
+                s.url = None;
+            }
+            sources = None;
+            path_index = 0;
+            if wait_or_shutdown(&mut shutdown_rx, RETRY_DELAY).await {
+                return;
+            }
+            continue;
+        };
+
+        let url = match start_path(&app, path, &current_sources, &target).await {
+            Ok(url) => url,
+            Err(e) => {
+                reason = format!("{} failed to start: {}", path.as_str(), e);
+                eprintln!("[playback] {}", reason);
+                path_index += 1;
+                continue;
+            }
+        };
+        if active.is_some() {
+            failovers += 1;
+        }
+        emit_transition(&app, &live_id, active, path.as_str(), &reason);
+        active = Some(path);
+        if let Some(s) = status.write().await.as_mut() {
+            s.path = Some(path.as_str().to_string());
+            s.url = Some(url);
+            s.candidates = candidates.iter().map(|p| p.as_str().to_string()).collect();
+            s.failovers = failovers;
+        }
+
+        let watched = Instant::now();
+        let outcome = watch_health(&app, path, &target.player_id, &mut shutdown_rx).await;
+        if watched.elapsed() >= RETRY_RESET_AFTER {
+            retry_rounds = 0;
+        }
+        match outcome {
+            HealthOutcome::Shutdown => return,
+            HealthOutcome::PlayerClosed => {
+                if path.uses_relay() {
+                    app.state::<LlstreamRelayManager>().stop().await;
+                }
+                emit_transition(&app, &live_id, Some(path), "stopped", "player closed");
+                *status.write().await = None;
+                return;
+            }
+            HealthOutcome::Unhealthy(why) => {
+                reason = format!("{}: {}", path.as_str(), why);
+                path_index += 1;
+            }
+        }
+    }
+}
+
+/// 再試行の上限に達した: リレーと mpv を止めて再生を終える
+async fn give_up(
+    app: &AppHandle,
+    live_id: &str,
+    active: Option<PlaybackPath>,
+    target: &PlayerTarget,
+    status: &RwLock<Option<PlaybackStatus>>,
+    reason: &str,
+) {
+    app.state::<LlstreamRelayManager>().stop().await;
+    let player_id = Some(target.player_id.clone());
+    let _ = mpv_player::stop_mpv(app.clone(), app.state(), None, player_id).await;
+    emit_transition(app, live_id, active, "failed", reason);
+    *status.write().await = None;
+}
+
+/// 指定時間待機する。停止要求を受けた場合は true を返す
+async fn wait_or_shutdown(shutdown_rx: &mut watch::Receiver<bool>, delay: Duration) -> bool {
+    tokio::select! {
+        _ = shutdown_rx.changed() => *shutdown_rx.borrow(),
+        _ = tokio::time::sleep(delay) => false,
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// live_id の配信を最適な経路で再生し、異常時は自動でフェイルオーバーする。
+/// 経路の切り替えは playback://transition イベントで通知される。
+#[tauri::command]
+pub async fn start_playback(
+    app: AppHandle,
+    manager: tauri::State<'_, PlaybackManager>,
+    live_id: String,
+    embedded: Option<bool>,
+    window_label: Option<String>,
+) -> Result<PlaybackStatus, String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+
+    let target = PlayerTarget {
+        embedded,
+        window_label,
+        player_id: DEFAULT_PLAYER_ID.to_string(),
+        live_id: live_id.clone(),
+    };
+    manager.start(&app, live_id, None, target).await
+}
+
+/// 再生を停止する（監視タスク・リレー・mpv をすべて止める）
+#[tauri::command]
+pub async fn stop_playback(
+    app: AppHandle,
+    manager: tauri::State<'_, PlaybackManager>,
+) -> Result<(), String> {
+    let current = manager
+        .status
+        .read()
+        .await
+        .as_ref()
+        .map(|s| (s.live_id.clone(), s.player_id.clone()));
+    let (live_id, player_id) = current.unzip();
+    manager.stop_supervisor().await;
+
+    app.state::<LlstreamRelayManager>().stop().await;
+    let _ = mpv_player::stop_mpv(app.clone(), app.state(), None, player_id).await;
+
+    if let Some(live_id) = live_id {
+        emit_transition(&app, &live_id, None, "stopped", "stopped by request");
+    }
+    Ok(())
+}
+
+/// 現在の再生状態を取得する（再生していなければ None）
+#[tauri::command]
+pub async fn get_playback_state(
+    manager: tauri::State<'_, PlaybackManager>,
+) -> Result<Option<PlaybackStatus>, String> {
+    Ok(manager.status.read().await.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn pick_field_prefers_top_level_then_live_then_data() {
+        let status = json!({
+            "streaming_key": "  ",
+            "live": { "streaming_key": " live-key " },
+            "data": { "streaming_key": "data-key" },
+        });
+        assert_eq!(pick_field(&status, "streaming_key").as_deref(), Some("live-key"));
+        assert_eq!(pick_field(&json!({ "data": { "k": "v" } }), "k").as_deref(), Some("v"));
+        assert_eq!(pick_field(&json!({ "k": 1 }), "k"), None);
+    }
+
+    #[test]
+    fn hls_url_checks_direct_keys_before_lists() {
+        let status = json!({
+            "streaming_url": "",
+            "hls_url": "https://example.com/a.m3u8",
+            "streaming_url_list": ["https://example.com/b.m3u8"],
+        });
+        assert_eq!(hls_url(&status).as_deref(), Some("https://example.com/a.m3u8"));
+
+        let status = json!({
+            "streaming_urls": [{ "url": "" }, { "streaming_url": "https://example.com/c.m3u8" }],
+        });
+        assert_eq!(hls_url(&status).as_deref(), Some("https://example.com/c.m3u8"));
+        assert_eq!(hls_url(&json!({})), None);
+    }
+
+    #[test]
+    fn build_llstream_ws_url_handles_edge_forms() {
+        assert_eq!(
+            build_llstream_ws_url("wss://edge.example.com/", "key", "video/avc").as_deref(),
+            Some("wss://edge.example.com/ws/key/video/avc")
+        );
+        assert_eq!(
+            build_llstream_ws_url("edge.example.com", "key", "audio/aac").as_deref(),
+            Some("ws://edge.example.com:1883/ws/key/audio/aac")
+        );
+        assert_eq!(
+            build_llstream_ws_url("edge.example.com:8080", "key", "video/avc").as_deref(),
+            Some("ws://edge.example.com:8080/ws/key/video/avc")
+        );
+        assert_eq!(build_llstream_ws_url("", "key", "video/avc"), None);
+        assert_eq!(build_llstream_ws_url("edge.example.com", "", "video/avc"), None);
+    }
+
+    #[test]
+    fn sources_build_ws_urls_from_edge_and_key() {
+        let status = json!({
+            "streaming_url_llstream_video": "wss://direct/video",
+            "live": { "streaming_url_edge": "edge.example.com", "streaming_key": "key" },
+        });
+        let sources = PlaybackSources::from_status(&status);
+        assert_eq!(sources.video_ws.as_deref(), Some("wss://direct/video"));
+        assert_eq!(
+            sources.audio_ws.as_deref(),
+            Some("ws://edge.example.com:1883/ws/key/audio/aac")
+        );
+        assert_eq!(sources.hls, None);
+    }
+
+    #[test]
+    fn candidates_are_ordered_by_priority() {
+        let all = PlaybackSources {
+            hls: Some("hls".to_string()),
+            video_ws: Some("video".to_string()),
+            audio_ws: Some("audio".to_string()),
+        };
+        let paths: Vec<_> = all.candidates().into_iter().map(PlaybackPath::as_str).collect();
+        assert_eq!(paths, ["llstream-av", "llstream-video", "hls"]);
+
+        let video_only = PlaybackSources {
+            video_ws: Some("video".to_string()),
+            ..Default::default()
+        };
+        let paths: Vec<_> = video_only.candidates().into_iter().map(PlaybackPath::as_str).collect();
+        assert_eq!(paths, ["llstream-video"]);
+
+        let audio_only = PlaybackSources {
+            audio_ws: Some("audio".to_string()),
+            ..Default::default()
+        };
+        assert!(audio_only.candidates().is_empty());
+        assert!(playable_sources(&json!({})).is_err());
+    }
+}
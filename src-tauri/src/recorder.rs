@@ -0,0 +1,649 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// recorder.rs
+//
+// 配信をバックグラウンドで録画するキューと履歴の管理。
+//
+// 主な責務:
+//   - 録画キュー（自動録画対象ユーザーの配信開始・手動追加）と同時録画数の制御
+//   - get_live_status からの llstream URL 解決と、視聴用とは別の AV TS リレーの起動
+//   - リレーの TS とコメント（Broadcast WS）のファイルへの書き込み
+//   - Broadcast WS の t=123・live_polling による配信終了の検出
+//   - app_data_dir の recordings.json へのキュー・履歴の保存
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, watch, Notify};
+use tokio::time::{Duration, MissedTickBehavior};
+
+use crate::live_poller::live_ended;
+use crate::mirrativ::client::broadcast::spawn_broadcast_listener;
+use crate::mirrativ::client::live::{get_live_info, get_live_status, live_polling};
+use crate::mirrativ::client::llstream_relay::start_detached_av_ts_relay;
+use crate::playback::{hls_url, llstream_av_urls};
+use crate::viewing::broadcast_config;
+
+const STORE_FILE: &str = "recordings.json";
+/// 同時に録画する配信数（超えた分はキューで待つ）
+const MAX_ACTIVE_RECORDINGS: usize = 2;
+/// 保持する履歴の件数（古いものから削除）
+const MAX_HISTORY: usize = 200;
+/// live_polling で配信終了を確認する間隔
+const END_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// 録画中の容量・コメント数を状態に反映する間隔
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+/// リレーの HTTP ストリームが途切れた場合に再接続する回数
+const MAX_STREAM_RECONNECTS: u32 = 5;
+/// Broadcast WS の配信終了メッセージ
+const BROADCAST_ENDED_TYPE: i64 = 123;
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 型定義
+// ─────────────────────────────────────────────────────────────────────────────
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RecordingState {
+    Queued,
+    Recording,
+    Completed,
+    Failed,
+    Cancelled,
+    /// 録画できる経路がなかった（end_reason が "hls-only" / "no-stream"）
+    Skipped,
+    /// アプリが録画中に終了した（次回起動時に設定される）
+    Interrupted,
+}
+
+/// キュー・履歴の 1 件（recording://queued / started / finished イベントの payload）
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RecordingEntry {
+    pub id: String,
+    pub live_id: String,
+    pub user_id: Option<String>,
+    pub user_name: Option<String>,
+    pub title: Option<String>,
+    pub state: RecordingState,
+    /// "auto"（自動録画）/ "manual"
+    pub trigger: String,
+    pub queued_at_ms: u64,
+    pub started_at_ms: Option<u64>,
+    pub ended_at_ms: Option<u64>,
+    pub video_path: Option<String>,
+    /// コメント（Broadcast WS のメッセージ）の JSON Lines
+    pub comments_path: Option<String>,
+    pub bytes: u64,
+    pub comments: u64,
+    /// "broadcast-ended" / "live-ended" / "stream-ended" / "stopped" / "app-exit"
+    /// （Skipped では "hls-only" / "no-stream"）
+    pub end_reason: Option<String>,
+    pub error: Option<String>,
+}
+
+/// recordings.json の内容（list_recordings の戻り値）
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RecordingStore {
+    /// 待機中・録画中
+    #[serde(default)]
+    pub queue: Vec<RecordingEntry>,
+    /// 終了したもの（新しい順）
+    #[serde(default)]
+    pub history: Vec<RecordingEntry>,
+}
+
+/// 録画を追加する配信
+pub(crate) struct RecordingRequest {
+    pub(crate) live_id: String,
+    pub(crate) user_id: Option<String>,
+    pub(crate) user_name: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) trigger: &'static str,
+}
+
+/// 録画タスクの結果
+struct RecordingOutcome {
+    end_reason: &'static str,
+    bytes: u64,
+    comments: u64,
+}
+
+/// 録画タスクが録画せずに終わった理由
+enum RecordingFailure {
+    /// 録画できる経路がない（end_reason）
+    Skipped(&'static str),
+    Failed(String),
+}
+
+impl From<String> for RecordingFailure {
+    fn from(error: String) -> Self {
+        Self::Failed(error)
+    }
+}
+
+/// 録画中の進捗（録画タスクと状態の反映で共有する）
+#[derive(Default)]
+struct Progress {
+    bytes: u64,
+    comments: u64,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// RecordingManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 録画キューの管理（Tauri の管理状態として登録される）
+#[derive(Default)]
+pub struct RecordingManager {
+    store: Mutex<RecordingStore>,
+    /// 録画中のタスクの停止要求（id → 停止理由）
+    active: Mutex<HashMap<String, watch::Sender<Option<&'static str>>>>,
+    /// アプリ終了中（キューの次の録画を開始しない）
+    closing: AtomicBool,
+    /// 録画中のものがなくなったときに stop_all を起こす
+    idle: Notify,
+}
+
+impl RecordingManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// ストアを変更して保存する
+    fn update_store<T>(&self, app: &AppHandle, update: impl FnOnce(&mut RecordingStore) -> T) -> T {
+        let Ok(mut store) = self.store.lock() else {
+            return update(&mut RecordingStore::default());
+        };
+        let result = update(&mut store);
+        if let Err(e) = save_store(app, &store) {
+            eprintln!("[recorder] failed to save {}: {}", STORE_FILE, e);
+        }
+        result
+    }
+}
+
+/// 保存済みのキューを読み込んで録画を再開する（setup から呼ぶ）。
+/// 前回録画中のまま終了したものは interrupted として履歴に移す。
+pub(crate) fn start(app: &AppHandle) {
+    let mut loaded = load_store(app).unwrap_or_else(|e| {
+        eprintln!("[recorder] {}", e);
+        RecordingStore::default()
+    });
+    let now = unix_millis();
+    let (interrupted, queued): (Vec<_>, Vec<_>) = std::mem::take(&mut loaded.queue)
+        .into_iter()
+        .partition(|entry| entry.state == RecordingState::Recording);
+    loaded.queue = queued;
+    for mut entry in interrupted {
+        entry.state = RecordingState::Interrupted;
+        entry.ended_at_ms.get_or_insert(now);
+        push_history(&mut loaded, entry);
+    }
+
+    let manager = app.state::<RecordingManager>();
+    manager.update_store(app, |store| *store = loaded);
+    pump(app);
+}
+
+/// 録画をキューに追加する。同じ配信がキューにある場合はそのエントリを返す
+pub(crate) fn enqueue(app: &AppHandle, request: RecordingRequest) -> RecordingEntry {
+    let manager = app.state::<RecordingManager>();
+    let (entry, added) = manager.update_store(app, |store| {
+        if let Some(existing) = store.queue.iter().find(|e| e.live_id == request.live_id) {
+            return (existing.clone(), false);
+        }
+        let entry = RecordingEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            live_id: request.live_id,
+            user_id: request.user_id,
+            user_name: request.user_name,
+            title: request.title,
+            state: RecordingState::Queued,
+            trigger: request.trigger.to_string(),
+            queued_at_ms: unix_millis(),
+            started_at_ms: None,
+            ended_at_ms: None,
+            video_path: None,
+            comments_path: None,
+            bytes: 0,
+            comments: 0,
+            end_reason: None,
+            error: None,
+        };
+        store.queue.push(entry.clone());
+        (entry, true)
+    });
+    if added {
+        emit(app, "recording://queued", &entry);
+        pump(app);
+    }
+    entry
+}
+
+/// 録画中のものをすべて止め、終了するまで待つ（アプリ終了時）
+pub(crate) async fn stop_all(app: &AppHandle, reason: &'static str) {
+    let manager = app.state::<RecordingManager>();
+    manager.closing.store(true, Ordering::Relaxed);
+    if let Ok(active) = manager.active.lock() {
+        for stop_tx in active.values() {
+            let _ = stop_tx.send(Some(reason));
+        }
+    }
+    loop {
+        // 確認より先に待機を登録して、その間に終わった録画の通知を取りこぼさない
+        let idle = manager.idle.notified();
+        if manager.active.lock().map_or(true, |active| active.is_empty()) {
+            return;
+        }
+        idle.await;
+    }
+}
+
+/// 空きがあればキューの先頭から録画を開始する
+fn pump(app: &AppHandle) {
+    let manager = app.state::<RecordingManager>();
+    if manager.closing.load(Ordering::Relaxed) {
+        return;
+    }
+    let Ok(mut active) = manager.active.lock() else {
+        return;
+    };
+    let started = manager.update_store(app, |store| {
+        let mut started = Vec::new();
+        for entry in store.queue.iter_mut() {
+            if active.len() >= MAX_ACTIVE_RECORDINGS {
+                break;
+            }
+            if entry.state != RecordingState::Queued || active.contains_key(&entry.id) {
+                continue;
+            }
+            entry.state = RecordingState::Recording;
+            entry.started_at_ms = Some(unix_millis());
+            let (stop_tx, stop_rx) = watch::channel(None);
+            active.insert(entry.id.clone(), stop_tx);
+            started.push((entry.clone(), stop_rx));
+        }
+        started
+    });
+    drop(active);
+
+    for (entry, stop_rx) in started {
+        emit(app, "recording://started", &entry);
+        tauri::async_runtime::spawn(run_recording(app.clone(), entry, stop_rx));
+    }
+}
+
+/// 録画を終えたエントリを履歴に移し、次の録画を開始する
+fn finish(app: &AppHandle, id: &str, result: Result<RecordingOutcome, RecordingFailure>) {
+    let manager = app.state::<RecordingManager>();
+    let entry = manager.update_store(app, |store| {
+        let index = store.queue.iter().position(|entry| entry.id == id)?;
+        let mut entry = store.queue.remove(index);
+        entry.ended_at_ms = Some(unix_millis());
+        match result {
+            Ok(outcome) => {
+                entry.state = RecordingState::Completed;
+                entry.end_reason = Some(outcome.end_reason.to_string());
+                entry.bytes = outcome.bytes;
+                entry.comments = outcome.comments;
+            }
+            Err(RecordingFailure::Skipped(reason)) => {
+                entry.state = RecordingState::Skipped;
+                entry.end_reason = Some(reason.to_string());
+            }
+            Err(RecordingFailure::Failed(e)) => {
+                entry.state = RecordingState::Failed;
+                entry.error = Some(e);
+            }
+        }
+        push_history(store, entry.clone());
+        Some(entry)
+    });
+    if let Ok(mut active) = manager.active.lock() {
+        active.remove(id);
+        if active.is_empty() {
+            manager.idle.notify_waiters();
+        }
+    }
+
+    if let Some(entry) = entry {
+        eprintln!(
+            "[recorder] {} finished: {}",
+            entry.live_id,
+            entry.end_reason.as_deref().or(entry.error.as_deref()).unwrap_or("-")
+        );
+        emit(app, "recording://finished", &entry);
+    }
+    pump(app);
+}
+
+fn push_history(store: &mut RecordingStore, entry: RecordingEntry) {
+    store.history.insert(0, entry);
+    store.history.truncate(MAX_HISTORY);
+}
+
+fn emit(app: &AppHandle, event: &str, entry: &RecordingEntry) {
+    if let Err(err) = app.emit(event, entry) {
+        eprintln!("Failed to emit {}: {}", event, err);
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 保存・読み込み
+// ─────────────────────────────────────────────────────────────────────────────
+
+fn store_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(STORE_FILE))
+}
+
+fn load_store(app: &AppHandle) -> Result<RecordingStore, String> {
+    let path = store_path(app)?;
+    if !path.exists() {
+        return Ok(RecordingStore::default());
+    }
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", STORE_FILE, e))
+}
+
+fn save_store(app: &AppHandle, store: &RecordingStore) -> Result<(), String> {
+    let path = store_path(app)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_vec_pretty(store).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// video_dir/Mirrativ/recordings/<live_id>-<開始時刻>.ts（とコメントの .comments.jsonl）
+fn output_paths(app: &AppHandle, entry: &RecordingEntry) -> Result<(PathBuf, PathBuf), String> {
+    let dir = app
+        .path()
+        .video_dir()
+        .map_err(|e| e.to_string())?
+        .join("Mirrativ")
+        .join("recordings");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let live_id: String = entry
+        .live_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect();
+    let stem = format!("{}-{}", live_id, entry.started_at_ms.unwrap_or_else(unix_millis));
+    Ok((
+        dir.join(format!("{}.ts", stem)),
+        dir.join(format!("{}.comments.jsonl", stem)),
+    ))
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 録画タスク
+// ─────────────────────────────────────────────────────────────────────────────
+
+async fn run_recording(
+    app: AppHandle,
+    entry: RecordingEntry,
+    mut stop_rx: watch::Receiver<Option<&'static str>>,
+) {
+    let result = record(&app, &entry, &mut stop_rx).await;
+    finish(&app, &entry.id, result);
+}
+
+async fn record(
+    app: &AppHandle,
+    entry: &RecordingEntry,
+    stop_rx: &mut watch::Receiver<Option<&'static str>>,
+) -> Result<RecordingOutcome, RecordingFailure> {
+    let live_id = entry.live_id.clone();
+    let status = get_live_status(app.state(), live_id.clone()).await?;
+    // 録画は llstream の AV リレーからのみ行う（HLS のみの配信は録画しない）
+    let Some((video_ws, audio_ws)) = llstream_av_urls(&status) else {
+        let reason = if hls_url(&status).is_some() { "hls-only" } else { "no-stream" };
+        return Err(RecordingFailure::Skipped(reason));
+    };
+    let info = get_live_info(app.state(), live_id.clone()).await.ok();
+    let broadcast = broadcast_config(&[info.as_ref(), Some(&status)]);
+
+    let (video_path, comments_path) = output_paths(app, entry)?;
+    app.state::<RecordingManager>().update_store(app, |store| {
+        if let Some(queued) = store.queue.iter_mut().find(|e| e.id == entry.id) {
+            queued.video_path = Some(video_path.to_string_lossy().into_owned());
+            queued.comments_path = Some(comments_path.to_string_lossy().into_owned());
+        }
+    });
+
+    let relay = start_detached_av_ts_relay(video_ws, audio_ws).await?;
+    eprintln!("[recorder] {} recording to {}", live_id, video_path.display());
+    let result = capture(
+        app,
+        entry,
+        &relay.url,
+        broadcast,
+        (&video_path, &comments_path),
+        stop_rx,
+    )
+    .await;
+    relay.stop().await;
+    Ok(result?)
+}
+
+/// リレーの TS とコメントを配信終了・停止要求まで書き込む
+async fn capture(
+    app: &AppHandle,
+    entry: &RecordingEntry,
+    relay_url: &str,
+    broadcast: Option<(String, String)>,
+    (video_path, comments_path): (&Path, &Path),
+    stop_rx: &mut watch::Receiver<Option<&'static str>>,
+) -> Result<RecordingOutcome, String> {
+    let mut video = File::create(video_path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", video_path.display(), e))?;
+    let mut comments = File::create(comments_path)
+        .await
+        .map_err(|e| format!("Failed to create {}: {}", comments_path.display(), e))?;
+
+    // コメントは視聴用とは別の接続で受信する（フロントエンドには流さない）
+    let (message_tx, mut message_rx) = mpsc::unbounded_channel::<Value>();
+    let (listener_shutdown, listener_shutdown_rx) = watch::channel(false);
+    let listener = match broadcast {
+        Some((key, host)) => Some(spawn_broadcast_listener(
+            key,
+            host,
+            message_tx,
+            listener_shutdown_rx,
+        )),
+        None => {
+            eprintln!("[recorder] {}: no broadcast_host, skipping comments", entry.live_id);
+            None
+        }
+    };
+
+    let client = reqwest::Client::new();
+    let mut response = open_stream(&client, relay_url).await?;
+    let mut reconnects = 0;
+    let mut progress = Progress::default();
+
+    let mut end_poll = tokio::time::interval(END_POLL_INTERVAL);
+    end_poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    end_poll.tick().await;
+    let mut progress_tick = tokio::time::interval(PROGRESS_INTERVAL);
+    progress_tick.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    let result = loop {
+        tokio::select! {
+            _ = stop_rx.changed() => {
+                if let Some(reason) = *stop_rx.borrow() {
+                    break Ok(reason);
+                }
+            }
+            chunk = response.chunk() => match chunk {
+                Ok(Some(data)) => {
+                    if let Err(e) = video.write_all(&data).await {
+                        break Err(format!("Failed to write {}: {}", video_path.display(), e));
+                    }
+                    progress.bytes += data.len() as u64;
+                    reconnects = 0;
+                }
+                Ok(None) | Err(_) => {
+                    reconnects += 1;
+                    if reconnects > MAX_STREAM_RECONNECTS {
+                        break Ok("stream-ended");
+                    }
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    match open_stream(&client, relay_url).await {
+                        Ok(next) => response = next,
+                        Err(e) => eprintln!("[recorder] {} reconnect failed: {}", entry.live_id, e),
+                    }
+                }
+            },
+            Some(message) = message_rx.recv() => {
+                let line = json!({ "received_at_ms": unix_millis(), "message": message });
+                if let Err(e) = comments.write_all(format!("{}\n", line).as_bytes()).await {
+                    eprintln!("[recorder] failed to write comments: {}", e);
+                }
+                progress.comments += 1;
+                if message["t"].as_i64() == Some(BROADCAST_ENDED_TYPE) {
+                    break Ok("broadcast-ended");
+                }
+            }
+            _ = end_poll.tick() => {
+                let polled =
+                    live_polling(app.state(), entry.live_id.clone(), None, None, None, None).await;
+                if polled.is_ok_and(|response| live_ended(&response)) {
+                    break Ok("live-ended");
+                }
+            }
+            _ = progress_tick.tick() => report_progress(app, &entry.id, &progress),
+        }
+    };
+
+    let _ = listener_shutdown.send(true);
+    if let Some(mut listener) = listener {
+        if tokio::time::timeout(Duration::from_secs(2), &mut listener)
+            .await
+            .is_err()
+        {
+            listener.abort();
+        }
+    }
+    let _ = video.flush().await;
+    let _ = comments.flush().await;
+
+    result.map(|end_reason| RecordingOutcome {
+        end_reason,
+        bytes: progress.bytes,
+        comments: progress.comments,
+    })
+}
+
+async fn open_stream(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, String> {
+    client
+        .get(url)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| format!("relay stream failed: {}", e))
+}
+
+/// 録画中の容量・コメント数をキューのエントリに反映する（保存はしない）
+fn report_progress(app: &AppHandle, id: &str, progress: &Progress) {
+    let manager = app.state::<RecordingManager>();
+    let Ok(mut store) = manager.store.lock() else {
+        return;
+    };
+    if let Some(entry) = store.queue.iter_mut().find(|entry| entry.id == id) {
+        entry.bytes = progress.bytes;
+        entry.comments = progress.comments;
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 配信を録画キューに追加する（空きがあればすぐに録画を開始する）
+#[tauri::command]
+pub async fn enqueue_recording(
+    app: AppHandle,
+    live_id: String,
+    user_id: Option<String>,
+    user_name: Option<String>,
+    title: Option<String>,
+) -> Result<RecordingEntry, String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+    Ok(enqueue(
+        &app,
+        RecordingRequest {
+            live_id,
+            user_id,
+            user_name,
+            title,
+            trigger: "manual",
+        },
+    ))
+}
+
+/// 録画を止める（待機中の場合はキャンセルする）。該当が無い場合は false を返す
+#[tauri::command]
+pub async fn stop_recording(
+    app: AppHandle,
+    manager: tauri::State<'_, RecordingManager>,
+    id: String,
+) -> Result<bool, String> {
+    if let Some(stop_tx) = manager.active.lock().map_err(|e| e.to_string())?.get(&id) {
+        let _ = stop_tx.send(Some("stopped"));
+        return Ok(true);
+    }
+    let cancelled = manager.update_store(&app, |store| {
+        let index = store.queue.iter().position(|entry| entry.id == id)?;
+        let mut entry = store.queue.remove(index);
+        entry.state = RecordingState::Cancelled;
+        entry.ended_at_ms = Some(unix_millis());
+        push_history(store, entry.clone());
+        Some(entry)
+    });
+    if let Some(entry) = &cancelled {
+        emit(&app, "recording://finished", entry);
+    }
+    Ok(cancelled.is_some())
+}
+
+/// 録画キューと履歴を取得する
+#[tauri::command]
+pub async fn list_recordings(
+    manager: tauri::State<'_, RecordingManager>,
+) -> Result<RecordingStore, String> {
+    manager
+        .store
+        .lock()
+        .map(|store| store.clone())
+        .map_err(|e| e.to_string())
+}
+
+/// 録画履歴を消去する（録画ファイルは削除しない）
+#[tauri::command]
+pub async fn clear_recording_history(
+    app: AppHandle,
+    manager: tauri::State<'_, RecordingManager>,
+) -> Result<(), String> {
+    manager.update_store(&app, |store| store.history.clear());
+    Ok(())
+}
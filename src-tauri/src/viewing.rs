@@ -0,0 +1,311 @@
+// ─────────────────────────────────────────────────────────────────────────────
+// viewing.rs
+//
+// 1 つの視聴中の配信を入室から退室まで管理するセッションマネージャー。
+//
+// 主な責務:
+//   - 入室（type=3 コメント）と配信情報・再生 URL の取得
+//   - live info の bcsvr_key / broadcast_host による Broadcast WS 接続
+//   - live_polling のバックグラウンド実行と再生オーケストレーターの起動
+//   - プレイヤーを閉じた・配信終了・別の配信への切り替え・アプリ終了時の
+//     leave_live と後片付け（送信待ちのコメントの取り消しを含む。viewing://ended で通知）
+// ─────────────────────────────────────────────────────────────────────────────
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{watch, Mutex};
+use tokio::time::Duration;
+
+use crate::comment_queue::CommentQueueManager;
+use crate::live_poller::{start_live_polling, LivePollStatus, LivePollerManager};
+use crate::mirrativ::client::broadcast::{connect_broadcast, disconnect_broadcast};
+use crate::mirrativ::client::complex;
+use crate::mirrativ::client::live::leave_live;
+use crate::mirrativ::MirrativClient;
+use crate::mpv_player::DEFAULT_PLAYER_ID;
+use crate::playback::{stop_playback, PlaybackManager, PlaybackStatus, PlayerTarget};
+
+/// プレイヤー・ポーリングの状態を確認する間隔
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// アプリ終了時の後片付け（leave_live を含む）の上限時間
+pub(crate) const EXIT_TEARDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 状態とイベント
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 視聴セッションの概要
+#[derive(Serialize, Clone)]
+pub struct ViewingSessionState {
+    pub live_id: String,
+    pub started_at_ms: u64,
+    /// Broadcast WS（コメント受信）に接続したか
+    pub broadcast_connected: bool,
+}
+
+/// start_viewing の戻り値（入室時に取得した情報をすべて返す）
+#[derive(Serialize)]
+pub struct ViewingSessionInfo {
+    pub session: ViewingSessionState,
+    pub live_info: Option<Value>,
+    pub notice: Option<Value>,
+    pub comments: Option<Value>,
+    /// get_streaming_url の結果
+    pub streaming: Value,
+    pub playback: PlaybackStatus,
+    pub polling: LivePollStatus,
+}
+
+/// viewing://ended イベントの payload
+#[derive(Serialize, Clone)]
+struct ViewingEnded {
+    live_id: String,
+    /// "stopped" / "switch" / "player-closed" / "live-ended" / "app-exit" / "start-failed"
+    reason: &'static str,
+}
+
+/// 視聴中のセッション
+struct ViewingSession {
+    state: ViewingSessionState,
+    /// 監視タスクの停止用（監視タスク自身が後片付けを呼ぶことがあるため abort はしない）
+    watcher_shutdown: watch::Sender<bool>,
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// ViewingSessionManager
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// 視聴セッションマネージャー（Tauri の管理状態として登録される）。
+/// 開始・終了はロックで直列化し、常に 1 つの配信だけを視聴する。
+#[derive(Default)]
+pub struct ViewingSessionManager {
+    session: Arc<Mutex<Option<ViewingSession>>>,
+}
+
+impl ViewingSessionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 視聴中のセッションを終了する。live_id を指定した場合はそのセッションの場合のみ終了する。
+    /// 終了した場合は true を返す。
+    pub(crate) async fn end(
+        &self,
+        app: &AppHandle,
+        live_id: Option<&str>,
+        reason: &'static str,
+    ) -> bool {
+        let mut guard = self.session.lock().await;
+        if live_id.is_some_and(|id| guard.as_ref().map(|s| s.state.live_id.as_str()) != Some(id)) {
+            return false;
+        }
+        let Some(session) = guard.take() else {
+            return false;
+        };
+        teardown(app, &session, reason).await;
+        true
+    }
+}
+
+/// セッションの後片付け（再生・ポーリング・Broadcast WS を止めて退室する）。
+/// 各段階の失敗はログに出して続行する。
+async fn teardown(app: &AppHandle, session: &ViewingSession, reason: &'static str) {
+    let live_id = session.state.live_id.clone();
+    eprintln!("[viewing] {} ending: {}", live_id, reason);
+    let _ = session.watcher_shutdown.send(true);
+
+    // 別の配信の再生に切り替わっている場合は止めない
+    let playback = app.state::<PlaybackManager>();
+    if playback.current_live_id().await.is_none_or(|id| id == live_id) {
+        if let Err(e) = stop_playback(app.clone(), app.state()).await {
+            eprintln!("[viewing] stop playback failed: {}", e);
+        }
+    }
+    app.state::<LivePollerManager>().stop(&live_id).await;
+    app.state::<CommentQueueManager>().clear(app, &live_id);
+    if session.state.broadcast_connected {
+        let _ = disconnect_broadcast(app.clone(), app.state()).await;
+    }
+    if let Err(e) = leave_live(app.state(), live_id.clone()).await {
+        eprintln!("[viewing] leave_live {} failed: {}", live_id, e);
+    }
+
+    let _ = app.emit("viewing://ended", ViewingEnded { live_id, reason });
+}
+
+/// live info（なければ再生 URL の結果）から Broadcast WS の接続先を取り出す。
+/// watch-broadcast.ts の extractBroadcastConfig と同じ候補を探す。
+pub(crate) fn broadcast_config(sources: &[Option<&Value>]) -> Option<(String, String)> {
+    let pick = |value: &Value, keys: &[&str]| {
+        keys.iter().find_map(|key| {
+            [&value[key], &value["live"][key], &value["data"][key]]
+                .into_iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .find(|s| !s.is_empty())
+                .map(str::to_string)
+        })
+    };
+    sources.iter().flatten().find_map(|value| {
+        let key = pick(value, &["bcsvr_key", "broadcast_key"])?;
+        let host = pick(value, &["broadcast_host"])?;
+        Some((key, host))
+    })
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// 監視タスク
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// プレイヤーが閉じられた・配信が終了したことを検出してセッションを終了する
+async fn watch_session(app: AppHandle, live_id: String, mut shutdown_rx: watch::Receiver<bool>) {
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = shutdown_rx.changed() => {
+                if *shutdown_rx.borrow() {
+                    return;
+                }
+                continue;
+            }
+            _ = interval.tick() => {}
+        }
+
+        // 再生オーケストレーターはプレイヤーが閉じられると状態を破棄する
+        let reason = if app.state::<PlaybackManager>().current_live_id().await.as_deref()
+            != Some(live_id.as_str())
+        {
+            "player-closed"
+        } else if !app.state::<LivePollerManager>().is_polling(&live_id).await {
+            // ポーリングは配信終了を検出すると自動で止まる
+            "live-ended"
+        } else {
+            continue;
+        };
+        app.state::<ViewingSessionManager>()
+            .end(&app, Some(&live_id), reason)
+            .await;
+        return;
+    }
+}
+
+// ─────────────────────────────────────────────────────────────────────────────
+// Tauri コマンド
+// ─────────────────────────────────────────────────────────────────────────────
+
+/// live_id の配信の視聴を開始する（視聴中の配信があれば退室してから切り替える）。
+/// 入室・Broadcast WS 接続・live_polling・再生をまとめて行い、
+/// 終了時は viewing://ended で理由を通知する。
+#[tauri::command]
+pub async fn start_viewing(
+    app: AppHandle,
+    manager: tauri::State<'_, ViewingSessionManager>,
+    live_id: String,
+    embedded: Option<bool>,
+    window_label: Option<String>,
+) -> Result<ViewingSessionInfo, String> {
+    let live_id = live_id.trim().to_string();
+    if live_id.is_empty() {
+        return Err("live_id is empty".to_string());
+    }
+
+    let mut guard = manager.session.lock().await;
+    if let Some(previous) = guard.take() {
+        teardown(&app, &previous, "switch").await;
+    }
+
+    // 入室コメント（type=3）の送信後に失敗した場合も入室扱いのまま残さないよう退室しておく
+    let joined = match complex::join(&app.state::<MirrativClient>(), &live_id).await {
+        Ok(joined) => joined,
+        Err(e) => {
+            if let Err(leave) = leave_live(app.state(), live_id.clone()).await {
+                eprintln!("[viewing] leave_live {} failed: {}", live_id, leave);
+            }
+            return Err(e);
+        }
+    };
+    let (watcher_shutdown, watcher_rx) = watch::channel(false);
+    let mut session = ViewingSession {
+        state: ViewingSessionState {
+            live_id: live_id.clone(),
+            started_at_ms: unix_millis(),
+            broadcast_connected: false,
+        },
+        watcher_shutdown,
+    };
+
+    // コメント受信は任意（接続できなくても視聴は続ける）
+    if let Some((key, host)) = broadcast_config(&[joined.info.as_ref(), Some(&joined.status)]) {
+        let echo_live = Some(live_id.clone());
+        match connect_broadcast(app.clone(), app.state(), key, host, None, None, echo_live).await {
+            Ok(()) => session.state.broadcast_connected = true,
+            Err(e) => eprintln!("[viewing] broadcast connect failed: {}", e),
+        }
+    } else {
+        eprintln!("[viewing] {}: no bcsvr_key/broadcast_host in live info", live_id);
+    }
+
+    let started = async {
+        let polling = start_live_polling(app.clone(), app.state(), live_id.clone(), None).await?;
+        let target = PlayerTarget {
+            embedded,
+            window_label,
+            player_id: DEFAULT_PLAYER_ID.to_string(),
+            live_id: live_id.clone(),
+        };
+        let playback = app
+            .state::<PlaybackManager>()
+            .start(&app, live_id.clone(), Some(&joined.status), target)
+            .await?;
+        Ok::<_, String>((polling, playback))
+    }
+    .await;
+    let (polling, playback) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            teardown(&app, &session, "start-failed").await;
+            return Err(e);
+        }
+    };
+
+    tokio::spawn(watch_session(app.clone(), live_id, watcher_rx));
+    let state = session.state.clone();
+    *guard = Some(session);
+
+    Ok(ViewingSessionInfo {
+        session: state,
+        live_info: joined.info,
+        notice: joined.notice,
+        comments: joined.comments,
+        streaming: joined.status,
+        playback,
+        polling,
+    })
+}
+
+/// 視聴を終了して退室する。視聴していなかった場合は false を返す
+#[tauri::command]
+pub async fn stop_viewing(
+    app: AppHandle,
+    manager: tauri::State<'_, ViewingSessionManager>,
+) -> Result<bool, String> {
+    Ok(manager.end(&app, None, "stopped").await)
+}
+
+/// 視聴中のセッションを取得する（視聴していなければ None）
+#[tauri::command]
+pub async fn get_viewing_session(
+    manager: tauri::State<'_, ViewingSessionManager>,
+) -> Result<Option<ViewingSessionState>, String> {
+    Ok(manager.session.lock().await.as_ref().map(|s| s.state.clone()))
+}
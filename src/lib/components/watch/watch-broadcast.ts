@@ -85,6 +85,8 @@ export const toBroadcastComment = (msg: any) => {
     yell_level: msg.yell_level ?? 0,
     profile_frame_image_url: msg.profile_frame_image_url ?? "",
     push_image_url: msg.push_image_url ?? "",
+    // バックエンドのモデレーション判定（hide / highlight / alert）
+    verdict: msg.verdict ?? null,
     _raw: msg,
   };
 };